@@ -0,0 +1,167 @@
+//! Contexts describe what a partial transcript proves about its contents.
+//!
+//! A context is built from a [`PartialTranscript`](crate::transcript::PartialTranscript),
+//! in which only some bytes are authenticated. Every value in a context
+//! records how much of it was disclosed as a [`Redactable`], so that a
+//! hidden value can never be mistaken for a revealed one.
+
+use rangeset::{Difference, Disjoint, Intersection, RangeSet, Subset};
+use serde::{Deserialize, Serialize};
+
+/// A value in a context, with its disclosure state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Redactable<T> {
+    /// Every byte of the value is revealed.
+    Revealed {
+        /// The value.
+        value: T,
+    },
+    /// No byte of the value is revealed.
+    Redacted {
+        /// The length of the value in bytes.
+        len: usize,
+    },
+    /// Only part of the value, such as the quotes of a JSON string, is
+    /// revealed. The content is not reported.
+    StructureOnly {
+        /// The length of the value in bytes.
+        len: usize,
+    },
+}
+
+impl<T> Redactable<T> {
+    /// Classifies the value at `ranges` against the `authed` ranges of the
+    /// transcript, calling `value` only if it is fully revealed.
+    pub(crate) fn new(
+        ranges: &RangeSet<usize>,
+        authed: &RangeSet<usize>,
+        value: impl FnOnce() -> T,
+    ) -> Self {
+        if ranges.is_subset(authed) {
+            Redactable::Revealed { value: value() }
+        } else if ranges.is_disjoint(authed) {
+            Redactable::Redacted { len: ranges.len() }
+        } else {
+            Redactable::StructureOnly { len: ranges.len() }
+        }
+    }
+
+    /// Returns the value if it is revealed.
+    pub fn revealed(&self) -> Option<&T> {
+        match self {
+            Redactable::Revealed { value } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value is revealed.
+    pub fn is_revealed(&self) -> bool {
+        matches!(self, Redactable::Revealed { .. })
+    }
+
+    /// Maps a revealed value.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Redactable<U> {
+        match self {
+            Redactable::Revealed { value } => Redactable::Revealed { value: f(value) },
+            Redactable::Redacted { len } => Redactable::Redacted { len },
+            Redactable::StructureOnly { len } => Redactable::StructureOnly { len },
+        }
+    }
+}
+
+impl<T, E> Redactable<Result<T, E>> {
+    /// Transposes a redactable result into a result of a redactable value.
+    pub(crate) fn transpose(self) -> Result<Redactable<T>, E> {
+        match self {
+            Redactable::Revealed { value } => value.map(|value| Redactable::Revealed { value }),
+            Redactable::Redacted { len } => Ok(Redactable::Redacted { len }),
+            Redactable::StructureOnly { len } => Ok(Redactable::StructureOnly { len }),
+        }
+    }
+}
+
+/// Splits `data`, located at `ranges` of the transcript, into runs of revealed
+/// and redacted bytes.
+pub(crate) fn segments(
+    ranges: &RangeSet<usize>,
+    data: &[u8],
+    authed: &RangeSet<usize>,
+) -> Vec<Redactable<Vec<u8>>> {
+    let mut segments: Vec<Redactable<Vec<u8>>> = Vec::new();
+    let mut offset = 0;
+
+    for range in ranges.iter_ranges() {
+        let range_set = RangeSet::from(range.clone());
+        let revealed = range_set.intersection(authed);
+        let redacted = range_set.difference(authed);
+
+        let mut runs = revealed
+            .iter_ranges()
+            .map(|run| (run, true))
+            .chain(redacted.iter_ranges().map(|run| (run, false)))
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|(run, _)| run.start);
+
+        for (run, is_revealed) in runs {
+            let start = offset + run.start - range.start;
+            let bytes = data.get(start..start + run.len()).unwrap_or_default();
+
+            match (segments.last_mut(), is_revealed) {
+                (Some(Redactable::Revealed { value }), true) => value.extend_from_slice(bytes),
+                (Some(Redactable::Redacted { len }), false) => *len += run.len(),
+                (_, true) => segments.push(Redactable::Revealed {
+                    value: bytes.to_vec(),
+                }),
+                (_, false) => segments.push(Redactable::Redacted { len: run.len() }),
+            }
+        }
+
+        offset += range.len();
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case::revealed(0..4, Redactable::Revealed { value: "abcd" })]
+    #[case::redacted(10..14, Redactable::Redacted { len: 4 })]
+    #[case::partial(6..10, Redactable::StructureOnly { len: 4 })]
+    fn test_redactable_new(
+        #[case] range: std::ops::Range<usize>,
+        #[case] expected: Redactable<&str>,
+    ) {
+        let authed = RangeSet::from(0..8);
+
+        assert_eq!(
+            Redactable::new(&RangeSet::from(range), &authed, || "abcd"),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_segments() {
+        let ranges = RangeSet::from([2..6, 8..12]);
+        let authed = RangeSet::from([0..3, 5..6, 8..10]);
+
+        assert_eq!(
+            segments(&ranges, b"abcdefgh", &authed),
+            [
+                Redactable::Revealed {
+                    value: b"a".to_vec()
+                },
+                Redactable::Redacted { len: 2 },
+                Redactable::Revealed {
+                    value: b"def".to_vec()
+                },
+                Redactable::Redacted { len: 2 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Test fixtures.
+
+// Some fixtures are only used by tests that need a hash algorithm.
+#![cfg_attr(not(any(feature = "sha256", feature = "blake3")), allow(dead_code))]
+
+pub(crate) mod http {
+    pub(crate) mod request {
+        pub(crate) const GET_EMPTY: &[u8] = b"GET / HTTP/1.1\r\n\r\n";
+
+        pub(crate) const GET_EMPTY_HEADER: &[u8] = b"GET / HTTP/1.1\r\nHost: \r\n\r\n";
+
+        pub(crate) const GET_WITH_HEADER: &[u8] =
+            b"GET /home.html HTTP/1.1\r\nHost: developer.mozilla.org\r\nAccept: */*\r\n\r\n";
+
+        pub(crate) const POST_JSON: &[u8] = b"\
+            POST /hello HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: 43\r\n\r\n\
+            {\"foo\": \"bar\", \"bazz\": 123, \"buzz\": [1, 5]}";
+    }
+
+    pub(crate) mod response {
+        pub(crate) const OK_EMPTY: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+
+        pub(crate) const OK_EMPTY_HEADER: &[u8] =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nCookie: \r\n\r\n";
+
+        pub(crate) const OK_TEXT: &[u8] = b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: 14\r\n\r\n\
+            Hello, world!\n";
+
+        pub(crate) const OK_JSON: &[u8] = b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: 43\r\n\r\n\
+            {\"foo\": \"bar\", \"bazz\": 123, \"buzz\": [1, 5]}";
+
+        pub(crate) const OK_CHUNKED_JSON: &[u8] = b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: application/json\r\n\
+            Transfer-Encoding: chunked\r\n\r\n\
+            E\r\n\
+            {\"foo\": \"bar\"}\r\n\
+            0\r\n\r\n";
+    }
+}
+
+pub(crate) mod json {
+    pub(crate) const ARRAY: &[u8] = b"[1, \"two\", true, null, [], {}]";
+
+    pub(crate) const INTEGER: &[u8] = b"42";
+
+    pub(crate) const NESTED_OBJECT: &[u8] =
+        b"{\"foo\": {\"bar\": {\"baz\": [1, 2, 3]}}, \"empty\": \"\"}";
+
+    pub(crate) const VALUES: &[u8] =
+        b"{\"string\": \"hello\", \"number\": -1.5e3, \"bool\": false, \"null\": null}";
+}
+
+#[cfg(feature = "decrypt")]
+pub(crate) mod tls {
+    use crate::tls::{
+        decrypt::{CipherSuite, TrafficKeys, EXPLICIT_NONCE_LEN, TAG_LEN},
+        record::ContentType,
+    };
+
+    fn header(content_type: ContentType, len: usize) -> Vec<u8> {
+        let mut header = vec![content_type as u8, 3, 3];
+        header.extend_from_slice(&(len as u16).to_be_bytes());
+        header
+    }
+
+    /// Returns an unencrypted record.
+    pub(crate) fn plaintext(content_type: ContentType, fragment: &[u8]) -> Vec<u8> {
+        [header(content_type, fragment.len()), fragment.to_vec()].concat()
+    }
+
+    /// Returns a TLS 1.3 record.
+    pub(crate) fn tls13(
+        suite: CipherSuite,
+        keys: &TrafficKeys,
+        seq: u64,
+        content_type: ContentType,
+        content: &[u8],
+    ) -> Vec<u8> {
+        // Pad the inner plaintext to check that padding is removed.
+        let inner = [content, &[content_type as u8], &[0u8; 3]].concat();
+        let header = header(ContentType::ApplicationData, inner.len() + TAG_LEN);
+
+        let mut nonce: [u8; 12] = keys.iv.as_slice().try_into().unwrap();
+        for (n, s) in nonce[4..].iter_mut().zip(seq.to_be_bytes()) {
+            *n ^= s;
+        }
+        let ciphertext = suite.aead(&keys.key).unwrap().seal(&nonce, &header, &inner);
+
+        [header, ciphertext].concat()
+    }
+
+    /// Returns a TLS 1.2 record.
+    pub(crate) fn tls12(
+        suite: CipherSuite,
+        keys: &TrafficKeys,
+        seq: u64,
+        content_type: ContentType,
+        content: &[u8],
+    ) -> Vec<u8> {
+        let explicit = (seq + 100).to_be_bytes();
+        let mut nonce = [0u8; 12];
+        nonce[..4].copy_from_slice(&keys.iv);
+        nonce[4..].copy_from_slice(&explicit);
+
+        let aad = [
+            seq.to_be_bytes().as_slice(),
+            &header(content_type, content.len()),
+        ]
+        .concat();
+        let ciphertext = suite.aead(&keys.key).unwrap().seal(&nonce, &aad, content);
+
+        [
+            header(content_type, EXPLICIT_NONCE_LEN + ciphertext.len()),
+            explicit.to_vec(),
+            ciphertext,
+        ]
+        .concat()
+    }
+
+    /// Returns a record containing a ClientHello.
+    pub(crate) fn client_hello(random: [u8; 32]) -> Vec<u8> {
+        let body = [
+            &[3, 3],
+            random.as_slice(),
+            &[0],
+            &[0, 2, 0x13, 0x01],
+            &[1, 0],
+        ]
+        .concat();
+        handshake(1, &body)
+    }
+
+    /// Returns a record containing a ServerHello.
+    pub(crate) fn server_hello(random: [u8; 32], cipher_suite: u16) -> Vec<u8> {
+        let body = [
+            &[3, 3],
+            random.as_slice(),
+            &[0],
+            &cipher_suite.to_be_bytes(),
+            &[0],
+        ]
+        .concat();
+        handshake(2, &body)
+    }
+
+    fn handshake(typ: u8, body: &[u8]) -> Vec<u8> {
+        let len = (body.len() as u32).to_be_bytes();
+        let message = [&[typ], &len[1..], body].concat();
+        plaintext(ContentType::Handshake, &message)
+    }
+}
+
+pub(crate) mod capture {
+    /// A TCP segment between 10.0.0.1:50000 and 10.0.0.2:443.
+    pub(crate) struct Segment {
+        pub(crate) from_client: bool,
+        pub(crate) flags: u8,
+        pub(crate) seq: u32,
+        pub(crate) payload: Vec<u8>,
+    }
+
+    pub(crate) const SYN: u8 = 0x02;
+    pub(crate) const ACK: u8 = 0x10;
+
+    /// Returns the segments of a connection which sends each of `sent` and
+    /// receives each of `received` in a separate segment.
+    pub(crate) fn segments(sent: &[&[u8]], received: &[&[u8]]) -> Vec<Segment> {
+        let mut segments = vec![
+            Segment {
+                from_client: true,
+                flags: SYN,
+                seq: 1000,
+                payload: Vec::new(),
+            },
+            Segment {
+                from_client: false,
+                flags: SYN | ACK,
+                seq: u32::MAX - 10,
+                payload: Vec::new(),
+            },
+        ];
+
+        for (from_client, mut seq, payloads) in
+            [(true, 1001, sent), (false, u32::MAX - 9, received)]
+        {
+            for payload in payloads {
+                segments.push(Segment {
+                    from_client,
+                    flags: ACK,
+                    seq,
+                    payload: payload.to_vec(),
+                });
+                seq = seq.wrapping_add(payload.len() as u32);
+            }
+        }
+
+        segments
+    }
+
+    /// Returns an Ethernet frame containing a segment.
+    pub(crate) fn frame(segment: &Segment) -> Vec<u8> {
+        let (src, dst, src_port, dst_port) = if segment.from_client {
+            ([10, 0, 0, 1], [10, 0, 0, 2], 50000u16, 443u16)
+        } else {
+            ([10, 0, 0, 2], [10, 0, 0, 1], 443, 50000)
+        };
+
+        let mut tcp = Vec::new();
+        tcp.extend_from_slice(&src_port.to_be_bytes());
+        tcp.extend_from_slice(&dst_port.to_be_bytes());
+        tcp.extend_from_slice(&segment.seq.to_be_bytes());
+        tcp.extend_from_slice(&[0; 4]);
+        tcp.extend_from_slice(&[5 << 4, segment.flags, 0xff, 0xff, 0, 0, 0, 0]);
+        tcp.extend_from_slice(&segment.payload);
+
+        let mut ip = vec![0x45, 0];
+        ip.extend_from_slice(&(20 + tcp.len() as u16).to_be_bytes());
+        ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+        ip.extend_from_slice(&src);
+        ip.extend_from_slice(&dst);
+        ip.extend_from_slice(&tcp);
+
+        [&[0u8; 12], [0x08, 0x00].as_slice(), &ip].concat()
+    }
+
+    /// Returns a pcap capture of Ethernet frames.
+    pub(crate) fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut pcap = Vec::new();
+        for word in [0xa1b2c3d4u32, 0x00040002, 0, 0, 65535, 1] {
+            pcap.extend_from_slice(&word.to_le_bytes());
+        }
+        for (i, frame) in frames.iter().enumerate() {
+            for word in [i as u32, 0, frame.len() as u32, frame.len() as u32] {
+                pcap.extend_from_slice(&word.to_le_bytes());
+            }
+            pcap.extend_from_slice(frame);
+        }
+        pcap
+    }
+
+    /// Returns a pcapng capture of Ethernet frames.
+    pub(crate) fn pcapng(frames: &[Vec<u8>]) -> Vec<u8> {
+        fn block(typ: u32, body: &[u8]) -> Vec<u8> {
+            let mut body = body.to_vec();
+            body.resize(body.len().next_multiple_of(4), 0);
+            let len = (12 + body.len() as u32).to_be_bytes();
+            [&typ.to_be_bytes(), len.as_slice(), &body, &len].concat()
+        }
+
+        let shb = [
+            0x1a2b3c4du32.to_be_bytes(),
+            [0, 1, 0, 0],
+            [0xff; 4],
+            [0xff; 4],
+        ]
+        .concat();
+        let idb = [[0, 1, 0, 0], 65535u32.to_be_bytes()].concat();
+
+        let mut pcapng = [block(0x0a0d0d0a, &shb), block(1, &idb)].concat();
+        for frame in frames {
+            let header = [0u32, 0, 0, frame.len() as u32, frame.len() as u32]
+                .map(u32::to_be_bytes)
+                .concat();
+            pcapng.extend_from_slice(&block(6, &[header.as_slice(), frame].concat()));
+        }
+        pcapng
+    }
+}
+
+/// A TLS 1.2 connection to `test.example` whose certificate chains to
+/// `tls/fixtures/data/roots.pem`.
+#[cfg(feature = "webpki")]
+pub(crate) struct ConnectionFixture {
+    pub(crate) server_name: crate::tls::ServerName,
+    pub(crate) connection_info: crate::tls::ConnectionInfo,
+    pub(crate) server_cert_data: crate::tls::HandshakeData,
+}
+
+#[cfg(feature = "webpki")]
+impl ConnectionFixture {
+    /// Returns a connection authenticated with an ECDSA P-256 certificate.
+    pub(crate) fn ecdsa(transcript_length: crate::transcript::TranscriptLength) -> Self {
+        Self::new(
+            transcript_length,
+            crate::tls::SignatureAlgorithm::ECDSA_NISTP256_SHA256,
+            include_bytes!("tls/fixtures/data/ecdsa/ee.der"),
+            include_bytes!("tls/fixtures/data/ecdsa/pubkey"),
+            include_bytes!("tls/fixtures/data/ecdsa/signature"),
+        )
+    }
+
+    /// Returns a connection authenticated with an RSA certificate.
+    pub(crate) fn rsa(transcript_length: crate::transcript::TranscriptLength) -> Self {
+        Self::new(
+            transcript_length,
+            crate::tls::SignatureAlgorithm::RSA_PKCS1_2048_8192_SHA256,
+            include_bytes!("tls/fixtures/data/rsa/ee.der"),
+            include_bytes!("tls/fixtures/data/rsa/pubkey"),
+            include_bytes!("tls/fixtures/data/rsa/signature"),
+        )
+    }
+
+    fn new(
+        transcript_length: crate::transcript::TranscriptLength,
+        alg: crate::tls::SignatureAlgorithm,
+        ee: &[u8],
+        pubkey: &[u8],
+        sig: &[u8],
+    ) -> Self {
+        use crate::{
+            tls::{
+                CertBinding, CertBindingV1_2, ConnectionInfo, DnsName, HandshakeData, KeyType,
+                ServerEphemKey, ServerName, ServerSignature, TlsVersion,
+            },
+            webpki::CertificateDer,
+        };
+
+        Self {
+            server_name: ServerName::Dns(DnsName::try_from("test.example").unwrap()),
+            connection_info: ConnectionInfo {
+                // 2025-06-01, within the validity of the certificates.
+                time: 1748736000,
+                version: TlsVersion::V1_2,
+                transcript_length,
+            },
+            server_cert_data: HandshakeData {
+                certs: vec![
+                    CertificateDer(ee.to_vec()),
+                    CertificateDer(include_bytes!("tls/fixtures/data/inter.der").to_vec()),
+                    CertificateDer(include_bytes!("tls/fixtures/data/ca.der").to_vec()),
+                ],
+                sig: ServerSignature {
+                    alg,
+                    sig: sig.to_vec(),
+                },
+                binding: CertBinding::V1_2(CertBindingV1_2 {
+                    client_random: [1; 32],
+                    server_random: [2; 32],
+                    server_ephemeral_key: ServerEphemKey {
+                        typ: KeyType::SECP256R1,
+                        key: pubkey.to_vec(),
+                    },
+                }),
+            },
+        }
+    }
+
+    /// Returns the server's ephemeral key of the connection.
+    pub(crate) fn server_ephemeral_key(&self) -> &crate::tls::ServerEphemKey {
+        let crate::tls::CertBinding::V1_2(binding) = &self.server_cert_data.binding;
+        &binding.server_ephemeral_key
+    }
+}
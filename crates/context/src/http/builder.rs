@@ -0,0 +1,885 @@
+//! Builder for HTTP transcripts.
+//!
+//! Assembles requests and responses from their parts, computing the message
+//! framing, and records the spans a parser is expected to find. This replaces
+//! hand-written byte literals with manual `Content-Length` values in tests:
+//!
+//! ```
+//! use http_transcript_context::http::{
+//!     builder::{HttpTranscriptBuilder, HttpTranscriptSpans, RequestBuilder, ResponseBuilder},
+//!     HttpTranscript,
+//! };
+//!
+//! let built = HttpTranscriptBuilder::new()
+//!     .request(
+//!         RequestBuilder::post("/login")
+//!             .header("Host", "example.com")
+//!             .form([("user", "alice"), ("remember", "1")]),
+//!     )
+//!     .response(
+//!         ResponseBuilder::ok()
+//!             .json(&serde_json::json!({"token": "abc"}))
+//!             .chunked(4)
+//!             .trailer("Digest", "sha-256=x"),
+//!     )
+//!     .build();
+//!
+//! let http = HttpTranscript::parse(&built.transcript).unwrap();
+//! assert_eq!(HttpTranscriptSpans::from(&http), built.spans);
+//! ```
+
+use std::ops::Range;
+
+use rangeset::{RangeSet, ToRangeSet};
+use spanner::http::{Header, Request, Response};
+
+use crate::{http::HttpTranscript, transcript::Transcript};
+
+const VERSION: &str = "HTTP/1.1";
+
+/// A message body and its media type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MessageBody {
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+impl MessageBody {
+    fn new(content_type: Option<&str>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            content_type: content_type.map(String::from),
+            data: data.into(),
+        }
+    }
+
+    fn json(value: &serde_json::Value) -> Self {
+        Self::new(
+            Some("application/json"),
+            serde_json::to_vec(value).expect("JSON values are serializable"),
+        )
+    }
+}
+
+/// Builder for an HTTP request.
+///
+/// The `Content-Length` and `Content-Type` headers are written after the
+/// headers which are set explicitly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestBuilder {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Option<MessageBody>,
+}
+
+impl RequestBuilder {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The request method.
+    /// * `target` - The request target.
+    pub fn new(method: impl Into<String>, target: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            target: target.into(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Creates a builder for a `GET` request.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The request target.
+    pub fn get(target: impl Into<String>) -> Self {
+        Self::new("GET", target)
+    }
+
+    /// Creates a builder for a `POST` request.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The request target.
+    pub fn post(target: impl Into<String>) -> Self {
+        Self::new("POST", target)
+    }
+
+    /// Adds a header.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the body.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` - The media type of the body, if any.
+    /// * `data` - The body.
+    pub fn body(mut self, content_type: Option<&str>, data: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(MessageBody::new(content_type, data));
+        self
+    }
+
+    /// Sets a `text/plain` body.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The body.
+    pub fn text(self, text: &str) -> Self {
+        self.body(Some("text/plain"), text)
+    }
+
+    /// Sets an `application/json` body.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The JSON value, which is serialized compactly.
+    pub fn json(mut self, value: &serde_json::Value) -> Self {
+        self.body = Some(MessageBody::json(value));
+        self
+    }
+
+    /// Sets an `application/x-www-form-urlencoded` body.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - The form fields, which are percent-encoded.
+    pub fn form<K, V>(self, pairs: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish();
+
+        self.body(Some("application/x-www-form-urlencoded"), form)
+    }
+
+    /// Returns the request as bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        self.write(&mut writer);
+        writer.buf
+    }
+
+    fn write(&self, writer: &mut Writer) -> RequestSpans {
+        let start = writer.pos();
+        let method = writer.push(&self.method);
+        writer.push(" ");
+        let target = writer.push(&self.target);
+        writer.push(" ");
+        writer.push(VERSION);
+        writer.push("\r\n");
+        let request_line = start..writer.pos();
+
+        let mut headers = writer.headers(&self.headers);
+        let body = self.body.as_ref().filter(|body| !body.data.is_empty());
+        if let Some(body) = body {
+            headers.extend(writer.framing(body.content_type.as_deref(), body.data.len()));
+        }
+        writer.push("\r\n");
+
+        let body = body.map(|body| RangeSet::from(writer.push(&body.data)));
+
+        RequestSpans {
+            span: RangeSet::from(start..writer.pos()),
+            request_line: RangeSet::from(request_line),
+            method: RangeSet::from(method),
+            target: RangeSet::from(target),
+            headers,
+            body,
+        }
+    }
+}
+
+/// Builder for an HTTP response.
+///
+/// The framing headers, `Content-Length` or `Transfer-Encoding`, and the
+/// `Content-Type` header are written after the headers which are set
+/// explicitly. Responses with a 1xx, 204 or 304 status are written without a
+/// body or framing headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseBuilder {
+    code: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    body: Option<MessageBody>,
+    chunk_size: Option<usize>,
+    trailers: Vec<(String, String)>,
+}
+
+impl ResponseBuilder {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The status code.
+    /// * `reason` - The reason phrase.
+    pub fn new(code: u16, reason: impl Into<String>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+            headers: Vec::new(),
+            body: None,
+            chunk_size: None,
+            trailers: Vec::new(),
+        }
+    }
+
+    /// Creates a builder for a `200 OK` response.
+    pub fn ok() -> Self {
+        Self::new(200, "OK")
+    }
+
+    /// Adds a header.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the body.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` - The media type of the body, if any.
+    /// * `data` - The body.
+    pub fn body(mut self, content_type: Option<&str>, data: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(MessageBody::new(content_type, data));
+        self
+    }
+
+    /// Sets a `text/plain` body.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The body.
+    pub fn text(self, text: &str) -> Self {
+        self.body(Some("text/plain"), text)
+    }
+
+    /// Sets an `application/json` body.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The JSON value, which is serialized compactly.
+    pub fn json(mut self, value: &serde_json::Value) -> Self {
+        self.body = Some(MessageBody::json(value));
+        self
+    }
+
+    /// Sends the body with chunked transfer coding.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_size` - The size of each chunk but the last, which may be
+    ///   shorter. A size of zero is treated as one.
+    pub fn chunked(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    /// Adds a trailer field.
+    ///
+    /// Trailers are only sent with chunked transfer coding. If no chunk size
+    /// is set, the body is sent as a single chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The field name.
+    /// * `value` - The field value.
+    pub fn trailer(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.trailers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns the response as bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        self.write(&mut writer);
+        writer.buf
+    }
+
+    fn write(&self, writer: &mut Writer) -> ResponseSpans {
+        let start = writer.pos();
+        writer.push(VERSION);
+        writer.push(" ");
+        let code = writer.push(self.code.to_string());
+        writer.push(" ");
+        let reason = writer.push(&self.reason);
+        writer.push("\r\n");
+        let status = start..writer.pos();
+
+        let mut headers = writer.headers(&self.headers);
+
+        let mut spans = ResponseSpans {
+            span: RangeSet::default(),
+            status: RangeSet::from(status),
+            code: RangeSet::from(code),
+            reason: RangeSet::from(reason),
+            headers: Vec::new(),
+            body: None,
+            boundaries: Vec::new(),
+            trailers: Vec::new(),
+        };
+
+        // These responses are terminated by the end of the header section.
+        if matches!(self.code, 100..=199 | 204 | 304) {
+            writer.push("\r\n");
+            spans.headers = headers;
+            spans.span = RangeSet::from(start..writer.pos());
+
+            return spans;
+        }
+
+        let content_type = self
+            .body
+            .as_ref()
+            .and_then(|body| body.content_type.as_deref());
+        let data = self.body.as_ref().map_or(&[][..], |body| &body.data);
+        let chunk_size = self
+            .chunk_size
+            .or_else(|| (!self.trailers.is_empty()).then_some(data.len().max(1)));
+
+        let Some(chunk_size) = chunk_size else {
+            headers.extend(writer.framing(content_type, data.len()));
+            writer.push("\r\n");
+            spans.headers = headers;
+            spans.body = (!data.is_empty()).then(|| RangeSet::from(writer.push(data)));
+            spans.span = RangeSet::from(start..writer.pos());
+
+            return spans;
+        };
+
+        if let Some(content_type) = content_type {
+            headers.push(writer.header("Content-Type", content_type));
+        }
+        headers.push(writer.header("Transfer-Encoding", "chunked"));
+        writer.push("\r\n");
+        spans.headers = headers;
+
+        let mut body = Vec::new();
+        for chunk in data.chunks(chunk_size).chain([&[][..]]) {
+            spans.boundaries.push(RangeSet::from(
+                writer.push(format!("{:X}\r\n", chunk.len())),
+            ));
+            if !chunk.is_empty() {
+                body.push(writer.push(chunk));
+                writer.push("\r\n");
+            }
+        }
+        spans.body = (!body.is_empty()).then(|| RangeSet::new(&body));
+        spans.trailers = writer.headers(&self.trailers);
+        writer.push("\r\n");
+        spans.span = RangeSet::from(start..writer.pos());
+
+        spans
+    }
+}
+
+/// Builder for an HTTP transcript.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpTranscriptBuilder {
+    requests: Vec<RequestBuilder>,
+    responses: Vec<ResponseBuilder>,
+}
+
+impl HttpTranscriptBuilder {
+    /// Creates a new builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a request to the sent data.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request.
+    pub fn request(mut self, request: RequestBuilder) -> Self {
+        self.requests.push(request);
+        self
+    }
+
+    /// Adds a response to the received data.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The response.
+    pub fn response(mut self, response: ResponseBuilder) -> Self {
+        self.responses.push(response);
+        self
+    }
+
+    /// Builds the transcript and its expected spans.
+    pub fn build(&self) -> BuiltHttpTranscript {
+        let mut sent = Writer::default();
+        let requests = self
+            .requests
+            .iter()
+            .map(|request| request.write(&mut sent))
+            .collect();
+
+        let mut received = Writer::default();
+        let responses = self
+            .responses
+            .iter()
+            .map(|response| response.write(&mut received))
+            .collect();
+
+        BuiltHttpTranscript {
+            transcript: Transcript::new(sent.buf, received.buf),
+            spans: HttpTranscriptSpans {
+                requests,
+                responses,
+            },
+        }
+    }
+}
+
+/// A transcript built by [`HttpTranscriptBuilder`].
+pub struct BuiltHttpTranscript {
+    /// The transcript.
+    pub transcript: Transcript,
+    /// The spans a parser is expected to find in the transcript.
+    pub spans: HttpTranscriptSpans,
+}
+
+impl std::fmt::Debug for BuiltHttpTranscript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuiltHttpTranscript")
+            .field("spans", &self.spans)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The spans of an HTTP transcript, as indices into the transcript.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpTranscriptSpans {
+    /// The spans of the requests.
+    pub requests: Vec<RequestSpans>,
+    /// The spans of the responses.
+    pub responses: Vec<ResponseSpans>,
+}
+
+impl From<&HttpTranscript> for HttpTranscriptSpans {
+    fn from(http: &HttpTranscript) -> Self {
+        Self {
+            requests: http.requests.iter().map(RequestSpans::from).collect(),
+            responses: http.responses.iter().map(ResponseSpans::from).collect(),
+        }
+    }
+}
+
+/// The spans of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestSpans {
+    /// The whole request.
+    pub span: RangeSet<usize>,
+    /// The request line, including its CRLF.
+    pub request_line: RangeSet<usize>,
+    /// The method.
+    pub method: RangeSet<usize>,
+    /// The target.
+    pub target: RangeSet<usize>,
+    /// The headers.
+    pub headers: Vec<HeaderSpans>,
+    /// The body, if it is not empty.
+    pub body: Option<RangeSet<usize>>,
+}
+
+impl From<&Request> for RequestSpans {
+    fn from(request: &Request) -> Self {
+        Self {
+            span: request.to_range_set(),
+            request_line: request.request.to_range_set(),
+            method: request.request.method.to_range_set(),
+            target: request.request.target.to_range_set(),
+            headers: request.headers.iter().map(HeaderSpans::from).collect(),
+            body: request.body.as_ref().map(ToRangeSet::to_range_set),
+        }
+    }
+}
+
+/// The spans of a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseSpans {
+    /// The whole response.
+    pub span: RangeSet<usize>,
+    /// The status line, including its CRLF.
+    pub status: RangeSet<usize>,
+    /// The status code.
+    pub code: RangeSet<usize>,
+    /// The reason phrase.
+    pub reason: RangeSet<usize>,
+    /// The headers.
+    pub headers: Vec<HeaderSpans>,
+    /// The body content, if it is not empty, excluding any chunk framing.
+    pub body: Option<RangeSet<usize>>,
+    /// The chunk size lines of a chunked body, including the last chunk.
+    pub boundaries: Vec<RangeSet<usize>>,
+    /// The trailer fields of a chunked body.
+    pub trailers: Vec<HeaderSpans>,
+}
+
+impl From<&Response> for ResponseSpans {
+    fn from(response: &Response) -> Self {
+        Self {
+            span: response.to_range_set(),
+            status: response.status.to_range_set(),
+            code: response.status.code.to_range_set(),
+            reason: response.status.reason.to_range_set(),
+            headers: response.headers.iter().map(HeaderSpans::from).collect(),
+            body: response.body.as_ref().map(ToRangeSet::to_range_set),
+            boundaries: response
+                .boundaries
+                .iter()
+                .flatten()
+                .map(ToRangeSet::to_range_set)
+                .collect(),
+            trailers: response
+                .trailers
+                .iter()
+                .flatten()
+                .map(HeaderSpans::from)
+                .collect(),
+        }
+    }
+}
+
+/// The spans of a header or trailer field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderSpans {
+    /// The whole field, including its CRLF.
+    pub span: RangeSet<usize>,
+    /// The name.
+    pub name: RangeSet<usize>,
+    /// The value.
+    pub value: RangeSet<usize>,
+}
+
+impl From<&Header> for HeaderSpans {
+    fn from(header: &Header) -> Self {
+        Self {
+            span: header.to_range_set(),
+            name: header.name.to_range_set(),
+            value: header.value.to_range_set(),
+        }
+    }
+}
+
+/// Writes messages, tracking the range of each part.
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn pos(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn push(&mut self, data: impl AsRef<[u8]>) -> Range<usize> {
+        let start = self.pos();
+        self.buf.extend_from_slice(data.as_ref());
+        start..self.pos()
+    }
+
+    fn header(&mut self, name: &str, value: &str) -> HeaderSpans {
+        let start = self.pos();
+        let name = self.push(name);
+        self.push(": ");
+        let value = self.push(value);
+        self.push("\r\n");
+
+        HeaderSpans {
+            span: RangeSet::from(start..self.pos()),
+            name: RangeSet::from(name),
+            value: RangeSet::from(value),
+        }
+    }
+
+    fn headers(&mut self, headers: &[(String, String)]) -> Vec<HeaderSpans> {
+        headers
+            .iter()
+            .map(|(name, value)| self.header(name, value))
+            .collect()
+    }
+
+    /// Writes the headers of a body with a `Content-Length`.
+    fn framing(&mut self, content_type: Option<&str>, len: usize) -> Vec<HeaderSpans> {
+        let mut headers = Vec::new();
+        if let Some(content_type) = content_type {
+            headers.push(self.header("Content-Type", content_type));
+        }
+        headers.push(self.header("Content-Length", &len.to_string()));
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use rstest::rstest;
+    use serde_json::json;
+    use spanner::http::BodyContent;
+
+    use super::*;
+    use crate::fixtures::http as fixtures;
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    use crate::{
+        http::{DefaultHttpCommitter, HttpCommit},
+        transcript::{Direction, HashCommitmentBuilder, TranscriptCommitmentBuilder},
+    };
+
+    fn assert_spans(built: &BuiltHttpTranscript) -> HttpTranscript {
+        let http = HttpTranscript::parse(&built.transcript).unwrap();
+        assert_eq!(HttpTranscriptSpans::from(&http), built.spans);
+        http
+    }
+
+    #[rstest]
+    fn test_request_bytes() {
+        assert_eq!(
+            RequestBuilder::get("/").to_bytes(),
+            fixtures::request::GET_EMPTY
+        );
+        assert_eq!(
+            RequestBuilder::get("/home.html")
+                .header("Host", "developer.mozilla.org")
+                .header("Accept", "*/*")
+                .to_bytes(),
+            fixtures::request::GET_WITH_HEADER
+        );
+        assert_eq!(
+            RequestBuilder::post("/hello")
+                .header("Host", "localhost")
+                .text("Hello, world!\n")
+                .to_bytes(),
+            b"POST /hello HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\n\
+              Content-Length: 14\r\n\r\nHello, world!\n"
+        );
+    }
+
+    #[rstest]
+    fn test_response_bytes() {
+        assert_eq!(
+            ResponseBuilder::ok().to_bytes(),
+            fixtures::response::OK_EMPTY
+        );
+        assert_eq!(
+            ResponseBuilder::ok().text("Hello, world!\n").to_bytes(),
+            fixtures::response::OK_TEXT
+        );
+        assert_eq!(
+            ResponseBuilder::ok()
+                .body(Some("application/json"), r#"{"foo": "bar"}"#)
+                .chunked(64)
+                .to_bytes(),
+            fixtures::response::OK_CHUNKED_JSON
+        );
+        assert_eq!(
+            ResponseBuilder::new(204, "No Content")
+                .text("ignored")
+                .to_bytes(),
+            b"HTTP/1.1 204 No Content\r\n\r\n"
+        );
+    }
+
+    #[rstest]
+    #[case::get(RequestBuilder::get("/").header("Host", "localhost"))]
+    #[case::text(RequestBuilder::post("/").text("hello"))]
+    #[case::json(RequestBuilder::post("/api").json(&json!({"a": [1, true, null], "b": {}})))]
+    #[case::form(RequestBuilder::post("/login").form([("user", "a b"), ("pass", "&=?")]))]
+    #[case::empty_body(RequestBuilder::new("PUT", "/").body(None, ""))]
+    fn test_build_request(#[case] request: RequestBuilder) {
+        let built = HttpTranscriptBuilder::new()
+            .request(request.clone())
+            .request(request)
+            .build();
+
+        let http = assert_spans(&built);
+        assert_eq!(http.requests.len(), 2);
+    }
+
+    #[rstest]
+    #[case::empty(ResponseBuilder::ok())]
+    #[case::text(ResponseBuilder::ok().text("hello"))]
+    #[case::json(ResponseBuilder::ok().json(&json!({"a": [1, 2, 3]})))]
+    #[case::chunked(ResponseBuilder::ok().text("hello world").chunked(4))]
+    #[case::chunked_empty(ResponseBuilder::ok().chunked(4))]
+    #[case::chunked_json(ResponseBuilder::ok().json(&json!({"a": "bcd"})).chunked(3))]
+    #[case::trailers(ResponseBuilder::ok().text("hello").trailer("Digest", "x").trailer("Expires", "y"))]
+    #[case::no_content(ResponseBuilder::new(204, "No Content").header("Server", "test"))]
+    #[case::not_found(ResponseBuilder::new(404, "Not Found").text("missing"))]
+    fn test_build_response(#[case] response: ResponseBuilder) {
+        let built = HttpTranscriptBuilder::new()
+            .response(response.clone())
+            .response(response)
+            .build();
+
+        let http = assert_spans(&built);
+        assert_eq!(http.responses.len(), 2);
+    }
+
+    #[rstest]
+    fn test_build_json_body() {
+        let built = HttpTranscriptBuilder::new()
+            .request(RequestBuilder::post("/").json(&json!({"foo": "bar"})))
+            .response(ResponseBuilder::ok().json(&json!([1, 2])))
+            .build();
+
+        let http = assert_spans(&built);
+        let body = http.requests[0].body.as_ref().unwrap();
+        assert!(matches!(body.content, BodyContent::Json(_)));
+        assert_eq!(body.as_bytes(), br#"{"foo":"bar"}"#);
+
+        let body = http.responses[0].body.as_ref().unwrap();
+        let BodyContent::Json(json) = &body.content else {
+            panic!("body is not JSON");
+        };
+        assert_eq!(
+            json.to_range_set(),
+            built.spans.responses[0].body.clone().unwrap()
+        );
+    }
+
+    fn token(rng: &mut StdRng, alphabet: &[u8], len: Range<usize>) -> String {
+        let len = rng.gen_range(len);
+        (0..len)
+            .map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char)
+            .collect()
+    }
+
+    fn headers(rng: &mut StdRng) -> Vec<(String, String)> {
+        const NAME: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ-";
+        const VALUE: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789 ;=/,.*";
+
+        (0..rng.gen_range(0..5))
+            .map(|i| {
+                let name = format!("X{i}{}", token(rng, NAME, 0..12));
+                let value = format!("v{}", token(rng, VALUE, 0..24));
+                (name, value.trim_end().to_string())
+            })
+            .collect()
+    }
+
+    fn json_value(rng: &mut StdRng, depth: usize) -> serde_json::Value {
+        const TEXT: &[u8] = b"abc xyz\"\\/\n";
+
+        match rng.gen_range(0..if depth == 0 { 4 } else { 6 }) {
+            0 => json!(null),
+            1 => json!(rng.gen::<bool>()),
+            2 => json!(rng.gen_range(-1000..1000)),
+            3 => json!(token(rng, TEXT, 0..8)),
+            4 => (0..rng.gen_range(0..4))
+                .map(|_| json_value(rng, depth - 1))
+                .collect(),
+            _ => (0..rng.gen_range(0..4))
+                .map(|i| (format!("k{i}"), json_value(rng, depth - 1)))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+        }
+    }
+
+    fn random_transcript(rng: &mut StdRng) -> HttpTranscriptBuilder {
+        const TARGET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789/?=&%-";
+        const TEXT: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789 \r\n{}\"";
+
+        let mut builder = HttpTranscriptBuilder::new();
+        for _ in 0..rng.gen_range(1..4) {
+            let mut request = RequestBuilder::new(
+                ["GET", "POST", "PUT", "DELETE"][rng.gen_range(0..4)],
+                format!("/{}", token(rng, TARGET, 0..16)),
+            );
+            for (name, value) in headers(rng) {
+                request = request.header(name, value);
+            }
+            request = match rng.gen_range(0..4) {
+                0 => request,
+                1 => request.text(&token(rng, TEXT, 0..32)),
+                2 => request.json(&json_value(rng, 3)),
+                _ => request.form(
+                    (0..rng.gen_range(0..4))
+                        .map(|i| (format!("f{i}"), token(rng, TEXT, 0..8)))
+                        .collect::<Vec<_>>(),
+                ),
+            };
+
+            let mut response = ResponseBuilder::new(
+                [200, 201, 204, 304, 404, 500][rng.gen_range(0..6)],
+                ["OK", "Some Reason"][rng.gen_range(0..2)],
+            );
+            for (name, value) in headers(rng) {
+                response = response.header(name, value);
+            }
+            response = match rng.gen_range(0..3) {
+                0 => response,
+                1 => response.text(&token(rng, TEXT, 0..32)),
+                _ => response.json(&json_value(rng, 3)),
+            };
+            if rng.gen_bool(0.5) {
+                response = response.chunked(rng.gen_range(1..16));
+            }
+            for (name, value) in headers(rng) {
+                response = response.trailer(name, value);
+            }
+
+            builder = builder.request(request).response(response);
+        }
+
+        builder
+    }
+
+    #[rstest]
+    fn test_build_random() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..256 {
+            assert_spans(&random_transcript(&mut rng).build());
+        }
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_default_committer_random() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..64 {
+            let built = random_transcript(&mut rng).build();
+            let http = assert_spans(&built);
+            let mut builder = HashCommitmentBuilder::new(&built.transcript);
+
+            DefaultHttpCommitter::default()
+                .commit_transcript(&mut builder, &http)
+                .unwrap();
+
+            let commitment = builder.build().unwrap();
+            for spans in &built.spans.requests {
+                assert!(commitment.contains(&spans.span, Direction::Sent));
+                assert!(commitment.contains(&spans.target, Direction::Sent));
+                for header in &spans.headers {
+                    assert!(commitment.contains(&header.span, Direction::Sent));
+                }
+            }
+            for spans in &built.spans.responses {
+                assert!(commitment.contains(&spans.span, Direction::Received));
+                for header in &spans.headers {
+                    assert!(commitment.contains(&header.span, Direction::Received));
+                }
+            }
+        }
+    }
+}
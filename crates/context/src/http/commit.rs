@@ -1,12 +1,16 @@
-use std::error::Error;
+use std::{collections::BTreeMap, error::Error};
 
-use spanner::Spanned;
+use derive_builder::Builder;
+use rangeset::{RangeSet, ToRangeSet};
+use serde::{Deserialize, Serialize};
+use spanner::{Span, Spanned};
 use crate::transcript::{Direction, TranscriptCommitmentBuilder, TranscriptCommitmentBuilderError};
 
 use crate::{
     http::{Body, BodyContent, Header, HttpTranscript, Request, Response, Target},
     http::transcript::MessageKind,
-    json::{DefaultJsonCommitter, JsonCommit},
+    json::{ConfigurableJsonCommitter, DefaultJsonCommitter, JsonCommit},
+    text::TextCommitter,
 };
 
 /// HTTP commitment error.
@@ -260,6 +264,24 @@ pub trait HttpCommit<C: TranscriptCommitmentBuilder> {
                         )
                     })?;
             }
+            BodyContent::Unknown(text) => {
+                builder.commit(text, direction).map_err(|e| {
+                    HttpCommitError::new_with_source(
+                        MessageKind::Request,
+                        "failed to commit to unknown content body",
+                        e,
+                    )
+                })?;
+
+                self.commit_text_body(builder, direction, &parent.headers, text)
+                    .map_err(|e| {
+                        HttpCommitError::new_with_source(
+                            MessageKind::Request,
+                            "failed to commit to text body",
+                            e,
+                        )
+                    })?;
+            }
             body => {
                 builder.commit(body, direction).map_err(|e| {
                     HttpCommitError::new_with_source(
@@ -397,10 +419,28 @@ pub trait HttpCommit<C: TranscriptCommitmentBuilder> {
                         )
                     })?;
             }
+            BodyContent::Unknown(text) => {
+                builder.commit(text, direction).map_err(|e| {
+                    HttpCommitError::new_with_source(
+                        MessageKind::Response,
+                        "failed to commit to unknown content body",
+                        e,
+                    )
+                })?;
+
+                self.commit_text_body(builder, direction, &parent.headers, text)
+                    .map_err(|e| {
+                        HttpCommitError::new_with_source(
+                            MessageKind::Response,
+                            "failed to commit to text body",
+                            e,
+                        )
+                    })?;
+            }
             body => {
                 builder.commit(body, direction).map_err(|e| {
                     HttpCommitError::new_with_source(
-                        MessageKind::Request,
+                        MessageKind::Response,
                         "failed to commit to unknown content body",
                         e,
                     )
@@ -411,6 +451,29 @@ pub trait HttpCommit<C: TranscriptCommitmentBuilder> {
         Ok(())
     }
 
+    /// Commits to a body of an unknown format, such as HTML or plain text.
+    ///
+    /// The body as a whole has already been committed. The default
+    /// implementation commits nothing else, so the body can only be revealed
+    /// entirely. Implementations can commit to parts of the body with a
+    /// [`TextCommitter`] chosen by the media type in `headers`.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The transcript commitment builder.
+    /// * `direction` - The direction of the message (sent or received).
+    /// * `headers` - The headers of the message.
+    /// * `text` - The body content.
+    fn commit_text_body(
+        &mut self,
+        builder: &mut C,
+        direction: Direction,
+        headers: &[Header],
+        text: &Span,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        Ok(())
+    }
+
     /// Commits the structure of the HTTP transcript.
     fn commit_structure(
         &mut self,
@@ -503,15 +566,356 @@ impl<C: TranscriptCommitmentBuilder> HttpCommit<C> for DefaultHttpCommitter {
     }
 }
 
-/*
+/// How a header is committed as structure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderRule {
+    /// The whole header, including its value, is structural.
+    Full,
+    /// Only the header excluding its value is structural.
+    Name,
+}
+
+/// How much of a request target is committed as structure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetGranularity {
+    /// The whole target is structural.
+    #[default]
+    Full,
+    /// The path is structural, while the query is committed separately.
+    Path,
+    /// The target is not structural.
+    Hidden,
+}
+
+/// How a body is committed as structure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyRule {
+    /// The whole body is structural.
+    Full,
+    /// The structure of the body format is structural, such as the JSON
+    /// structure. Bodies of an unknown format are structural as a whole.
+    #[default]
+    Structure,
+    /// The body is not structural.
+    Hidden,
+}
+
+/// An HTTP committer whose notion of structure is configured by rules rather
+/// than code.
+///
+/// The rules are serialisable, so that a prover and verifier can share a
+/// committer policy. The default rules reveal the same bytes as
+/// [`DefaultHttpCommitter`], although the committed ranges differ: headers
+/// with a [`HeaderRule::Full`] rule are committed as a whole, without a
+/// separate commitment to their name.
+#[derive(Debug, Clone, PartialEq, Eq, Builder, Serialize, Deserialize)]
+#[builder(default)]
+#[serde(default)]
+pub struct ConfigurableHttpCommitter {
+    /// Rules for headers by name, which are compared case-insensitively.
+    #[builder(setter(custom))]
+    pub headers: BTreeMap<String, HeaderRule>,
+    /// Rule for headers without a rule of their own.
+    pub default_header: HeaderRule,
+    /// How much of the request target is structural.
+    pub target: TargetGranularity,
+    /// Rules for bodies by media type, such as `application/json`, which are
+    /// compared case-insensitively.
+    #[builder(setter(custom))]
+    pub bodies: BTreeMap<String, BodyRule>,
+    /// Rule for bodies without a rule for their media type.
+    pub default_body: BodyRule,
+    /// Committer for JSON bodies.
+    pub json: ConfigurableJsonCommitter,
+    /// Committers for bodies of an unknown format by media type, such as
+    /// `text/html`, which are compared case-insensitively.
+    #[builder(setter(custom))]
+    pub text: BTreeMap<String, TextCommitter>,
+}
+
+impl ConfigurableHttpCommitter {
+    /// Returns a builder for the committer.
+    pub fn builder() -> ConfigurableHttpCommitterBuilder {
+        ConfigurableHttpCommitterBuilder::default()
+    }
+
+    /// Returns the rule for headers with the given name.
+    pub fn header_rule(&self, name: &str) -> HeaderRule {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map_or(self.default_header, |(_, rule)| *rule)
+    }
+
+    /// Returns the rule for bodies of a message with the given headers.
+    pub fn body_rule(&self, headers: &[Header]) -> BodyRule {
+        media_type(headers)
+            .and_then(|media_type| {
+                self.bodies
+                    .iter()
+                    .find(|(body, _)| body.eq_ignore_ascii_case(media_type))
+            })
+            .map_or(self.default_body, |(_, rule)| *rule)
+    }
+
+    /// Returns the text committer for bodies of a message with the given
+    /// headers, if any.
+    pub fn text_committer(&self, headers: &[Header]) -> Option<&TextCommitter> {
+        let media_type = media_type(headers)?;
+
+        self.text
+            .iter()
+            .find(|(text, _)| text.eq_ignore_ascii_case(media_type))
+            .map(|(_, committer)| committer)
+    }
+
+    fn commit_headers<C: TranscriptCommitmentBuilder>(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        headers: &[Header],
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        for header in headers {
+            match self.header_rule(header.name.as_str()) {
+                HeaderRule::Full => builder.commit(header, direction)?,
+                HeaderRule::Name => builder.commit(&header.without_value(), direction)?,
+            };
+        }
+
+        Ok(())
+    }
+
+    fn commit_body<C: TranscriptCommitmentBuilder>(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        headers: &[Header],
+        body: &Body,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        match (self.body_rule(headers), &body.content) {
+            (BodyRule::Hidden, _) => {}
+            (BodyRule::Structure, BodyContent::Json(json)) => {
+                JsonCommit::<C>::commit_structure(&self.json, builder, direction, json)?;
+            }
+            (BodyRule::Structure, BodyContent::Unknown(text)) => {
+                match self.text_committer(headers) {
+                    Some(committer) => committer.commit_structure(
+                        builder,
+                        direction,
+                        text.indices(),
+                        text.as_bytes(),
+                    )?,
+                    None => {
+                        builder.commit(text, direction)?;
+                    }
+                }
+            }
+            (BodyRule::Full | BodyRule::Structure, content) => {
+                builder.commit(content, direction)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ConfigurableHttpCommitterBuilder {
+    /// Sets the rule for headers with the given name.
+    pub fn header(&mut self, name: impl Into<String>, rule: HeaderRule) -> &mut Self {
+        self.headers
+            .get_or_insert_with(|| ConfigurableHttpCommitter::default().headers)
+            .insert(name.into().to_ascii_lowercase(), rule);
+        self
+    }
+
+    /// Sets the text committer for bodies with the given media type.
+    pub fn text(&mut self, media_type: impl Into<String>, committer: TextCommitter) -> &mut Self {
+        self.text
+            .get_or_insert_with(BTreeMap::new)
+            .insert(media_type.into().to_ascii_lowercase(), committer);
+        self
+    }
+
+    /// Sets the rule for bodies with the given media type.
+    pub fn body(&mut self, media_type: impl Into<String>, rule: BodyRule) -> &mut Self {
+        self.bodies
+            .get_or_insert_with(BTreeMap::new)
+            .insert(media_type.into().to_ascii_lowercase(), rule);
+        self
+    }
+}
+
+impl Default for ConfigurableHttpCommitter {
+    fn default() -> Self {
+        Self {
+            headers: [
+                "host",
+                "content-length",
+                "content-type",
+                "transfer-encoding",
+            ]
+            .into_iter()
+            .map(|name| (name.to_string(), HeaderRule::Full))
+            .collect(),
+            default_header: HeaderRule::Name,
+            target: TargetGranularity::default(),
+            bodies: BTreeMap::new(),
+            default_body: BodyRule::default(),
+            json: ConfigurableJsonCommitter::default(),
+            text: BTreeMap::new(),
+        }
+    }
+}
+
+/// Returns the media type of a message with the given headers, excluding any
+/// parameters.
+fn media_type(headers: &[Header]) -> Option<&str> {
+    headers
+        .iter()
+        .find(|header| header.name.as_str().eq_ignore_ascii_case("content-type"))
+        .and_then(|header| std::str::from_utf8(header.value.as_bytes()).ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim)
+}
+
+/// Splits a target into its path and its query, including the `?`.
+fn split_target(target: &Target) -> (RangeSet<usize>, RangeSet<usize>) {
+    let ranges = target.to_range_set();
+    let (Some(start), Some(end)) = (ranges.min(), ranges.end()) else {
+        return (ranges, RangeSet::default());
+    };
+
+    match target.as_str().find('?') {
+        Some(query) => (
+            RangeSet::from(start..start + query),
+            RangeSet::from(start + query..end),
+        ),
+        None => (ranges, RangeSet::default()),
+    }
+}
+
+impl<C: TranscriptCommitmentBuilder> HttpCommit<C> for ConfigurableHttpCommitter {
+    fn json_committer(&mut self) -> &mut dyn JsonCommit<C> {
+        &mut self.json
+    }
+
+    /// Commits to each token of a body with a text committer for its media
+    /// type.
+    fn commit_text_body(
+        &mut self,
+        builder: &mut C,
+        direction: Direction,
+        headers: &[Header],
+        text: &Span,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        match self.text_committer(headers) {
+            Some(committer) => {
+                committer.commit_tokens(builder, direction, text.indices(), text.as_bytes())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Commits to a request target.
+    ///
+    /// Commits to the target as a whole and, if the target granularity is
+    /// [`TargetGranularity::Path`], to its path and query separately.
+    fn commit_target(
+        &mut self,
+        builder: &mut C,
+        direction: Direction,
+        _request: &Request,
+        target: &Target,
+    ) -> Result<(), HttpCommitError> {
+        let commit_error = |e| {
+            HttpCommitError::new_with_source(
+                MessageKind::Request,
+                "failed to commit to target in request",
+                e,
+            )
+        };
+
+        builder.commit(target, direction).map_err(commit_error)?;
+
+        if self.target == TargetGranularity::Path {
+            let (path, query) = split_target(target);
+            for ranges in [path, query] {
+                if !ranges.is_empty() {
+                    builder.commit(&ranges, direction).map_err(commit_error)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn commit_structure(
+        &mut self,
+        builder: &mut C,
+        transcript: &HttpTranscript,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        for request in &transcript.requests {
+            let direction = Direction::Sent;
+            builder.commit(&request.without_data(), direction)?;
+
+            match self.target {
+                TargetGranularity::Full => {
+                    builder.commit(&request.request.target, direction)?;
+                }
+                TargetGranularity::Path => {
+                    let (path, _) = split_target(&request.request.target);
+                    builder.commit(&path, direction)?;
+                }
+                TargetGranularity::Hidden => {}
+            }
+
+            self.commit_headers(builder, direction, &request.headers)?;
+
+            if let Some(body) = &request.body {
+                self.commit_body(builder, direction, &request.headers, body)?;
+            }
+        }
+
+        for response in &transcript.responses {
+            let direction = Direction::Received;
+            builder.commit(&response.without_data(), direction)?;
+
+            self.commit_headers(builder, direction, &response.headers)?;
+
+            if let Some(body) = &response.body {
+                self.commit_body(builder, direction, &response.headers, body)?;
+            }
+
+            if let Some(boundaries) = &response.boundaries {
+                for boundary in boundaries {
+                    builder.commit(boundary, direction)?;
+                }
+            }
+
+            if let Some(trailers) = &response.trailers {
+                for trailer in trailers {
+                    builder.commit(&trailer.without_value(), direction)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::http as fixtures;
+    use crate::transcript::{CommitmentSet, DryRunBuilder, HashCommitmentBuilder, Transcript};
+    use rangeset::{Difference, Disjoint, Subset};
     use rstest::*;
-    use spansy::http::{parse_request, parse_response};
-    use tlsn_core::transcript::Transcript;
-    use tlsn_data_fixtures::http as fixtures;
+    use spanner::http::{parse_request, parse_response};
 
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
     #[rstest]
     #[case::get_empty(fixtures::request::GET_EMPTY)]
     #[case::get_empty_header(fixtures::request::GET_EMPTY_HEADER)]
@@ -521,15 +925,22 @@ mod tests {
         let transcript = Transcript::new(src, []);
         let request = parse_request(src).unwrap();
         let mut committer = DefaultHttpCommitter::default();
-        let mut builder = TranscriptCommitConfigBuilder::new(&transcript);
+        let mut builder = HashCommitmentBuilder::new(&transcript);
 
         committer
             .commit_request(&mut builder, Direction::Sent, &request)
             .unwrap();
 
-        builder.build().unwrap();
+        let commitment = builder.build().unwrap();
+
+        assert!(commitment.contains(&request, Direction::Sent));
+        assert!(commitment.contains(&request.request.target, Direction::Sent));
+        for header in &request.headers {
+            assert!(commitment.contains(header, Direction::Sent));
+        }
     }
 
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
     #[rstest]
     #[case::empty(fixtures::response::OK_EMPTY)]
     #[case::empty_header(fixtures::response::OK_EMPTY_HEADER)]
@@ -540,13 +951,212 @@ mod tests {
         let transcript = Transcript::new([], src);
         let response = parse_response(src).unwrap();
         let mut committer = DefaultHttpCommitter::default();
-        let mut builder = TranscriptCommitConfigBuilder::new(&transcript);
+        let mut builder = HashCommitmentBuilder::new(&transcript);
 
         committer
             .commit_response(&mut builder, Direction::Received, &response)
             .unwrap();
 
-        builder.build().unwrap();
+        let commitment = builder.build().unwrap();
+
+        assert!(commitment.contains(&response, Direction::Received));
+        for header in &response.headers {
+            assert!(commitment.contains(header, Direction::Received));
+        }
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_http_commit_transcript_open(
+        #[values(fixtures::request::GET_WITH_HEADER, fixtures::request::POST_JSON)]
+        request: &'static [u8],
+        #[values(fixtures::response::OK_TEXT, fixtures::response::OK_JSON)]
+        response: &'static [u8],
+    ) {
+        let transcript = Transcript::new(request, response);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let mut committer = DefaultHttpCommitter::default();
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+
+        committer.commit_transcript(&mut builder, &http).unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+
+        // Open only the request and response excluding their data.
+        let openings: Vec<_> = openings
+            .into_iter()
+            .filter(|opening| match opening.direction {
+                Direction::Sent => opening.idx == http.requests[0].without_data(),
+                Direction::Received => opening.idx == http.responses[0].without_data(),
+            })
+            .collect();
+        assert_eq!(openings.len(), 2);
+
+        let partial = transcript.to_partial(
+            http.requests[0].without_data(),
+            http.responses[0].without_data(),
+        );
+
+        commitment.verify_transcript(&openings, &partial).unwrap();
+    }
+
+    fn structure<H>(committer: &mut H, request: &[u8], response: &[u8]) -> CommitmentSet
+    where
+        H: HttpCommit<DryRunBuilder>,
+    {
+        let transcript = Transcript::new(request, response);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let mut builder = DryRunBuilder::new(&transcript);
+
+        committer.commit_structure(&mut builder, &http).unwrap();
+
+        builder.commitment_set()
+    }
+
+    #[rstest]
+    fn test_configurable_commit_default(
+        #[values(fixtures::request::GET_WITH_HEADER, fixtures::request::POST_JSON)]
+        request: &'static [u8],
+        #[values(
+            fixtures::response::OK_TEXT,
+            fixtures::response::OK_JSON,
+            fixtures::response::OK_CHUNKED_JSON
+        )]
+        response: &'static [u8],
+    ) {
+        let configurable = structure(&mut ConfigurableHttpCommitter::default(), request, response);
+        let default = structure(&mut DefaultHttpCommitter::default(), request, response);
+
+        // The same bytes are revealed, but not by the same commitments.
+        for direction in [Direction::Sent, Direction::Received] {
+            assert_eq!(configurable.coverage(direction), default.coverage(direction));
+        }
+        assert_ne!(configurable, default);
+    }
+
+    #[rstest]
+    fn test_configurable_commit_rules() {
+        const REQUEST: &[u8] =
+            b"GET /home?user=alice HTTP/1.1\r\nAccept: */*\r\nCookie: a=1\r\n\r\n";
+
+        let mut committer = ConfigurableHttpCommitter::builder()
+            .header("Accept", HeaderRule::Full)
+            .target(TargetGranularity::Path)
+            .body("text/plain", BodyRule::Hidden)
+            .build()
+            .unwrap();
+        assert_eq!(committer.header_rule("HOST"), HeaderRule::Full);
+
+        let commitments = structure(&mut committer, REQUEST, fixtures::response::OK_TEXT);
+        let sent = commitments.coverage(Direction::Sent);
+        let received = commitments.coverage(Direction::Received);
+
+        let request = parse_request(REQUEST).unwrap();
+        let (path, query) = split_target(&request.request.target);
+        assert_eq!(path.len(), "/home".len());
+        assert!(path.is_subset(&sent));
+        assert!(query.is_disjoint(&sent));
+        assert!(request
+            .headers_with_name("accept")
+            .next()
+            .unwrap()
+            .to_range_set()
+            .is_subset(&sent));
+        assert!(request
+            .headers_with_name("cookie")
+            .next()
+            .unwrap()
+            .value
+            .to_range_set()
+            .is_disjoint(&sent));
+
+        let response = parse_response(fixtures::response::OK_TEXT).unwrap();
+        assert!(response.body.unwrap().to_range_set().is_disjoint(&received));
+
+        // The path and query can be opened separately.
+        let transcript = Transcript::new(REQUEST, []);
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        committer
+            .commit_target(
+                &mut builder,
+                Direction::Sent,
+                &request,
+                &request.request.target,
+            )
+            .unwrap();
+        assert!(builder.commits().iter().any(|(_, ranges)| *ranges == query));
+    }
+
+    #[rstest]
+    fn test_configurable_commit_serde() {
+        let committer = ConfigurableHttpCommitter::builder()
+            .header("Authorization", HeaderRule::Full)
+            .default_header(HeaderRule::Full)
+            .body("application/json", BodyRule::Full)
+            .text("text/html", TextCommitter::regex("<td>(.*?)</td>").unwrap())
+            .json(
+                ConfigurableJsonCommitter::builder()
+                    .max_depth(1)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let toml = toml::to_string(&committer).unwrap();
+        assert_eq!(
+            toml::from_str::<ConfigurableHttpCommitter>(&toml).unwrap(),
+            committer
+        );
+
+        let json = serde_json::to_string(&committer).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ConfigurableHttpCommitter>(&json).unwrap(),
+            committer
+        );
+
+        let partial: ConfigurableHttpCommitter = toml::from_str("target = \"hidden\"").unwrap();
+        assert_eq!(partial.target, TargetGranularity::Hidden);
+        assert_eq!(
+            partial.headers,
+            ConfigurableHttpCommitter::default().headers
+        );
+    }
+
+    #[rstest]
+    fn test_configurable_commit_text_body() {
+        let transcript = Transcript::new(
+            fixtures::request::GET_WITH_HEADER,
+            fixtures::response::OK_TEXT,
+        );
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let mut committer = ConfigurableHttpCommitter::builder()
+            .text("Text/Plain", TextCommitter::words())
+            .build()
+            .unwrap();
+
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        committer.commit_transcript(&mut builder, &http).unwrap();
+        committer.commit_structure(&mut builder, &http).unwrap();
+
+        let body = http.responses[0].body.as_ref().unwrap().to_range_set();
+        let start = body.min().unwrap();
+        let hello = RangeSet::from(start..start + "Hello,".len());
+        let world = RangeSet::from(start + 7..start + 13);
+        let structure = body.difference(&hello).difference(&world);
+
+        for ranges in [&body, &hello, &world, &structure] {
+            assert!(builder
+                .commits()
+                .iter()
+                .any(|(direction, idx)| *direction == Direction::Received && idx == ranges));
+        }
+
+        // Without a text committer the body is only committed as a whole.
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        DefaultHttpCommitter::default()
+            .commit_transcript(&mut builder, &http)
+            .unwrap();
+        assert!(!builder.commits().iter().any(|(_, idx)| *idx == hello));
     }
 }
-*/
\ No newline at end of file
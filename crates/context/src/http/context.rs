@@ -1,93 +1,125 @@
 use spanner::http::BodyContent;
 
-use crate::http::HttpTranscript;
-use crate::json::JsonContext;
+use crate::http::{Header, HttpTranscript};
+use crate::context::{segments, Redactable};
+use crate::json::{JsonContext, JsonContextError};
+use crate::matcher::PredicateOutcome;
 use crate::transcript::PartialTranscript;
 
 use http::{Method, StatusCode};
+use rangeset::{RangeSet, Subset, ToRangeSet, Union};
 use std::str::FromStr;
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The current context format version.
+pub const CONTEXT_VERSION: u32 = 1;
+
+/// JSON Schema describing the serialised form of [`HttpContext`].
+pub const CONTEXT_SCHEMA: &str = include_str!("../../schema/http-context.schema.json");
 
 /// A verifier of contextual integrity for HTTP presentations.
 ///
 /// See the [module level documentation](crate::context) for more information.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HttpContext {
-    requests: Vec<RequestContext>,
-    responses: Vec<ResponseContext>,
+    #[serde(deserialize_with = "deserialize_version")]
+    pub(crate) version: u32,
+    pub(crate) exchanges: Vec<ExchangeContext>,
 }
 
 impl HttpContext {
+    /// Returns a builder for the context of a presented transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The presented transcript.
     pub fn builder(transcript: PartialTranscript) -> HttpContextBuilder {
         HttpContextBuilder::new(transcript)
     }
+
+    /// Returns the context format version.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the exchanges, in transcript order.
+    pub fn exchanges(&self) -> &[ExchangeContext] {
+        &self.exchanges
+    }
 }
 
+/// Builder for [`HttpContext`].
 pub struct HttpContextBuilder {
     transcript: PartialTranscript,
 }
 
 impl HttpContextBuilder {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The presented transcript.
     pub fn new(transcript: PartialTranscript) -> Self {
         Self { transcript }
     }
 
-    pub fn build(self) -> Result<HttpContext, Box<dyn std::error::Error>> {
+    /// Builds the context from the authenticated data of the transcript.
+    pub fn build(self) -> Result<HttpContext, HttpContextError> {
         let transcript = HttpTranscript::parse_partial(&self.transcript)?;
+        let sent_authed = self.transcript.sent_authed();
+        let received_authed = self.transcript.received_authed();
 
-        let mut request_contexts: Vec<RequestContext> = Vec::new();
-        let mut response_contexts: Vec<ResponseContext> = Vec::new();
-
-        for request in transcript.requests.iter() {
-            let mut request_context_headers = Vec::new();
+        let mut request_contexts = Vec::new();
+        let mut response_contexts = Vec::new();
 
-            let headers = request.headers.iter()
-                .filter(|h| !["content-length"].contains(&h.name.as_str().to_lowercase().as_str()));
-
-            for header in headers {
-                let header = request.headers_with_name(&header.name.as_str()).next()
-                    .ok_or_else(|| format!("Missing required header: {}", header.name.as_str()))?;
-                request_context_headers.push((header.name.as_str().to_string(), String::from_utf8_lossy(header.value.as_bytes()).to_string()));
-            }
+        for (index, request) in transcript.requests.iter().enumerate() {
+            let request_context_headers = request.headers.iter()
+                .filter(|h| !["content-length"].contains(&h.name.as_str().to_lowercase().as_str()))
+                .filter_map(|h| HeaderField::from_header(h, sent_authed))
+                .collect();
 
             let request_body_context = if let Some(body) = &request.body {
                 match &body.content {
-                    BodyContent::Json(json) => {
-                        Some(BodyContext::Json(JsonContext::builder(json.clone()).build()?))
-                    }
-                    BodyContent::Unknown(unknown) => {
-                        Some(BodyContext::Unknown(unknown.clone().to_bytes()))
-                    }
+                    BodyContent::Json(json) => Some(BodyContext::Json(
+                        JsonContext::builder(json.clone()).authed(sent_authed.clone()).build()?,
+                    )),
+                    BodyContent::Unknown(unknown) => Some(BodyContext::unknown(unknown, sent_authed)),
                     _ => None,
                 }
             } else {
                 None
             };
 
-            request_contexts.push(RequestContext {
-                target: request.request.target.as_str().to_string(),
-                method: Method::from_str(request.request.method.as_str()).unwrap(),
+            request_contexts.push((request.to_range_set(), RequestContext {
+                target: Redactable::new(&request.request.target.to_range_set(), sent_authed, || {
+                    request.request.target.as_str().to_string()
+                }),
+                method: Redactable::new(&request.request.method.to_range_set(), sent_authed, || {
+                    Method::from_str(request.request.method.as_str())
+                })
+                .transpose()
+                .map_err(|_| HttpContextError::InvalidMethod {
+                    index,
+                    method: request.request.method.as_str().to_string(),
+                })?,
                 headers: request_context_headers,
                 body: request_body_context,
-            });
+                predicates: Vec::new(),
+            }));
         }
 
-        for response in transcript.responses.iter() {
-            let mut response_context_headers = Vec::new();
-
-            for header in response.headers.iter() {
-                let header = response.headers_with_name(&header.name.as_str()).next()
-                    .ok_or_else(|| format!("Missing required header: {}", header.name.as_str()))?;
-                response_context_headers.push((header.name.as_str().to_string(), String::from_utf8_lossy(header.value.as_bytes()).to_string()));
-            }
+        for (index, response) in transcript.responses.iter().enumerate() {
+            let response_context_headers = response.headers.iter()
+                .filter_map(|h| HeaderField::from_header(h, received_authed))
+                .collect();
 
             let response_body_context = if let Some(body) = &response.body {
                 match &body.content {
-                    BodyContent::Json(json) => {
-                        Some(BodyContext::Json(JsonContext::builder(json.clone()).build()?))
-                    }
+                    BodyContent::Json(json) => Some(BodyContext::Json(
+                        JsonContext::builder(json.clone()).authed(received_authed.clone()).build()?,
+                    )),
                     BodyContent::Unknown(unknown) => {
-                        Some(BodyContext::Unknown(unknown.clone().to_bytes()))
+                        Some(BodyContext::unknown(unknown, received_authed))
                     }
                     _ => None,
                 }
@@ -95,71 +127,796 @@ impl HttpContextBuilder {
                 None
             };
 
-            response_contexts.push(ResponseContext {
-                status: StatusCode::from_str(response.status.code.as_str()).unwrap(),
+            response_contexts.push((response.to_range_set(), ResponseContext {
+                status: Redactable::new(&response.status.code.to_range_set(), received_authed, || {
+                    StatusCode::from_str(response.status.code.as_str())
+                })
+                .transpose()
+                .map_err(|_| HttpContextError::InvalidStatus {
+                    index,
+                    code: response.status.code.as_str().to_string(),
+                })?,
                 headers: response_context_headers,
                 body: response_body_context,
-            });
+                predicates: Vec::new(),
+            }));
+        }
+
+        let (exchanges, unpaired) = pair_exchanges(request_contexts, response_contexts);
+        if !unpaired.is_empty() {
+            return Err(HttpContextError::UnpairedResponses(unpaired.len()));
         }
 
         Ok(HttpContext {
-            requests: request_contexts,
-            responses: response_contexts,
+            version: CONTEXT_VERSION,
+            exchanges,
         })
     }
 }
 
+/// Error for [`HttpContextBuilder`].
+#[derive(Debug, thiserror::Error)]
+pub enum HttpContextError {
+    /// The transcript could not be parsed.
+    #[error("failed to parse transcript: {0}")]
+    Parse(#[from] spanner::ParseError),
+    /// The method of a request is not a valid HTTP method.
+    #[error("request {index} has an invalid method \"{method}\"")]
+    InvalidMethod {
+        /// The index of the request in the transcript.
+        index: usize,
+        /// The presented method.
+        method: String,
+    },
+    /// The status code of a response is not a valid HTTP status code.
+    #[error("response {index} has an invalid status code \"{code}\"")]
+    InvalidStatus {
+        /// The index of the response in the transcript.
+        index: usize,
+        /// The presented status code.
+        code: String,
+    },
+    /// A JSON body could not be decoded.
+    #[error("invalid JSON body: {0}")]
+    Json(#[from] JsonContextError),
+    /// Responses follow the final response to every request.
+    #[error("{0} responses without a matching request")]
+    UnpairedResponses(usize),
+}
+
+/// The context of an exchange: a request, any interim (1xx) responses and
+/// its final response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeContext {
+    pub(crate) request: RequestContext,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) interim: Vec<ResponseContext>,
+    pub(crate) response: Option<ResponseContext>,
+    pub(crate) sent: RangeSet<usize>,
+    pub(crate) received: RangeSet<usize>,
+}
+
+impl ExchangeContext {
+    /// Returns the request.
+    pub fn request(&self) -> &RequestContext {
+        &self.request
+    }
+
+    /// Returns the interim (1xx) responses, in order.
+    pub fn interim(&self) -> &[ResponseContext] {
+        &self.interim
+    }
+
+    /// Returns the final response, if the transcript contains one.
+    pub fn response(&self) -> Option<&ResponseContext> {
+        self.response.as_ref()
+    }
+
+    /// Returns the ranges of the sent transcript containing the request.
+    pub fn sent(&self) -> &RangeSet<usize> {
+        &self.sent
+    }
+
+    /// Returns the ranges of the received transcript containing the
+    /// responses.
+    pub fn received(&self) -> &RangeSet<usize> {
+        &self.received
+    }
+}
+
+/// Pairs requests with their responses, each given with its transcript ranges.
+///
+/// Each request is answered by any number of interim (1xx) responses
+/// followed by a final response, where `101 Switching Protocols` and any
+/// response with a hidden status are final. Returns the exchanges along
+/// with any responses left without a request.
+pub(crate) fn pair_exchanges(
+    requests: impl IntoIterator<Item = (RangeSet<usize>, RequestContext)>,
+    responses: impl IntoIterator<Item = (RangeSet<usize>, ResponseContext)>,
+) -> (Vec<ExchangeContext>, Vec<(RangeSet<usize>, ResponseContext)>) {
+    let mut responses = responses.into_iter();
+    let mut exchanges = Vec::new();
+
+    for (sent, request) in requests {
+        let mut interim = Vec::new();
+        let mut response = None;
+        let mut received = RangeSet::default();
+
+        for (ranges, context) in responses.by_ref() {
+            received = received.union(&ranges);
+            let is_interim = context.status.revealed().is_some_and(|status| {
+                status.is_informational() && *status != StatusCode::SWITCHING_PROTOCOLS
+            });
+            if is_interim {
+                interim.push(context);
+            } else {
+                response = Some(context);
+                break;
+            }
+        }
+
+        exchanges.push(ExchangeContext {
+            request,
+            interim,
+            response,
+            sent,
+            received,
+        });
+    }
+
+    (exchanges, responses.collect())
+}
+
 /// The context of a request.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RequestContext {
-    pub(crate) target: String,
-    #[serde(serialize_with = "serialize_method")]
-    pub(crate) method: Method,
-    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) target: Redactable<String>,
+    #[serde(serialize_with = "serialize_method", deserialize_with = "deserialize_method")]
+    pub(crate) method: Redactable<Method>,
+    pub(crate) headers: Headers,
     pub(crate) body: Option<BodyContext>,
+    /// Predicates satisfied by the message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) predicates: Vec<PredicateOutcome>,
+}
+
+impl RequestContext {
+    /// Returns the request target.
+    pub fn target(&self) -> &Redactable<String> {
+        &self.target
+    }
+
+    /// Returns the request method.
+    pub fn method(&self) -> &Redactable<Method> {
+        &self.method
+    }
+
+    /// Returns the headers.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Returns the value of the first header with the given name, ignoring
+    /// case, or `None` if there is no such header.
+    pub fn header(&self, name: &str) -> Option<&Redactable<String>> {
+        self.headers.get(name)
+    }
+
+    /// Returns the body, if any.
+    pub fn body(&self) -> Option<&BodyContext> {
+        self.body.as_ref()
+    }
+
+    /// Returns the predicates satisfied by the request.
+    pub fn predicates(&self) -> &[PredicateOutcome] {
+        &self.predicates
+    }
 }
 
 /// The context of a response.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResponseContext {
-    #[serde(serialize_with = "serialize_status_code")]
-    pub(crate) status: StatusCode,
-    pub(crate) headers: Vec<(String, String)>,
+    #[serde(
+        serialize_with = "serialize_status_code",
+        deserialize_with = "deserialize_status_code"
+    )]
+    pub(crate) status: Redactable<StatusCode>,
+    pub(crate) headers: Headers,
     pub(crate) body: Option<BodyContext>,
+    /// Predicates satisfied by the message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) predicates: Vec<PredicateOutcome>,
 }
 
+impl ResponseContext {
+    /// Returns the status code.
+    pub fn status(&self) -> &Redactable<StatusCode> {
+        &self.status
+    }
+
+    /// Returns the headers.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Returns the value of the first header with the given name, ignoring
+    /// case, or `None` if there is no such header.
+    pub fn header(&self, name: &str) -> Option<&Redactable<String>> {
+        self.headers.get(name)
+    }
+
+    /// Returns the body, if any.
+    pub fn body(&self) -> Option<&BodyContext> {
+        self.body.as_ref()
+    }
+
+    /// Returns the predicates satisfied by the response.
+    pub fn predicates(&self) -> &[PredicateOutcome] {
+        &self.predicates
+    }
+}
 
 /// The context of a body.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BodyContext {
     /// The body is JSON.
     Json(JsonContext),
-    /// The body is unknown.
-    #[serde(serialize_with = "serialize_bytes")]
-    Unknown(bytes::Bytes),
+    /// The body is unknown, given as runs of revealed and redacted bytes.
+    Unknown(Vec<Redactable<Vec<u8>>>),
+}
+
+impl BodyContext {
+    pub(crate) fn unknown(unknown: &spanner::Span, authed: &RangeSet<usize>) -> Self {
+        BodyContext::Unknown(segments(unknown.indices(), unknown.as_bytes(), authed))
+    }
+}
+
+/// A header field instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderField {
+    /// The field name, as presented.
+    pub name: String,
+    /// The field value.
+    pub value: Redactable<String>,
+}
+
+impl HeaderField {
+    /// Creates a new header field with a revealed value.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: Redactable::Revealed {
+                value: value.into(),
+            },
+        }
+    }
+
+    /// Creates a header field from a presented header, or `None` if its name
+    /// is not revealed.
+    pub(crate) fn from_header(header: &Header, authed: &RangeSet<usize>) -> Option<Self> {
+        if !header.name.to_range_set().is_subset(authed) {
+            return None;
+        }
+
+        Some(Self {
+            name: header.name.as_str().to_string(),
+            value: Redactable::new(&header.value.to_range_set(), authed, || {
+                String::from_utf8_lossy(header.value.as_bytes()).to_string()
+            }),
+        })
+    }
+}
+
+/// The header fields of a message, preserving every instance in order.
+///
+/// Field names are compared case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Headers(Vec<HeaderField>);
+
+impl Headers {
+    /// Returns the number of field instances.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the field instances, in order.
+    pub fn iter(&self) -> std::slice::Iter<'_, HeaderField> {
+        self.0.iter()
+    }
+
+    /// Returns the value of the first field with the given name.
+    pub fn get(&self, name: &str) -> Option<&Redactable<String>> {
+        self.0
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+            .map(|field| &field.value)
+    }
+
+    /// Returns the values of every field with the given name, in order.
+    pub fn get_all<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Redactable<String>> + 'a {
+        self.0
+            .iter()
+            .filter(move |field| field.name.eq_ignore_ascii_case(name))
+            .map(|field| &field.value)
+    }
+
+    /// Returns the combined value of the fields with the given name.
+    ///
+    /// Values are joined with `", "` as described in RFC 9110 section 5.3.
+    /// Returns `None` if there is no such field, if any of its values is not
+    /// revealed, or if the field is `Set-Cookie` and appears more than once,
+    /// as its values can not be combined.
+    pub fn combined(&self, name: &str) -> Option<String> {
+        let values = self
+            .get_all(name)
+            .map(Redactable::revealed)
+            .collect::<Option<Vec<_>>>()?;
+        match values.as_slice() {
+            [] => None,
+            [value] => Some(value.to_string()),
+            _ if is_uncombinable(name) => None,
+            values => Some(
+                values
+                    .iter()
+                    .map(|value| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+
+    /// Returns a merged view with one field per name, in order of first
+    /// appearance, using the name as first presented.
+    ///
+    /// `Set-Cookie` fields, and fields with a value which is not revealed,
+    /// are kept as separate instances.
+    pub fn merged(&self) -> Vec<HeaderField> {
+        let mut merged: Vec<HeaderField> = Vec::new();
+        for field in &self.0 {
+            let combinable = !is_uncombinable(&field.name)
+                && self.get_all(&field.name).all(Redactable::is_revealed);
+            if !combinable {
+                merged.push(field.clone());
+                continue;
+            }
+
+            let existing = merged
+                .iter_mut()
+                .find(|merged| merged.name.eq_ignore_ascii_case(&field.name));
+            match (existing, &field.value) {
+                (
+                    Some(HeaderField {
+                        value: Redactable::Revealed { value: merged },
+                        ..
+                    }),
+                    Redactable::Revealed { value },
+                ) => {
+                    merged.push_str(", ");
+                    merged.push_str(value);
+                }
+                _ => merged.push(field.clone()),
+            }
+        }
+        merged
+    }
+}
+
+impl FromIterator<HeaderField> for Headers {
+    fn from_iter<I: IntoIterator<Item = HeaderField>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a HeaderField;
+    type IntoIter = std::slice::Iter<'a, HeaderField>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Returns whether fields with the given name can not be combined into one.
+fn is_uncombinable(name: &str) -> bool {
+    name.eq_ignore_ascii_case("set-cookie")
+}
+
+fn deserialize_version<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let version = u32::deserialize(deserializer)?;
+    if version != CONTEXT_VERSION {
+        return Err(de::Error::custom(format!(
+            "unsupported context version {version}, expected {CONTEXT_VERSION}"
+        )));
+    }
+    Ok(version)
 }
 
 // Serialization function for http::Method
-fn serialize_method<S>(method: &Method, serializer: S) -> Result<S::Ok, S::Error>
+fn serialize_method<S>(method: &Redactable<Method>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    method.as_str().serialize(serializer)
+    method.clone().map(|method| method.to_string()).serialize(serializer)
 }
 
 // Serialization function for http::StatusCode
-fn serialize_status_code<S>(status: &StatusCode, serializer: S) -> Result<S::Ok, S::Error>
+fn serialize_status_code<S>(
+    status: &Redactable<StatusCode>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    status.as_u16().serialize(serializer)
+    status.clone().map(|status| status.as_u16()).serialize(serializer)
 }
 
-// Serialization function for bytes::Bytes
-fn serialize_bytes<S>(bytes: &bytes::Bytes, serializer: S) -> Result<S::Ok, S::Error>
+fn deserialize_method<'de, D>(deserializer: D) -> Result<Redactable<Method>, D::Error>
 where
-    S: Serializer,
+    D: Deserializer<'de>,
+{
+    Redactable::<String>::deserialize(deserializer)?
+        .map(|method| Method::from_str(&method))
+        .transpose()
+        .map_err(de::Error::custom)
+}
+
+fn deserialize_status_code<'de, D>(deserializer: D) -> Result<Redactable<StatusCode>, D::Error>
+where
+    D: Deserializer<'de>,
 {
-    bytes.as_ref().serialize(serializer)
+    Redactable::<u16>::deserialize(deserializer)?
+        .map(StatusCode::from_u16)
+        .transpose()
+        .map_err(de::Error::custom)
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use rangeset::{Difference, RangeSet};
+    use rstest::*;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        fixtures::http as fixtures,
+        http::{HttpEnforceError, HttpEnforcer, MismatchKind},
+        transcript::Transcript,
+    };
+
+    fn context(request: &[u8], response: &[u8]) -> HttpContext {
+        let transcript = Transcript::new(request, response);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+        let structure = HttpTranscript::parse(&transcript).unwrap();
+
+        HttpEnforcer::new(partial, structure).build().unwrap()
+    }
+
+    #[rstest]
+    #[case::json(fixtures::request::POST_JSON, fixtures::response::OK_JSON)]
+    #[case::text(fixtures::request::GET_WITH_HEADER, fixtures::response::OK_TEXT)]
+    fn test_context_roundtrip(#[case] request: &[u8], #[case] response: &[u8]) {
+        let context = context(request, response);
+
+        let json = serde_json::to_string(&context).unwrap();
+        let decoded: HttpContext = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, context);
+        assert_eq!(decoded.version(), CONTEXT_VERSION);
+    }
+
+    #[rstest]
+    fn test_context_accessors() {
+        let context = context(fixtures::request::POST_JSON, fixtures::response::OK_JSON);
+
+        let exchange = &context.exchanges()[0];
+        assert_eq!(exchange.sent(), &RangeSet::from(0..fixtures::request::POST_JSON.len()));
+        assert_eq!(
+            exchange.received(),
+            &RangeSet::from(0..fixtures::response::OK_JSON.len())
+        );
+
+        let request = exchange.request();
+        assert_eq!(request.method().revealed(), Some(&Method::POST));
+        assert_eq!(request.target().revealed().unwrap(), "/hello");
+        assert_eq!(
+            request.header("content-type").and_then(Redactable::revealed).unwrap(),
+            "application/json"
+        );
+        assert!(matches!(request.body(), Some(BodyContext::Json(_))));
+
+        let response = exchange.response().unwrap();
+        assert_eq!(response.status().revealed(), Some(&StatusCode::OK));
+        assert_eq!(response.header("Content-Length"), None);
+    }
+
+    const OK_REPEATED_HEADERS: &[u8] = b"\
+        HTTP/1.1 200 OK\r\n\
+        Set-Cookie: a=1\r\n\
+        Vary: Accept\r\n\
+        set-cookie: b=2\r\n\
+        Vary: Origin\r\n\
+        Content-Length: 0\r\n\r\n";
+
+    #[rstest]
+    fn test_context_repeated_headers() {
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, OK_REPEATED_HEADERS);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+
+        let built = HttpContext::builder(partial).build().unwrap();
+        let enforced = context(fixtures::request::GET_EMPTY, OK_REPEATED_HEADERS);
+
+        for context in [built, enforced] {
+            let headers = context.exchanges()[0].response().unwrap().headers();
+
+            let cookies = headers
+                .get_all("Set-Cookie")
+                .filter_map(Redactable::revealed)
+                .collect::<Vec<_>>();
+            assert_eq!(cookies, ["a=1", "b=2"]);
+            assert_eq!(headers.combined("vary").as_deref(), Some("Accept, Origin"));
+            assert_eq!(headers.combined("set-cookie"), None);
+            assert_eq!(headers.combined("Cookie"), None);
+        }
+    }
+
+    const CONTINUE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+    #[rstest]
+    fn test_context_exchanges() {
+        let sent = [fixtures::request::POST_JSON, fixtures::request::GET_EMPTY].concat();
+        let recv = [CONTINUE, fixtures::response::OK_EMPTY, fixtures::response::OK_TEXT].concat();
+
+        let transcript = Transcript::new(sent.as_slice(), recv.as_slice());
+        let partial = transcript.to_partial(
+            RangeSet::from(0..sent.len()),
+            RangeSet::from(0..recv.len()),
+        );
+        let built = HttpContext::builder(partial).build().unwrap();
+
+        let post = fixtures::request::POST_JSON.len();
+        let first = CONTINUE.len() + fixtures::response::OK_EMPTY.len();
+
+        for context in [built, context(&sent, &recv)] {
+            let [first_exchange, second_exchange] = context.exchanges() else {
+                panic!("expected two exchanges");
+            };
+
+            assert_eq!(first_exchange.request().method().revealed(), Some(&Method::POST));
+            assert_eq!(first_exchange.interim().len(), 1);
+            assert_eq!(
+                first_exchange.interim()[0].status().revealed(),
+                Some(&StatusCode::CONTINUE)
+            );
+            assert_eq!(
+                first_exchange.response().unwrap().status().revealed(),
+                Some(&StatusCode::OK)
+            );
+            assert_eq!(first_exchange.sent(), &RangeSet::from(0..post));
+            assert_eq!(first_exchange.received(), &RangeSet::from(0..first));
+
+            assert_eq!(second_exchange.request().method().revealed(), Some(&Method::GET));
+            assert!(second_exchange.interim().is_empty());
+            assert_eq!(second_exchange.sent(), &RangeSet::from(post..sent.len()));
+            assert_eq!(second_exchange.received(), &RangeSet::from(first..recv.len()));
+        }
+    }
+
+    #[rstest]
+    fn test_context_unpaired_response() {
+        let recv = [fixtures::response::OK_EMPTY, fixtures::response::OK_TEXT].concat();
+
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, recv.as_slice());
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..recv.len()),
+        );
+        assert!(matches!(
+            HttpContext::builder(partial.clone()).build(),
+            Err(HttpContextError::UnpairedResponses(1))
+        ));
+
+        let structure = HttpTranscript::parse(&transcript).unwrap();
+        let err = HttpEnforcer::new(partial, structure).build().unwrap_err();
+        let [mismatch] = err.mismatches() else {
+            panic!("expected a single mismatch");
+        };
+        assert_eq!(mismatch.index, 1);
+        assert_eq!(mismatch.detail, MismatchKind::UnpairedResponse);
+    }
+
+    #[rstest]
+    fn test_context_invalid_status() {
+        let recv = b"HTTP/1.1 099 Odd\r\nContent-Length: 0\r\n\r\n";
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, recv.as_slice());
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..recv.len()),
+        );
+
+        let err = HttpContext::builder(partial).build().unwrap_err();
+        assert!(matches!(
+            &err,
+            HttpContextError::InvalidStatus { index: 0, code } if code == "099"
+        ));
+        assert_eq!(err.to_string(), "response 0 has an invalid status code \"099\"");
+    }
+
+    #[rstest]
+    fn test_context_error_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+        assert_send_sync::<HttpContextError>();
+        assert_send_sync::<HttpEnforceError>();
+        assert_send_sync::<crate::json::JsonContextError>();
+    }
+
+    #[rstest]
+    fn test_context_redacted() {
+        let transcript = Transcript::new(fixtures::request::GET_WITH_HEADER, fixtures::response::OK_JSON);
+        let sent = transcript.sent();
+        let received = transcript.received();
+
+        // Hide the value of the request header and the "bar" string content.
+        let header = find(sent, b"developer.mozilla.org");
+        let bar = find(received, b"bar");
+        let partial = transcript.to_partial(
+            RangeSet::from(0..sent.len()).difference(&header),
+            RangeSet::from(0..received.len()).difference(&bar),
+        );
+
+        let built = HttpContext::builder(partial.clone()).build().unwrap();
+        let structure = HttpTranscript::parse_partial(&partial).unwrap();
+        let enforced = HttpEnforcer::new(partial, structure).build().unwrap();
+
+        for context in [built, enforced] {
+            let exchange = &context.exchanges()[0];
+            assert_eq!(
+                exchange.request().header("host"),
+                Some(&Redactable::Redacted { len: header.len() })
+            );
+            assert_eq!(exchange.request().header("Cookie"), None);
+
+            let Some(BodyContext::Json(json)) = exchange.response().unwrap().body() else {
+                panic!("expected a JSON body");
+            };
+            let serialized = serde_json::to_value(json).unwrap();
+            assert_eq!(
+                serialized["entries"][0]["value"],
+                serde_json::json!({"type": "value", "state": "structure_only", "len": 5})
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_context_redacted_method() {
+        let transcript =
+            Transcript::new(fixtures::request::GET_EMPTY, fixtures::response::OK_EMPTY);
+        let partial = transcript.to_partial(
+            RangeSet::from(3..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+
+        let context = HttpContext::builder(partial).build().unwrap();
+
+        let exchange = &context.exchanges()[0];
+        assert_eq!(exchange.request().method(), &Redactable::Redacted { len: 3 });
+
+        let json = serde_json::to_value(&context).unwrap();
+        assert_eq!(
+            json["exchanges"][0]["request"]["method"],
+            serde_json::json!({"state": "redacted", "len": 3})
+        );
+        let decoded: HttpContext = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, context);
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> RangeSet<usize> {
+        let start = haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap();
+        RangeSet::from(start..start + needle.len())
+    }
+
+    #[rstest]
+    fn test_headers_merged() {
+        let headers = [
+            HeaderField::new("Set-Cookie", "a=1"),
+            HeaderField::new("Vary", "Accept"),
+            HeaderField::new("set-cookie", "b=2"),
+            HeaderField::new("vary", "Origin"),
+            HeaderField::new("Host", "localhost"),
+        ]
+        .into_iter()
+        .collect::<Headers>();
+
+        assert_eq!(
+            headers.merged(),
+            [
+                HeaderField::new("Set-Cookie", "a=1"),
+                HeaderField::new("Vary", "Accept, Origin"),
+                HeaderField::new("set-cookie", "b=2"),
+                HeaderField::new("Host", "localhost"),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_context_unsupported_version() {
+        let context = context(fixtures::request::GET_EMPTY, fixtures::response::OK_EMPTY);
+
+        let mut json = serde_json::to_value(&context).unwrap();
+        json["version"] = Value::from(CONTEXT_VERSION + 1);
+
+        let err = serde_json::from_value::<HttpContext>(json).unwrap_err();
+        assert!(err.to_string().contains("unsupported context version"));
+    }
+
+    #[rstest]
+    fn test_context_schema() {
+        let schema: Value = serde_json::from_str(CONTEXT_SCHEMA).unwrap();
+        assert_eq!(schema["properties"]["version"]["const"], CONTEXT_VERSION);
+
+        let context = context(fixtures::request::POST_JSON, fixtures::response::OK_TEXT);
+        let json = serde_json::to_value(&context).unwrap();
+
+        let properties = |value: &Value, schema: &Value| {
+            for key in value.as_object().unwrap().keys() {
+                assert!(schema["properties"].get(key).is_some(), "{key} is not in the schema");
+            }
+        };
+        let exchange = &json["exchanges"][0];
+        properties(&json, &schema);
+        properties(exchange, &schema["$defs"]["exchange"]);
+        properties(&exchange["request"], &schema["$defs"]["request"]);
+        properties(&exchange["response"], &schema["$defs"]["response"]);
+        properties(&exchange["sent"][0], &schema["$defs"]["ranges"]["items"]);
+
+        // Each body is tagged with the key its schema variant requires.
+        let body_tags = schema["$defs"]["body"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|variant| variant["required"][0].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(body_tags, ["json", "unknown"]);
+
+        let tag = |body: &Value| body.as_object().unwrap().keys().next().unwrap().clone();
+        assert_eq!(tag(&exchange["request"]["body"]), "json");
+        assert_eq!(tag(&exchange["response"]["body"]), "unknown");
+        assert_eq!(exchange["response"]["body"]["unknown"][0]["state"], "revealed");
+
+        // Method and status carry their disclosure state.
+        assert_eq!(
+            schema["$defs"]["request"]["properties"]["method"]["$ref"],
+            "#/$defs/redactable_string"
+        );
+        assert_eq!(
+            schema["$defs"]["response"]["properties"]["status"]["$ref"],
+            "#/$defs/redactable_status"
+        );
+        assert_eq!(exchange["request"]["method"]["value"], "POST");
+        assert_eq!(exchange["response"]["status"]["value"], 200);
+    }
+}
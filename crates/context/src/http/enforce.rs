@@ -1,190 +1,1169 @@
-use crate::{http::{Body, BodyContent, HttpTranscript, Request}, json::JsonContextEnforcer, transcript::PartialTranscript};
-use crate::json::JsonContextEnforcer;
-use crate::http::context::BodyContext;
-use crate::http::context::RequestContext;
-use crate::http::context::ResponseContext;
-use crate::http::HttpContext;
+use std::{fmt, str::FromStr};
 
 use http::{Method, StatusCode};
-use std::str::FromStr;
+use rangeset::{RangeSet, Subset, ToRangeSet};
+use spanner::Spanned;
+
+use crate::{
+    http::{
+        context::{
+            pair_exchanges, BodyContext, HeaderField, Headers, RequestContext, ResponseContext,
+            CONTEXT_VERSION,
+        },
+        template::{BodyTemplate, HeaderTemplate, HttpTemplate, TemplateError},
+        transcript::MessageKind,
+        Body, BodyContent, Header, HttpContext, HttpTranscript, Request,
+    },
+    json::{
+        DefaultJsonContextEnforcer, JsonContext, JsonContextEnforcer, JsonContextError,
+        JsonEnforceError, JsonEnforceErrorKind, JsonPointer, JsonValue,
+    },
+    context::Redactable,
+    matcher::{Captures, Matcher, MatcherError, Predicate, PredicateOutcome},
+    transcript::PartialTranscript,
+};
 
 /// Enforcer for [`HttpContext`].
+///
+/// Checks that a presented transcript matches an expected structure.
 pub struct HttpEnforcer {
     transcript: PartialTranscript,
-    structure: HttpTranscript,
+    structure: HttpTemplate,
+    json_enforcer: Box<dyn JsonContextEnforcer>,
 }
 
 impl HttpEnforcer {
-    /// Creates a new enforcer.
-    pub fn new(
-        transcript: PartialTranscript,
-        structure: HttpTranscript,
-    ) -> Self {
-        Self { transcript, structure }
+    /// Creates a new enforcer from a parsed transcript.
+    ///
+    /// Redacted values in the structure match any value, and the
+    /// `Content-Length` header is not enforced.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The presented transcript.
+    /// * `structure` - The expected structure.
+    pub fn new(transcript: PartialTranscript, structure: HttpTranscript) -> Self {
+        Self::from_template(transcript, HttpTemplate::from(&structure))
     }
 
-    /// Enforces the body of an HTTP request or response.
-    fn enforce_body(&self, structure_body: &Body, request_body: &Body) -> Result<BodyContext, Box<dyn std::error::Error>> {
-        match (&structure_body.content, &request_body.content) {
-            (BodyContent::Json(structure_json), BodyContent::Json(request_json)) => {
-                Ok(BodyContext::Json(JsonContext::builder(request_json.clone()).build()?))
-            }
-
-            (BodyContent::Unknown(structure_unknown), BodyContent::Unknown(request_unknown)) => {
-                assert_eq!(structure_unknown.as_bytes(), request_unknown.as_bytes(), "Body content mismatch");
-                Ok(BodyContext::Unknown(request_unknown.clone().to_bytes()))
-            }
-
-            _ => {
-                return Err("Body type mismatch".into());
-            }
+    /// Creates a new enforcer from a template.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The presented transcript.
+    /// * `template` - The expected structure.
+    pub fn from_template(transcript: PartialTranscript, template: HttpTemplate) -> Self {
+        Self {
+            transcript,
+            structure: template,
+            json_enforcer: Box::new(DefaultJsonContextEnforcer::default()),
         }
     }
 
-    // Enforces the request target.
-    // The request target must match the structure target.
-    // If the structure target starts with "/", the request target may be an absolute URL or a relative URL
-    // If the structure target does not start with "/", the request target must be a full URL that matches the structure target.
-    fn enforce_request_target(&self, request: &Request, structure_request: &Request) -> Result<(), Box<dyn std::error::Error>> {
-        let structure_target = structure_request.request.target.as_str();
-        let request_target = request.request.target.as_str();
+    /// Sets the enforcer used for JSON bodies.
+    pub fn json_enforcer(mut self, enforcer: impl JsonContextEnforcer + 'static) -> Self {
+        self.json_enforcer = Box::new(enforcer);
+        self
+    }
 
-        let base = url::Url::parse("https://example.com")?;
+    /// Builds the context.
+    ///
+    /// Returns an error listing every mismatch if the transcript does not
+    /// match the structure.
+    pub fn build(mut self) -> Result<HttpContext, HttpEnforceError> {
+        self.structure.validate()?;
 
-        let structure_url = base.join(structure_target)?;
-        let request_url = base.join(request_target)?;
+        let transcript = HttpTranscript::parse_partial(&self.transcript)?;
 
-        let structure_path_and_query = if let Some(query) = structure_url.query() {
-            format!("{}?{}", structure_url.path(), query)
-        } else {
-            structure_url.path().to_string()
+        let mut enforcer = Enforcer {
+            transcript: &self.transcript,
+            json_enforcer: self.json_enforcer.as_mut(),
+            mismatches: Vec::new(),
+            predicates: Vec::new(),
         };
+        let context = enforcer.enforce_structure(&self.structure, &transcript);
 
-        let request_path_and_query = if let Some(query) = request_url.query() {
-            format!("{}?{}", request_url.path(), query)
+        if enforcer.mismatches.is_empty() {
+            Ok(context)
         } else {
-            request_url.path().to_string()
+            Err(HttpEnforceError::Mismatch(enforcer.mismatches))
+        }
+    }
+}
+
+/// Error for [`HttpEnforcer`].
+#[derive(Debug, thiserror::Error)]
+pub enum HttpEnforceError {
+    /// The template is invalid.
+    #[error("invalid template: {0}")]
+    Template(#[from] TemplateError),
+    /// The presented transcript could not be parsed.
+    #[error("failed to parse presented transcript: {0}")]
+    Parse(#[from] spanner::ParseError),
+    /// The presented transcript does not match the structure.
+    #[error(
+        "presented transcript does not match structure ({} mismatches){}",
+        .0.len(),
+        .0.first().map(|mismatch| format!(": {mismatch}")).unwrap_or_default()
+    )]
+    Mismatch(Vec<Mismatch>),
+}
+
+impl HttpEnforceError {
+    /// Returns the mismatches, if any.
+    pub fn mismatches(&self) -> &[Mismatch] {
+        match self {
+            HttpEnforceError::Mismatch(mismatches) => mismatches,
+            _ => &[],
+        }
+    }
+}
+
+/// A mismatch between a presented message and the structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The kind of message.
+    pub kind: MessageKind,
+    /// The index of the message in the transcript.
+    pub index: usize,
+    /// The ranges of the presented data which do not match.
+    ///
+    /// Empty if the data is missing.
+    pub span: RangeSet<usize>,
+    /// What did not match.
+    pub detail: MismatchKind,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            MessageKind::Request => "request",
+            MessageKind::Response => "response",
         };
 
-        assert_eq!(request_path_and_query, structure_path_and_query, "Request target mismatch");
-        if !structure_target.starts_with("/") {
-            assert_eq!(request_url.host_str(), structure_url.host_str(), "Request target mismatch");
+        write!(f, "{kind} {}: {}", self.index, self.detail)?;
+
+        if !self.span.is_empty() {
+            write!(f, " at {:?}", self.span)?;
         }
 
         Ok(())
     }
+}
+
+/// The kind of [`Mismatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// The number of messages differs.
+    Count {
+        /// Expected number of messages.
+        expected: usize,
+        /// Presented number of messages.
+        actual: usize,
+    },
+    /// The request method differs.
+    Method {
+        /// Expected method.
+        expected: String,
+        /// Presented method.
+        actual: String,
+    },
+    /// The request target differs.
+    Target {
+        /// Expected target.
+        expected: String,
+        /// Presented target.
+        actual: String,
+    },
+    /// A header is missing.
+    MissingHeader {
+        /// The header name.
+        name: String,
+    },
+    /// A header value differs.
+    Header {
+        /// The header name.
+        name: String,
+        /// Expected value.
+        expected: String,
+        /// Presented value.
+        actual: String,
+    },
+    /// The response status differs.
+    Status {
+        /// Expected status code.
+        expected: String,
+        /// Presented status code.
+        actual: String,
+    },
+    /// The body is missing.
+    MissingBody,
+    /// The body type differs.
+    BodyType {
+        /// Expected body type.
+        expected: &'static str,
+        /// Presented body type.
+        actual: &'static str,
+    },
+    /// The body content differs.
+    Body,
+    /// A value within a JSON body does not match.
+    Json(JsonEnforceError),
+    /// A revealed value within a JSON body cannot be decoded.
+    InvalidJson(JsonContextError),
+    /// A response follows the final response to every request.
+    UnpairedResponse,
+    /// A predicate applies to data which is not authenticated.
+    Unauthenticated {
+        /// Where the predicate applies, e.g. `header Host`.
+        location: String,
+    },
+}
+
+impl fmt::Display for MismatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MismatchKind::Count { expected, actual } => {
+                write!(f, "expected {expected} messages, got {actual}")
+            }
+            MismatchKind::Method { expected, actual } => {
+                write!(f, "expected method {expected}, got {actual}")
+            }
+            MismatchKind::Target { expected, actual } => {
+                write!(f, "expected target \"{expected}\", got \"{actual}\"")
+            }
+            MismatchKind::MissingHeader { name } => write!(f, "missing header \"{name}\""),
+            MismatchKind::Header {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "expected header \"{name}\" to be \"{expected}\", got \"{actual}\""
+            ),
+            MismatchKind::Status { expected, actual } => {
+                write!(f, "expected status {expected}, got {actual}")
+            }
+            MismatchKind::MissingBody => write!(f, "missing body"),
+            MismatchKind::BodyType { expected, actual } => {
+                write!(f, "expected {expected} body, got {actual}")
+            }
+            MismatchKind::Body => write!(f, "body content differs"),
+            MismatchKind::Json(err) => err.fmt(f),
+            MismatchKind::InvalidJson(err) => err.fmt(f),
+            MismatchKind::UnpairedResponse => write!(f, "response without a matching request"),
+            MismatchKind::Unauthenticated { location } => {
+                write!(f, "predicate on unauthenticated {location}")
+            }
+        }
+    }
+}
+
+fn body_type(content: &BodyContent) -> &'static str {
+    match content {
+        BodyContent::Json(_) => "json",
+        _ => "unknown",
+    }
+}
+
+/// Returns the deepest value in `value` along `path`.
+fn resolve<'a>(value: &'a JsonValue, path: &JsonPointer) -> &'a JsonValue {
+    let mut value = value;
+    for token in path.tokens() {
+        match child(value, token) {
+            Some(child) => value = child,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Returns the value in `value` at `path`, if present.
+fn lookup<'a>(value: &'a JsonValue, path: &JsonPointer) -> Option<&'a JsonValue> {
+    path.tokens()
+        .iter()
+        .try_fold(value, |value, token| child(value, token))
+}
+
+fn child<'a>(value: &'a JsonValue, token: &str) -> Option<&'a JsonValue> {
+    match value {
+        JsonValue::Object(object) => object
+            .elems
+            .iter()
+            .find(|kv| kv.key.span().as_str() == token)
+            .map(|kv| &kv.value),
+        JsonValue::Array(array) => token.parse::<usize>().ok().and_then(|i| array.elems.get(i)),
+        _ => None,
+    }
+}
+
+/// Collects mismatches between a transcript and its structure.
+struct Enforcer<'a> {
+    transcript: &'a PartialTranscript,
+    json_enforcer: &'a mut dyn JsonContextEnforcer,
+    mismatches: Vec<Mismatch>,
+    /// Predicates satisfied by the current message.
+    predicates: Vec<PredicateOutcome>,
+}
+
+impl Enforcer<'_> {
+    /// Returns the authenticated ranges in the direction of `kind`.
+    fn authed(&self, kind: MessageKind) -> &RangeSet<usize> {
+        match kind {
+            MessageKind::Request => self.transcript.sent_authed(),
+            MessageKind::Response => self.transcript.received_authed(),
+        }
+    }
+
+    fn mismatch(
+        &mut self,
+        kind: MessageKind,
+        index: usize,
+        span: RangeSet<usize>,
+        detail: MismatchKind,
+    ) {
+        self.mismatches.push(Mismatch {
+            kind,
+            index,
+            span,
+            detail,
+        });
+    }
+
+    /// Returns whether `span` is authenticated, recording a mismatch if not.
+    ///
+    /// Predicates must only be evaluated on authenticated data, as anything
+    /// else is a placeholder chosen by the Prover.
+    fn check_authed(
+        &mut self,
+        kind: MessageKind,
+        index: usize,
+        span: &RangeSet<usize>,
+        location: &str,
+    ) -> bool {
+        if span.is_subset(self.authed(kind)) {
+            return true;
+        }
+
+        self.mismatch(
+            kind,
+            index,
+            span.clone(),
+            MismatchKind::Unauthenticated {
+                location: location.to_string(),
+            },
+        );
+        false
+    }
+
+    /// Records the outcome of a predicate, returning whether it was satisfied.
+    ///
+    /// Invalid predicates are not satisfied.
+    fn record(
+        &mut self,
+        location: impl Into<String>,
+        predicate: &Predicate,
+        outcome: Result<Option<Captures>, MatcherError>,
+    ) -> bool {
+        match outcome {
+            Ok(Some(captures)) => {
+                self.predicates.push(PredicateOutcome {
+                    location: location.into(),
+                    predicate: predicate.clone(),
+                    captures,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
 
     // Enforces the structure of the transcript.
     // The transcript must have the same number of requests and responses as the structure.
-    // The request method and target must match.
-    // The request and response headers exist if present, and must match values if specified.
+    // The request method and target must match if specified.
+    // The request and response headers must exist, and must match values if specified.
     // The response status code must match if specified.
     // If the request or response body is JSON, the body must be valid JSON, and the body must match the structure.
-    fn enforce_structure(&self, transcript: &HttpTranscript) -> Result<HttpContext, Box<dyn std::error::Error>> {
-        assert_eq!(transcript.requests.len(), self.structure.requests.len(), "Request count mismatch");
-        assert_eq!(transcript.responses.len(), self.structure.responses.len(), "Response count mismatch");
-
-        let mut request_contexts: Vec<RequestContext> = Vec::new();
-        let mut response_contexts: Vec<ResponseContext> = Vec::new();
-
-        for (structure_request, request) in self.structure.requests.iter().zip(transcript.requests.iter()) {
-            assert_eq!(request.request.method, structure_request.request.method, "Request method mismatch");
-            
-            self.enforce_request_target(request, structure_request)?;
-
-            let mut request_context_headers = Vec::new();
-
-            let structure_headers = structure_request.headers.iter()
-                .filter(|h| !["content-length"].contains(&h.name.as_str().to_lowercase().as_str()));
-
-            for structure_header in structure_headers {
-                let header = request.headers_with_name(&structure_header.name.as_str()).next()
-                    .ok_or_else(|| format!("Missing required header: {}", structure_header.name.as_str()))?;
-                let header_name = header.name.as_str();
-                let header_value = header.value.as_bytes();
-
-                if structure_header.value.as_bytes().iter().any(|&b| b != b'*') {
-                    assert_eq!(
-                        header_value,
-                        structure_header.value.as_bytes(),
-                        "Header value mismatch: {}",
-                        header_name,
-                    );
-
-                    request_context_headers.push((header_name.to_string(), String::from_utf8_lossy(header_value).to_string()));
+    fn enforce_structure(
+        &mut self,
+        structure: &HttpTemplate,
+        transcript: &HttpTranscript,
+    ) -> HttpContext {
+        for (kind, expected, actual) in [
+            (
+                MessageKind::Request,
+                structure.requests.len(),
+                transcript.requests.len(),
+            ),
+            (
+                MessageKind::Response,
+                structure.responses.len(),
+                transcript.responses.len(),
+            ),
+        ] {
+            if expected != actual {
+                self.mismatch(
+                    kind,
+                    expected.min(actual),
+                    RangeSet::default(),
+                    MismatchKind::Count { expected, actual },
+                );
+            }
+        }
+
+        let mut requests = Vec::new();
+        let mut responses = Vec::new();
+
+        for (index, (structure_request, request)) in structure
+            .requests
+            .iter()
+            .zip(transcript.requests.iter())
+            .enumerate()
+        {
+            let kind = MessageKind::Request;
+
+            let method = request.request.method.as_str();
+            let span = request.request.method.to_range_set();
+            let parsed_method =
+                Redactable::new(&span, self.authed(kind), || Method::from_str(method))
+                    .transpose()
+                    .ok();
+            let expected = structure_request.method.as_deref();
+            let authed = expected.is_none() || self.check_authed(kind, index, &span, "method");
+            if authed
+                && (parsed_method.is_none() || expected.is_some_and(|expected| expected != method))
+            {
+                self.mismatch(
+                    kind,
+                    index,
+                    span,
+                    MismatchKind::Method {
+                        expected: expected.unwrap_or("any").to_string(),
+                        actual: method.to_string(),
+                    },
+                );
+            }
+
+            match &structure_request.target {
+                Some(Matcher::Exact(target)) => self.enforce_request_target(index, target, request),
+                Some(Matcher::Predicate(predicate)) => {
+                    let target = request.request.target.as_str();
+                    let span = request.request.target.to_range_set();
+                    if self.check_authed(kind, index, &span, "target") {
+                        let outcome = predicate.evaluate(target);
+                        if !self.record("target", predicate, outcome) {
+                            self.mismatch(
+                                kind,
+                                index,
+                                span,
+                                MismatchKind::Target {
+                                    expected: predicate.to_string(),
+                                    actual: target.to_string(),
+                                },
+                            );
+                        }
+                    }
                 }
+                None => {}
+            }
+
+            let headers =
+                self.enforce_headers(kind, index, &structure_request.headers, &request.headers);
+
+            let body = self.enforce_body(
+                kind,
+                index,
+                structure_request.body.as_ref(),
+                request.body.as_ref(),
+            );
+
+            if let Some(method) = parsed_method {
+                requests.push((request.to_range_set(), RequestContext {
+                    target: Redactable::new(
+                        &request.request.target.to_range_set(),
+                        self.authed(kind),
+                        || request.request.target.as_str().to_string(),
+                    ),
+                    method,
+                    headers,
+                    body,
+                    predicates: std::mem::take(&mut self.predicates),
+                }));
+            }
+        }
+
+        for (index, (structure_response, response)) in structure
+            .responses
+            .iter()
+            .zip(transcript.responses.iter())
+            .enumerate()
+        {
+            let kind = MessageKind::Response;
+
+            let code = response.status.code.as_str();
+            let span = response.status.code.to_range_set();
+            let status = Redactable::new(&span, self.authed(kind), || StatusCode::from_str(code))
+                .transpose()
+                .ok();
+            let expected = structure_response.status;
+            let authed = expected.is_none() || self.check_authed(kind, index, &span, "status");
+            if authed
+                && (status.is_none()
+                    || expected.is_some_and(|expected| code.parse::<u16>() != Ok(expected)))
+            {
+                self.mismatch(
+                    kind,
+                    index,
+                    span,
+                    MismatchKind::Status {
+                        expected: expected.map_or("any".to_string(), |code| code.to_string()),
+                        actual: code.to_string(),
+                    },
+                );
+            }
+
+            let headers =
+                self.enforce_headers(kind, index, &structure_response.headers, &response.headers);
+
+            let body = self.enforce_body(
+                kind,
+                index,
+                structure_response.body.as_ref(),
+                response.body.as_ref(),
+            );
+
+            if let Some(status) = status {
+                responses.push((response.to_range_set(), ResponseContext {
+                    status,
+                    headers,
+                    body,
+                    predicates: std::mem::take(&mut self.predicates),
+                }));
+            }
+        }
+
+        let (exchanges, unpaired) = pair_exchanges(requests, responses);
+        let offset = transcript.responses.len().saturating_sub(unpaired.len());
+        for (index, (ranges, _)) in unpaired.into_iter().enumerate() {
+            self.mismatch(
+                MessageKind::Response,
+                offset + index,
+                ranges,
+                MismatchKind::UnpairedResponse,
+            );
+        }
+
+        HttpContext {
+            version: CONTEXT_VERSION,
+            exchanges,
+        }
+    }
+
+    // Enforces the request target.
+    // The request target must match the structure target.
+    // If the structure target starts with "/", the request target may be an absolute URL or a relative URL
+    // If the structure target does not start with "/", the request target must be a full URL that matches the structure target.
+    fn enforce_request_target(&mut self, index: usize, structure_target: &str, request: &Request) {
+        let request_target = request.request.target.as_str();
+
+        let base = url::Url::parse("https://example.com").expect("base url is valid");
+
+        let matches = match (base.join(structure_target), base.join(request_target)) {
+            (Ok(structure_url), Ok(request_url)) => {
+                structure_url.path() == request_url.path()
+                    && structure_url.query() == request_url.query()
+                    && (structure_target.starts_with('/')
+                        || structure_url.host_str() == request_url.host_str())
             }
+            _ => false,
+        };
+
+        if !matches {
+            self.mismatch(
+                MessageKind::Request,
+                index,
+                request.request.target.to_range_set(),
+                MismatchKind::Target {
+                    expected: structure_target.to_string(),
+                    actual: request_target.to_string(),
+                },
+            );
+        }
+    }
 
-            let request_body_context = if let Some(structure_body) = &structure_request.body {
-                if let Some(request_body) = &request.body {
-                    Some(self.enforce_body(structure_body, request_body)?)
-                } else {
-                    return Err("Request body missing".into());
+    /// Enforces that each header in the structure is present, and matches its
+    /// value if specified. Returns the enforced headers.
+    fn enforce_headers(
+        &mut self,
+        kind: MessageKind,
+        index: usize,
+        structure: &[HeaderTemplate],
+        presented: &[Header],
+    ) -> Headers {
+        let mut headers = Vec::new();
+        // Number of instances of each name seen so far, so that repeated
+        // structure headers match repeated presented headers in order.
+        let mut seen: Vec<(&str, usize)> = Vec::new();
+
+        for structure_header in structure {
+            let name = structure_header.name.as_str();
+
+            let instance = match seen.iter_mut().find(|(seen, _)| seen.eq_ignore_ascii_case(name)) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count - 1
+                }
+                None => {
+                    seen.push((name, 1));
+                    0
                 }
-            } else {
-                None
             };
 
-            request_contexts.push(RequestContext {
-                target: request.request.target.as_str().to_string(),
-                method: Method::from_str(request.request.method.as_str()).unwrap(),
-                headers: request_context_headers,
-                body: request_body_context,
-            });
+            let Some(header) = presented
+                .iter()
+                .filter(|header| header.name.as_str().eq_ignore_ascii_case(name))
+                .nth(instance)
+            else {
+                self.mismatch(
+                    kind,
+                    index,
+                    RangeSet::default(),
+                    MismatchKind::MissingHeader {
+                        name: name.to_string(),
+                    },
+                );
+                continue;
+            };
+
+            // Headers without an expected value are reported with whatever
+            // of them is revealed.
+            let Some(expected) = &structure_header.value else {
+                headers.extend(HeaderField::from_header(header, self.authed(kind)));
+                continue;
+            };
+            let actual = String::from_utf8_lossy(header.value.as_bytes());
+
+            let matches = match expected {
+                Matcher::Exact(expected) => expected.as_str() == actual,
+                Matcher::Predicate(predicate) => {
+                    let location = format!("header {name}");
+                    let span = header.value.span().to_range_set();
+                    if !self.check_authed(kind, index, &span, &location) {
+                        continue;
+                    }
+
+                    let outcome = predicate.evaluate(&actual);
+                    self.record(location, predicate, outcome)
+                }
+            };
+
+            if !matches {
+                let expected = match expected {
+                    Matcher::Exact(expected) => expected.clone(),
+                    Matcher::Predicate(predicate) => predicate.to_string(),
+                };
+
+                self.mismatch(
+                    kind,
+                    index,
+                    header.value.span().to_range_set(),
+                    MismatchKind::Header {
+                        name: name.to_string(),
+                        expected,
+                        actual: actual.to_string(),
+                    },
+                );
+                continue;
+            }
+
+            headers.extend(HeaderField::from_header(header, self.authed(kind)));
         }
 
-        for (structure_response, response) in self.structure.responses.iter().zip(transcript.responses.iter()) {
-            assert_eq!(response.status, structure_response.status, "Response status mismatch");
+        headers.into_iter().collect()
+    }
+
+    /// Enforces the body of an HTTP request or response.
+    fn enforce_body(
+        &mut self,
+        kind: MessageKind,
+        index: usize,
+        structure: Option<&BodyTemplate>,
+        body: Option<&Body>,
+    ) -> Option<BodyContext> {
+        let structure = structure?;
 
-            let mut response_context_headers = Vec::new();
+        let Some(body) = body else {
+            self.mismatch(kind, index, RangeSet::default(), MismatchKind::MissingBody);
+            return None;
+        };
 
-            let structure_headers = structure_response.headers.iter()
-                .filter(|h| !["content-length"].contains(&h.name.as_str().to_lowercase().as_str()));
+        match (structure, &body.content) {
+            (
+                BodyTemplate::Json {
+                    shape,
+                    array_strategy,
+                },
+                BodyContent::Json(json),
+            ) => {
+                // The template is validated before enforcement.
+                let structure_json = shape.to_json_value().ok()?;
 
-            for structure_header in structure_headers {
-                let header = response.headers_with_name(&structure_header.name.as_str()).next()
-                    .ok_or_else(|| format!("Missing required header: {}", structure_header.name.as_str()))?;
-                let header_name = header.name.as_str();
-                let header_value = header.value.as_bytes();
+                let result = match array_strategy {
+                    Some(strategy) => {
+                        DefaultJsonContextEnforcer::new(*strategy).enforce(&structure_json, json)
+                    }
+                    None => self.json_enforcer.enforce(&structure_json, json),
+                };
 
-                if structure_header.value.as_bytes().iter().any(|&b| b != b'*') {
-                    assert_eq!(
-                        header_value,
-                        structure_header.value.as_bytes(),
-                        "Header value mismatch: {}",
-                        header_name
-                    );
-                    response_context_headers.push((header_name.to_string(), String::from_utf8_lossy(header_value).to_string()));
+                if let Err(err) = result {
+                    let span = resolve(json, err.path()).to_range_set();
+                    self.mismatch(kind, index, span, MismatchKind::Json(err));
+                    return None;
                 }
-            }
 
-            let response_body_context = if let Some(structure_body) = &structure_response.body {
-                if let Some(response_body) = &response.body {
-                    Some(self.enforce_body(structure_body, response_body)?)
-                } else {
-                    return Err("Response body missing".into());
+                for (path, predicate) in shape.predicates() {
+                    let value = resolve(json, &path);
+                    let outcome = match lookup(json, &path) {
+                        Some(value) => {
+                            let span = value.to_range_set();
+                            if !self.check_authed(kind, index, &span, &path.to_string()) {
+                                return None;
+                            }
+
+                            predicate.evaluate_json(value)
+                        }
+                        None => Ok(None),
+                    };
+
+                    if !self.record(path.to_string(), predicate, outcome) {
+                        let err = JsonEnforceError::new(
+                            path,
+                            JsonEnforceErrorKind::Predicate {
+                                predicate: predicate.to_string(),
+                                actual: value.span().as_str().to_string(),
+                            },
+                            match predicate {
+                                Predicate::Type(kind) => *kind,
+                                _ => value.into(),
+                            },
+                            Some(value.into()),
+                        );
+                        self.mismatch(kind, index, value.to_range_set(), MismatchKind::Json(err));
+                        return None;
+                    }
+                }
+
+                match JsonContext::builder(json.clone())
+                    .authed(self.authed(kind).clone())
+                    .build()
+                {
+                    Ok(context) => Some(BodyContext::Json(context)),
+                    Err(err) => {
+                        self.mismatch(
+                            kind,
+                            index,
+                            body.to_range_set(),
+                            MismatchKind::InvalidJson(err),
+                        );
+                        None
+                    }
                 }
-            } else {
+            }
+            (BodyTemplate::Text { content }, BodyContent::Unknown(unknown)) => {
+                if content
+                    .as_ref()
+                    .is_some_and(|content| content.as_bytes() != unknown.as_bytes())
+                {
+                    self.mismatch(kind, index, body.to_range_set(), MismatchKind::Body);
+                    return None;
+                }
+
+                Some(BodyContext::unknown(unknown, self.authed(kind)))
+            }
+            (structure, content) => {
+                self.mismatch(
+                    kind,
+                    index,
+                    body.to_range_set(),
+                    MismatchKind::BodyType {
+                        expected: match structure {
+                            BodyTemplate::Json { .. } => "json",
+                            BodyTemplate::Text { .. } => "unknown",
+                        },
+                        actual: body_type(content),
+                    },
+                );
                 None
-            };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rangeset::Difference;
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        fixtures::http as fixtures,
+        http::{DisclosurePolicy, RequestTemplate, ResponseTemplate},
+        transcript::Transcript,
+    };
+
+    const POST_JSON_MODIFIED: &[u8] = b"\
+        PUT /goodbye HTTP/1.1\r\n\
+        Host: localhost\r\n\
+        Content-Length: 38\r\n\r\n\
+        {\"foo\": \"baz\", \"bazz\": 1, \"buzz\": [1]}";
+
+    const OK_TEXT_MODIFIED: &[u8] = b"\
+        HTTP/1.1 404 Not Found\r\n\
+        Content-Type: text/plain\r\n\
+        Content-Length: 14\r\n\r\n\
+        Hello, world?\n";
+
+    fn full(transcript: &Transcript) -> PartialTranscript {
+        transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        )
+    }
+
+    fn structure(sent: &[u8], recv: &[u8]) -> HttpTranscript {
+        HttpTranscript::parse(&Transcript::new(sent, recv)).unwrap()
+    }
+
+    #[rstest]
+    #[case::get_empty(fixtures::request::GET_EMPTY, fixtures::response::OK_EMPTY)]
+    #[case::get_with_header(fixtures::request::GET_WITH_HEADER, fixtures::response::OK_TEXT)]
+    #[case::post_json(fixtures::request::POST_JSON, fixtures::response::OK_JSON)]
+    fn test_enforce_match(#[case] sent: &[u8], #[case] recv: &[u8]) {
+        let transcript = Transcript::new(sent, recv);
+
+        let context = HttpEnforcer::new(full(&transcript), structure(sent, recv))
+            .build()
+            .unwrap();
+
+        assert_eq!(context.exchanges.len(), 1);
+        assert!(context.exchanges[0].response.is_some());
+    }
+
+    #[rstest]
+    fn test_enforce_redacted_structure() {
+        let transcript = Transcript::new(fixtures::request::POST_JSON, fixtures::response::OK_JSON);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        // Redact everything but the structure.
+        let compiled = DisclosurePolicy::default().compile(&http).unwrap();
+        let structure = HttpTranscript::parse_partial(&compiled.to_partial(&transcript)).unwrap();
 
-            response_contexts.push(ResponseContext {
-                status: StatusCode::from_str(response.status.code.as_str()).unwrap(),
-                headers: response_context_headers,
-                body: response_body_context,
+        HttpEnforcer::new(full(&transcript), structure)
+            .build()
+            .unwrap();
+    }
+
+    #[rstest]
+    fn test_enforce_template() {
+        let transcript = Transcript::new(fixtures::request::POST_JSON, fixtures::response::OK_JSON);
+
+        let template = HttpTemplate::builder()
+            .request(
+                RequestTemplate::new()
+                    .method("POST")
+                    .target("/hello")
+                    .header(HeaderTemplate::new("Content-Type").value("application/json"))
+                    .body(BodyTemplate::json(
+                        serde_json::from_str(r#"{"foo": "*", "buzz": [1, "*"]}"#).unwrap(),
+                    )),
+            )
+            .response(ResponseTemplate::new().status(200))
+            .build();
+
+        let context = HttpEnforcer::from_template(full(&transcript), template.clone())
+            .build()
+            .unwrap();
+        assert_eq!(context.exchanges[0].request.headers.len(), 1);
+
+        let mut template = template;
+        template.version += 1;
+        assert!(matches!(
+            HttpEnforcer::from_template(full(&transcript), template).build(),
+            Err(HttpEnforceError::Template(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_enforce_predicates() {
+        let transcript = Transcript::new(fixtures::request::POST_JSON, fixtures::response::OK_JSON);
+
+        let template = |minimum: f64| {
+            let shape = serde_json::json!({
+                "foo": "*",
+                "bazz": {"$match": {"number": {"gte": minimum}}},
+                "buzz": "*",
             });
-        }
 
-        Ok(HttpContext {
-            requests: request_contexts,
-            responses: response_contexts,
-        })
+            HttpTemplate::builder()
+                .request(
+                    RequestTemplate::new()
+                        .target(Predicate::Path("/{greeting}".to_string()))
+                        .header(
+                            HeaderTemplate::new("Content-Type")
+                                .value(Predicate::Prefix("application/".to_string())),
+                        )
+                        .body(BodyTemplate::json(shape.into())),
+                )
+                .response(ResponseTemplate::new())
+                .build()
+        };
+
+        let context = HttpEnforcer::from_template(full(&transcript), template(100.0))
+            .build()
+            .unwrap();
+
+        let predicates = &context.exchanges[0].request.predicates;
+        let locations = predicates
+            .iter()
+            .map(|outcome| outcome.location.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(locations, ["target", "header Content-Type", "/bazz"]);
+        assert_eq!(
+            predicates[0].captures,
+            [("greeting".to_string(), "hello".to_string())]
+        );
+        assert!(context.exchanges[0].response.as_ref().unwrap().predicates.is_empty());
+
+        let err = HttpEnforcer::from_template(full(&transcript), template(1000.0))
+            .build()
+            .unwrap_err();
+
+        let [mismatch] = err.mismatches() else {
+            panic!("expected a single mismatch");
+        };
+        let MismatchKind::Json(err) = &mismatch.detail else {
+            panic!("expected a JSON mismatch");
+        };
+        assert_eq!(err.path().to_string(), "/bazz");
+        assert!(matches!(
+            err.kind(),
+            JsonEnforceErrorKind::Predicate { actual, .. } if actual == "123"
+        ));
     }
 
-    /// Builds the context.
-    pub fn build(self) -> Result<HttpContext, Box<dyn std::error::Error>> {
-        let transcript = HttpTranscript::parse_partial(&self.transcript)?;
+    #[rstest]
+    #[case::target("/hello", "target")]
+    #[case::header("application/json", "header Content-Type")]
+    #[case::json("123", "/bazz")]
+    fn test_enforce_predicates_unauthenticated(#[case] hidden: &str, #[case] location: &str) {
+        let transcript = Transcript::new(fixtures::request::POST_JSON, fixtures::response::OK_JSON);
+
+        let sent = transcript.sent();
+        let start = sent
+            .windows(hidden.len())
+            .position(|window| window == hidden.as_bytes())
+            .unwrap();
+        let hidden = RangeSet::from(start..start + hidden.len());
+        let partial = transcript.to_partial(
+            RangeSet::from(0..sent.len()).difference(&hidden),
+            RangeSet::from(0..transcript.received().len()),
+        );
+
+        // The target and header predicates accept the placeholder bytes, so
+        // only the authentication check rejects them.
+        let shape = serde_json::json!({"bazz": {"$match": {"number": {"gte": 100}}}});
+        let template = HttpTemplate::builder()
+            .request(
+                RequestTemplate::new()
+                    .target(Predicate::Path("/{greeting}".to_string()))
+                    .header(
+                        HeaderTemplate::new("Content-Type")
+                            .value(Predicate::Regex("^[a-z/*]+$".to_string())),
+                    )
+                    .body(BodyTemplate::json(shape.into())),
+            )
+            .response(ResponseTemplate::new())
+            .build();
 
-        self.enforce_structure(&transcript)
+        let err = HttpEnforcer::from_template(partial, template)
+            .build()
+            .unwrap_err();
+
+        // Hiding the content type also hides the body type, so only the first
+        // mismatch is of interest.
+        let mismatch = &err.mismatches()[0];
+        assert_eq!(
+            mismatch.detail,
+            MismatchKind::Unauthenticated {
+                location: location.to_string()
+            }
+        );
+        assert_eq!(mismatch.span, hidden);
     }
-}
\ No newline at end of file
+
+    #[rstest]
+    fn test_enforce_redacted_method() {
+        let transcript =
+            Transcript::new(fixtures::request::GET_EMPTY, fixtures::response::OK_EMPTY);
+        let partial = transcript.to_partial(
+            RangeSet::from(3..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+        let template = |request: RequestTemplate| {
+            HttpTemplate::builder()
+                .request(request)
+                .response(ResponseTemplate::new())
+                .build()
+        };
+
+        // Without an expected method, the hidden method is reported as redacted.
+        let context =
+            HttpEnforcer::from_template(partial.clone(), template(RequestTemplate::new()))
+                .build()
+                .unwrap();
+        assert_eq!(
+            context.exchanges[0].request.method,
+            Redactable::Redacted { len: 3 }
+        );
+
+        let template = template(RequestTemplate::new().method("GET"));
+        let err = HttpEnforcer::from_template(partial, template)
+            .build()
+            .unwrap_err();
+        let [mismatch] = err.mismatches() else {
+            panic!("expected a single mismatch");
+        };
+        assert_eq!(mismatch.span, RangeSet::from(0..3));
+        assert_eq!(
+            mismatch.detail,
+            MismatchKind::Unauthenticated {
+                location: "method".to_string()
+            }
+        );
+    }
+
+    #[rstest]
+    fn test_enforce_request_mismatches() {
+        let transcript = Transcript::new(POST_JSON_MODIFIED, fixtures::response::OK_JSON);
+
+        let err = HttpEnforcer::new(
+            full(&transcript),
+            structure(fixtures::request::POST_JSON, fixtures::response::OK_JSON),
+        )
+        .build()
+        .unwrap_err();
+
+        let details = err
+            .mismatches()
+            .iter()
+            .map(|mismatch| {
+                assert_eq!(mismatch.kind, MessageKind::Request);
+                assert_eq!(mismatch.index, 0);
+                mismatch.detail.clone()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            details,
+            vec![
+                MismatchKind::Method {
+                    expected: "POST".to_string(),
+                    actual: "PUT".to_string(),
+                },
+                MismatchKind::Target {
+                    expected: "/hello".to_string(),
+                    actual: "/goodbye".to_string(),
+                },
+                MismatchKind::MissingHeader {
+                    name: "Content-Type".to_string(),
+                },
+                MismatchKind::BodyType {
+                    expected: "json",
+                    actual: "unknown",
+                },
+            ]
+        );
+
+        assert_eq!(err.mismatches()[0].span, RangeSet::from(0..3));
+    }
+
+    #[rstest]
+    fn test_enforce_json_mismatches() {
+        let sent = b"\
+            POST /hello HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: 38\r\n\r\n\
+            {\"foo\": \"baz\", \"bazz\": 1, \"buzz\": [1]}";
+        let transcript = Transcript::new(sent, fixtures::response::OK_JSON);
+
+        let err = HttpEnforcer::new(
+            full(&transcript),
+            structure(fixtures::request::POST_JSON, fixtures::response::OK_JSON),
+        )
+        .build()
+        .unwrap_err();
+
+        let paths = err
+            .mismatches()
+            .iter()
+            .map(|mismatch| match &mismatch.detail {
+                MismatchKind::Json(err) => err.path().to_string(),
+                detail => panic!("unexpected mismatch: {detail}"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths, vec!["/foo"]);
+        assert_eq!(err.mismatches()[0].span, RangeSet::from(102..105));
+    }
+
+    #[rstest]
+    fn test_enforce_response_mismatches() {
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, OK_TEXT_MODIFIED);
+
+        let err = HttpEnforcer::new(
+            full(&transcript),
+            structure(fixtures::request::GET_EMPTY, fixtures::response::OK_TEXT),
+        )
+        .build()
+        .unwrap_err();
+
+        let details = err
+            .mismatches()
+            .iter()
+            .map(|mismatch| mismatch.detail.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            details,
+            vec![
+                MismatchKind::Status {
+                    expected: "200".to_string(),
+                    actual: "404".to_string(),
+                },
+                MismatchKind::Body,
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_enforce_count_mismatch() {
+        let sent = [fixtures::request::GET_EMPTY, fixtures::request::GET_EMPTY].concat();
+        let transcript = Transcript::new(sent, fixtures::response::OK_EMPTY);
+
+        let err = HttpEnforcer::new(
+            full(&transcript),
+            structure(fixtures::request::GET_EMPTY, fixtures::response::OK_EMPTY),
+        )
+        .build()
+        .unwrap_err();
+
+        assert_eq!(
+            err.mismatches()[0].detail,
+            MismatchKind::Count {
+                expected: 1,
+                actual: 2
+            }
+        );
+    }
+}
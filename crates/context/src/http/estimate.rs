@@ -0,0 +1,314 @@
+//! Commitment cost estimates for HTTP transcripts.
+//!
+//! [`estimate`] runs an [`HttpCommit`] implementation against a
+//! [`DryRunBuilder`] and attributes each recorded commitment to the message
+//! and header containing it, so that the cost of a committer policy can be
+//! inspected offline.
+
+use rangeset::{RangeSet, Subset, ToRangeSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    http::{transcript::MessageKind, Header, HttpCommit, HttpCommitError, HttpTranscript},
+    transcript::{
+        CostModel, CostReport, Direction, DryRunBuilder, Transcript,
+        TranscriptCommitmentBuilderError,
+    },
+};
+
+/// Number of commitments and committed bytes attributed to a node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostBreakdown {
+    /// Number of commitments.
+    pub commitments: usize,
+    /// Committed bytes, counting overlapping bytes once per commitment.
+    pub bytes: usize,
+}
+
+impl CostBreakdown {
+    fn add(&mut self, idx: &RangeSet<usize>) {
+        self.commitments += 1;
+        self.bytes += idx.len();
+    }
+
+    /// Returns the cost under the given model.
+    pub fn cost(&self, model: &CostModel) -> u64 {
+        model.cost(self.commitments, self.bytes)
+    }
+}
+
+/// Commitments attributed to a header or trailer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderCost {
+    /// The header name, as it appears in the message.
+    pub name: String,
+    /// Whether the field is a trailer.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trailer: bool,
+    /// Commitments contained in the header.
+    #[serde(flatten)]
+    pub cost: CostBreakdown,
+}
+
+/// Commitments attributed to a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageCost {
+    /// The kind of message.
+    pub kind: MessageKind,
+    /// The index of the message in the transcript.
+    pub index: usize,
+    /// Commitments contained in the message, including those of its headers.
+    #[serde(flatten)]
+    pub cost: CostBreakdown,
+    /// Each header and trailer of the message, in order.
+    pub headers: Vec<HeaderCost>,
+}
+
+/// Report of the commitments an [`HttpCommit`] implementation would make.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpCostReport {
+    /// Summary of every commitment.
+    pub summary: CostReport,
+    /// Each request, followed by each response.
+    pub messages: Vec<MessageCost>,
+    /// Commitments which span more than one message.
+    pub unattributed: CostBreakdown,
+}
+
+impl HttpCostReport {
+    /// Creates a report of the commitments recorded by a builder.
+    ///
+    /// Each commitment is attributed to the message containing it and, if
+    /// any, to the header or trailer containing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The builder which recorded the commitments.
+    /// * `http` - The HTTP transcript the commitments were made to.
+    pub fn new(builder: &DryRunBuilder, http: &HttpTranscript) -> Self {
+        let mut messages = http
+            .requests
+            .iter()
+            .enumerate()
+            .map(|(index, request)| {
+                (
+                    Direction::Sent,
+                    request.to_range_set(),
+                    message(MessageKind::Request, index, &request.headers, None),
+                )
+            })
+            .chain(http.responses.iter().enumerate().map(|(index, response)| {
+                (
+                    Direction::Received,
+                    response.to_range_set(),
+                    message(
+                        MessageKind::Response,
+                        index,
+                        &response.headers,
+                        response.trailers.as_deref(),
+                    ),
+                )
+            }))
+            .collect::<Vec<_>>();
+
+        let mut unattributed = CostBreakdown::default();
+        for (direction, idx) in builder.commits() {
+            let Some((_, _, (message, headers))) = messages
+                .iter_mut()
+                .find(|(d, range, _)| d == direction && idx.is_subset(range))
+            else {
+                unattributed.add(idx);
+                continue;
+            };
+
+            message.cost.add(idx);
+            if let Some((_, cost)) = headers
+                .iter()
+                .zip(message.headers.iter_mut())
+                .find(|(range, _)| idx.is_subset(*range))
+            {
+                cost.cost.add(idx);
+            }
+        }
+
+        Self {
+            summary: builder.report(),
+            messages: messages
+                .into_iter()
+                .map(|(_, _, (message, _))| message)
+                .collect(),
+            unattributed,
+        }
+    }
+
+    /// Returns the cost of every commitment under the given model.
+    pub fn cost(&self, model: &CostModel) -> u64 {
+        self.summary.cost(model)
+    }
+}
+
+/// Estimates the commitments `committer` would make to a transcript.
+///
+/// This records the commitments of both [`HttpCommit::commit_transcript`] and
+/// [`HttpCommit::commit_structure`]. To estimate a different combination, run
+/// the committer against a [`DryRunBuilder`] and use [`HttpCostReport::new`].
+///
+/// # Arguments
+///
+/// * `committer` - The committer to estimate.
+/// * `transcript` - The transcript to commit to.
+/// * `http` - The HTTP transcript parsed from `transcript`.
+pub fn estimate<H: HttpCommit<DryRunBuilder>>(
+    committer: &mut H,
+    transcript: &Transcript,
+    http: &HttpTranscript,
+) -> Result<HttpCostReport, EstimateError> {
+    let mut builder = DryRunBuilder::new(transcript);
+    committer.commit_transcript(&mut builder, http)?;
+    committer
+        .commit_structure(&mut builder, http)
+        .map_err(EstimateError::Structure)?;
+
+    Ok(HttpCostReport::new(&builder, http))
+}
+
+/// Error for [`estimate`].
+#[derive(Debug, thiserror::Error)]
+pub enum EstimateError {
+    /// The committer failed to commit to a message.
+    #[error(transparent)]
+    Commit(#[from] HttpCommitError),
+    /// The committer failed to commit to the structure of the transcript.
+    #[error("failed to commit to transcript structure: {0}")]
+    Structure(#[source] TranscriptCommitmentBuilderError),
+}
+
+/// Returns an empty cost for a message, alongside the ranges of its headers.
+fn message(
+    kind: MessageKind,
+    index: usize,
+    headers: &[Header],
+    trailers: Option<&[Header]>,
+) -> (MessageCost, Vec<RangeSet<usize>>) {
+    let fields = headers.iter().map(|header| (header, false)).chain(
+        trailers
+            .unwrap_or_default()
+            .iter()
+            .map(|header| (header, true)),
+    );
+
+    let (headers, ranges) = fields
+        .map(|(header, trailer)| {
+            (
+                HeaderCost {
+                    name: header.name.as_str().to_string(),
+                    trailer,
+                    cost: CostBreakdown::default(),
+                },
+                header.to_range_set(),
+            )
+        })
+        .unzip();
+
+    (
+        MessageCost {
+            kind,
+            index,
+            cost: CostBreakdown::default(),
+            headers,
+        },
+        ranges,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{
+        fixtures::http as fixtures,
+        http::{ConfigurableHttpCommitter, DefaultHttpCommitter, TargetGranularity},
+    };
+
+    #[rstest]
+    fn test_estimate() {
+        let transcript = Transcript::new(
+            fixtures::request::GET_WITH_HEADER,
+            fixtures::response::OK_CHUNKED_JSON,
+        );
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let report = estimate(&mut DefaultHttpCommitter::default(), &transcript, &http).unwrap();
+
+        assert_eq!(report.messages.len(), 2);
+        assert_eq!(report.unattributed, CostBreakdown::default());
+        assert_eq!(
+            report
+                .messages
+                .iter()
+                .map(|message| message.cost.commitments)
+                .sum::<usize>(),
+            report.summary.commitments
+        );
+
+        // Every commitment to the request is attributed to it.
+        let request = &report.messages[0];
+        assert_eq!(request.kind, MessageKind::Request);
+        assert_eq!(
+            request.cost.bytes,
+            report.summary.total_bytes - report.messages[1].cost.bytes
+        );
+        assert_eq!(request.headers.len(), http.requests[0].headers.len());
+
+        let host = request
+            .headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case("host"))
+            .unwrap();
+        assert!(host.cost.commitments > 0);
+        assert!(host.cost.bytes <= request.cost.bytes);
+
+        let largest = report.summary.largest.unwrap();
+        assert_eq!(largest.direction, Direction::Received);
+        assert_eq!(largest.len, fixtures::response::OK_CHUNKED_JSON.len());
+    }
+
+    #[rstest]
+    fn test_estimate_compares_policies() {
+        let transcript = Transcript::new(
+            b"GET /home?user=alice HTTP/1.1\r\nHost: example.com\r\n\r\n".as_slice(),
+            fixtures::response::OK_TEXT,
+        );
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let model = CostModel {
+            per_commitment: 10,
+            per_byte: 1,
+        };
+
+        let default = estimate(&mut DefaultHttpCommitter::default(), &transcript, &http).unwrap();
+        let by_path = estimate(
+            &mut ConfigurableHttpCommitter::builder()
+                .target(TargetGranularity::Path)
+                .build()
+                .unwrap(),
+            &transcript,
+            &http,
+        )
+        .unwrap();
+
+        // The path and query are committed in addition to the whole target.
+        assert_eq!(by_path.summary.commitments, default.summary.commitments + 2);
+        assert_eq!(
+            by_path.cost(&model) - default.cost(&model),
+            2 * 10 + "/home?user=alice".len() as u64
+        );
+        assert_eq!(by_path.messages[0].headers, default.messages[0].headers);
+
+        let json = serde_json::to_value(&default).unwrap();
+        assert_eq!(
+            serde_json::from_value::<HttpCostReport>(json).unwrap(),
+            default
+        );
+    }
+}
@@ -0,0 +1,1338 @@
+//! HAR (HTTP Archive) import and export.
+//!
+//! [`Har::to_transcript`] reconstructs the entries of an archive, e.g. one
+//! saved from the network panel of a browser, as HTTP/1.1 messages framed
+//! with `Content-Length`. An archive does not record messages byte for byte,
+//! so everything which could not be reconstructed faithfully is reported as a
+//! [`HarIssue`].
+//!
+//! [`Har::from_transcript`] and [`Har::from_context`] export the other way,
+//! for inspection in standard tooling. Bytes which are not revealed in a
+//! context are exported as `*`, as in
+//! [`HttpTranscript::parse_partial`].
+
+use std::fmt;
+
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+use http::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use spanner::http::{Header, Request, Response};
+use url::Url;
+
+use crate::{
+    context::Redactable,
+    http::{
+        context::{BodyContext, HttpContext, RequestContext, ResponseContext},
+        transcript::{HttpTranscript, MessageKind},
+    },
+    json::JsonNode,
+    transcript::Transcript,
+};
+
+/// The HAR format version which is exported.
+pub const HAR_VERSION: &str = "1.2";
+
+/// An HTTP archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Har {
+    /// The log of the archive.
+    pub log: HarLog,
+}
+
+/// The log of an HTTP archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarLog {
+    /// The HAR format version.
+    pub version: String,
+    /// The application which created the archive.
+    pub creator: HarCreator,
+    /// The exchanges, in order.
+    #[serde(default)]
+    pub entries: Vec<HarEntry>,
+    /// A comment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// The application which created an archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarCreator {
+    /// The name of the application.
+    pub name: String,
+    /// The version of the application.
+    pub version: String,
+}
+
+/// A request and its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    /// When the request was started, in ISO 8601 format.
+    #[serde(default)]
+    pub started_date_time: String,
+    /// Total time of the request, in milliseconds.
+    #[serde(default)]
+    pub time: f64,
+    /// The request.
+    pub request: HarRequest,
+    /// The response.
+    pub response: HarResponse,
+    /// Information about the cache, which is not used.
+    #[serde(default)]
+    pub cache: serde_json::Map<String, serde_json::Value>,
+    /// Timings of the request.
+    #[serde(default)]
+    pub timings: HarTimings,
+}
+
+/// Timings of a request, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HarTimings {
+    /// Time to send the request.
+    pub send: f64,
+    /// Time waiting for the response.
+    pub wait: f64,
+    /// Time to receive the response.
+    pub receive: f64,
+}
+
+/// A request of an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    /// The request method.
+    pub method: String,
+    /// The absolute URL of the request.
+    pub url: String,
+    /// The HTTP version, e.g. `HTTP/1.1` or `h2`.
+    #[serde(default)]
+    pub http_version: String,
+    /// The cookies of the request.
+    #[serde(default)]
+    pub cookies: Vec<HarParam>,
+    /// The header fields, in order.
+    pub headers: Vec<HarHeader>,
+    /// The query parameters of the URL.
+    #[serde(default)]
+    pub query_string: Vec<HarParam>,
+    /// The body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    /// The size of the head in bytes, or -1 if unknown.
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    /// The size of the body in bytes, or -1 if unknown.
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+/// The body of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    /// The media type of the body.
+    #[serde(default)]
+    pub mime_type: String,
+    /// The body.
+    #[serde(default)]
+    pub text: String,
+    /// The parameters of a form body, recorded instead of the text by some
+    /// tools.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<HarParam>,
+}
+
+/// A response of an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    /// The status code, or 0 if there is no response.
+    pub status: u16,
+    /// The reason phrase.
+    #[serde(default)]
+    pub status_text: String,
+    /// The HTTP version, e.g. `HTTP/1.1` or `h2`.
+    #[serde(default)]
+    pub http_version: String,
+    /// The cookies set by the response.
+    #[serde(default)]
+    pub cookies: Vec<HarParam>,
+    /// The header fields, in order.
+    pub headers: Vec<HarHeader>,
+    /// The body.
+    pub content: HarContent,
+    /// The target of a redirect.
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    /// The size of the head in bytes, or -1 if unknown.
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    /// The size of the body as transferred in bytes, or -1 if unknown.
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+/// The body of a response, after any content coding is removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    /// The size of the body in bytes.
+    pub size: i64,
+    /// The media type of the body.
+    #[serde(default)]
+    pub mime_type: String,
+    /// The body, if it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The encoding of the text, `base64` for binary bodies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// A header field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarHeader {
+    /// The field name.
+    pub name: String,
+    /// The field value.
+    pub value: String,
+}
+
+/// A cookie or query parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarParam {
+    /// The name of the parameter.
+    pub name: String,
+    /// The value of the parameter.
+    #[serde(default)]
+    pub value: String,
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+/// Error for [`Har`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HarError {
+    /// The archive is not valid HAR.
+    #[error("invalid HAR: {0}")]
+    Json(String),
+    /// The URL of a request is not an absolute URL.
+    #[error("entry {entry} has an invalid URL \"{url}\"")]
+    InvalidUrl {
+        /// The index of the entry.
+        entry: usize,
+        /// The URL.
+        url: String,
+    },
+    /// The method of a request is not a valid HTTP method.
+    #[error("entry {entry} has an invalid method \"{method}\"")]
+    InvalidMethod {
+        /// The index of the entry.
+        entry: usize,
+        /// The method.
+        method: String,
+    },
+    /// The status code of a response is not a valid HTTP status code.
+    #[error("entry {entry} has an invalid status code {status}")]
+    InvalidStatus {
+        /// The index of the entry.
+        entry: usize,
+        /// The status code.
+        status: u16,
+    },
+    /// A header field can not be written in HTTP/1.1.
+    #[error("entry {entry} has an invalid {kind} header \"{name}\"")]
+    InvalidHeader {
+        /// The index of the entry.
+        entry: usize,
+        /// The kind of message.
+        kind: MessageKind,
+        /// The field name.
+        name: String,
+    },
+    /// A body is not valid base64.
+    #[error("entry {entry} has an invalid base64 {kind} body")]
+    InvalidBase64 {
+        /// The index of the entry.
+        entry: usize,
+        /// The kind of message.
+        kind: MessageKind,
+    },
+}
+
+/// Something which could not be reconstructed faithfully from an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HarIssue {
+    /// The kind of message.
+    pub kind: MessageKind,
+    /// The index of the entry.
+    pub entry: usize,
+    /// What could not be reconstructed.
+    pub detail: HarIssueKind,
+}
+
+impl fmt::Display for HarIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entry {} {}: {}", self.entry, self.kind, self.detail)
+    }
+}
+
+/// The kind of [`HarIssue`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HarIssueKind {
+    /// The message was not sent over HTTP/1.x and is written as HTTP/1.1.
+    HttpVersion {
+        /// The recorded HTTP version.
+        version: String,
+    },
+    /// An HTTP/2 or HTTP/3 pseudo-header was dropped.
+    PseudoHeader {
+        /// The field name, e.g. `:authority`.
+        name: String,
+    },
+    /// The body was recorded after removing its content coding, so the
+    /// `Content-Encoding` header was dropped.
+    DecodedBody {
+        /// The content coding, e.g. `gzip`.
+        encoding: String,
+    },
+    /// The body was not recorded, so it is empty.
+    MissingBody,
+    /// The framing of the body was replaced with a `Content-Length` header.
+    Reframed,
+}
+
+impl fmt::Display for HarIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HarIssueKind::HttpVersion { version } => {
+                write!(f, "{version} message is written as HTTP/1.1")
+            }
+            HarIssueKind::PseudoHeader { name } => write!(f, "dropped pseudo-header \"{name}\""),
+            HarIssueKind::DecodedBody { encoding } => {
+                write!(f, "body was recorded without its {encoding} content coding")
+            }
+            HarIssueKind::MissingBody => write!(f, "body was not recorded"),
+            HarIssueKind::Reframed => write!(f, "body framing was replaced"),
+        }
+    }
+}
+
+/// A transcript reconstructed from an archive.
+pub struct HarImport {
+    /// The reconstructed transcript.
+    pub transcript: Transcript,
+    /// Everything which could not be reconstructed faithfully.
+    pub issues: Vec<HarIssue>,
+}
+
+impl fmt::Debug for HarImport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Omit the data, like `Transcript`.
+        f.debug_struct("HarImport")
+            .field("issues", &self.issues)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HarImport {
+    /// Returns `true` if the archive was reconstructed without issues.
+    pub fn is_faithful(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Parses the reconstructed transcript.
+    pub fn http(&self) -> Result<HttpTranscript, spanner::ParseError> {
+        HttpTranscript::parse(&self.transcript)
+    }
+}
+
+impl Har {
+    /// Parses an archive from JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The archive.
+    pub fn parse(json: &str) -> Result<Self, HarError> {
+        serde_json::from_str(json).map_err(|err| HarError::Json(err.to_string()))
+    }
+
+    /// Reconstructs the entries as a transcript of HTTP/1.1 messages.
+    ///
+    /// The archive is expected to contain the entries of a single
+    /// connection, e.g. after filtering [`HarLog::entries`] by host.
+    pub fn to_transcript(&self) -> Result<HarImport, HarError> {
+        let mut sent = Vec::new();
+        let mut received = Vec::new();
+        let mut issues = Vec::new();
+
+        for (index, entry) in self.log.entries.iter().enumerate() {
+            let mut writer = Writer {
+                entry: index,
+                issues: &mut issues,
+            };
+
+            let method = writer.request(&entry.request, &mut sent)?;
+            writer.response(&entry.response, &method, &mut received)?;
+        }
+
+        Ok(HarImport {
+            transcript: Transcript::new(sent, received),
+            issues,
+        })
+    }
+
+    /// Exports a transcript.
+    ///
+    /// Each request is exported with its final response. Request URLs are
+    /// formed from the `Host` header with the `https` scheme.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The transcript.
+    pub fn from_transcript(transcript: &HttpTranscript) -> Self {
+        let mut responses = transcript
+            .responses
+            .iter()
+            .filter(|response| !is_interim(response.status.code.as_str().parse().ok()));
+
+        let entries = transcript
+            .requests
+            .iter()
+            .map(|request| {
+                entry(
+                    export_request(request),
+                    responses.next().map(export_response),
+                )
+            })
+            .collect();
+
+        Self::new(entries, None)
+    }
+
+    /// Exports a context.
+    ///
+    /// Bytes which are not revealed are exported as `*`, and values of
+    /// headers removed from the context are not exported.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context.
+    pub fn from_context(context: &HttpContext) -> Self {
+        let entries = context
+            .exchanges()
+            .iter()
+            .map(|exchange| {
+                entry(
+                    export_request_context(exchange.request()),
+                    exchange.response().map(export_response_context),
+                )
+            })
+            .collect();
+
+        Self::new(
+            entries,
+            Some(
+                "Exported from a partial transcript, bytes which are not revealed are shown as *."
+                    .to_string(),
+            ),
+        )
+    }
+
+    fn new(entries: Vec<HarEntry>, comment: Option<String>) -> Self {
+        Self {
+            log: HarLog {
+                version: HAR_VERSION.to_string(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries,
+                comment,
+            },
+        }
+    }
+}
+
+/// Writes the messages of an entry in HTTP/1.1 wire format.
+struct Writer<'a> {
+    entry: usize,
+    issues: &'a mut Vec<HarIssue>,
+}
+
+impl Writer<'_> {
+    fn issue(&mut self, kind: MessageKind, detail: HarIssueKind) {
+        self.issues.push(HarIssue {
+            kind,
+            entry: self.entry,
+            detail,
+        });
+    }
+
+    fn version(&mut self, kind: MessageKind, version: &str) {
+        let http1 = version.is_empty()
+            || version.eq_ignore_ascii_case("HTTP/1.1")
+            || version.eq_ignore_ascii_case("HTTP/1.0");
+        if !http1 {
+            self.issue(
+                kind,
+                HarIssueKind::HttpVersion {
+                    version: version.to_string(),
+                },
+            );
+        }
+    }
+
+    /// Writes the header fields other than pseudo-headers and the skipped
+    /// fields, returning the value of the `:authority` pseudo-header.
+    fn headers(
+        &mut self,
+        kind: MessageKind,
+        headers: &[HarHeader],
+        skip: &[&str],
+        dst: &mut Vec<u8>,
+    ) -> Result<Option<String>, HarError> {
+        let mut authority = None;
+
+        for header in headers {
+            let name = header.name.as_str();
+            if let Some(pseudo) = name.strip_prefix(':') {
+                if pseudo == "authority" {
+                    authority = Some(header.value.clone());
+                }
+                self.issue(
+                    kind,
+                    HarIssueKind::PseudoHeader {
+                        name: name.to_string(),
+                    },
+                );
+                continue;
+            }
+
+            if skip.iter().any(|skip| name.eq_ignore_ascii_case(skip)) {
+                continue;
+            }
+
+            let valid = http::HeaderName::from_bytes(name.as_bytes()).is_ok()
+                && http::HeaderValue::from_str(&header.value).is_ok();
+            if !valid {
+                return Err(HarError::InvalidHeader {
+                    entry: self.entry,
+                    kind,
+                    name: name.to_string(),
+                });
+            }
+
+            write_header(dst, name, &header.value);
+        }
+
+        Ok(authority)
+    }
+
+    /// Writes a request, returning its method.
+    fn request(&mut self, request: &HarRequest, dst: &mut Vec<u8>) -> Result<Method, HarError> {
+        let kind = MessageKind::Request;
+        self.version(kind, &request.http_version);
+
+        let method =
+            Method::from_bytes(request.method.as_bytes()).map_err(|_| HarError::InvalidMethod {
+                entry: self.entry,
+                method: request.method.clone(),
+            })?;
+        let url = Url::parse(&request.url)
+            .ok()
+            .filter(Url::has_host)
+            .ok_or_else(|| HarError::InvalidUrl {
+                entry: self.entry,
+                url: request.url.clone(),
+            })?;
+        let authority = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let target = if method == Method::CONNECT {
+            url.port_or_known_default()
+                .map(|port| format!("{}:{port}", url.host_str().unwrap_or_default()))
+                .unwrap_or_else(|| authority.clone())
+        } else {
+            url[url::Position::BeforePath..url::Position::AfterQuery].to_string()
+        };
+
+        let body = match &request.post_data {
+            Some(post_data) => {
+                if post_data.text.is_empty() && !post_data.params.is_empty() {
+                    self.issue(kind, HarIssueKind::MissingBody);
+                }
+                post_data.text.as_bytes()
+            }
+            None => {
+                if request.body_size > 0 {
+                    self.issue(kind, HarIssueKind::MissingBody);
+                }
+                &[]
+            }
+        };
+
+        let mut headers = Vec::new();
+        let pseudo_authority = self.headers(kind, &request.headers, FRAMING, &mut headers)?;
+        let (chunked, length) = framing(&request.headers);
+        if chunked || length.is_some_and(|length| length != body.len()) {
+            self.issue(kind, HarIssueKind::Reframed);
+        }
+
+        dst.extend_from_slice(format!("{method} {target} HTTP/1.1\r\n").as_bytes());
+        let has_host = request
+            .headers
+            .iter()
+            .any(|header| header.name.eq_ignore_ascii_case("host"));
+        if !has_host {
+            write_header(dst, "Host", &pseudo_authority.unwrap_or(authority));
+        }
+        dst.extend_from_slice(&headers);
+        if !body.is_empty() || length.is_some() {
+            write_header(dst, "Content-Length", &body.len().to_string());
+        }
+        dst.extend_from_slice(b"\r\n");
+        dst.extend_from_slice(body);
+
+        Ok(method)
+    }
+
+    /// Writes the response to a request with the given method.
+    fn response(
+        &mut self,
+        response: &HarResponse,
+        method: &Method,
+        dst: &mut Vec<u8>,
+    ) -> Result<(), HarError> {
+        let kind = MessageKind::Response;
+        self.version(kind, &response.http_version);
+
+        let status =
+            StatusCode::from_u16(response.status).map_err(|_| HarError::InvalidStatus {
+                entry: self.entry,
+                status: response.status,
+            })?;
+        let reason = match response.status_text.as_str() {
+            "" => status.canonical_reason().unwrap_or_default(),
+            reason => reason,
+        };
+
+        // Some status codes have no body and are written without a
+        // `Content-Length`. Responses to HEAD requests are written with an
+        // empty body, as their `Content-Length` would otherwise be taken to
+        // frame a body when parsing the transcript.
+        let bodiless = status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED;
+
+        let body = match (&response.content.text, &response.content.encoding) {
+            _ if bodiless || method == Method::HEAD => Vec::new(),
+            (Some(text), Some(encoding)) if encoding.eq_ignore_ascii_case("base64") => {
+                base64_decode(text).ok_or(HarError::InvalidBase64 {
+                    entry: self.entry,
+                    kind,
+                })?
+            }
+            (Some(text), _) => text.as_bytes().to_vec(),
+            (None, _) => {
+                if response.content.size > 0 {
+                    self.issue(kind, HarIssueKind::MissingBody);
+                }
+                Vec::new()
+            }
+        };
+
+        let encoding = response
+            .headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case("content-encoding"))
+            .map(|header| header.value.trim())
+            .filter(|encoding| !encoding.eq_ignore_ascii_case("identity"));
+        let decoded = encoding.filter(|_| !body.is_empty());
+        if let Some(encoding) = decoded {
+            self.issue(
+                kind,
+                HarIssueKind::DecodedBody {
+                    encoding: encoding.to_string(),
+                },
+            );
+        }
+
+        // The length of a decoded body is expected to differ.
+        let (chunked, length) = framing(&response.headers);
+        let length_differs = decoded.is_none() && length.is_some_and(|length| length != body.len());
+        if chunked || length_differs {
+            self.issue(kind, HarIssueKind::Reframed);
+        }
+
+        let skip: &[&str] = match decoded {
+            Some(_) => &["transfer-encoding", "content-length", "content-encoding"],
+            None => FRAMING,
+        };
+        let mut headers = Vec::new();
+        self.headers(kind, &response.headers, skip, &mut headers)?;
+
+        dst.extend_from_slice(format!("HTTP/1.1 {} {reason}\r\n", status.as_u16()).as_bytes());
+        dst.extend_from_slice(&headers);
+        if !bodiless {
+            write_header(dst, "Content-Length", &body.len().to_string());
+        }
+        dst.extend_from_slice(b"\r\n");
+        dst.extend_from_slice(&body);
+
+        Ok(())
+    }
+}
+
+/// Header fields which frame the body, and are replaced when writing a
+/// message.
+const FRAMING: &[&str] = &["transfer-encoding", "content-length"];
+
+/// Returns whether the header fields contain a `Transfer-Encoding`, and the
+/// value of the `Content-Length`.
+///
+/// An invalid `Content-Length` is returned as `usize::MAX`, so that it
+/// differs from the length of any body.
+fn framing(headers: &[HarHeader]) -> (bool, Option<usize>) {
+    let chunked = headers
+        .iter()
+        .any(|header| header.name.eq_ignore_ascii_case("transfer-encoding"));
+    let length = headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-length"))
+        .map(|header| header.value.trim().parse().unwrap_or(usize::MAX));
+
+    (chunked, length)
+}
+
+fn write_header(dst: &mut Vec<u8>, name: &str, value: &str) {
+    dst.extend_from_slice(name.as_bytes());
+    dst.extend_from_slice(b": ");
+    dst.extend_from_slice(value.as_bytes());
+    dst.extend_from_slice(b"\r\n");
+}
+
+fn is_interim(status: Option<u16>) -> bool {
+    matches!(status, Some(100..=199)) && status != Some(101)
+}
+
+fn entry(request: HarRequest, response: Option<HarResponse>) -> HarEntry {
+    HarEntry {
+        started_date_time: String::new(),
+        time: 0.0,
+        request,
+        response: response.unwrap_or_else(|| HarResponse {
+            status: 0,
+            status_text: String::new(),
+            http_version: String::new(),
+            cookies: Vec::new(),
+            headers: Vec::new(),
+            content: HarContent {
+                size: 0,
+                mime_type: String::new(),
+                text: None,
+                encoding: None,
+            },
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: -1,
+        }),
+        cache: Default::default(),
+        timings: HarTimings::default(),
+    }
+}
+
+/// Returns the URL of a request with the given target and `Host` header.
+fn url(target: &str, host: Option<&str>) -> String {
+    if target.starts_with('/') || target == "*" {
+        format!("https://{}{target}", host.unwrap_or("localhost"))
+    } else if target.contains("://") {
+        target.to_string()
+    } else {
+        format!("https://{target}")
+    }
+}
+
+fn query_string(url: &str) -> Vec<HarParam> {
+    Url::parse(url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| HarParam {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn mime_type(headers: &[HarHeader]) -> String {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-type"))
+        .map(|header| header.value.clone())
+        .unwrap_or_default()
+}
+
+fn export_headers(headers: &[Header]) -> Vec<HarHeader> {
+    headers
+        .iter()
+        .map(|header| HarHeader {
+            name: header.name.as_str().to_string(),
+            value: String::from_utf8_lossy(header.value.as_bytes()).to_string(),
+        })
+        .collect()
+}
+
+/// Returns the text of a body, encoded with base64 if it is not UTF-8.
+fn text(body: &[u8]) -> (String, Option<String>) {
+    match std::str::from_utf8(body) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (STANDARD.encode(body), Some("base64".to_string())),
+    }
+}
+
+fn export_request(request: &Request) -> HarRequest {
+    let headers = export_headers(&request.headers);
+    let host = request
+        .headers_with_name("host")
+        .next()
+        .map(|host| String::from_utf8_lossy(host.value.as_bytes()).to_string());
+    let url = url(request.request.target.as_str(), host.as_deref());
+    let body = request.body.as_ref().map(|body| body.as_bytes());
+
+    HarRequest {
+        method: request.request.method.as_str().to_string(),
+        query_string: query_string(&url),
+        url,
+        http_version: "HTTP/1.1".to_string(),
+        cookies: Vec::new(),
+        post_data: body.map(|body| HarPostData {
+            mime_type: mime_type(&headers),
+            text: String::from_utf8_lossy(body).to_string(),
+            params: Vec::new(),
+        }),
+        headers,
+        headers_size: -1,
+        body_size: body.map_or(0, |body| body.len() as i64),
+    }
+}
+
+fn export_response(response: &Response) -> HarResponse {
+    let headers = export_headers(&response.headers);
+    let body = response
+        .body
+        .as_ref()
+        .map(|body| body.as_bytes())
+        .unwrap_or_default();
+    let (text, encoding) = text(body);
+
+    HarResponse {
+        status: response.status.code.as_str().parse().unwrap_or_default(),
+        status_text: response.status.reason.as_str().to_string(),
+        http_version: "HTTP/1.1".to_string(),
+        cookies: Vec::new(),
+        content: HarContent {
+            size: body.len() as i64,
+            mime_type: mime_type(&headers),
+            text: Some(text),
+            encoding,
+        },
+        headers,
+        redirect_url: String::new(),
+        headers_size: -1,
+        body_size: body.len() as i64,
+    }
+}
+
+/// Returns a value, with bytes which are not revealed shown as `*`.
+fn redactable(value: &Redactable<String>) -> String {
+    match value {
+        Redactable::Revealed { value } => value.clone(),
+        Redactable::Redacted { len } | Redactable::StructureOnly { len } => "*".repeat(*len),
+    }
+}
+
+fn export_context_headers(headers: &crate::http::context::Headers) -> Vec<HarHeader> {
+    headers
+        .iter()
+        .map(|field| HarHeader {
+            name: field.name.clone(),
+            value: redactable(&field.value),
+        })
+        .collect()
+}
+
+fn export_body_context(body: &BodyContext) -> String {
+    match body {
+        BodyContext::Json(json) => json_text(json.value()),
+        BodyContext::Unknown(segments) => segments
+            .iter()
+            .map(|segment| match segment {
+                Redactable::Revealed { value } => String::from_utf8_lossy(value).to_string(),
+                Redactable::Redacted { len } | Redactable::StructureOnly { len } => {
+                    "*".repeat(*len)
+                }
+            })
+            .collect(),
+    }
+}
+
+fn json_text(node: &JsonNode) -> String {
+    match node {
+        JsonNode::Object { entries } => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    let key = match &entry.key {
+                        Redactable::Revealed { value } => {
+                            serde_json::Value::from(value.as_str()).to_string()
+                        }
+                        key => format!("\"{}\"", redactable(key)),
+                    };
+                    format!("{key}: {}", json_text(&entry.value))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        JsonNode::Array { items } => {
+            let items: Vec<_> = items.iter().map(json_text).collect();
+            format!("[{}]", items.join(", "))
+        }
+        JsonNode::Value(Redactable::Revealed { value }) => value.to_string(),
+        JsonNode::Value(Redactable::Redacted { len } | Redactable::StructureOnly { len }) => {
+            "*".repeat(*len)
+        }
+    }
+}
+
+fn export_request_context(request: &RequestContext) -> HarRequest {
+    let headers = export_context_headers(request.headers());
+    let host = request.header("host").map(redactable);
+    let url = url(&redactable(request.target()), host.as_deref());
+    let body = request.body().map(export_body_context);
+
+    HarRequest {
+        method: redactable(&request.method().clone().map(|method| method.to_string())),
+        query_string: query_string(&url),
+        url,
+        http_version: "HTTP/1.1".to_string(),
+        cookies: Vec::new(),
+        body_size: body.as_ref().map_or(0, |body| body.len() as i64),
+        post_data: body.map(|text| HarPostData {
+            mime_type: mime_type(&headers),
+            text,
+            params: Vec::new(),
+        }),
+        headers,
+        headers_size: -1,
+    }
+}
+
+fn export_response_context(response: &ResponseContext) -> HarResponse {
+    let headers = export_context_headers(response.headers());
+    let body = response.body().map(export_body_context).unwrap_or_default();
+
+    HarResponse {
+        // A hidden status is exported as 0, which HAR uses for unknown statuses.
+        status: response.status().revealed().map_or(0, StatusCode::as_u16),
+        status_text: response
+            .status()
+            .revealed()
+            .and_then(StatusCode::canonical_reason)
+            .unwrap_or_default()
+            .to_string(),
+        http_version: "HTTP/1.1".to_string(),
+        cookies: Vec::new(),
+        content: HarContent {
+            size: body.len() as i64,
+            mime_type: mime_type(&headers),
+            text: Some(body.clone()),
+            encoding: None,
+        },
+        headers,
+        redirect_url: String::new(),
+        headers_size: -1,
+        body_size: body.len() as i64,
+    }
+}
+
+/// Decodes base64 text, ignoring whitespace. Padding is optional, but must
+/// complete the last group if present.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text: String = text.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let engine = if text.ends_with('=') {
+        &STANDARD
+    } else {
+        &STANDARD_NO_PAD
+    };
+    engine.decode(text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use rangeset::{Difference, RangeSet};
+    use rstest::rstest;
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::http as fixtures;
+
+    fn har(entries: serde_json::Value) -> Har {
+        serde_json::from_value(json!({
+            "log": {
+                "version": "1.2",
+                "creator": {"name": "test", "version": "1"},
+                "entries": entries,
+            }
+        }))
+        .unwrap()
+    }
+
+    fn http1_entry() -> serde_json::Value {
+        json!({
+            "startedDateTime": "2025-06-01T00:00:00.000Z",
+            "time": 12.5,
+            "request": {
+                "method": "POST",
+                "url": "https://api.example.com/v1/items?page=2",
+                "httpVersion": "HTTP/1.1",
+                "headers": [
+                    {"name": "Host", "value": "api.example.com"},
+                    {"name": "Content-Type", "value": "application/json"},
+                    {"name": "Content-Length", "value": "11"},
+                ],
+                "postData": {"mimeType": "application/json", "text": "{\"id\": 42}\n"},
+                "headersSize": -1,
+                "bodySize": 11,
+            },
+            "response": {
+                "status": 200,
+                "statusText": "OK",
+                "httpVersion": "HTTP/1.1",
+                "headers": [{"name": "Content-Type", "value": "text/plain"}],
+                "content": {"size": 2, "mimeType": "text/plain", "text": "ok"},
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": 2,
+            },
+        })
+    }
+
+    #[rstest]
+    fn test_import_http1() {
+        let import = har(json!([http1_entry()])).to_transcript().unwrap();
+
+        assert!(import.is_faithful(), "{:?}", import.issues);
+        assert_eq!(
+            import.transcript.sent(),
+            b"POST /v1/items?page=2 HTTP/1.1\r\n\
+              Host: api.example.com\r\n\
+              Content-Type: application/json\r\n\
+              Content-Length: 11\r\n\r\n\
+              {\"id\": 42}\n"
+                .as_slice()
+        );
+        assert_eq!(
+            import.transcript.received(),
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nok"
+                .as_slice()
+        );
+
+        let http = import.http().unwrap();
+        assert_eq!(http.requests.len(), 1);
+        assert_eq!(http.responses.len(), 1);
+    }
+
+    #[rstest]
+    fn test_import_http2() {
+        let entry = json!({
+            "request": {
+                "method": "GET",
+                "url": "https://api.example.com:8443/data",
+                "httpVersion": "h2",
+                "headers": [
+                    {"name": ":method", "value": "GET"},
+                    {"name": ":authority", "value": "api.example.com:8443"},
+                    {"name": ":path", "value": "/data"},
+                    {"name": "accept", "value": "*/*"},
+                ],
+            },
+            "response": {
+                "status": 200,
+                "statusText": "",
+                "httpVersion": "h2",
+                "headers": [
+                    {"name": "content-encoding", "value": "gzip"},
+                    {"name": "content-length", "value": "20"},
+                    {"name": "content-type", "value": "application/octet-stream"},
+                ],
+                "content": {"size": 3, "text": "AAEC", "encoding": "base64"},
+            },
+        });
+        let import = har(json!([entry])).to_transcript().unwrap();
+
+        assert_eq!(
+            import.transcript.sent(),
+            b"GET /data HTTP/1.1\r\nHost: api.example.com:8443\r\naccept: */*\r\n\r\n".as_slice()
+        );
+        assert_eq!(
+            import.transcript.received(),
+            b"HTTP/1.1 200 OK\r\n\
+              content-type: application/octet-stream\r\n\
+              Content-Length: 3\r\n\r\n\x00\x01\x02"
+                .as_slice()
+        );
+
+        let details: Vec<_> = import
+            .issues
+            .iter()
+            .map(|issue| (issue.kind, issue.detail.clone()))
+            .collect();
+        let pseudo = |name: &str| {
+            (
+                MessageKind::Request,
+                HarIssueKind::PseudoHeader {
+                    name: name.to_string(),
+                },
+            )
+        };
+        assert_eq!(
+            details,
+            [
+                (
+                    MessageKind::Request,
+                    HarIssueKind::HttpVersion {
+                        version: "h2".to_string()
+                    }
+                ),
+                pseudo(":method"),
+                pseudo(":authority"),
+                pseudo(":path"),
+                (
+                    MessageKind::Response,
+                    HarIssueKind::HttpVersion {
+                        version: "h2".to_string()
+                    }
+                ),
+                (
+                    MessageKind::Response,
+                    HarIssueKind::DecodedBody {
+                        encoding: "gzip".to_string()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            import.issues[5].to_string(),
+            "entry 0 response: body was recorded without its gzip content coding"
+        );
+    }
+
+    #[rstest]
+    fn test_import_framing() {
+        let entries = json!([
+            {
+                "request": {"method": "DELETE", "url": "http://example.com/1", "headers": []},
+                "response": {"status": 204, "headers": [], "content": {"size": 0}},
+            },
+            {
+                "request": {"method": "HEAD", "url": "http://example.com/", "headers": []},
+                "response": {
+                    "status": 200,
+                    "headers": [{"name": "Content-Length", "value": "1234"}],
+                    "content": {"size": 0},
+                },
+            },
+            {
+                "request": {"method": "GET", "url": "http://example.com/", "headers": []},
+                "response": {
+                    "status": 200,
+                    "headers": [{"name": "Transfer-Encoding", "value": "chunked"}],
+                    "content": {"size": 5},
+                },
+            },
+        ]);
+        let import = har(entries).to_transcript().unwrap();
+
+        assert_eq!(
+            import.transcript.received(),
+            b"HTTP/1.1 204 No Content\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"
+                .as_slice()
+        );
+        assert_eq!(
+            import
+                .issues
+                .iter()
+                .map(|issue| (issue.entry, issue.detail.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (1, HarIssueKind::Reframed),
+                (2, HarIssueKind::MissingBody),
+                (2, HarIssueKind::Reframed)
+            ]
+        );
+        assert_eq!(import.http().unwrap().responses.len(), 3);
+    }
+
+    #[rstest]
+    #[case::url(
+        json!({"method": "GET", "url": "/relative", "headers": []}),
+        HarError::InvalidUrl { entry: 0, url: "/relative".to_string() }
+    )]
+    #[case::method(
+        json!({"method": "G T", "url": "https://example.com/", "headers": []}),
+        HarError::InvalidMethod { entry: 0, method: "G T".to_string() }
+    )]
+    #[case::header(
+        json!({
+            "method": "GET",
+            "url": "https://example.com/",
+            "headers": [{"name": "X-Injected", "value": "a\r\nHost: evil.com"}],
+        }),
+        HarError::InvalidHeader {
+            entry: 0,
+            kind: MessageKind::Request,
+            name: "X-Injected".to_string(),
+        }
+    )]
+    fn test_import_invalid_request(#[case] request: serde_json::Value, #[case] expected: HarError) {
+        let har = har(json!([{
+            "request": request,
+            "response": {"status": 204, "headers": [], "content": {"size": 0}},
+        }]));
+
+        assert_eq!(har.to_transcript().unwrap_err(), expected);
+    }
+
+    #[rstest]
+    fn test_import_invalid_response() {
+        let mut entry = http1_entry();
+        entry["response"]["content"] = json!({"size": 2, "text": "!!", "encoding": "base64"});
+        assert_eq!(
+            har(json!([entry])).to_transcript().unwrap_err(),
+            HarError::InvalidBase64 {
+                entry: 0,
+                kind: MessageKind::Response
+            }
+        );
+
+        let mut entry = http1_entry();
+        entry["response"]["status"] = json!(0);
+        assert_eq!(
+            har(json!([entry])).to_transcript().unwrap_err(),
+            HarError::InvalidStatus {
+                entry: 0,
+                status: 0
+            }
+        );
+
+        assert!(matches!(Har::parse("{}").unwrap_err(), HarError::Json(_)));
+    }
+
+    #[rstest]
+    #[case::get(fixtures::request::GET_WITH_HEADER, fixtures::response::OK_JSON)]
+    #[case::post(fixtures::request::POST_JSON, fixtures::response::OK_TEXT)]
+    fn test_export_roundtrip(#[case] request: &[u8], #[case] response: &[u8]) {
+        let transcript = Transcript::new(request, response);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let har = Har::from_transcript(&http);
+        let json = serde_json::to_string(&har).unwrap();
+        let import = Har::parse(&json).unwrap().to_transcript().unwrap();
+
+        assert!(import.is_faithful(), "{:?}", import.issues);
+        assert_eq!(import.transcript.sent(), request);
+        assert_eq!(import.transcript.received(), response);
+    }
+
+    #[rstest]
+    fn test_export_transcript() {
+        let recv = [
+            b"HTTP/1.1 100 Continue\r\n\r\n".as_slice(),
+            fixtures::response::OK_CHUNKED_JSON,
+        ]
+        .concat();
+        let transcript = Transcript::new(fixtures::request::POST_JSON, recv);
+        let har = Har::from_transcript(&HttpTranscript::parse(&transcript).unwrap());
+
+        let [entry] = har.log.entries.as_slice() else {
+            panic!("expected a single entry");
+        };
+        assert_eq!(entry.request.url, "https://localhost/hello");
+        assert_eq!(
+            entry.request.post_data.as_ref().unwrap().mime_type,
+            "application/json"
+        );
+        assert_eq!(entry.response.status, 200);
+        assert_eq!(
+            entry.response.content.text.as_deref(),
+            Some("{\"foo\": \"bar\"}")
+        );
+
+        let json = serde_json::to_value(&har).unwrap();
+        assert_eq!(json["log"]["version"], HAR_VERSION);
+        assert_eq!(json["log"]["entries"][0]["response"]["redirectURL"], "");
+    }
+
+    #[rstest]
+    fn test_export_context() {
+        let transcript = Transcript::new(
+            fixtures::request::GET_WITH_HEADER,
+            fixtures::response::OK_JSON,
+        );
+        let received = transcript.received();
+        let bar = received
+            .windows(3)
+            .position(|window| window == b"bar")
+            .unwrap();
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..received.len()).difference(&RangeSet::from(bar..bar + 3)),
+        );
+        let context = HttpContext::builder(partial).build().unwrap();
+
+        let har = Har::from_context(&context);
+        let entry = &har.log.entries[0];
+        assert_eq!(entry.request.url, "https://developer.mozilla.org/home.html");
+        assert_eq!(
+            entry.response.content.text.as_deref(),
+            Some("{\"foo\": *****, \"bazz\": 123, \"buzz\": [1, 5]}")
+        );
+        assert!(har.log.comment.is_some());
+    }
+
+    #[rstest]
+    #[case::empty(b"", "")]
+    #[case::one(b"f", "Zg==")]
+    #[case::two(b"fo", "Zm8=")]
+    #[case::three(b"foo", "Zm9v")]
+    #[case::binary(&[0, 0xff, 0x10, 0x80], "AP8QgA==")]
+    fn test_base64(#[case] data: &[u8], #[case] encoded: &str) {
+        assert_eq!(STANDARD.encode(data), encoded);
+        assert_eq!(base64_decode(encoded).unwrap(), data);
+    }
+
+    #[rstest]
+    #[case::unpadded("Zm8", b"fo")]
+    #[case::whitespace("Zm9v\r\nZm9v", b"foofoo")]
+    fn test_base64_decode(#[case] encoded: &str, #[case] data: &[u8]) {
+        assert_eq!(base64_decode(encoded).unwrap(), data);
+    }
+
+    #[rstest]
+    #[case::length("Zg=")]
+    #[case::alphabet("Z*==")]
+    #[case::single("Z")]
+    fn test_base64_invalid(#[case] encoded: &str) {
+        assert_eq!(base64_decode(encoded), None);
+    }
+}
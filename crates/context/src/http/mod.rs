@@ -1,12 +1,42 @@
 //! Tooling for working with HTTP data.
 
+pub mod builder;
 pub mod commit;
 pub mod context;
-//pub mod enforce;
+pub mod enforce;
+pub mod estimate;
+pub mod har;
+pub mod policy;
+pub mod presentation;
+pub mod soundness;
+pub mod template;
 pub mod transcript;
 
-pub use commit::{DefaultHttpCommitter, HttpCommit, HttpCommitError};
-pub use context::{HttpContext, BodyContext, RequestContext, ResponseContext};
+pub use builder::{
+    BuiltHttpTranscript, HeaderSpans, HttpTranscriptBuilder, HttpTranscriptSpans, RequestBuilder,
+    RequestSpans, ResponseBuilder, ResponseSpans,
+};
+pub use commit::{
+    BodyRule, ConfigurableHttpCommitter, ConfigurableHttpCommitterBuilder, DefaultHttpCommitter,
+    HeaderRule, HttpCommit, HttpCommitError, TargetGranularity,
+};
+pub use estimate::{estimate, CostBreakdown, EstimateError, HeaderCost, HttpCostReport, MessageCost};
+pub use har::{
+    Har, HarContent, HarCreator, HarEntry, HarError, HarHeader, HarImport, HarIssue, HarIssueKind,
+    HarLog, HarParam, HarPostData, HarRequest, HarResponse, HarTimings, HAR_VERSION,
+};
+pub use enforce::{HttpEnforceError, HttpEnforcer, Mismatch, MismatchKind};
+pub use context::{
+    BodyContext, ExchangeContext, HeaderField, Headers, HttpContext, HttpContextBuilder,
+    HttpContextError, RequestContext, ResponseContext, CONTEXT_SCHEMA, CONTEXT_VERSION,
+};
+pub use policy::{CompiledPolicy, DisclosurePolicy, PolicyEntry, PolicyError, Selector};
+pub use presentation::{PresentationContext, PresentationError};
+pub use soundness::{check_disclosure, SoundnessError};
+pub use template::{
+    BodyTemplate, HeaderTemplate, HttpTemplate, JsonTemplate, RequestTemplate, ResponseTemplate,
+    TemplateError,
+};
 pub use transcript::HttpTranscript;
 
 #[doc(hidden)]
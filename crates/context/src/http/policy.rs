@@ -0,0 +1,551 @@
+//! Declarative disclosure policies.
+//!
+//! A [`DisclosurePolicy`] describes which parts of an HTTP transcript should be
+//! revealed to a Verifier, e.g. "reveal the method, target path and `Host`
+//! header of every request, and the status and `$.data.user.id` of every
+//! response". Compiling a policy against an [`HttpTranscript`] yields the sent
+//! and received [`RangeSet`]s for [`Transcript::to_partial`].
+//!
+//! Policies are serialisable, so they can be written in any serde format:
+//!
+//! ```toml
+//! default = "structure"
+//!
+//! [[reveal]]
+//! message = "request"
+//! select = "method"
+//!
+//! [[reveal]]
+//! message = "request"
+//! select = { header = "Host" }
+//!
+//! [[reveal]]
+//! message = "response"
+//! select = { json = "$.data.user.id" }
+//! ```
+
+use rangeset::{RangeSet, ToRangeSet, Union};
+use serde::{Deserialize, Serialize};
+use spanner::{json::JsonValue, Spanned};
+
+use crate::{
+    http::{
+        transcript::MessageKind, BodyContent, DefaultHttpCommitter, HttpCommit, HttpTranscript,
+    },
+    transcript::{
+        Direction, DryRunBuilder, PartialTranscript, Transcript, TranscriptCommitmentBuilderError,
+    },
+};
+
+/// What to reveal of the parts of a transcript which no entry selects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultDisclosure {
+    /// Reveal nothing.
+    Hidden,
+    /// Reveal the structure, as defined by [`HttpCommit::commit_structure`].
+    #[default]
+    Structure,
+}
+
+/// A part of an HTTP message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Selector {
+    /// The entire message.
+    Message,
+    /// The request method.
+    Method,
+    /// The entire request target.
+    Target,
+    /// The request target excluding the query.
+    TargetPath,
+    /// The response status code.
+    Status,
+    /// The response reason phrase.
+    Reason,
+    /// Every header with the given name (case-insensitive).
+    Header(String),
+    /// The entire body.
+    Body,
+    /// The value at the given path within a JSON body, e.g. `$.data.items[0]`.
+    ///
+    /// Keys containing `.` are not supported.
+    Json(String),
+}
+
+/// A policy entry selecting part of a request or response to reveal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyEntry {
+    /// The kind of message the entry applies to.
+    pub message: MessageKind,
+    /// The index of the message to apply the entry to, or every message of
+    /// that kind if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// The part of the message to reveal.
+    pub select: Selector,
+}
+
+impl PolicyEntry {
+    /// Creates a new entry applying to every message of the given kind.
+    pub fn new(message: MessageKind, select: Selector) -> Self {
+        Self {
+            message,
+            index: None,
+            select,
+        }
+    }
+
+    /// Restricts the entry to the message with the given index.
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+}
+
+/// A declarative disclosure policy.
+///
+/// See the [module level documentation](crate::http::policy) for more
+/// information.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosurePolicy {
+    /// What to reveal of the parts of the transcript no entry selects.
+    #[serde(default)]
+    pub default: DefaultDisclosure,
+    /// Parts of the transcript to reveal.
+    #[serde(default)]
+    pub reveal: Vec<PolicyEntry>,
+}
+
+impl DisclosurePolicy {
+    /// Creates a new policy.
+    pub fn new(default: DefaultDisclosure) -> Self {
+        Self {
+            default,
+            reveal: Vec::new(),
+        }
+    }
+
+    /// Adds an entry to the policy.
+    pub fn reveal(mut self, entry: PolicyEntry) -> Self {
+        self.reveal.push(entry);
+        self
+    }
+
+    /// Compiles the policy against an HTTP transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The transcript to compile the policy against.
+    pub fn compile(&self, transcript: &HttpTranscript) -> Result<CompiledPolicy, PolicyError> {
+        let (mut sent, mut received) = (RangeSet::default(), RangeSet::default());
+
+        if self.default == DefaultDisclosure::Structure {
+            let len_sent = transcript
+                .requests
+                .iter()
+                .filter_map(|request| request.to_range_set().end())
+                .max()
+                .unwrap_or(0);
+            let len_received = transcript
+                .responses
+                .iter()
+                .filter_map(|response| response.to_range_set().end())
+                .max()
+                .unwrap_or(0);
+
+            let mut builder = DryRunBuilder::with_len(len_sent, len_received);
+            DefaultHttpCommitter::default()
+                .commit_structure(&mut builder, transcript)
+                .map_err(PolicyError::Structure)?;
+
+            let structure = builder.commitment_set();
+            sent = structure.coverage(Direction::Sent);
+            received = structure.coverage(Direction::Received);
+        }
+
+        let mut unmatched = Vec::new();
+
+        for (i, entry) in self.reveal.iter().enumerate() {
+            let path = match &entry.select {
+                Selector::Json(path) => Some(parse_json_path(path)?),
+                _ => None,
+            };
+
+            let mut matched = false;
+
+            match entry.message {
+                MessageKind::Request => {
+                    for (idx, request) in transcript.requests.iter().enumerate() {
+                        if entry.index.is_some_and(|index| index != idx) {
+                            continue;
+                        }
+
+                        let ranges = match &entry.select {
+                            Selector::Message => Some(request.to_range_set()),
+                            Selector::Method => Some(request.request.method.to_range_set()),
+                            Selector::Target => Some(request.request.target.to_range_set()),
+                            Selector::TargetPath => {
+                                let target = request.request.target.span();
+                                let start = target.indices().min().unwrap_or(0);
+                                let len =
+                                    target.as_str().find('?').unwrap_or(target.as_str().len());
+                                Some(RangeSet::from(start..start + len))
+                            }
+                            Selector::Header(name) => request
+                                .headers_with_name(name)
+                                .map(|header| header.to_range_set())
+                                .reduce(|acc, idx| acc.union(&idx)),
+                            Selector::Body => request.body.as_ref().map(|body| body.to_range_set()),
+                            Selector::Json(_) => request
+                                .body
+                                .as_ref()
+                                .and_then(|body| json_ranges(&body.content, path.as_deref()?)),
+                            Selector::Status | Selector::Reason => None,
+                        };
+
+                        if let Some(ranges) = ranges.filter(|ranges| !ranges.is_empty()) {
+                            sent = sent.union(&ranges);
+                            matched = true;
+                        }
+                    }
+                }
+                MessageKind::Response => {
+                    for (idx, response) in transcript.responses.iter().enumerate() {
+                        if entry.index.is_some_and(|index| index != idx) {
+                            continue;
+                        }
+
+                        let ranges = match &entry.select {
+                            Selector::Message => Some(response.to_range_set()),
+                            Selector::Status => Some(response.status.code.to_range_set()),
+                            Selector::Reason => Some(response.status.reason.to_range_set()),
+                            Selector::Header(name) => response
+                                .headers_with_name(name)
+                                .map(|header| header.to_range_set())
+                                .reduce(|acc, idx| acc.union(&idx)),
+                            Selector::Body => {
+                                response.body.as_ref().map(|body| body.to_range_set())
+                            }
+                            Selector::Json(_) => response
+                                .body
+                                .as_ref()
+                                .and_then(|body| json_ranges(&body.content, path.as_deref()?)),
+                            Selector::Method | Selector::Target | Selector::TargetPath => None,
+                        };
+
+                        if let Some(ranges) = ranges.filter(|ranges| !ranges.is_empty()) {
+                            received = received.union(&ranges);
+                            matched = true;
+                        }
+                    }
+                }
+            }
+
+            if !matched {
+                unmatched.push(i);
+            }
+        }
+
+        Ok(CompiledPolicy {
+            sent,
+            received,
+            unmatched,
+        })
+    }
+}
+
+/// A policy compiled against an HTTP transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledPolicy {
+    /// Sent ranges to reveal.
+    pub sent: RangeSet<usize>,
+    /// Received ranges to reveal.
+    pub received: RangeSet<usize>,
+    /// Indices of the policy entries which matched nothing in the transcript.
+    pub unmatched: Vec<usize>,
+}
+
+impl CompiledPolicy {
+    /// Returns a partial transcript revealing the compiled ranges.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transcript is not the one the policy was compiled against.
+    pub fn to_partial(&self, transcript: &Transcript) -> PartialTranscript {
+        transcript.to_partial(self.sent.clone(), self.received.clone())
+    }
+}
+
+/// Error for [`DisclosurePolicy::compile`].
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    /// A JSON path in the policy is malformed.
+    #[error("invalid JSON path \"{0}\"")]
+    InvalidJsonPath(String),
+    /// The structure of the transcript could not be collected.
+    #[error("failed to collect transcript structure: {0}")]
+    Structure(#[source] TranscriptCommitmentBuilderError),
+}
+
+/// Converts a JSON path such as `$.data.items[0].id` into the dotted form used
+/// by [`JsonValue::get`].
+fn parse_json_path(path: &str) -> Result<String, PolicyError> {
+    let invalid = || PolicyError::InvalidJsonPath(path.to_string());
+
+    let rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+    let mut chars = rest.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut segment = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    segment.push(c);
+                    chars.next();
+                }
+                if segment.is_empty() {
+                    return Err(invalid());
+                }
+                segments.push(segment);
+            }
+            '[' => {
+                let mut segment = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    segment.push(c);
+                }
+                if !closed {
+                    return Err(invalid());
+                }
+                let segment = segment.trim_matches(|c| c == '"' || c == '\'');
+                // Segments are joined with `.`, so keys containing one can not
+                // be resolved.
+                if segment.is_empty() || segment.contains('.') {
+                    return Err(invalid());
+                }
+                segments.push(segment.to_string());
+            }
+            _ if segments.is_empty() && !path.starts_with('$') => {
+                // Allow paths without the leading `$.`.
+                let mut segment = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    segment.push(c);
+                    chars.next();
+                }
+                segments.push(segment);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(segments.join("."))
+}
+
+/// Returns the ranges of the JSON value at the given path in a body.
+fn json_ranges(content: &BodyContent, path: &str) -> Option<RangeSet<usize>> {
+    let BodyContent::Json(json) = content else {
+        return None;
+    };
+
+    let value = if path.is_empty() {
+        json
+    } else {
+        json.get(path)?
+    };
+
+    match value {
+        JsonValue::Redacted(_) => None,
+        value => Some(value.to_range_set()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::fixtures::http as fixtures;
+
+    const USER_RESPONSE: &[u8] = b"\
+        HTTP/1.1 200 OK\r\n\
+        Content-Type: application/json\r\n\
+        Set-Cookie: session=secret\r\n\
+        Content-Length: 58\r\n\r\n\
+        {\"data\": {\"user\": {\"id\": 1234, \"name\": \"Alice\"}}, \"ok\": 1}";
+
+    fn policy() -> DisclosurePolicy {
+        DisclosurePolicy::new(DefaultDisclosure::Structure)
+            .reveal(PolicyEntry::new(MessageKind::Request, Selector::Method))
+            .reveal(PolicyEntry::new(MessageKind::Request, Selector::TargetPath))
+            .reveal(PolicyEntry::new(
+                MessageKind::Request,
+                Selector::Header("host".to_string()),
+            ))
+            .reveal(PolicyEntry::new(MessageKind::Response, Selector::Status))
+            .reveal(PolicyEntry::new(
+                MessageKind::Response,
+                Selector::Json("$.data.user.id".to_string()),
+            ))
+    }
+
+    #[rstest]
+    fn test_policy_compile() {
+        let transcript = Transcript::new(fixtures::request::GET_WITH_HEADER, USER_RESPONSE);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let compiled = policy().compile(&http).unwrap();
+        assert!(compiled.unmatched.is_empty());
+
+        let partial = compiled.to_partial(&transcript);
+        let parsed = HttpTranscript::parse_partial(&partial).unwrap();
+
+        let request = &parsed.requests[0];
+        assert_eq!(request.request.method.as_str(), "GET");
+        assert_eq!(request.request.target.as_str(), "/home.html");
+        assert_eq!(
+            request
+                .headers_with_name("host")
+                .next()
+                .unwrap()
+                .value
+                .as_bytes(),
+            b"developer.mozilla.org"
+        );
+        assert_eq!(
+            request
+                .headers_with_name("accept")
+                .next()
+                .unwrap()
+                .value
+                .as_bytes(),
+            b"***"
+        );
+
+        let response = &parsed.responses[0];
+        assert_eq!(response.status.code.as_str(), "200");
+        assert_eq!(
+            response
+                .headers_with_name("set-cookie")
+                .next()
+                .unwrap()
+                .value
+                .as_bytes(),
+            b"**************"
+        );
+
+        let Some(BodyContent::Json(json)) = response.body.as_ref().map(|body| &body.content) else {
+            panic!("body is not json");
+        };
+        assert_eq!(json.get("data.user.id").unwrap().span(), "1234");
+        assert_eq!(json.get("data.user.name").unwrap().span(), "*****");
+        assert_eq!(json.get("ok").unwrap().span(), "*");
+    }
+
+    #[rstest]
+    fn test_policy_hidden_default() {
+        let transcript = Transcript::new(fixtures::request::GET_WITH_HEADER, USER_RESPONSE);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let compiled = DisclosurePolicy::new(DefaultDisclosure::Hidden)
+            .reveal(PolicyEntry::new(MessageKind::Response, Selector::Status))
+            .compile(&http)
+            .unwrap();
+
+        assert!(compiled.sent.is_empty());
+        assert_eq!(
+            compiled.received,
+            http.responses[0].status.code.to_range_set()
+        );
+    }
+
+    #[rstest]
+    fn test_policy_unmatched() {
+        let transcript = Transcript::new(fixtures::request::GET_WITH_HEADER, USER_RESPONSE);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let compiled = policy()
+            .reveal(PolicyEntry::new(
+                MessageKind::Request,
+                Selector::Header("authorization".to_string()),
+            ))
+            .reveal(PolicyEntry::new(
+                MessageKind::Response,
+                Selector::Json("$.data.missing".to_string()),
+            ))
+            .reveal(PolicyEntry::new(MessageKind::Response, Selector::Status).with_index(1))
+            .reveal(PolicyEntry::new(MessageKind::Request, Selector::Body))
+            .compile(&http)
+            .unwrap();
+
+        assert_eq!(compiled.unmatched, vec![5, 6, 7, 8]);
+    }
+
+    #[rstest]
+    #[case::dotted("$.data.items[0].id", "data.items.0.id")]
+    #[case::quoted("$['data'].id", "data.id")]
+    #[case::bare("data.id", "data.id")]
+    #[case::root("$", "")]
+    fn test_parse_json_path(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(parse_json_path(path).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::empty_segment("$..id")]
+    #[case::trailing_dot("$.data.")]
+    #[case::dotted_key("$['a.b']")]
+    #[case::unclosed_index("$.a[0")]
+    #[case::unclosed_key("$.a[\"b")]
+    fn test_parse_json_path_invalid(#[case] path: &str) {
+        assert!(parse_json_path(path).is_err());
+    }
+
+    #[rstest]
+    fn test_policy_serialization() {
+        let policy = policy();
+
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(
+            policy,
+            serde_json::from_str::<DisclosurePolicy>(&json).unwrap()
+        );
+
+        let toml = r#"
+            default = "structure"
+
+            [[reveal]]
+            message = "request"
+            select = "method"
+
+            [[reveal]]
+            message = "request"
+            select = "target_path"
+
+            [[reveal]]
+            message = "request"
+            select = { header = "host" }
+
+            [[reveal]]
+            message = "response"
+            select = "status"
+
+            [[reveal]]
+            message = "response"
+            select = { json = "$.data.user.id" }
+        "#;
+
+        assert_eq!(policy, toml::from_str::<DisclosurePolicy>(toml).unwrap());
+    }
+}
@@ -0,0 +1,503 @@
+//! HTTP contexts bound to the TLS connection they were presented from.
+//!
+//! A [`PresentationContext`] combines an [`HttpContext`] with the server name
+//! and connection information of the TLS session. The server name is
+//! expected to be verified beforehand, e.g. with
+//! [`HandshakeData::verify`](crate::tls::HandshakeData::verify), so that the
+//! requests can be checked against it: the `Host` header and absolute-form
+//! targets of every request must name the same server.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    context::Redactable,
+    http::context::{HttpContext, RequestContext},
+    tls::connection::{ConnectionInfo, ServerName, TlsVersion},
+    transcript::{Direction, TranscriptLength},
+};
+
+/// An [`HttpContext`] bound to a TLS connection with a verified server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedPresentationContext")]
+pub struct PresentationContext {
+    server_name: ServerName,
+    connection: ConnectionInfo,
+    http: HttpContext,
+}
+
+#[derive(Deserialize)]
+struct UncheckedPresentationContext {
+    server_name: ServerName,
+    connection: ConnectionInfo,
+    http: HttpContext,
+}
+
+impl TryFrom<UncheckedPresentationContext> for PresentationContext {
+    type Error = PresentationError;
+
+    fn try_from(value: UncheckedPresentationContext) -> Result<Self, Self::Error> {
+        Self::new(value.server_name, value.connection, value.http)
+    }
+}
+
+impl PresentationContext {
+    /// Creates a new presentation context.
+    ///
+    /// Requests whose `Host` header or target is redacted can not be checked
+    /// and are accepted, see [`PresentationContext::new_strict`] to reject
+    /// them instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_name` - The verified name of the server.
+    /// * `connection` - Information about the TLS connection.
+    /// * `http` - The context of the transcript of the connection.
+    pub fn new(
+        server_name: ServerName,
+        connection: ConnectionInfo,
+        http: HttpContext,
+    ) -> Result<Self, PresentationError> {
+        Self::new_with(server_name, connection, http, false)
+    }
+
+    /// Creates a new presentation context, rejecting requests whose `Host`
+    /// header or target is redacted.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_name` - The verified name of the server.
+    /// * `connection` - Information about the TLS connection.
+    /// * `http` - The context of the transcript of the connection.
+    pub fn new_strict(
+        server_name: ServerName,
+        connection: ConnectionInfo,
+        http: HttpContext,
+    ) -> Result<Self, PresentationError> {
+        Self::new_with(server_name, connection, http, true)
+    }
+
+    fn new_with(
+        server_name: ServerName,
+        connection: ConnectionInfo,
+        http: HttpContext,
+        strict: bool,
+    ) -> Result<Self, PresentationError> {
+        check_length(&http, &connection.transcript_length)?;
+        for (index, exchange) in http.exchanges().iter().enumerate() {
+            check_request(index, exchange.request(), &server_name, strict)?;
+        }
+
+        Ok(Self {
+            server_name,
+            connection,
+            http,
+        })
+    }
+
+    /// Returns the verified name of the server.
+    pub fn server_name(&self) -> &ServerName {
+        &self.server_name
+    }
+
+    /// Returns the TLS version of the connection.
+    pub fn version(&self) -> TlsVersion {
+        self.connection.version
+    }
+
+    /// Returns the UNIX time when the connection started.
+    pub fn time(&self) -> u64 {
+        self.connection.time
+    }
+
+    /// Returns the lengths of the transcript.
+    pub fn transcript_length(&self) -> &TranscriptLength {
+        &self.connection.transcript_length
+    }
+
+    /// Returns the connection information.
+    pub fn connection(&self) -> &ConnectionInfo {
+        &self.connection
+    }
+
+    /// Returns the HTTP context.
+    pub fn http(&self) -> &HttpContext {
+        &self.http
+    }
+}
+
+/// Error for [`PresentationContext`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PresentationError {
+    /// The `Host` header of a request names another server.
+    #[error("request {index} has host \"{host}\", expected \"{server_name}\"")]
+    HostMismatch {
+        /// The index of the request in the transcript.
+        index: usize,
+        /// The presented `Host` header.
+        host: String,
+        /// The verified server name.
+        server_name: ServerName,
+    },
+    /// The absolute-form or authority-form target of a request names another
+    /// server.
+    #[error("request {index} has target \"{target}\", expected host \"{server_name}\"")]
+    TargetMismatch {
+        /// The index of the request in the transcript.
+        index: usize,
+        /// The presented target.
+        target: String,
+        /// The verified server name.
+        server_name: ServerName,
+    },
+    /// A request has more than one `Host` header.
+    #[error("request {index} has {count} host headers")]
+    MultipleHosts {
+        /// The index of the request in the transcript.
+        index: usize,
+        /// The number of `Host` headers.
+        count: usize,
+    },
+    /// The `Host` header of a request is redacted.
+    #[error("request {index} has a redacted host header")]
+    RedactedHost {
+        /// The index of the request in the transcript.
+        index: usize,
+    },
+    /// The target of a request is redacted.
+    #[error("request {index} has a redacted target")]
+    RedactedTarget {
+        /// The index of the request in the transcript.
+        index: usize,
+    },
+    /// The `Host` header of a request is not a valid host.
+    #[error("request {index} has an invalid host \"{host}\"")]
+    InvalidHost {
+        /// The index of the request in the transcript.
+        index: usize,
+        /// The presented `Host` header.
+        host: String,
+    },
+    /// The context refers to data beyond the length of the transcript.
+    #[error("{direction} data ends at {end}, beyond the transcript length {len}")]
+    TranscriptLength {
+        /// The direction of the data.
+        direction: Direction,
+        /// The end of the data.
+        end: usize,
+        /// The length of the transcript.
+        len: usize,
+    },
+}
+
+/// Checks that the exchanges are within the transcript.
+fn check_length(http: &HttpContext, length: &TranscriptLength) -> Result<(), PresentationError> {
+    for exchange in http.exchanges() {
+        for (direction, ranges, len) in [
+            (Direction::Sent, exchange.sent(), length.sent),
+            (Direction::Received, exchange.received(), length.received),
+        ] {
+            let len = len as usize;
+            if let Some(end) = ranges.end().filter(|end| *end > len) {
+                return Err(PresentationError::TranscriptLength {
+                    direction,
+                    end,
+                    len,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the `Host` header and target of a request name the server.
+///
+/// Redacted values are rejected if `strict` is set and skipped otherwise.
+fn check_request(
+    index: usize,
+    request: &RequestContext,
+    server_name: &ServerName,
+    strict: bool,
+) -> Result<(), PresentationError> {
+    let hosts: Vec<_> = request.headers().get_all("host").collect();
+    match hosts.as_slice() {
+        [] => {}
+        [Redactable::Revealed { value: host }] => {
+            let name = authority_host(host).ok_or_else(|| PresentationError::InvalidHost {
+                index,
+                host: host.clone(),
+            })?;
+            if !names_server(&name, server_name) {
+                return Err(PresentationError::HostMismatch {
+                    index,
+                    host: host.clone(),
+                    server_name: server_name.clone(),
+                });
+            }
+        }
+        [_] if strict => return Err(PresentationError::RedactedHost { index }),
+        [_] => {}
+        hosts => {
+            return Err(PresentationError::MultipleHosts {
+                index,
+                count: hosts.len(),
+            })
+        }
+    }
+
+    let Some(target) = request.target().revealed() else {
+        if strict {
+            return Err(PresentationError::RedactedTarget { index });
+        }
+        return Ok(());
+    };
+
+    let name = if request.method().revealed() == Some(&http::Method::CONNECT) {
+        authority_host(target)
+    } else if target.starts_with('/') || target == "*" {
+        return Ok(());
+    } else {
+        Url::parse(target)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+    };
+
+    match name {
+        Some(name) if names_server(&name, server_name) => Ok(()),
+        _ => Err(PresentationError::TargetMismatch {
+            index,
+            target: target.clone(),
+            server_name: server_name.clone(),
+        }),
+    }
+}
+
+/// Returns the normalized host of an authority, i.e. a host and optional port.
+fn authority_host(authority: &str) -> Option<String> {
+    if authority.is_empty() || authority.contains(['/', '?', '#', '@']) {
+        return None;
+    }
+
+    Url::parse(&format!("http://{authority}"))
+        .ok()?
+        .host_str()
+        .map(str::to_string)
+}
+
+/// Returns whether a normalized host names the server.
+fn names_server(host: &str, server_name: &ServerName) -> bool {
+    match server_name {
+        ServerName::Dns(name) => host
+            .trim_end_matches('.')
+            .eq_ignore_ascii_case(name.as_str().trim_end_matches('.')),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rangeset::{Difference, RangeSet};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{tls::connection::DnsName, transcript::Transcript};
+
+    const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+
+    fn server_name() -> ServerName {
+        ServerName::Dns(DnsName::try_from("api.example.com").unwrap())
+    }
+
+    fn connection(transcript: &Transcript) -> ConnectionInfo {
+        ConnectionInfo {
+            time: 1748736000,
+            version: TlsVersion::V1_2,
+            transcript_length: transcript.length(),
+        }
+    }
+
+    fn presentation(request: &[u8]) -> Result<PresentationContext, PresentationError> {
+        let transcript = Transcript::new(request, OK);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+        let http = HttpContext::builder(partial).build().unwrap();
+
+        PresentationContext::new(server_name(), connection(&transcript), http)
+    }
+
+    #[rstest]
+    #[case::host(b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n")]
+    #[case::host_case_and_port(b"GET / HTTP/1.1\r\nHost: API.Example.com:443\r\n\r\n")]
+    #[case::host_trailing_dot(b"GET / HTTP/1.1\r\nHost: api.example.com.\r\n\r\n")]
+    #[case::no_host(b"GET / HTTP/1.1\r\n\r\n")]
+    #[case::absolute_target(
+        b"GET https://api.example.com/v1?q=1 HTTP/1.1\r\nHost: api.example.com\r\n\r\n"
+    )]
+    #[case::asterisk(b"OPTIONS * HTTP/1.1\r\nHost: api.example.com\r\n\r\n")]
+    #[case::connect(b"CONNECT api.example.com:443 HTTP/1.1\r\nHost: api.example.com:443\r\n\r\n")]
+    fn test_presentation(#[case] request: &[u8]) {
+        let context = presentation(request).unwrap();
+
+        assert_eq!(context.server_name(), &server_name());
+        assert_eq!(context.version(), TlsVersion::V1_2);
+        assert_eq!(context.time(), 1748736000);
+        assert_eq!(context.transcript_length().sent as usize, request.len());
+        assert_eq!(context.http().exchanges().len(), 1);
+    }
+
+    #[rstest]
+    #[case::host(
+        b"GET / HTTP/1.1\r\nHost: evil.example.com\r\n\r\n",
+        PresentationError::HostMismatch {
+            index: 0,
+            host: "evil.example.com".to_string(),
+            server_name: server_name(),
+        }
+    )]
+    #[case::subdomain(
+        b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        PresentationError::HostMismatch {
+            index: 0,
+            host: "example.com".to_string(),
+            server_name: server_name(),
+        }
+    )]
+    #[case::absolute_target(
+        b"GET https://evil.example.com/ HTTP/1.1\r\nHost: api.example.com\r\n\r\n",
+        PresentationError::TargetMismatch {
+            index: 0,
+            target: "https://evil.example.com/".to_string(),
+            server_name: server_name(),
+        }
+    )]
+    #[case::connect(
+        b"CONNECT evil.example.com:443 HTTP/1.1\r\n\r\n",
+        PresentationError::TargetMismatch {
+            index: 0,
+            target: "evil.example.com:443".to_string(),
+            server_name: server_name(),
+        }
+    )]
+    #[case::userinfo(
+        b"GET / HTTP/1.1\r\nHost: api.example.com@evil.example.com\r\n\r\n",
+        PresentationError::InvalidHost {
+            index: 0,
+            host: "api.example.com@evil.example.com".to_string(),
+        }
+    )]
+    #[case::multiple_hosts(
+        b"GET / HTTP/1.1\r\nHost: api.example.com\r\nHost: evil.example.com\r\n\r\n",
+        PresentationError::MultipleHosts { index: 0, count: 2 }
+    )]
+    fn test_presentation_mismatch(#[case] request: &[u8], #[case] expected: PresentationError) {
+        assert_eq!(presentation(request).unwrap_err(), expected);
+    }
+
+    #[rstest]
+    fn test_presentation_second_request() {
+        let request = b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n\
+                        GET / HTTP/1.1\r\nHost: evil.example.com\r\n\r\n";
+        let transcript = Transcript::new(request, [OK, OK].concat());
+        let partial = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+        let http = HttpContext::builder(partial).build().unwrap();
+
+        assert!(matches!(
+            PresentationContext::new(server_name(), connection(&transcript), http).unwrap_err(),
+            PresentationError::HostMismatch { index: 1, .. }
+        ));
+    }
+
+    #[rstest]
+    fn test_presentation_redacted_host() {
+        let request = b"GET / HTTP/1.1\r\nHost: evil.example.com\r\n\r\n";
+        let transcript = Transcript::new(request, OK);
+        let host = RangeSet::from(22..38);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..request.len()).difference(&host),
+            RangeSet::from(0..OK.len()),
+        );
+        let http = HttpContext::builder(partial).build().unwrap();
+
+        assert!(PresentationContext::new(server_name(), connection(&transcript), http).is_ok());
+    }
+
+    #[rstest]
+    #[case::host(22..37, PresentationError::RedactedHost { index: 0 })]
+    #[case::target(4..5, PresentationError::RedactedTarget { index: 0 })]
+    fn test_presentation_strict_redacted(
+        #[case] hidden: std::ops::Range<usize>,
+        #[case] expected: PresentationError,
+    ) {
+        let request = b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n";
+        let transcript = Transcript::new(request, OK);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..request.len()).difference(&RangeSet::from(hidden)),
+            RangeSet::from(0..OK.len()),
+        );
+        let http = HttpContext::builder(partial).build().unwrap();
+
+        assert_eq!(
+            PresentationContext::new_strict(server_name(), connection(&transcript), http)
+                .unwrap_err(),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_presentation_strict() {
+        let request = b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n";
+        let transcript = Transcript::new(request, OK);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..request.len()),
+            RangeSet::from(0..OK.len()),
+        );
+        let http = HttpContext::builder(partial).build().unwrap();
+
+        assert!(
+            PresentationContext::new_strict(server_name(), connection(&transcript), http).is_ok()
+        );
+    }
+
+    #[rstest]
+    fn test_presentation_transcript_length() {
+        let request = b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n";
+        let transcript = Transcript::new(request, OK);
+        let partial = transcript.to_partial(
+            RangeSet::from(0..request.len()),
+            RangeSet::from(0..OK.len()),
+        );
+        let http = HttpContext::builder(partial).build().unwrap();
+
+        let mut connection = connection(&transcript);
+        connection.transcript_length.received = 10;
+
+        assert_eq!(
+            PresentationContext::new(server_name(), connection, http).unwrap_err(),
+            PresentationError::TranscriptLength {
+                direction: Direction::Received,
+                end: OK.len(),
+                len: 10,
+            }
+        );
+    }
+
+    #[rstest]
+    fn test_presentation_roundtrip() {
+        let context = presentation(b"GET / HTTP/1.1\r\nHost: api.example.com\r\n\r\n").unwrap();
+
+        let json = serde_json::to_value(&context).unwrap();
+        let decoded: PresentationContext = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(decoded, context);
+
+        // The binding is checked again when deserializing.
+        let mut tampered = json;
+        tampered["server_name"] = serde_json::json!({"Dns": "evil.example.com"});
+        let err = serde_json::from_value::<PresentationContext>(tampered).unwrap_err();
+        assert!(err.to_string().contains("expected \"evil.example.com\""));
+    }
+}
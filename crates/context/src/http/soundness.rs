@@ -0,0 +1,419 @@
+//! Disclosure soundness checks.
+//!
+//! Revealing a value without its surrounding structure is ambiguous: the bytes
+//! `"admin": true` may be a field of an object, or text inside a string
+//! literal. Similarly, a revealed header line may actually be part of a body.
+//!
+//! [`check_disclosure`] verifies that every revealed node of an
+//! [`HttpTranscript`] has its enclosing structure authenticated, using the
+//! same ranges as [`HttpCommit::commit_structure`](crate::http::HttpCommit::commit_structure).
+
+use std::fmt;
+
+use rangeset::{Difference, Disjoint, RangeSet, ToRangeSet, Union};
+use spanner::Spanned;
+
+use crate::{
+    http::{transcript::MessageKind, Body, BodyContent, Header, HttpTranscript},
+    json::JsonValue,
+    transcript::PartialTranscript,
+};
+
+/// Headers which determine how a message body is framed and interpreted.
+const FRAMING_HEADERS: [&str; 3] = ["content-length", "content-type", "transfer-encoding"];
+
+/// A node of an HTTP message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// The message itself, i.e. the start line and header terminator.
+    Message,
+    /// A header with the given name.
+    Header(String),
+    /// The message body.
+    Body,
+    /// A value within a JSON body at the given path.
+    Json(String),
+    /// A trailer with the given name.
+    Trailer(String),
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Message => write!(f, "message"),
+            Node::Header(name) => write!(f, "header \"{name}\""),
+            Node::Body => write!(f, "body"),
+            Node::Json(path) => write!(f, "json value {path}"),
+            Node::Trailer(name) => write!(f, "trailer \"{name}\""),
+        }
+    }
+}
+
+/// A revealed node whose structural context is not authenticated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The kind of message containing the node.
+    pub kind: MessageKind,
+    /// The index of the message in the transcript.
+    pub index: usize,
+    /// The node lacking context.
+    pub node: Node,
+    /// The structural ranges which are not authenticated.
+    pub missing: RangeSet<usize>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            MessageKind::Request => "request",
+            MessageKind::Response => "response",
+        };
+
+        write!(
+            f,
+            "{kind} {}: {} is revealed but its structure is not authenticated at {:?}",
+            self.index, self.node, self.missing
+        )
+    }
+}
+
+/// Error for [`check_disclosure`].
+#[derive(Debug, thiserror::Error)]
+#[error("unsound disclosure: {}", .violations[0])]
+pub struct SoundnessError {
+    violations: Vec<Violation>,
+}
+
+impl SoundnessError {
+    /// Returns every violation found, in transcript order.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+/// Checks that every revealed node of an HTTP transcript has its enclosing
+/// structure authenticated.
+///
+/// A node is revealed if any of its bytes are authenticated. For each revealed
+/// node the following must also be authenticated:
+///
+/// - Message: the start line and header terminator.
+/// - Header or trailer: the name, separator and line ending.
+/// - Body: the framing headers and, if chunked, the chunk boundaries.
+/// - JSON object: the braces, separators and each key.
+/// - JSON array: the brackets and separators.
+/// - JSON string: the quotation marks.
+///
+/// # Arguments
+///
+/// * `transcript` - The HTTP transcript, parsed from either the full or the
+///   partial transcript.
+/// * `partial` - The partial transcript being presented.
+pub fn check_disclosure(
+    transcript: &HttpTranscript,
+    partial: &PartialTranscript,
+) -> Result<(), SoundnessError> {
+    let mut violations = Vec::new();
+
+    for (index, request) in transcript.requests.iter().enumerate() {
+        let mut ctx = Context {
+            kind: MessageKind::Request,
+            index,
+            authed: partial.sent_authed(),
+            violations: &mut violations,
+        };
+
+        ctx.check(Node::Message, request, &request.without_data());
+
+        for header in &request.headers {
+            ctx.check_header(header, false);
+        }
+
+        if let Some(body) = &request.body {
+            let framing = framing_headers(request.headers.iter());
+            ctx.check_body(body, framing);
+        }
+    }
+
+    for (index, response) in transcript.responses.iter().enumerate() {
+        let mut ctx = Context {
+            kind: MessageKind::Response,
+            index,
+            authed: partial.received_authed(),
+            violations: &mut violations,
+        };
+
+        ctx.check(Node::Message, response, &response.without_data());
+
+        for header in &response.headers {
+            ctx.check_header(header, false);
+        }
+
+        if let Some(body) = &response.body {
+            let mut framing = framing_headers(response.headers.iter());
+            if let Some(boundaries) = &response.boundaries {
+                framing = boundaries
+                    .iter()
+                    .fold(framing, |acc, boundary| acc.union(&boundary.to_range_set()));
+            }
+            ctx.check_body(body, framing);
+        }
+
+        if let Some(trailers) = &response.trailers {
+            for trailer in trailers {
+                ctx.check_header(trailer, true);
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(SoundnessError { violations })
+    }
+}
+
+/// Returns whether a partial transcript is a sound disclosure of an HTTP
+/// transcript.
+///
+/// See [`check_disclosure`].
+pub fn is_sound(transcript: &HttpTranscript, partial: &PartialTranscript) -> bool {
+    check_disclosure(transcript, partial).is_ok()
+}
+
+fn framing_headers<'a>(headers: impl Iterator<Item = &'a Header>) -> RangeSet<usize> {
+    headers
+        .filter(|header| {
+            FRAMING_HEADERS
+                .iter()
+                .any(|name| header.name.as_str().eq_ignore_ascii_case(name))
+        })
+        .fold(RangeSet::default(), |acc, header| {
+            acc.union(&header.to_range_set())
+        })
+}
+
+struct Context<'a> {
+    kind: MessageKind,
+    index: usize,
+    authed: &'a RangeSet<usize>,
+    violations: &'a mut Vec<Violation>,
+}
+
+impl Context<'_> {
+    /// Checks that if any byte of `node` is revealed, all of `structure` is
+    /// authenticated.
+    fn check(&mut self, node: Node, ranges: &dyn ToRangeSet<usize>, structure: &RangeSet<usize>) {
+        if ranges.to_range_set().is_disjoint(self.authed) {
+            return;
+        }
+
+        let missing = structure.difference(self.authed);
+        if !missing.is_empty() {
+            self.violations.push(Violation {
+                kind: self.kind,
+                index: self.index,
+                node,
+                missing,
+            });
+        }
+    }
+
+    fn check_header(&mut self, header: &Header, trailer: bool) {
+        let name = header.name.as_str().to_string();
+        let node = if trailer {
+            Node::Trailer(name)
+        } else {
+            Node::Header(name)
+        };
+
+        self.check(node, header, &header.without_value());
+    }
+
+    fn check_body(&mut self, body: &Body, framing: RangeSet<usize>) {
+        self.check(Node::Body, body, &framing);
+
+        if let BodyContent::Json(json) = &body.content {
+            self.check_json(json, &mut String::from("$"));
+        }
+    }
+
+    fn check_json(&mut self, value: &JsonValue, path: &mut String) {
+        match value {
+            JsonValue::Object(object) => {
+                let structure = object.elems.iter().fold(object.without_pairs(), |acc, kv| {
+                    acc.union(&kv.without_value())
+                });
+                self.check(Node::Json(path.clone()), value, &structure);
+
+                for kv in &object.elems {
+                    let len = path.len();
+                    path.push('.');
+                    path.push_str(kv.key.span().as_str());
+                    self.check_json(&kv.value, path);
+                    path.truncate(len);
+                }
+            }
+            JsonValue::Array(array) => {
+                let structure = array.without_values().union(&array.separators());
+                self.check(Node::Json(path.clone()), value, &structure);
+
+                for (i, elem) in array.elems.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{i}]"));
+                    self.check_json(elem, path);
+                    path.truncate(len);
+                }
+            }
+            JsonValue::String(string) => {
+                let ranges = string.to_range_set();
+                if let (Some(start), Some(end)) = (ranges.min(), ranges.end()) {
+                    let quotes = RangeSet::from([start.saturating_sub(1)..start, end..end + 1]);
+                    self.check(Node::Json(path.clone()), value, &quotes);
+                }
+            }
+            JsonValue::Number(_) | JsonValue::Bool(_) | JsonValue::Null(_) => {}
+            JsonValue::Redacted(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::{
+        fixtures::http as fixtures,
+        http::{DefaultHttpCommitter, HttpCommit},
+        transcript::{Direction, HashCommitmentBuilder, Transcript},
+    };
+
+    fn structure(
+        transcript: &Transcript,
+        http: &HttpTranscript,
+    ) -> (RangeSet<usize>, RangeSet<usize>) {
+        let mut builder = HashCommitmentBuilder::new(transcript);
+        DefaultHttpCommitter::default()
+            .commit_structure(&mut builder, http)
+            .unwrap();
+
+        builder.commits().iter().fold(
+            Default::default(),
+            |(sent, recv): (RangeSet<usize>, RangeSet<usize>), (direction, idx)| match direction {
+                Direction::Sent => (sent.union(idx), recv),
+                Direction::Received => (sent, recv.union(idx)),
+            },
+        )
+    }
+
+    fn json(http: &HttpTranscript) -> &JsonValue {
+        match &http.responses[0].body.as_ref().unwrap().content {
+            BodyContent::Json(json) => json,
+            _ => panic!("body is not json"),
+        }
+    }
+
+    #[rstest]
+    #[case::get_empty(fixtures::request::GET_EMPTY, fixtures::response::OK_EMPTY)]
+    #[case::get_with_header(
+        fixtures::request::GET_WITH_HEADER,
+        fixtures::response::OK_EMPTY_HEADER
+    )]
+    #[case::post_json(fixtures::request::POST_JSON, fixtures::response::OK_JSON)]
+    #[case::chunked(fixtures::request::GET_EMPTY, fixtures::response::OK_CHUNKED_JSON)]
+    fn test_check_full_and_structure(#[case] sent: &[u8], #[case] recv: &[u8]) {
+        let transcript = Transcript::new(sent, recv);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let full = transcript.to_partial(
+            RangeSet::from(0..transcript.sent().len()),
+            RangeSet::from(0..transcript.received().len()),
+        );
+        check_disclosure(&http, &full).unwrap();
+
+        let (sent, recv) = structure(&transcript, &http);
+        let partial = transcript.to_partial(sent, recv);
+        check_disclosure(&http, &partial).unwrap();
+        check_disclosure(&HttpTranscript::parse_partial(&partial).unwrap(), &partial).unwrap();
+    }
+
+    #[rstest]
+    fn test_check_leaf_with_structure() {
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, fixtures::response::OK_JSON);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let (sent, recv) = structure(&transcript, &http);
+        let recv = recv.union(&json(&http).get("foo").unwrap().to_range_set());
+
+        assert!(is_sound(&http, &transcript.to_partial(sent, recv)));
+    }
+
+    #[rstest]
+    fn test_check_string_without_quotes() {
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, fixtures::response::OK_JSON);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let partial = transcript.to_partial(
+            RangeSet::default(),
+            json(&http).get("foo").unwrap().to_range_set(),
+        );
+
+        let err = check_disclosure(&http, &partial).unwrap_err();
+        let nodes = err
+            .violations()
+            .iter()
+            .map(|violation| violation.node.clone())
+            .collect::<Vec<_>>();
+
+        assert!(nodes.contains(&Node::Message));
+        assert!(nodes.contains(&Node::Body));
+        assert!(nodes.contains(&Node::Json("$".to_string())));
+        assert!(nodes.contains(&Node::Json("$.foo".to_string())));
+    }
+
+    #[rstest]
+    fn test_check_body_without_framing() {
+        let transcript = Transcript::new(fixtures::request::GET_EMPTY, fixtures::response::OK_TEXT);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let response = &http.responses[0];
+
+        // Reveal the body and message structure, but not the Content-Length
+        // value, so the body could be a header line.
+        let recv = response
+            .without_data()
+            .union(&response.body.as_ref().unwrap().to_range_set());
+        let recv = response
+            .headers
+            .iter()
+            .fold(recv, |acc, header| acc.union(&header.without_value()));
+        let partial = transcript.to_partial(RangeSet::default(), recv);
+
+        let err = check_disclosure(&http, &partial).unwrap_err();
+        assert_eq!(err.violations().len(), 1);
+
+        let violation = &err.violations()[0];
+        assert_eq!(violation.kind, MessageKind::Response);
+        assert_eq!(violation.index, 0);
+        assert_eq!(violation.node, Node::Body);
+    }
+
+    #[rstest]
+    fn test_check_header_value_only() {
+        let transcript = Transcript::new(
+            fixtures::request::GET_WITH_HEADER,
+            fixtures::response::OK_EMPTY,
+        );
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let request = &http.requests[0];
+
+        let host = request.headers_with_name("host").next().unwrap();
+        let sent = request.without_data().union(&host.value.to_range_set());
+        let partial = transcript.to_partial(sent, RangeSet::default());
+
+        let err = check_disclosure(&http, &partial).unwrap_err();
+        assert_eq!(err.violations().len(), 1);
+        assert_eq!(err.violations()[0].node, Node::Header("Host".to_string()));
+        assert_eq!(err.violations()[0].missing, host.without_value());
+    }
+}
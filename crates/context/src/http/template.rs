@@ -0,0 +1,695 @@
+//! Structure templates.
+//!
+//! An [`HttpTemplate`] describes the expected shape of an HTTP transcript for
+//! [`HttpEnforcer`](crate::http::HttpEnforcer), without having to construct
+//! raw HTTP bytes. Templates are serialisable, so they can be stored alongside
+//! a verifier's configuration and loaded at startup:
+//!
+//! ```toml
+//! version = 1
+//!
+//! [[requests]]
+//! method = "GET"
+//! target = "/api/balance"
+//! headers = [{ name = "Host", value = "bank.example" }, { name = "Authorization" }]
+//!
+//! [[responses]]
+//! status = 200
+//! body = { type = "json", shape = { account = "*", balance = "*" } }
+//! ```
+//!
+//! Within a JSON shape the string `"*"` matches any value, and an object with
+//! a single `$match` key matches values satisfying a [`Predicate`]:
+//!
+//! ```json
+//! { "balance": { "$match": { "number": { "gte": 1000 } } } }
+//! ```
+//!
+//! Targets and header values accept a [`Matcher`](crate::matcher::Matcher).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use spanner::Spanned;
+
+use crate::{
+    http::{BodyContent, Header, HttpTranscript, Request, Response},
+    json::{ArrayStrategy, JsonPointer, JsonValue},
+    matcher::{Matcher, MatcherError, Predicate},
+};
+
+/// The current template format version.
+pub const TEMPLATE_VERSION: u32 = 1;
+
+/// The wildcard which matches any value.
+const WILDCARD: &str = "*";
+
+/// The key of a JSON object which holds a predicate.
+const MATCH_KEY: &str = "$match";
+
+/// Headers which are not included in templates derived from a transcript, as
+/// their values depend on the presentation.
+const DERIVED_IGNORED_HEADERS: [&str; 1] = ["content-length"];
+
+/// Template error.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    /// The template version is not supported.
+    #[error("unsupported template version {0}, expected {TEMPLATE_VERSION}")]
+    UnsupportedVersion(u32),
+    /// A JSON shape could not be converted into a structure.
+    #[error("invalid json shape: {0}")]
+    InvalidShape(#[from] spanner::ParseError),
+    /// A matcher is invalid.
+    #[error(transparent)]
+    Matcher(#[from] MatcherError),
+}
+
+/// A template describing expected HTTP requests and responses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpTemplate {
+    /// The template format version.
+    pub version: u32,
+    /// The expected requests, in order.
+    #[serde(default)]
+    pub requests: Vec<RequestTemplate>,
+    /// The expected responses, in order.
+    #[serde(default)]
+    pub responses: Vec<ResponseTemplate>,
+}
+
+impl HttpTemplate {
+    /// Creates a new builder.
+    pub fn builder() -> HttpTemplateBuilder {
+        HttpTemplateBuilder::default()
+    }
+
+    /// Validates the template.
+    ///
+    /// Returns an error if the version is not supported, or a JSON shape is
+    /// invalid.
+    pub fn validate(&self) -> Result<(), TemplateError> {
+        if self.version != TEMPLATE_VERSION {
+            return Err(TemplateError::UnsupportedVersion(self.version));
+        }
+
+        let matchers = self
+            .requests
+            .iter()
+            .flat_map(|request| request.target.iter().chain(header_matchers(&request.headers)))
+            .chain(
+                self.responses
+                    .iter()
+                    .flat_map(|response| header_matchers(&response.headers)),
+            );
+
+        for matcher in matchers {
+            matcher.validate()?;
+        }
+
+        let bodies = self
+            .requests
+            .iter()
+            .filter_map(|request| request.body.as_ref())
+            .chain(
+                self.responses
+                    .iter()
+                    .filter_map(|response| response.body.as_ref()),
+            );
+
+        for body in bodies {
+            if let BodyTemplate::Json { shape, .. } = body {
+                shape.to_json_value()?;
+                for (_, predicate) in shape.predicates() {
+                    predicate.validate()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&HttpTranscript> for HttpTemplate {
+    /// Derives a template from a parsed transcript, where redacted values
+    /// match any value.
+    fn from(transcript: &HttpTranscript) -> Self {
+        Self {
+            version: TEMPLATE_VERSION,
+            requests: transcript
+                .requests
+                .iter()
+                .map(RequestTemplate::from)
+                .collect(),
+            responses: transcript
+                .responses
+                .iter()
+                .map(ResponseTemplate::from)
+                .collect(),
+        }
+    }
+}
+
+/// Builder for [`HttpTemplate`].
+#[derive(Debug, Default)]
+pub struct HttpTemplateBuilder {
+    requests: Vec<RequestTemplate>,
+    responses: Vec<ResponseTemplate>,
+}
+
+impl HttpTemplateBuilder {
+    /// Adds an expected request.
+    pub fn request(mut self, request: RequestTemplate) -> Self {
+        self.requests.push(request);
+        self
+    }
+
+    /// Adds an expected response.
+    pub fn response(mut self, response: ResponseTemplate) -> Self {
+        self.responses.push(response);
+        self
+    }
+
+    /// Builds the template.
+    pub fn build(self) -> HttpTemplate {
+        HttpTemplate {
+            version: TEMPLATE_VERSION,
+            requests: self.requests,
+            responses: self.responses,
+        }
+    }
+}
+
+/// A template for an HTTP request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RequestTemplate {
+    /// The expected method, or any method if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// The expected target, or any target if `None`.
+    ///
+    /// A target starting with `/` also matches an absolute URL with the same
+    /// path and query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<Matcher>,
+    /// Headers which must be present.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<HeaderTemplate>,
+    /// The expected body, or no requirement if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<BodyTemplate>,
+}
+
+impl RequestTemplate {
+    /// Creates a new request template which matches any request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the expected method.
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    /// Sets the expected target.
+    pub fn target(mut self, target: impl Into<Matcher>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Adds a required header.
+    pub fn header(mut self, header: HeaderTemplate) -> Self {
+        self.headers.push(header);
+        self
+    }
+
+    /// Sets the expected body.
+    pub fn body(mut self, body: BodyTemplate) -> Self {
+        self.body = Some(body);
+        self
+    }
+}
+
+impl From<&Request> for RequestTemplate {
+    fn from(request: &Request) -> Self {
+        Self {
+            method: non_wildcard(request.request.method.as_str()),
+            target: non_wildcard(request.request.target.as_str()).map(Matcher::Exact),
+            headers: derive_headers(&request.headers),
+            body: request.body.as_ref().map(|body| (&body.content).into()),
+        }
+    }
+}
+
+/// A template for an HTTP response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseTemplate {
+    /// The expected status code, or any status if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Headers which must be present.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<HeaderTemplate>,
+    /// The expected body, or no requirement if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<BodyTemplate>,
+}
+
+impl ResponseTemplate {
+    /// Creates a new response template which matches any response.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the expected status code.
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Adds a required header.
+    pub fn header(mut self, header: HeaderTemplate) -> Self {
+        self.headers.push(header);
+        self
+    }
+
+    /// Sets the expected body.
+    pub fn body(mut self, body: BodyTemplate) -> Self {
+        self.body = Some(body);
+        self
+    }
+}
+
+impl From<&Response> for ResponseTemplate {
+    fn from(response: &Response) -> Self {
+        Self {
+            status: response.status.code.as_str().parse().ok(),
+            headers: derive_headers(&response.headers),
+            body: response.body.as_ref().map(|body| (&body.content).into()),
+        }
+    }
+}
+
+/// A template for an HTTP header.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeaderTemplate {
+    /// The header name, matched case-insensitively.
+    pub name: String,
+    /// The expected value, or any value if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Matcher>,
+}
+
+impl HeaderTemplate {
+    /// Creates a template for a header which must be present with any value.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: None,
+        }
+    }
+
+    /// Sets the expected value.
+    pub fn value(mut self, value: impl Into<Matcher>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+}
+
+/// A template for an HTTP body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BodyTemplate {
+    /// A JSON body.
+    Json {
+        /// The expected shape of the body.
+        shape: JsonTemplate,
+        /// The strategy for matching arrays, overriding the enforcer's.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        array_strategy: Option<ArrayStrategy>,
+    },
+    /// A body with an unknown content type.
+    Text {
+        /// The expected content, or any content if `None`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+    },
+}
+
+impl BodyTemplate {
+    /// Creates a template for a JSON body with the given shape.
+    pub fn json(shape: JsonTemplate) -> Self {
+        Self::Json {
+            shape,
+            array_strategy: None,
+        }
+    }
+
+    /// Creates a template for a body with an unknown content type.
+    pub fn text(content: Option<String>) -> Self {
+        Self::Text { content }
+    }
+}
+
+impl From<&BodyContent> for BodyTemplate {
+    fn from(content: &BodyContent) -> Self {
+        match content {
+            BodyContent::Json(json) => Self::json(json.into()),
+            content => Self::text(
+                Some(String::from_utf8_lossy(content.span().as_bytes()).to_string())
+                    .filter(|content| !is_wildcard(content)),
+            ),
+        }
+    }
+}
+
+/// The expected shape of a JSON value.
+///
+/// Serialised as plain JSON, where the string `"*"` matches any value and an
+/// object with a single `$match` key holds a [`Predicate`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Value", into = "Value")]
+pub enum JsonTemplate {
+    /// Matches any value.
+    Any,
+    /// A null value.
+    Null,
+    /// A boolean value.
+    Bool(bool),
+    /// A number value.
+    Number(serde_json::Number),
+    /// A string value.
+    String(String),
+    /// An array, matched according to the array strategy.
+    Array(Vec<JsonTemplate>),
+    /// An object which must contain each of the keys.
+    ///
+    /// Keys are kept in document order, so that a template derived from a
+    /// transcript survives a round trip through its serialised form. This
+    /// relies on the `preserve_order` feature of `serde_json`.
+    Object(Vec<(String, JsonTemplate)>),
+    /// Matches values satisfying the predicate.
+    Match(Predicate),
+}
+
+impl JsonTemplate {
+    /// Converts the template into a JSON structure, where [`JsonTemplate::Any`]
+    /// is redacted.
+    pub fn to_json_value(&self) -> Result<JsonValue, TemplateError> {
+        let mut src = String::new();
+        self.render(&mut src);
+
+        Ok(spanner::json::parse_str(&src)?)
+    }
+
+    /// Returns the predicates in the template, with their paths.
+    ///
+    /// Paths within arrays refer to positions, regardless of the array
+    /// strategy.
+    pub fn predicates(&self) -> Vec<(JsonPointer, &Predicate)> {
+        let mut predicates = Vec::new();
+        self.collect_predicates(JsonPointer::root(), &mut predicates);
+        predicates
+    }
+
+    fn collect_predicates<'a>(
+        &'a self,
+        path: JsonPointer,
+        predicates: &mut Vec<(JsonPointer, &'a Predicate)>,
+    ) {
+        match self {
+            JsonTemplate::Match(predicate) => predicates.push((path, predicate)),
+            JsonTemplate::Array(elems) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    elem.collect_predicates(path.join(i), predicates);
+                }
+            }
+            JsonTemplate::Object(pairs) => {
+                for (key, value) in pairs {
+                    value.collect_predicates(path.join(key), predicates);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        match self {
+            // Predicates are enforced separately, so match any value here.
+            JsonTemplate::Any | JsonTemplate::Match(_) => out.push('*'),
+            JsonTemplate::Array(elems) => {
+                out.push('[');
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    elem.render(out);
+                }
+                out.push(']');
+            }
+            JsonTemplate::Object(pairs) => {
+                out.push('{');
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(&Value::String(key.clone()).to_string());
+                    out.push_str(": ");
+                    value.render(out);
+                }
+                out.push('}');
+            }
+            primitive => out.push_str(&Value::from(primitive.clone()).to_string()),
+        }
+    }
+}
+
+impl From<Value> for JsonTemplate {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::String(s) if s == WILDCARD => JsonTemplate::Any,
+            Value::Null => JsonTemplate::Null,
+            Value::Bool(b) => JsonTemplate::Bool(b),
+            Value::Number(n) => JsonTemplate::Number(n),
+            Value::String(s) => JsonTemplate::String(s),
+            Value::Array(elems) => JsonTemplate::Array(elems.into_iter().map(Into::into).collect()),
+            Value::Object(map) if map.len() == 1 && map.contains_key(MATCH_KEY) => {
+                match serde_json::from_value(map[MATCH_KEY].clone()) {
+                    Ok(predicate) => JsonTemplate::Match(predicate),
+                    Err(_) => JsonTemplate::Object(
+                        map.into_iter().map(|(k, v)| (k, v.into())).collect(),
+                    ),
+                }
+            }
+            Value::Object(map) => {
+                JsonTemplate::Object(map.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+impl From<JsonTemplate> for Value {
+    fn from(template: JsonTemplate) -> Self {
+        match template {
+            JsonTemplate::Any => Value::String(WILDCARD.to_string()),
+            JsonTemplate::Null => Value::Null,
+            JsonTemplate::Bool(b) => Value::Bool(b),
+            JsonTemplate::Number(n) => Value::Number(n),
+            JsonTemplate::String(s) => Value::String(s),
+            JsonTemplate::Array(elems) => Value::Array(elems.into_iter().map(Into::into).collect()),
+            JsonTemplate::Object(pairs) => {
+                Value::Object(pairs.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+            JsonTemplate::Match(predicate) => {
+                let mut map = serde_json::Map::new();
+                map.insert(
+                    MATCH_KEY.to_string(),
+                    serde_json::to_value(predicate).unwrap_or(Value::Null),
+                );
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+impl From<&JsonValue> for JsonTemplate {
+    /// Derives a template from a parsed JSON value, where redacted values
+    /// match any value.
+    fn from(value: &JsonValue) -> Self {
+        let raw = value.span().as_str();
+
+        match value {
+            JsonValue::Redacted(_) => JsonTemplate::Any,
+            JsonValue::Bool(_) | JsonValue::Number(_) | JsonValue::String(_)
+                if is_wildcard(raw) =>
+            {
+                JsonTemplate::Any
+            }
+            JsonValue::Null(_) => JsonTemplate::Null,
+            JsonValue::Bool(_) => JsonTemplate::Bool(raw == "true"),
+            JsonValue::Number(_) => serde_json::from_str(raw)
+                .map(JsonTemplate::Number)
+                .unwrap_or(JsonTemplate::Any),
+            JsonValue::String(_) => JsonTemplate::String(
+                serde_json::from_str(&format!("\"{raw}\"")).unwrap_or_else(|_| raw.to_string()),
+            ),
+            JsonValue::Array(array) => {
+                JsonTemplate::Array(array.elems.iter().map(Into::into).collect())
+            }
+            JsonValue::Object(object) => JsonTemplate::Object(
+                object
+                    .elems
+                    .iter()
+                    .map(|kv| (kv.key.span().as_str().to_string(), (&kv.value).into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Returns whether a redacted value matches any value.
+pub(crate) fn is_wildcard(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|b| b == b'*')
+}
+
+fn non_wildcard(value: &str) -> Option<String> {
+    (!is_wildcard(value)).then(|| value.to_string())
+}
+
+fn header_matchers(headers: &[HeaderTemplate]) -> impl Iterator<Item = &Matcher> {
+    headers.iter().filter_map(|header| header.value.as_ref())
+}
+
+fn derive_headers(headers: &[Header]) -> Vec<HeaderTemplate> {
+    headers
+        .iter()
+        .filter(|header| {
+            !DERIVED_IGNORED_HEADERS
+                .iter()
+                .any(|name| header.name.as_str().eq_ignore_ascii_case(name))
+        })
+        .map(|header| HeaderTemplate {
+            name: header.name.as_str().to_string(),
+            value: non_wildcard(&String::from_utf8_lossy(header.value.as_bytes()))
+                .map(Matcher::Exact),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::{fixtures::http as fixtures, transcript::Transcript};
+
+    fn template() -> HttpTemplate {
+        HttpTemplate::builder()
+            .request(
+                RequestTemplate::new()
+                    .method("GET")
+                    .target("/api/balance")
+                    .header(HeaderTemplate::new("Host").value("bank.example"))
+                    .header(HeaderTemplate::new("Authorization")),
+            )
+            .response(ResponseTemplate::new().status(200).body(BodyTemplate::json(
+                JsonTemplate::Object(vec![
+                    ("account".to_string(), JsonTemplate::Any),
+                    ("balance".to_string(), JsonTemplate::Any),
+                ]),
+            )))
+            .build()
+    }
+
+    #[rstest]
+    fn test_template_toml() {
+        let toml = r#"
+            version = 1
+
+            [[requests]]
+            method = "GET"
+            target = "/api/balance"
+            headers = [{ name = "Host", value = "bank.example" }, { name = "Authorization" }]
+
+            [[responses]]
+            status = 200
+            body = { type = "json", shape = { account = "*", balance = "*" } }
+        "#;
+
+        assert_eq!(toml::from_str::<HttpTemplate>(toml).unwrap(), template());
+    }
+
+    #[rstest]
+    fn test_template_json_roundtrip() {
+        let template = template();
+        let json = serde_json::to_string(&template).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<HttpTemplate>(&json).unwrap(),
+            template
+        );
+    }
+
+    #[rstest]
+    fn test_template_roundtrip_key_order() {
+        let transcript = Transcript::new(fixtures::request::POST_JSON, fixtures::response::OK_TEXT);
+        let template = HttpTemplate::from(&HttpTranscript::parse(&transcript).unwrap());
+
+        // The keys of the body are not sorted, and must keep their order.
+        let json = serde_json::to_string(&template).unwrap();
+        assert!(json.find("\"foo\"").unwrap() < json.find("\"bazz\"").unwrap());
+        assert_eq!(
+            serde_json::from_str::<HttpTemplate>(&json).unwrap(),
+            template
+        );
+    }
+
+    #[rstest]
+    fn test_template_validate_version() {
+        let mut template = template();
+        template.validate().unwrap();
+
+        template.version = TEMPLATE_VERSION + 1;
+        assert!(matches!(
+            template.validate(),
+            Err(TemplateError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_json_template_to_json_value() {
+        let template: JsonTemplate =
+            serde_json::from_str(r#"{"a": "*", "b": [1, "x\"y", null, true]}"#).unwrap();
+        let value = template.to_json_value().unwrap();
+
+        assert!(matches!(value.get("a"), Some(JsonValue::Redacted(_))));
+        assert_eq!(value.get("b.1").unwrap().span().as_str(), "x\\\"y");
+    }
+
+    #[rstest]
+    fn test_template_from_transcript() {
+        let transcript = Transcript::new(fixtures::request::POST_JSON, fixtures::response::OK_TEXT);
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let template = HttpTemplate::from(&http);
+
+        assert_eq!(
+            template.requests[0],
+            RequestTemplate::new()
+                .method("POST")
+                .target("/hello")
+                .header(HeaderTemplate::new("Host").value("localhost"))
+                .header(HeaderTemplate::new("Content-Type").value("application/json"))
+                .body(BodyTemplate::json(
+                    serde_json::from_str(r#"{"foo": "bar", "bazz": 123, "buzz": [1, 5]}"#).unwrap()
+                ))
+        );
+        assert_eq!(
+            template.responses[0],
+            ResponseTemplate::new()
+                .status(200)
+                .header(HeaderTemplate::new("Content-Type").value("text/plain"))
+                .body(BodyTemplate::text(Some("Hello, world!\n".to_string())))
+        );
+    }
+}
@@ -1,17 +1,17 @@
 use bytes::Bytes;
 pub use crate::http::commit::{DefaultHttpCommitter, HttpCommit, HttpCommitError};
-pub use crate::http::context::{HttpContext, BodyContext, RequestContext, ResponseContext};
+pub use crate::http::context::{HttpContext, BodyContext, ExchangeContext, RequestContext, ResponseContext};
 
 #[doc(hidden)]
 pub use spanner::http;
-use spanner::json::JsonValue;
 
-use spanner::http::{Request, Response, Requests, Responses, BodyContent};
+use spanner::http::{Request, Response, Requests, Responses};
 
-use crate::transcript::{Transcript, PartialTranscript, Direction, TranscriptCommitmentBuilder, TranscriptCommitmentBuilderError};
+use crate::transcript::{Transcript, PartialTranscript};
 
 /// The kind of HTTP message.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MessageKind {
     /// An HTTP request.
     Request,
@@ -19,6 +19,15 @@ pub enum MessageKind {
     Response,
 }
 
+impl std::fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageKind::Request => write!(f, "request"),
+            MessageKind::Response => write!(f, "response"),
+        }
+    }
+}
+
 /// An HTTP transcript.
 #[derive(Debug)]
 pub struct HttpTranscript {
@@ -1,5 +1,7 @@
 use std::error::Error;
 
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
 use spanner::{json::KeyValue, Spanned};
 
 use crate::{
@@ -53,7 +55,12 @@ impl JsonCommitError {
 
 /// A JSON committer.
 pub trait JsonCommit<C: TranscriptCommitmentBuilder> {
-    fn commit_structure(&self, builder: &mut C, direction: Direction, json: &JsonValue) -> Result<(), TranscriptCommitmentBuilderError> {
+    fn commit_structure(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        json: &JsonValue,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
         match json {
             JsonValue::Object(object) => {
                 // Reveal the object structure without its pairs
@@ -65,7 +72,7 @@ pub trait JsonCommit<C: TranscriptCommitmentBuilder> {
                         builder.commit(&object.without_pairs(), direction)?;
                     }
                 }
-    
+
                 // Reveal each key-value pair structure
                 for keyvalue in &object.elems {
                     match direction {
@@ -76,7 +83,7 @@ pub trait JsonCommit<C: TranscriptCommitmentBuilder> {
                             builder.commit(&keyvalue.without_value(), direction)?;
                         }
                     }
-    
+
                     // Recursively commit the value's structure
                     self.commit_structure(builder, direction, &keyvalue.value)?;
                 }
@@ -93,7 +100,7 @@ pub trait JsonCommit<C: TranscriptCommitmentBuilder> {
                         builder.commit(&array.separators(), direction)?;
                     }
                 }
-                
+
                 for value in &array.elems {
                     self.commit_structure(builder, direction, value)?;
                 }
@@ -315,17 +322,100 @@ pub struct DefaultJsonCommitter {}
 
 impl<C: TranscriptCommitmentBuilder> JsonCommit<C> for DefaultJsonCommitter {}
 
-/*
+/// A JSON committer whose notion of structure is configurable.
+///
+/// With the default configuration it commits the same structure as
+/// [`DefaultJsonCommitter`].
+#[derive(Debug, Clone, PartialEq, Eq, Builder, Serialize, Deserialize)]
+#[builder(default)]
+#[serde(default)]
+pub struct ConfigurableJsonCommitter {
+    /// Maximum depth at which structure is committed, where the root is at
+    /// depth 0. Values nested more deeply are hidden as a whole.
+    #[builder(setter(strip_option))]
+    pub max_depth: Option<usize>,
+    /// Whether the separators between array elements are structural. If not,
+    /// the number of elements in an array is not revealed by its structure.
+    pub array_separators: bool,
+}
+
+impl ConfigurableJsonCommitter {
+    /// Returns a builder for the committer.
+    pub fn builder() -> ConfigurableJsonCommitterBuilder {
+        ConfigurableJsonCommitterBuilder::default()
+    }
+
+    fn commit_structure_at<C: TranscriptCommitmentBuilder>(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        json: &JsonValue,
+        depth: usize,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return Ok(());
+        }
+
+        match json {
+            JsonValue::Object(object) => {
+                builder.commit(&object.without_pairs(), direction)?;
+
+                for keyvalue in &object.elems {
+                    builder.commit(&keyvalue.without_value(), direction)?;
+                    self.commit_structure_at(builder, direction, &keyvalue.value, depth + 1)?;
+                }
+            }
+            JsonValue::Array(array) => {
+                builder.commit(&array.without_values(), direction)?;
+                if self.array_separators {
+                    builder.commit(&array.separators(), direction)?;
+                }
+
+                for value in &array.elems {
+                    self.commit_structure_at(builder, direction, value, depth + 1)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ConfigurableJsonCommitter {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            array_separators: true,
+        }
+    }
+}
+
+impl<C: TranscriptCommitmentBuilder> JsonCommit<C> for ConfigurableJsonCommitter {
+    fn commit_structure(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        json: &JsonValue,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        self.commit_structure_at(builder, direction, json, 0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::json as fixtures;
+    use crate::transcript::{HashCommitmentBuilder, Transcript};
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    use crate::transcript::{HashCommitment, TranscriptCommitment};
+    use rangeset::{Difference, Disjoint, RangeSet, ToRangeSet, Union};
     use rstest::*;
-    use spansy::json::{parse_slice, JsonValue, JsonVisit};
-    use tlsn_core::transcript::{
-        Transcript, TranscriptCommitConfig, TranscriptCommitConfigBuilder,
-    };
-    use tlsn_data_fixtures::json as fixtures;
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    use spanner::json::JsonVisit;
+    use spanner::json::{parse_slice, JsonValue};
 
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
     #[rstest]
     #[case::array(fixtures::ARRAY)]
     #[case::integer(fixtures::INTEGER)]
@@ -335,28 +425,28 @@ mod tests {
         let transcript = Transcript::new([], src);
         let json_data = parse_slice(src).unwrap();
         let mut committer = DefaultJsonCommitter::default();
-        let mut builder = TranscriptCommitConfigBuilder::new(&transcript);
+        let mut builder = HashCommitmentBuilder::new(&transcript);
 
         committer
             .commit_value(&mut builder, &json_data, Direction::Received)
             .unwrap();
 
-        let config = builder.build().unwrap();
+        let (commitment, _) = builder.finalize().unwrap();
 
         struct CommitChecker<'a> {
-            config: &'a TranscriptCommitConfig,
+            commitment: &'a HashCommitment,
         }
-        impl<'a> JsonVisit for CommitChecker<'a> {
+        impl JsonVisit for CommitChecker<'_> {
             fn visit_value(&mut self, node: &JsonValue) {
                 match node {
                     JsonValue::Object(obj) => {
                         assert!(self
-                            .config
+                            .commitment
                             .contains(&obj.without_pairs(), Direction::Received));
 
                         for kv in &obj.elems {
                             assert!(self
-                                .config
+                                .commitment
                                 .contains(&kv.without_value(), Direction::Received));
                         }
 
@@ -365,7 +455,7 @@ mod tests {
 
                     JsonValue::Array(arr) => {
                         assert!(self
-                            .config
+                            .commitment
                             .contains(&arr.without_values(), Direction::Received));
 
                         JsonVisit::visit_array(self, arr);
@@ -374,7 +464,7 @@ mod tests {
                     _ => {
                         if !node.span().is_empty() {
                             assert!(
-                                self.config.contains(node, Direction::Received),
+                                self.commitment.contains(node, Direction::Received),
                                 "failed to commit to value ({}), at {:?}",
                                 node.span().as_str(),
                                 node.span()
@@ -385,7 +475,90 @@ mod tests {
             }
         }
 
-        CommitChecker { config: &config }.visit_value(&json_data);
+        CommitChecker {
+            commitment: &commitment,
+        }
+        .visit_value(&json_data);
+    }
+
+    fn structure<J>(committer: &J, src: &'static [u8]) -> RangeSet<usize>
+    where
+        J: for<'a> JsonCommit<HashCommitmentBuilder<'a>>,
+    {
+        let transcript = Transcript::new([], src);
+        let json = parse_slice(src).unwrap();
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+
+        committer
+            .commit_structure(&mut builder, Direction::Received, &json)
+            .unwrap();
+
+        builder
+            .commits()
+            .iter()
+            .fold(RangeSet::default(), |acc, (_, ranges)| acc.union(ranges))
+    }
+
+    #[rstest]
+    #[case::array(fixtures::ARRAY)]
+    #[case::json_object(fixtures::NESTED_OBJECT)]
+    #[case::values(fixtures::VALUES)]
+    fn test_configurable_json_commit_default(#[case] src: &'static [u8]) {
+        assert_eq!(
+            structure(&ConfigurableJsonCommitter::default(), src),
+            structure(&DefaultJsonCommitter::default(), src)
+        );
+    }
+
+    #[rstest]
+    fn test_configurable_json_commit() {
+        const SRC: &[u8] = br#"{"a": {"b": [1, 2]}, "c": [3, 4]}"#;
+
+        let JsonValue::Object(object) = parse_slice(SRC).unwrap() else {
+            panic!("expected an object");
+        };
+        let JsonValue::Array(array) = &object.elems[1].value else {
+            panic!("expected an array");
+        };
+
+        // Values below the root are hidden as a whole.
+        let shallow = ConfigurableJsonCommitter::builder()
+            .max_depth(0)
+            .build()
+            .unwrap();
+        let hidden = object.elems[0]
+            .value
+            .to_range_set()
+            .union(&array.to_range_set());
+        assert_eq!(
+            structure(&shallow, SRC),
+            RangeSet::from(0..SRC.len()).difference(&hidden)
+        );
+
+        let no_separators = ConfigurableJsonCommitter::builder()
+            .array_separators(false)
+            .build()
+            .unwrap();
+        assert!(structure(&no_separators, SRC).is_disjoint(&array.separators()));
+    }
+
+    #[rstest]
+    fn test_configurable_json_commit_serde() {
+        let committer = ConfigurableJsonCommitter::builder()
+            .max_depth(2)
+            .array_separators(false)
+            .build()
+            .unwrap();
+
+        let json = serde_json::to_string(&committer).unwrap();
+        assert_eq!(json, r#"{"max_depth":2,"array_separators":false}"#);
+        assert_eq!(
+            serde_json::from_str::<ConfigurableJsonCommitter>(&json).unwrap(),
+            committer
+        );
+        assert_eq!(
+            serde_json::from_str::<ConfigurableJsonCommitter>("{}").unwrap(),
+            ConfigurableJsonCommitter::default()
+        );
     }
 }
-*/
\ No newline at end of file
@@ -1,101 +1,287 @@
+use rangeset::{RangeSet, ToRangeSet, Union};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as SerdeJsonValue;
 use spanner::json::JsonValue;
-use spanner::json as types;
 use spanner::Spanned;
 
-use serde::{Serialize, Serializer};
-use serde_json::Value as SerdeJsonValue;
-use std::collections::HashMap;
+use crate::context::Redactable;
 
 /// A verifier of contextual integrity for JSON.
 ///
 /// See the [module level documentation](crate::context) for more information.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct JsonContext {
-    value: JsonValue,
+    value: JsonNode,
 }
 
 impl JsonContext {
     /// Creates a new builder.
-    pub fn builder(
-        value: JsonValue,
-    ) -> JsonContextBuilder {
+    pub fn builder(value: JsonValue) -> JsonContextBuilder {
         JsonContextBuilder::new(value)
     }
+
+    /// Returns the root node.
+    pub fn value(&self) -> &JsonNode {
+        &self.value
+    }
+}
+
+/// A node of a JSON value in a context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonNode {
+    /// An object.
+    Object {
+        /// The entries of the object, in order.
+        entries: Vec<JsonEntry>,
+    },
+    /// An array.
+    Array {
+        /// The items of the array, in order.
+        items: Vec<JsonNode>,
+    },
+    /// A null, boolean, number or string.
+    Value(Redactable<SerdeJsonValue>),
+}
+
+impl JsonNode {
+    /// Returns the node as a [`serde_json::Value`], if every value within it
+    /// is revealed.
+    pub fn to_revealed(&self) -> Option<SerdeJsonValue> {
+        match self {
+            JsonNode::Object { entries } => entries
+                .iter()
+                .map(|entry| Some((entry.key.revealed()?.clone(), entry.value.to_revealed()?)))
+                .collect::<Option<serde_json::Map<_, _>>>()
+                .map(SerdeJsonValue::Object),
+            JsonNode::Array { items } => items
+                .iter()
+                .map(JsonNode::to_revealed)
+                .collect::<Option<Vec<_>>>()
+                .map(SerdeJsonValue::Array),
+            JsonNode::Value(value) => value.revealed().cloned(),
+        }
+    }
+}
+
+/// An entry of a JSON object in a context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonEntry {
+    /// The key.
+    pub key: Redactable<String>,
+    /// The value.
+    pub value: JsonNode,
 }
 
 /// Builder for [`JsonContext`].
 pub struct JsonContextBuilder {
     value: JsonValue,
+    authed: Option<RangeSet<usize>>,
 }
 
 impl JsonContextBuilder {
     /// Creates a new builder.
-    pub fn new(
-        value: JsonValue,
-    ) -> Self {
-        Self { value }
+    pub fn new(value: JsonValue) -> Self {
+        Self {
+            value,
+            authed: None,
+        }
+    }
+
+    /// Sets the authenticated ranges of the transcript the value was parsed
+    /// from.
+    ///
+    /// If not set, every value is treated as revealed, except redacted
+    /// tokens.
+    pub fn authed(mut self, authed: RangeSet<usize>) -> Self {
+        self.authed = Some(authed);
+        self
     }
 
     /// Builds the context.
-    pub fn build(self) -> Result<JsonContext, Box<dyn std::error::Error>> {
-        Ok(JsonContext { value: self.value })
+    pub fn build(self) -> Result<JsonContext, JsonContextError> {
+        let authed = self.authed.unwrap_or_else(|| self.value.to_range_set());
+
+        Ok(JsonContext {
+            value: node(&self.value, &authed)?,
+        })
     }
 }
 
-/// A visitor for JSON values that converts spansy::json::JsonValue to serde_json::Value.
-pub trait JsonSerializationVisitor {
-    /// Visit a JSON value and convert it to serde_json::Value.
-    fn visit_value(&mut self, value: &JsonValue) -> SerdeJsonValue {
-        match value {
-            JsonValue::Null(_) => SerdeJsonValue::Null,
-            JsonValue::Redacted(_) => SerdeJsonValue::String("__REDACTED__".to_string()),
-            JsonValue::Bool(b) => SerdeJsonValue::Bool(b.span().as_str().parse().unwrap_or(false)),
-            JsonValue::Number(n) => {
-                let num_str = n.span().as_str();
-                if num_str.contains('.') {
-                    num_str.parse::<f64>().map(|f| SerdeJsonValue::Number(serde_json::Number::from_f64(f).unwrap_or(serde_json::Number::from(0)))).unwrap_or(SerdeJsonValue::String(num_str.to_string()))
-                } else {
-                    num_str.parse::<i64>().map(|n| SerdeJsonValue::Number(serde_json::Number::from(n))).unwrap_or(SerdeJsonValue::String(num_str.to_string()))
-                }
-            },
-            JsonValue::String(s) => SerdeJsonValue::String(s.span().as_str().to_string()),
-            JsonValue::Array(arr) => self.visit_array(arr),
-            JsonValue::Object(obj) => self.visit_object(obj),
+/// Error for [`JsonContextBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum JsonContextError {
+    /// A revealed string or number cannot be decoded.
+    #[error("invalid JSON value at {idx:?}: {reason}")]
+    InvalidValue {
+        /// The indices of the value in the transcript.
+        idx: RangeSet<usize>,
+        /// Why the value cannot be decoded.
+        reason: String,
+    },
+}
+
+fn node(value: &JsonValue, authed: &RangeSet<usize>) -> Result<JsonNode, JsonContextError> {
+    Ok(match value {
+        JsonValue::Object(object) => JsonNode::Object {
+            entries: object
+                .elems
+                .iter()
+                .map(|kv| {
+                    Ok(JsonEntry {
+                        key: quoted(kv.key.span().as_str(), &kv.key.to_range_set(), authed)?,
+                        value: node(&kv.value, authed)?,
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        JsonValue::Array(array) => JsonNode::Array {
+            items: array
+                .elems
+                .iter()
+                .map(|elem| node(elem, authed))
+                .collect::<Result<_, _>>()?,
+        },
+        JsonValue::String(string) => JsonNode::Value(
+            quoted(string.span().as_str(), &string.to_range_set(), authed)?
+                .map(SerdeJsonValue::String),
+        ),
+        JsonValue::Redacted(redacted) => JsonNode::Value(Redactable::Redacted {
+            len: redacted.to_range_set().len(),
+        }),
+        JsonValue::Null(_) | JsonValue::Bool(_) | JsonValue::Number(_) => {
+            let idx = value.to_range_set();
+            let span = value.span().as_str();
+            JsonNode::Value(
+                Redactable::new(&idx, authed, || serde_json::from_str(span))
+                    .transpose()
+                    .map_err(|e| invalid(idx, e))?,
+            )
         }
-    }
+    })
+}
 
-    /// Visit a JSON object and convert it to serde_json::Value.
-    fn visit_object(&mut self, obj: &types::Object) -> SerdeJsonValue {
-        let mut map = HashMap::new();
-        for elem in obj.elems.iter() {
-            let key = elem.key.span().as_str().to_string();
-            let value = self.visit_value(&elem.value);
-            map.insert(key, value);
+/// Classifies a string whose content is at `ranges`, together with its
+/// quotes, unescaping it if revealed.
+fn quoted(
+    content: &str,
+    ranges: &RangeSet<usize>,
+    authed: &RangeSet<usize>,
+) -> Result<Redactable<String>, JsonContextError> {
+    let ranges = match (ranges.min(), ranges.end()) {
+        (Some(start), Some(end)) if start > 0 => {
+            ranges.union(&RangeSet::from([start - 1..start, end..end + 1]))
         }
-        SerdeJsonValue::Object(serde_json::Map::from_iter(map))
-    }
+        _ => ranges.clone(),
+    };
+
+    Redactable::new(&ranges, authed, || {
+        serde_json::from_str(&format!("\"{content}\""))
+    })
+    .transpose()
+    .map_err(|e| invalid(ranges, e))
+}
 
-    /// Visit a JSON array and convert it to serde_json::Value.
-    fn visit_array(&mut self, arr: &types::Array) -> SerdeJsonValue {
-        let values: Vec<SerdeJsonValue> = arr.elems.iter().map(|elem| self.visit_value(elem)).collect();
-        SerdeJsonValue::Array(values)
+fn invalid(idx: RangeSet<usize>, err: serde_json::Error) -> JsonContextError {
+    JsonContextError::InvalidValue {
+        idx,
+        reason: err.to_string(),
     }
 }
 
-/// The default JSON serialization visitor.
-#[derive(Debug, Default)]
-pub struct DefaultJsonSerializationVisitor {}
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+    use serde_json::json;
 
-impl JsonSerializationVisitor for DefaultJsonSerializationVisitor {}
+    use super::*;
 
-impl Serialize for JsonContext {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut visitor = DefaultJsonSerializationVisitor::default();
-        let serde_value = visitor.visit_value(&self.value);
-        serde_value.serialize(serializer)
+    const SRC: &str = r#"{"foo": "bar", "bazz": ***, "buzz": [1, "****"], "A": null}"#;
+
+    #[rstest]
+    fn test_json_context_revealed() {
+        let value = spanner::json::parse_str(r#"{"foo": "b\"ar", "buzz": [1, true]}"#).unwrap();
+        let context = JsonContext::builder(value).build().unwrap();
+
+        assert_eq!(
+            context.value().to_revealed(),
+            Some(json!({"foo": "b\"ar", "buzz": [1, true]}))
+        );
     }
-}
 
+    #[rstest]
+    fn test_json_context_redacted() {
+        let value = spanner::json::parse_str(SRC).unwrap();
+
+        // Everything but the content of "****".
+        let hidden = SRC.find("****").unwrap();
+        let authed = RangeSet::from([0..hidden, hidden + 4..SRC.len()]);
+        let context = JsonContext::builder(value).authed(authed).build().unwrap();
+
+        let JsonNode::Object { entries } = context.value() else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            entries[0].value,
+            JsonNode::Value(Redactable::Revealed {
+                value: json!("bar")
+            })
+        );
+        assert_eq!(
+            entries[1].value,
+            JsonNode::Value(Redactable::Redacted { len: 3 })
+        );
+        assert_eq!(
+            entries[2].value,
+            JsonNode::Array {
+                items: vec![
+                    JsonNode::Value(Redactable::Revealed { value: json!(1) }),
+                    JsonNode::Value(Redactable::StructureOnly { len: 6 }),
+                ]
+            }
+        );
+        assert_eq!(
+            entries[3].key,
+            Redactable::Revealed {
+                value: "A".to_string()
+            }
+        );
+        assert_eq!(context.value().to_revealed(), None);
+    }
+
+    #[rstest]
+    #[case::number(r#"{"n": 1e999}"#)]
+    #[case::lone_surrogate(r#"{"s": "\ud800"}"#)]
+    fn test_json_context_invalid_value(#[case] src: &str) {
+        let value = spanner::json::parse_str(src).unwrap();
+
+        assert!(matches!(
+            JsonContext::builder(value).build(),
+            Err(JsonContextError::InvalidValue { .. })
+        ));
+    }
+
+    #[rstest]
+    fn test_json_context_serialize() {
+        let value = spanner::json::parse_str(SRC).unwrap();
+        let context = JsonContext::builder(value).build().unwrap();
+
+        let serialized = serde_json::to_value(&context).unwrap();
+        assert_eq!(
+            serialized["entries"][1],
+            json!({
+                "key": {"state": "revealed", "value": "bazz"},
+                "value": {"type": "value", "state": "redacted", "len": 3},
+            })
+        );
+        // A revealed string which looks like a redaction is still revealed.
+        assert_eq!(
+            serialized["entries"][2]["value"]["items"][1],
+            json!({"type": "value", "state": "revealed", "value": "****"})
+        );
+
+        let deserialized: JsonContext = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, context);
+    }
+}
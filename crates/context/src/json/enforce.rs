@@ -1,52 +1,505 @@
+use std::fmt;
+
 use spanner::json::JsonValue;
 use spanner::json as types;
 use spanner::Spanned;
 
+/// The kind of a JSON node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonKind {
+    /// A null value.
+    Null,
+    /// A boolean value.
+    Bool,
+    /// A number value.
+    Number,
+    /// A string value.
+    String,
+    /// An array.
+    Array,
+    /// An object.
+    Object,
+    /// A redacted value.
+    Redacted,
+}
+
+impl From<&JsonValue> for JsonKind {
+    fn from(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::Null(_) => JsonKind::Null,
+            JsonValue::Bool(_) => JsonKind::Bool,
+            JsonValue::Number(_) => JsonKind::Number,
+            JsonValue::String(_) => JsonKind::String,
+            JsonValue::Array(_) => JsonKind::Array,
+            JsonValue::Object(_) => JsonKind::Object,
+            JsonValue::Redacted(_) => JsonKind::Redacted,
+        }
+    }
+}
+
+impl fmt::Display for JsonKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JsonKind::Null => "null",
+            JsonKind::Bool => "bool",
+            JsonKind::Number => "number",
+            JsonKind::String => "string",
+            JsonKind::Array => "array",
+            JsonKind::Object => "object",
+            JsonKind::Redacted => "redacted",
+        })
+    }
+}
+
+/// A JSON pointer, as defined in [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonPointer(Vec<String>);
+
+impl JsonPointer {
+    /// Returns the pointer to the root value.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Returns a pointer to a child of this value.
+    pub fn join(&self, token: impl ToString) -> Self {
+        let mut tokens = self.0.clone();
+        tokens.push(token.to_string());
+        Self(tokens)
+    }
+
+    /// Returns the reference tokens of the pointer.
+    pub fn tokens(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.0 {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The reason a JSON value did not match the structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonEnforceErrorKind {
+    /// The value has a different kind.
+    Kind,
+    /// The value has the same kind but a different literal.
+    Value {
+        /// Expected literal.
+        expected: String,
+        /// Presented literal.
+        actual: String,
+    },
+    /// A key of the structure is missing.
+    MissingKey,
+    /// The array has an unexpected length.
+    Length {
+        /// Expected length.
+        expected: usize,
+        /// Presented length.
+        actual: usize,
+    },
+    /// An element of the structure matched no element of the array.
+    NoMatch,
+    /// The value does not satisfy a predicate.
+    Predicate {
+        /// The predicate.
+        predicate: String,
+        /// Presented literal.
+        actual: String,
+    },
+}
+
+/// Error for [`JsonContextEnforcer`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "json mismatch at \"{path}\": expected {expected}, got {}{}",
+    DisplayActual(.actual),
+    DisplayDetail(.kind)
+)]
+pub struct JsonEnforceError {
+    path: JsonPointer,
+    kind: JsonEnforceErrorKind,
+    expected: JsonKind,
+    actual: Option<JsonKind>,
+}
+
+/// Displays the kind of a presented node, if present.
+struct DisplayActual<'a>(&'a Option<JsonKind>);
+
+impl fmt::Display for DisplayActual<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(actual) => write!(f, "{actual}"),
+            None => write!(f, "nothing"),
+        }
+    }
+}
+
+/// Displays the details of a mismatch, if any.
+struct DisplayDetail<'a>(&'a JsonEnforceErrorKind);
+
+impl fmt::Display for DisplayDetail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            JsonEnforceErrorKind::Value { expected, actual } => {
+                write!(f, " (expected \"{expected}\", got \"{actual}\")")
+            }
+            JsonEnforceErrorKind::Length { expected, actual } => {
+                write!(f, " (expected length {expected}, got {actual})")
+            }
+            JsonEnforceErrorKind::NoMatch => write!(f, " (no matching element)"),
+            JsonEnforceErrorKind::Predicate { predicate, actual } => {
+                write!(f, " (\"{actual}\" does not satisfy {predicate})")
+            }
+            JsonEnforceErrorKind::Kind | JsonEnforceErrorKind::MissingKey => Ok(()),
+        }
+    }
+}
+
+impl JsonEnforceError {
+    /// Creates a new error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the value which did not match.
+    /// * `kind` - Why the value did not match.
+    /// * `expected` - The kind of the structure node.
+    /// * `actual` - The kind of the presented node, if present.
+    pub fn new(
+        path: JsonPointer,
+        kind: JsonEnforceErrorKind,
+        expected: JsonKind,
+        actual: Option<JsonKind>,
+    ) -> Self {
+        Self {
+            path,
+            kind,
+            expected,
+            actual,
+        }
+    }
+
+    /// Returns the path of the value which did not match.
+    pub fn path(&self) -> &JsonPointer {
+        &self.path
+    }
+
+    /// Returns why the value did not match.
+    pub fn kind(&self) -> &JsonEnforceErrorKind {
+        &self.kind
+    }
+
+    /// Returns the kind of the structure node.
+    pub fn expected(&self) -> JsonKind {
+        self.expected
+    }
+
+    /// Returns the kind of the presented node, or `None` if it is missing.
+    pub fn actual(&self) -> Option<JsonKind> {
+        self.actual
+    }
+}
+
+/// How arrays in the structure are matched against presented arrays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrayStrategy {
+    /// The array must have the same length, and each element must match the
+    /// corresponding element of the structure.
+    #[default]
+    Exact,
+    /// The array must start with elements matching the structure, and may
+    /// contain additional elements.
+    Prefix,
+    /// Each element of the structure must match a distinct element of the
+    /// array, in any order.
+    Set,
+}
+
+/// Returns whether a structure node is redacted, and so matches any value.
+///
+/// Redacted strings are parsed as strings, so a primitive consisting only of
+/// redaction characters is considered redacted.
+fn is_redacted(structure: &JsonValue) -> bool {
+    match structure {
+        JsonValue::Redacted(_) => true,
+        JsonValue::Bool(_) | JsonValue::Number(_) | JsonValue::String(_) => {
+            let s = structure.span().as_str();
+            !s.is_empty() && s.bytes().all(|b| b == b'*')
+        }
+        _ => false,
+    }
+}
+
 /// A visitor for JSON values that checks for structural integrity.
 pub trait JsonContextEnforcer {
+    /// Returns the strategy for matching arrays.
+    fn array_strategy(&self) -> ArrayStrategy {
+        ArrayStrategy::Exact
+    }
+
+    /// Enforces that a JSON value matches the structure.
+    ///
+    /// Redacted values in the structure match any value.
+    fn enforce(&mut self, structure: &JsonValue, value: &JsonValue) -> Result<(), JsonEnforceError> {
+        self.visit_value(&JsonPointer::root(), structure, value)
+    }
+
     /// Visit a JSON value.
-    fn visit_value(&mut self, structure: &JsonValue, value: &JsonValue) {
+    fn visit_value(
+        &mut self,
+        path: &JsonPointer,
+        structure: &JsonValue,
+        value: &JsonValue,
+    ) -> Result<(), JsonEnforceError> {
+        if is_redacted(structure) {
+            return Ok(());
+        }
+
         // Ensure value has same variant as structure
         match (structure, value) {
-            (JsonValue::Null(_), JsonValue::Null(_)) => (),
-            (JsonValue::Redacted(_), _) => (),
-
-            (JsonValue::Bool(structure), JsonValue::Bool(value)) => assert_eq!(structure.span().as_str(), value.span().as_str()),
-            (JsonValue::Number(structure), JsonValue::Number(value)) => assert_eq!(structure.span().as_str(), value.span().as_str()),
-            (JsonValue::String(structure), JsonValue::String(value)) => assert_eq!(structure.span().as_str(), value.span().as_str()),
+            (JsonValue::Null(_), JsonValue::Null(_)) => Ok(()),
 
+            (JsonValue::Bool(_), JsonValue::Bool(_))
+            | (JsonValue::Number(_), JsonValue::Number(_))
+            | (JsonValue::String(_), JsonValue::String(_)) => {
+                let expected = structure.span().as_str();
+                let actual = value.span().as_str();
+                if expected == actual {
+                    Ok(())
+                } else {
+                    Err(JsonEnforceError::new(
+                        path.clone(),
+                        JsonEnforceErrorKind::Value {
+                            expected: expected.to_string(),
+                            actual: actual.to_string(),
+                        },
+                        structure.into(),
+                        Some(value.into()),
+                    ))
+                }
+            }
 
-            (JsonValue::Array(structure), JsonValue::Array(value)) => self.visit_array(structure, value),
-            (JsonValue::Object(structure), JsonValue::Object(value)) => self.visit_object(structure, value),
+            (JsonValue::Array(structure), JsonValue::Array(value)) => self.visit_array(path, structure, value),
+            (JsonValue::Object(structure), JsonValue::Object(value)) => self.visit_object(path, structure, value),
 
-            _ => panic!("Type mismatch: expected {:?}, got {:?}", structure, value)
+            _ => Err(JsonEnforceError::new(
+                path.clone(),
+                JsonEnforceErrorKind::Kind,
+                structure.into(),
+                Some(value.into()),
+            )),
         }
     }
 
     /// Visit a JSON object.
-    fn visit_object(&mut self, structure: &types::Object, value: &types::Object) {
+    ///
+    /// Each key of the structure must be present in the value.
+    fn visit_object(
+        &mut self,
+        path: &JsonPointer,
+        structure: &types::Object,
+        value: &types::Object,
+    ) -> Result<(), JsonEnforceError> {
         for elem in structure.elems.iter() {
-            let matching = value.elems.iter().find(|e| e.key.span().as_str() == elem.key.span().as_str());
-            assert!(matching.is_some(), "Missing key: {}", elem.key.span().as_str());
+            let key = elem.key.span().as_str();
+            let path = path.join(key);
 
-            self.visit_value(&elem.value, &matching.unwrap().value);
+            let Some(matching) = value.elems.iter().find(|e| e.key.span().as_str() == key) else {
+                return Err(JsonEnforceError::new(
+                    path,
+                    JsonEnforceErrorKind::MissingKey,
+                    (&elem.value).into(),
+                    None,
+                ));
+            };
+
+            self.visit_value(&path, &elem.value, &matching.value)?;
         }
+
+        Ok(())
     }
 
     /// Visit a JSON array.
-    /// The default strategy enforces that if the array is non-empty, then the
-    /// value must be an array with the same number of elements, and that each
-    /// non-redacted element is structurally equivalent to the corresponding
-    /// element in the structure.
-    fn visit_array(&mut self, structure: &types::Array, value: &types::Array) {
-        for (i, elem) in structure.elems.iter().enumerate() {
-            self.visit_value(elem, &value.elems[i]);
+    ///
+    /// The array is matched according to [`JsonContextEnforcer::array_strategy`].
+    fn visit_array(
+        &mut self,
+        path: &JsonPointer,
+        structure: &types::Array,
+        value: &types::Array,
+    ) -> Result<(), JsonEnforceError> {
+        let (expected, actual) = (structure.elems.len(), value.elems.len());
+        let length_error = || {
+            JsonEnforceError::new(
+                path.clone(),
+                JsonEnforceErrorKind::Length { expected, actual },
+                JsonKind::Array,
+                Some(JsonKind::Array),
+            )
+        };
+
+        match self.array_strategy() {
+            ArrayStrategy::Exact if actual != expected => Err(length_error()),
+            ArrayStrategy::Prefix | ArrayStrategy::Set if actual < expected => Err(length_error()),
+            ArrayStrategy::Exact | ArrayStrategy::Prefix => {
+                for (i, (structure, value)) in structure.elems.iter().zip(value.elems.iter()).enumerate() {
+                    self.visit_value(&path.join(i), structure, value)?;
+                }
+                Ok(())
+            }
+            ArrayStrategy::Set => {
+                // Find a matching of structure elements to distinct value
+                // elements using augmenting paths.
+                let matches: Vec<Vec<usize>> = structure
+                    .elems
+                    .iter()
+                    .enumerate()
+                    .map(|(i, structure)| {
+                        (0..actual)
+                            .filter(|&j| self.visit_value(&path.join(i), structure, &value.elems[j]).is_ok())
+                            .collect()
+                    })
+                    .collect();
+
+                let mut assigned: Vec<Option<usize>> = vec![None; actual];
+                for i in 0..expected {
+                    let mut visited = vec![false; actual];
+                    if !augment(i, &matches, &mut assigned, &mut visited) {
+                        return Err(JsonEnforceError::new(
+                            path.join(i),
+                            JsonEnforceErrorKind::NoMatch,
+                            (&structure.elems[i]).into(),
+                            None,
+                        ));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Tries to assign structure element `i` to a value element, reassigning
+/// previously assigned elements if necessary.
+fn augment(
+    i: usize,
+    matches: &[Vec<usize>],
+    assigned: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for &j in &matches[i] {
+        if visited[j] {
+            continue;
+        }
+        visited[j] = true;
+
+        if assigned[j].is_none_or(|k| augment(k, matches, assigned, visited)) {
+            assigned[j] = Some(i);
+            return true;
         }
     }
+    false
 }
 
 /// The default JSON context visitor.
 #[derive(Debug, Default)]
-pub struct DefaultJsonContextEnforcer {}
+pub struct DefaultJsonContextEnforcer {
+    array_strategy: ArrayStrategy,
+}
+
+impl DefaultJsonContextEnforcer {
+    /// Creates a new enforcer with the given array strategy.
+    pub fn new(array_strategy: ArrayStrategy) -> Self {
+        Self { array_strategy }
+    }
+}
 
-impl JsonContextEnforcer for DefaultJsonContextEnforcer { }
\ No newline at end of file
+impl JsonContextEnforcer for DefaultJsonContextEnforcer {
+    fn array_strategy(&self) -> ArrayStrategy {
+        self.array_strategy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+    use spanner::json::parse_str;
+
+    use super::*;
+
+    fn enforce(strategy: ArrayStrategy, structure: &str, value: &str) -> Result<(), JsonEnforceError> {
+        DefaultJsonContextEnforcer::new(strategy)
+            .enforce(&parse_str(structure).unwrap(), &parse_str(value).unwrap())
+    }
+
+    #[rstest]
+    #[case::equal(r#"{"a": 1, "b": [true, null]}"#, r#"{"a": 1, "b": [true, null]}"#)]
+    #[case::extra_key(r#"{"a": 1}"#, r#"{"b": 2, "a": 1}"#)]
+    #[case::redacted_string(r#"{"a": "***"}"#, r#"{"a": "secret"}"#)]
+    #[case::redacted_number(r#"{"a": **}"#, r#"{"a": 12}"#)]
+    fn test_enforce_match(#[case] structure: &str, #[case] value: &str) {
+        enforce(ArrayStrategy::Exact, structure, value).unwrap();
+    }
+
+    #[rstest]
+    #[case::kind(r#"{"a": {"b": 1}}"#, r#"{"a": {"b": "1"}}"#, "/a/b", JsonEnforceErrorKind::Kind, JsonKind::Number, Some(JsonKind::String))]
+    #[case::value(r#"{"a": [1, 2]}"#, r#"{"a": [1, 3]}"#, "/a/1", JsonEnforceErrorKind::Value { expected: "2".to_string(), actual: "3".to_string() }, JsonKind::Number, Some(JsonKind::Number))]
+    #[case::missing_key(r#"{"a/b": {"c": null}}"#, r#"{"a/b": {}}"#, "/a~1b/c", JsonEnforceErrorKind::MissingKey, JsonKind::Null, None)]
+    #[case::shorter(r#"[1, 2]"#, r#"[1]"#, "", JsonEnforceErrorKind::Length { expected: 2, actual: 1 }, JsonKind::Array, Some(JsonKind::Array))]
+    fn test_enforce_mismatch(
+        #[case] structure: &str,
+        #[case] value: &str,
+        #[case] path: &str,
+        #[case] kind: JsonEnforceErrorKind,
+        #[case] expected: JsonKind,
+        #[case] actual: Option<JsonKind>,
+    ) {
+        let err = enforce(ArrayStrategy::Exact, structure, value).unwrap_err();
+
+        assert_eq!(err.path().to_string(), path);
+        assert_eq!(err.kind(), &kind);
+        assert_eq!(err.expected(), expected);
+        assert_eq!(err.actual(), actual);
+    }
+
+    #[rstest]
+    #[case::value(r#"{"a": 1}"#, r#"{"a": 2}"#, r#"json mismatch at "/a": expected number, got number (expected "1", got "2")"#)]
+    #[case::missing_key(r#"{"a": 1}"#, r#"{}"#, r#"json mismatch at "/a": expected number, got nothing"#)]
+    fn test_enforce_error_display(
+        #[case] structure: &str,
+        #[case] value: &str,
+        #[case] expected: &str,
+    ) {
+        let err = enforce(ArrayStrategy::Exact, structure, value).unwrap_err();
+
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case::exact_equal(ArrayStrategy::Exact, "[1, 2]", "[1, 2]", true)]
+    #[case::exact_longer(ArrayStrategy::Exact, "[1, 2]", "[1, 2, 3]", false)]
+    #[case::exact_reordered(ArrayStrategy::Exact, "[1, 2]", "[2, 1]", false)]
+    #[case::prefix_longer(ArrayStrategy::Prefix, "[1, 2]", "[1, 2, 3]", true)]
+    #[case::prefix_shorter(ArrayStrategy::Prefix, "[1, 2]", "[1]", false)]
+    #[case::prefix_reordered(ArrayStrategy::Prefix, "[1, 2]", "[2, 1, 3]", false)]
+    #[case::set_reordered(ArrayStrategy::Set, "[1, 2]", "[3, 2, 1]", true)]
+    #[case::set_missing(ArrayStrategy::Set, "[1, 4]", "[3, 2, 1]", false)]
+    #[case::set_distinct(ArrayStrategy::Set, "[1, 1]", "[1, 2]", false)]
+    #[case::set_redacted_first(ArrayStrategy::Set, "[*, 1]", "[1, 2]", true)]
+    fn test_enforce_array_strategy(
+        #[case] strategy: ArrayStrategy,
+        #[case] structure: &str,
+        #[case] value: &str,
+        #[case] ok: bool,
+    ) {
+        assert_eq!(enforce(strategy, structure, value).is_ok(), ok);
+    }
+}
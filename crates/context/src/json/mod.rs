@@ -5,11 +5,15 @@ mod context;
 mod enforce;
 use spanner::json;
 
-pub use commit::{DefaultJsonCommitter, JsonCommit, JsonCommitError};
-pub use context::{
-    JsonContext, JsonContextBuilder, JsonSerializationVisitor, DefaultJsonSerializationVisitor,
+pub use commit::{
+    ConfigurableJsonCommitter, ConfigurableJsonCommitterBuilder, DefaultJsonCommitter, JsonCommit,
+    JsonCommitError,
+};
+pub use context::{JsonContext, JsonContextBuilder, JsonContextError, JsonEntry, JsonNode};
+pub use enforce::{
+    ArrayStrategy, DefaultJsonContextEnforcer, JsonContextEnforcer, JsonEnforceError,
+    JsonEnforceErrorKind, JsonKind, JsonPointer,
 };
-pub use enforce::{DefaultJsonContextEnforcer, JsonContextEnforcer};
 pub use json::{
     Array, Bool, JsonKey, JsonValue, JsonVisit, KeyValue, Null, Number, Object, String,
 };
@@ -1,4 +1,11 @@
+pub mod context;
 pub mod http;
 pub mod json;
-//pub mod tls;
-pub mod transcript;
\ No newline at end of file
+pub mod matcher;
+pub mod text;
+pub mod tls;
+pub mod transcript;
+pub mod webpki;
+
+#[cfg(test)]
+pub(crate) mod fixtures;
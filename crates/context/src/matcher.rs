@@ -0,0 +1,541 @@
+//! Pattern and predicate matching.
+//!
+//! A [`Matcher`] is either an exact value, or a [`Predicate`] such as a regular
+//! expression or numeric comparison. Matchers are used by
+//! [`HttpTemplate`](crate::http::HttpTemplate) for request targets, header
+//! values and JSON leaves.
+//!
+//! Matchers are serialised as a plain string for exact values, or a table
+//! for predicates:
+//!
+//! ```toml
+//! target = { path = "/accounts/{id}" }
+//! headers = [{ name = "Authorization", value = { prefix = "Bearer " } }]
+//! ```
+
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use spanner::Spanned;
+
+use crate::json::{JsonKind, JsonValue};
+
+/// Matcher error.
+#[derive(Debug, thiserror::Error)]
+pub enum MatcherError {
+    /// A regular expression is invalid.
+    #[error("invalid regex: {0}")]
+    Regex(#[from] regex::Error),
+    /// A date bound is not a valid ISO-8601 date or date-time.
+    #[error("invalid ISO-8601 date: {0}")]
+    Date(String),
+    /// A path template is invalid.
+    #[error("invalid path template: {0}")]
+    PathTemplate(String),
+}
+
+/// Captured values, e.g. the parameters of a path template.
+pub type Captures = Vec<(String, String)>;
+
+/// A value matcher.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Matcher {
+    /// Matches the exact value.
+    Exact(String),
+    /// Matches values satisfying the predicate.
+    Predicate(Predicate),
+}
+
+impl Matcher {
+    /// Validates the matcher.
+    pub fn validate(&self) -> Result<(), MatcherError> {
+        match self {
+            Matcher::Exact(_) => Ok(()),
+            Matcher::Predicate(predicate) => predicate.validate(),
+        }
+    }
+}
+
+impl From<&str> for Matcher {
+    fn from(value: &str) -> Self {
+        Matcher::Exact(value.to_string())
+    }
+}
+
+impl From<String> for Matcher {
+    fn from(value: String) -> Self {
+        Matcher::Exact(value)
+    }
+}
+
+impl From<Predicate> for Matcher {
+    fn from(predicate: Predicate) -> Self {
+        Matcher::Predicate(predicate)
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::Exact(value) => write!(f, "\"{value}\""),
+            Matcher::Predicate(predicate) => predicate.fmt(f),
+        }
+    }
+}
+
+/// A predicate over a value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Predicate {
+    /// A path template such as `/users/{id}`, where each `{name}` matches a
+    /// single non-empty segment. The query, if any, is ignored.
+    Path(String),
+    /// A regular expression, which must match the entire value.
+    Regex(String),
+    /// A prefix.
+    Prefix(String),
+    /// One of a set of values.
+    OneOf(Vec<String>),
+    /// A numeric comparison.
+    Number(NumberPredicate),
+    /// An ISO-8601 date or date-time range.
+    Date(DateRange),
+    /// Any value of the given kind.
+    Type(JsonKind),
+}
+
+impl Predicate {
+    /// Validates the predicate.
+    pub fn validate(&self) -> Result<(), MatcherError> {
+        match self {
+            Predicate::Path(template) => {
+                if !template.starts_with('/') {
+                    return Err(MatcherError::PathTemplate(template.clone()));
+                }
+                for segment in template.split('/') {
+                    if let Some(name) = segment.strip_prefix('{') {
+                        if !name.ends_with('}') || name.len() < 2 {
+                            return Err(MatcherError::PathTemplate(template.clone()));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Predicate::Regex(regex) => anchored(regex).map(|_| ()),
+            Predicate::Date(range) => {
+                for bound in [&range.from, &range.to].into_iter().flatten() {
+                    parse_date(bound).ok_or_else(|| MatcherError::Date(bound.clone()))?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the predicate against a string value.
+    ///
+    /// Returns the captures if the value matches, or `None` otherwise.
+    pub fn evaluate(&self, value: &str) -> Result<Option<Captures>, MatcherError> {
+        let matched = match self {
+            Predicate::Path(template) => return Ok(match_path(template, value)),
+            Predicate::Regex(regex) => anchored(regex)?.is_match(value),
+            Predicate::Prefix(prefix) => value.starts_with(prefix.as_str()),
+            Predicate::OneOf(values) => values.iter().any(|v| v == value),
+            Predicate::Number(predicate) => {
+                parse_number(value).is_some_and(|value| predicate.matches(value))
+            }
+            Predicate::Date(range) => range.matches(value)?,
+            Predicate::Type(kind) => match kind {
+                JsonKind::String => true,
+                JsonKind::Number => parse_number(value).is_some(),
+                JsonKind::Bool => matches!(value, "true" | "false"),
+                JsonKind::Null => value == "null",
+                _ => false,
+            },
+        };
+
+        Ok(matched.then(Vec::new))
+    }
+
+    /// Evaluates the predicate against a JSON value.
+    ///
+    /// Strings are compared by their unescaped content, and other primitives by
+    /// their literal. Arrays and objects only match [`Predicate::Type`], and
+    /// only numbers match [`Predicate::Number`].
+    pub fn evaluate_json(&self, value: &JsonValue) -> Result<Option<Captures>, MatcherError> {
+        match self {
+            Predicate::Type(kind) => return Ok((JsonKind::from(value) == *kind).then(Vec::new)),
+            Predicate::Number(_) if !matches!(value, JsonValue::Number(_)) => return Ok(None),
+            _ => {}
+        }
+
+        let raw = value.span().as_str();
+        let text = match value {
+            JsonValue::String(_) => {
+                serde_json::from_str(&format!("\"{raw}\"")).unwrap_or_else(|_| raw.to_string())
+            }
+            JsonValue::Number(_) | JsonValue::Bool(_) | JsonValue::Null(_) => raw.to_string(),
+            _ => return Ok(None),
+        };
+
+        self.evaluate(&text)
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::Path(template) => write!(f, "path {template}"),
+            Predicate::Regex(regex) => write!(f, "regex /{regex}/"),
+            Predicate::Prefix(prefix) => write!(f, "prefix \"{prefix}\""),
+            Predicate::OneOf(values) => write!(f, "one of {values:?}"),
+            Predicate::Number(predicate) => predicate.fmt(f),
+            Predicate::Date(range) => range.fmt(f),
+            Predicate::Type(kind) => write!(f, "any {kind}"),
+        }
+    }
+}
+
+/// A numeric comparison, where every bound which is set must hold.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NumberPredicate {
+    /// Equal to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eq: Option<f64>,
+    /// Greater than.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gt: Option<f64>,
+    /// Greater than or equal to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gte: Option<f64>,
+    /// Less than.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lt: Option<f64>,
+    /// Less than or equal to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lte: Option<f64>,
+}
+
+impl NumberPredicate {
+    /// Returns whether the value satisfies every bound.
+    pub fn matches(&self, value: f64) -> bool {
+        self.eq.is_none_or(|eq| value == eq)
+            && self.gt.is_none_or(|gt| value > gt)
+            && self.gte.is_none_or(|gte| value >= gte)
+            && self.lt.is_none_or(|lt| value < lt)
+            && self.lte.is_none_or(|lte| value <= lte)
+    }
+}
+
+impl fmt::Display for NumberPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bounds = [
+            ("==", self.eq),
+            (">", self.gt),
+            (">=", self.gte),
+            ("<", self.lt),
+            ("<=", self.lte),
+        ];
+
+        let mut first = true;
+        for (op, bound) in bounds {
+            if let Some(bound) = bound {
+                if !first {
+                    write!(f, " and ")?;
+                }
+                write!(f, "{op} {bound}")?;
+                first = false;
+            }
+        }
+
+        if first {
+            write!(f, "any number")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An inclusive range of ISO-8601 dates or date-times.
+///
+/// Dates without a time are treated as midnight UTC, and date-times without an
+/// offset as UTC.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DateRange {
+    /// The earliest date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// The latest date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+}
+
+impl DateRange {
+    /// Returns whether the value is a date within the range.
+    pub fn matches(&self, value: &str) -> Result<bool, MatcherError> {
+        let Some(value) = parse_date(value) else {
+            return Ok(false);
+        };
+
+        let bound =
+            |bound: &String| parse_date(bound).ok_or_else(|| MatcherError::Date(bound.clone()));
+
+        if let Some(from) = &self.from {
+            if value < bound(from)? {
+                return Ok(false);
+            }
+        }
+
+        if let Some(to) = &self.to {
+            if value > bound(to)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "date from {} to {}",
+            self.from.as_deref().unwrap_or("any"),
+            self.to.as_deref().unwrap_or("any")
+        )
+    }
+}
+
+/// The outcome of a predicate which was satisfied during enforcement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredicateOutcome {
+    /// Where the predicate was evaluated, e.g. `target` or `/balance`.
+    pub location: String,
+    /// The predicate.
+    pub predicate: Predicate,
+    /// Values captured by the predicate.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub captures: Captures,
+}
+
+fn anchored(regex: &str) -> Result<regex::Regex, MatcherError> {
+    Ok(regex::Regex::new(&format!("^(?:{regex})$"))?)
+}
+
+/// Parses a finite number in the JSON grammar of RFC 8259, ignoring
+/// surrounding whitespace.
+///
+/// Unlike `f64::from_str`, this rejects `inf`, `NaN` and literals such as
+/// `1e999` which overflow to infinity.
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = value.strip_prefix('-').unwrap_or(value);
+    let rest = match rest.as_bytes() {
+        [b'0', ..] => &rest[1..],
+        [b'1'..=b'9', ..] => &rest[digits(rest)..],
+        _ => return None,
+    };
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) if digits(fraction) > 0 => &fraction[digits(fraction)..],
+        Some(_) => return None,
+        None => rest,
+    };
+    let rest = match rest.strip_prefix(['e', 'E']) {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            match digits(exponent) {
+                0 => return None,
+                n => &exponent[n..],
+            }
+        }
+        None => rest,
+    };
+    if !rest.is_empty() {
+        return None;
+    }
+
+    value.parse::<f64>().ok().filter(|value| value.is_finite())
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|dt| dt.and_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|dt| dt.and_utc())
+        })
+}
+
+fn match_path(template: &str, target: &str) -> Option<Captures> {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+
+    let mut template_segments = template.split('/');
+    let mut path_segments = path.split('/');
+    let mut captures = Vec::new();
+
+    loop {
+        match (template_segments.next(), path_segments.next()) {
+            (None, None) => return Some(captures),
+            (Some(expected), Some(actual)) => {
+                match expected.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) if !actual.is_empty() => {
+                        captures.push((name.to_string(), actual.to_string()));
+                    }
+                    Some(_) => return None,
+                    None if expected == actual => {}
+                    None => return None,
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+
+    fn number(gte: Option<f64>, lt: Option<f64>) -> Predicate {
+        Predicate::Number(NumberPredicate {
+            gte,
+            lt,
+            ..Default::default()
+        })
+    }
+
+    fn date(from: Option<&str>, to: Option<&str>) -> Predicate {
+        Predicate::Date(DateRange {
+            from: from.map(String::from),
+            to: to.map(String::from),
+        })
+    }
+
+    #[rstest]
+    #[case::path(Predicate::Path("/users/{id}".into()), "/users/42?x=1", true)]
+    #[case::path_empty_param(Predicate::Path("/users/{id}".into()), "/users/", false)]
+    #[case::path_extra_segment(Predicate::Path("/users/{id}".into()), "/users/42/posts", false)]
+    #[case::regex(Predicate::Regex("[a-z]+-\\d+".into()), "abc-12", true)]
+    #[case::regex_anchored(Predicate::Regex("\\d+".into()), "abc-12", false)]
+    #[case::prefix(Predicate::Prefix("Bearer ".into()), "Bearer abc", true)]
+    #[case::one_of(Predicate::OneOf(vec!["GBP".into(), "EUR".into()]), "EUR", true)]
+    #[case::one_of_missing(Predicate::OneOf(vec!["GBP".into(), "EUR".into()]), "USD", false)]
+    #[case::number(number(Some(1000.0), None), "1000", true)]
+    #[case::number_below(number(Some(1000.0), None), "999.99", false)]
+    #[case::number_range(number(Some(0.0), Some(10.0)), "10", false)]
+    #[case::number_invalid(number(Some(0.0), None), "abc", false)]
+    #[case::number_inf(number(Some(1000.0), None), "inf", false)]
+    #[case::number_infinity(number(Some(1000.0), None), "infinity", false)]
+    #[case::number_nan(number(None, None), "NaN", false)]
+    #[case::number_overflow(number(Some(1000.0), None), "1e999", false)]
+    #[case::number_plus(number(Some(0.0), None), "+1", false)]
+    #[case::number_leading_zero(number(Some(0.0), None), "01", false)]
+    #[case::number_exponent(number(Some(1000.0), None), "1.5E+3", true)]
+    #[case::number_whitespace(number(Some(0.0), None), " 0.5 ", true)]
+    #[case::date(
+        date(Some("2024-01-01"), Some("2024-12-31")),
+        "2024-06-01T12:00:00Z",
+        true
+    )]
+    #[case::date_offset(date(Some("2024-01-01"), None), "2024-01-01T00:30:00+01:00", false)]
+    #[case::date_after(date(None, Some("2024-12-31")), "2025-01-01", false)]
+    #[case::date_invalid(date(None, None), "yesterday", false)]
+    #[case::type_number(Predicate::Type(JsonKind::Number), "-1.5e3", true)]
+    #[case::type_bool(Predicate::Type(JsonKind::Bool), "yes", false)]
+    #[case::type_number_inf(Predicate::Type(JsonKind::Number), "inf", false)]
+    #[case::type_number_nan(Predicate::Type(JsonKind::Number), "NaN", false)]
+    fn test_predicate_evaluate(
+        #[case] predicate: Predicate,
+        #[case] value: &str,
+        #[case] expected: bool,
+    ) {
+        predicate.validate().unwrap();
+        assert_eq!(predicate.evaluate(value).unwrap().is_some(), expected);
+    }
+
+    #[rstest]
+    fn test_predicate_path_captures() {
+        let captures = Predicate::Path("/accounts/{id}/tx/{tx}".into())
+            .evaluate("/accounts/7/tx/abc")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            captures,
+            vec![
+                ("id".to_string(), "7".to_string()),
+                ("tx".to_string(), "abc".to_string())
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::string(Predicate::Prefix("a\"".into()), r#"{"v": "a\"b"}"#, true)]
+    #[case::number(number(Some(1000.0), None), r#"{"v": 1500}"#, true)]
+    #[case::number_overflow(number(Some(1000.0), None), r#"{"v": 1e999}"#, false)]
+    #[case::number_string(number(Some(1000.0), None), r#"{"v": "1500"}"#, false)]
+    #[case::number_string_infinity(number(Some(1000.0), None), r#"{"v": "infinity"}"#, false)]
+    #[case::type_number_string(Predicate::Type(JsonKind::Number), r#"{"v": "1500"}"#, false)]
+    #[case::type_object(Predicate::Type(JsonKind::Object), r#"{"v": {}}"#, true)]
+    #[case::object(Predicate::Prefix("{".into()), r#"{"v": {}}"#, false)]
+    fn test_predicate_evaluate_json(
+        #[case] predicate: Predicate,
+        #[case] json: &str,
+        #[case] expected: bool,
+    ) {
+        let json = spanner::json::parse_str(json).unwrap();
+        let value = json.get("v").unwrap();
+
+        assert_eq!(predicate.evaluate_json(value).unwrap().is_some(), expected);
+    }
+
+    #[rstest]
+    #[case::regex(Predicate::Regex("(".into()))]
+    #[case::date(date(Some("01/02/2024"), None))]
+    #[case::path(Predicate::Path("users/{id".into()))]
+    fn test_predicate_validate_invalid(#[case] predicate: Predicate) {
+        assert!(predicate.validate().is_err());
+    }
+
+    #[rstest]
+    fn test_matcher_serde() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Doc {
+            exact: Matcher,
+            path: Matcher,
+            number: Matcher,
+            kind: Matcher,
+        }
+
+        let doc: Doc = toml::from_str(
+            r#"
+            exact = "/hello"
+            path = { path = "/users/{id}" }
+            number = { number = { gte = 1000 } }
+            kind = { type = "number" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(doc.exact, Matcher::from("/hello"));
+        assert_eq!(doc.path, Predicate::Path("/users/{id}".into()).into());
+        assert_eq!(doc.number, number(Some(1000.0), None).into());
+        assert_eq!(doc.kind, Predicate::Type(JsonKind::Number).into());
+    }
+}
@@ -0,0 +1,321 @@
+//! Commitments to unstructured text.
+//!
+//! Bodies of an unknown format, such as HTML or plain text, are committed as
+//! a whole by default, so they can only be revealed entirely. A
+//! [`TextCommitter`] splits such a body into tokens, i.e. lines, words or
+//! regex matches, and commits to each of them, so that individual tokens can
+//! be revealed alongside the structure between them.
+//!
+//! Text committers are serialised by their split:
+//!
+//! ```toml
+//! "text/html" = "lines"
+//! "text/plain" = { regex = 'balance: (\d+)' }
+//! ```
+
+use std::ops::Range;
+
+use rangeset::{Difference, RangeSet};
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::{Direction, TranscriptCommitmentBuilder, TranscriptCommitmentBuilderError};
+
+/// How text is split into tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSplit {
+    /// Each non-empty line, excluding its line terminator.
+    Lines,
+    /// Each run of non-whitespace bytes.
+    Words,
+    /// Each capture group of each match of a regular expression or, if it has
+    /// no capture groups, each match.
+    Regex(String),
+}
+
+/// A committer for unstructured text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TextSplit", into = "TextSplit")]
+pub struct TextCommitter {
+    split: TextSplit,
+    regex: Option<Regex>,
+}
+
+impl TextCommitter {
+    /// Creates a committer which splits text into lines.
+    pub fn lines() -> Self {
+        Self {
+            split: TextSplit::Lines,
+            regex: None,
+        }
+    }
+
+    /// Creates a committer which splits text into words.
+    pub fn words() -> Self {
+        Self {
+            split: TextSplit::Words,
+            regex: None,
+        }
+    }
+
+    /// Creates a committer which splits text into the matches of a regular
+    /// expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The regular expression.
+    pub fn regex(pattern: impl Into<String>) -> Result<Self, regex::Error> {
+        let pattern = pattern.into();
+        let regex = Regex::new(&pattern)?;
+
+        Ok(Self {
+            split: TextSplit::Regex(pattern),
+            regex: Some(regex),
+        })
+    }
+
+    /// Returns how text is split.
+    pub fn split(&self) -> &TextSplit {
+        &self.split
+    }
+
+    /// Returns the tokens of text located at `indices` of the transcript, as
+    /// ranges of the transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - The indices of the text in the transcript.
+    /// * `text` - The text.
+    pub fn tokens(&self, indices: &RangeSet<usize>, text: &[u8]) -> Vec<RangeSet<usize>> {
+        let tokens: Vec<Range<usize>> = match (&self.split, &self.regex) {
+            (TextSplit::Regex(_), Some(regex)) if regex.captures_len() > 1 => regex
+                .captures_iter(text)
+                .flat_map(|captures| {
+                    captures
+                        .iter()
+                        .skip(1)
+                        .flatten()
+                        .map(|group| group.range())
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            (TextSplit::Regex(_), Some(regex)) => {
+                regex.find_iter(text).map(|found| found.range()).collect()
+            }
+            (TextSplit::Regex(_), None) => Vec::new(),
+            (TextSplit::Lines, _) => lines(text),
+            (TextSplit::Words, _) => words(text),
+        };
+
+        tokens
+            .into_iter()
+            .filter(|token| !token.is_empty())
+            .map(|token| to_transcript(indices, token))
+            .collect()
+    }
+
+    /// Commits to each token of the text.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The transcript commitment builder.
+    /// * `direction` - The direction of the text.
+    /// * `indices` - The indices of the text in the transcript.
+    /// * `text` - The text.
+    pub fn commit_tokens<C: TranscriptCommitmentBuilder>(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        indices: &RangeSet<usize>,
+        text: &[u8],
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        for token in self.tokens(indices, text) {
+            builder.commit(&token, direction)?;
+        }
+
+        Ok(())
+    }
+
+    /// Commits to the text excluding its tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The transcript commitment builder.
+    /// * `direction` - The direction of the text.
+    /// * `indices` - The indices of the text in the transcript.
+    /// * `text` - The text.
+    pub fn commit_structure<C: TranscriptCommitmentBuilder>(
+        &self,
+        builder: &mut C,
+        direction: Direction,
+        indices: &RangeSet<usize>,
+        text: &[u8],
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        let structure = self
+            .tokens(indices, text)
+            .iter()
+            .fold(indices.clone(), |structure, token| {
+                structure.difference(token)
+            });
+
+        if !structure.is_empty() {
+            builder.commit(&structure, direction)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for TextCommitter {
+    fn eq(&self, other: &Self) -> bool {
+        self.split == other.split
+    }
+}
+
+impl Eq for TextCommitter {}
+
+impl TryFrom<TextSplit> for TextCommitter {
+    type Error = regex::Error;
+
+    fn try_from(split: TextSplit) -> Result<Self, Self::Error> {
+        match split {
+            TextSplit::Lines => Ok(Self::lines()),
+            TextSplit::Words => Ok(Self::words()),
+            TextSplit::Regex(pattern) => Self::regex(pattern),
+        }
+    }
+}
+
+impl From<TextCommitter> for TextSplit {
+    fn from(committer: TextCommitter) -> Self {
+        committer.split
+    }
+}
+
+/// Returns the lines of the text, excluding `\n` or `\r\n`.
+fn lines(text: &[u8]) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (i, _) in text.iter().enumerate().filter(|(_, b)| **b == b'\n') {
+        let end = if i > start && text[i - 1] == b'\r' {
+            i - 1
+        } else {
+            i
+        };
+        lines.push(start..end);
+        start = i + 1;
+    }
+    lines.push(start..text.len());
+
+    lines
+}
+
+/// Returns the runs of non-whitespace bytes of the text.
+fn words(text: &[u8]) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (i, b) in text.iter().enumerate() {
+        match (start, b.is_ascii_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..text.len());
+    }
+
+    words
+}
+
+/// Maps a range of text located at `indices` to ranges of the transcript.
+fn to_transcript(indices: &RangeSet<usize>, token: Range<usize>) -> RangeSet<usize> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+
+    for range in indices.iter_ranges() {
+        let start = token.start.max(offset);
+        let end = token.end.min(offset + range.len());
+        if start < end {
+            ranges.push(range.start + start - offset..range.start + end - offset);
+        }
+        offset += range.len();
+    }
+
+    RangeSet::from(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    const TEXT: &[u8] = b"Hello, World!\r\n\r\nbalance: 42 USD\nend";
+
+    fn tokens(committer: &TextCommitter) -> Vec<&[u8]> {
+        committer
+            .tokens(&RangeSet::from(0..TEXT.len()), TEXT)
+            .iter()
+            .map(|token| {
+                let range = token.iter_ranges().next().unwrap();
+                &TEXT[range]
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::lines(TextCommitter::lines(), vec!["Hello, World!", "balance: 42 USD", "end"])]
+    #[case::words(TextCommitter::words(), vec!["Hello,", "World!", "balance:", "42", "USD", "end"])]
+    #[case::regex(TextCommitter::regex(r"\d+ [A-Z]+").unwrap(), vec!["42 USD"])]
+    #[case::regex_groups(TextCommitter::regex(r"(\w+): (\d+)").unwrap(), vec!["balance", "42"])]
+    fn test_tokens(#[case] committer: TextCommitter, #[case] expected: Vec<&str>) {
+        assert_eq!(
+            tokens(&committer),
+            expected.iter().map(|s| s.as_bytes()).collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    fn test_tokens_across_ranges() {
+        // The text "ab cd" split over two chunks, at 10..13 and 20..22.
+        let indices = RangeSet::from([10..13, 20..22]);
+
+        assert_eq!(
+            TextCommitter::words().tokens(&indices, b"ab cd"),
+            [RangeSet::from(10..12), RangeSet::from(20..22)]
+        );
+        assert_eq!(
+            TextCommitter::regex("b c")
+                .unwrap()
+                .tokens(&indices, b"ab cd"),
+            [RangeSet::from([11..13, 20..21])]
+        );
+    }
+
+    #[rstest]
+    fn test_serde() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            html: TextCommitter,
+            text: TextCommitter,
+        }
+
+        let config: Config =
+            toml::from_str("html = \"lines\"\ntext = { regex = '(\\d+)' }").unwrap();
+        assert_eq!(config.html, TextCommitter::lines());
+        assert_eq!(config.text, TextCommitter::regex(r"(\d+)").unwrap());
+        assert_eq!(
+            toml::from_str::<Config>(&toml::to_string(&config).unwrap()).unwrap(),
+            config
+        );
+
+        assert!(toml::from_str::<Config>("html = \"lines\"\ntext = { regex = '(' }").is_err());
+    }
+}
@@ -0,0 +1,582 @@
+//! Packet captures.
+//!
+//! Parses pcap and pcapng captures and reassembles the byte streams of the
+//! TCP connections they contain, so that the TLS records of a connection can
+//! be parsed and decrypted offline.
+//!
+//! Captures of Ethernet, Linux cooked (v1 and v2), loopback and raw IP
+//! link types are supported. Fragmented IP packets are ignored.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::transcript::{Direction, Transcript};
+
+/// Error for [`Capture`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CaptureError {
+    /// The capture is neither a pcap nor a pcapng capture.
+    #[error("unknown capture format")]
+    UnknownFormat,
+    /// The capture ends within a header or block.
+    #[error("capture is truncated at {offset}")]
+    Truncated {
+        /// Offset in the capture.
+        offset: usize,
+    },
+    /// A block is malformed.
+    #[error("invalid block at {offset}")]
+    InvalidBlock {
+        /// Offset in the capture.
+        offset: usize,
+    },
+    /// A packet refers to an interface which was not described.
+    #[error("packet at {offset} refers to unknown interface {interface}")]
+    UnknownInterface {
+        /// Offset in the capture.
+        offset: usize,
+        /// The interface id.
+        interface: u32,
+    },
+    /// The link type of an interface is not supported.
+    #[error("unsupported link type {0}")]
+    UnsupportedLinkType(u32),
+    /// A stream is missing data, e.g. because a packet was not captured.
+    #[error("{direction} stream is missing data at {offset}")]
+    Gap {
+        /// Direction of the stream.
+        direction: Direction,
+        /// Offset of the missing data in the stream.
+        offset: usize,
+    },
+}
+
+/// A packet capture.
+#[derive(Debug, Clone, Default)]
+pub struct Capture {
+    connections: Vec<TcpConnection>,
+}
+
+impl Capture {
+    /// Parses a pcap or pcapng capture.
+    pub fn parse(src: &[u8]) -> Result<Self, CaptureError> {
+        let mut capture = Capture::default();
+
+        let magic = read(src, 0, 4)?;
+        match magic {
+            [0xd4, 0xc3, 0xb2, 0xa1] | [0x4d, 0x3c, 0xb2, 0xa1] => {
+                parse_pcap(src, Endian::Little, &mut capture)?
+            }
+            [0xa1, 0xb2, 0xc3, 0xd4] | [0xa1, 0xb2, 0x3c, 0x4d] => {
+                parse_pcap(src, Endian::Big, &mut capture)?
+            }
+            [0x0a, 0x0d, 0x0d, 0x0a] => parse_pcapng(src, &mut capture)?,
+            _ => return Err(CaptureError::UnknownFormat),
+        }
+
+        Ok(capture)
+    }
+
+    /// Returns the TCP connections of the capture, in the order they were
+    /// first seen.
+    ///
+    /// A SYN which reuses the addresses of an earlier connection starts a new
+    /// connection.
+    pub fn connections(&self) -> &[TcpConnection] {
+        &self.connections
+    }
+
+    /// Returns the first connection to the given server address.
+    pub fn connection_to(&self, server: SocketAddr) -> Option<&TcpConnection> {
+        self.connections
+            .iter()
+            .find(|connection| connection.server == server)
+    }
+
+    fn push(&mut self, packet: Packet<'_>) {
+        let existing = self.connections.iter().rposition(|connection| {
+            (connection.client, connection.server) == (packet.src, packet.dst)
+                || (connection.client, connection.server) == (packet.dst, packet.src)
+        });
+
+        let connection = match existing {
+            Some(index) if !self.connections[index].is_reused_by(&packet) => {
+                &mut self.connections[index]
+            }
+            _ => {
+                // The sender of a SYN is the client, and the sender of a
+                // SYN-ACK is the server. Without either, the server is assumed
+                // to have the lower port, which is usually a well-known one.
+                let (client, server) = match (packet.syn, packet.ack) {
+                    (true, false) => (packet.src, packet.dst),
+                    (true, true) => (packet.dst, packet.src),
+                    _ if packet.src.port() < packet.dst.port() => (packet.dst, packet.src),
+                    _ => (packet.src, packet.dst),
+                };
+                self.connections.push(TcpConnection {
+                    client,
+                    server,
+                    sent: Segments::default(),
+                    received: Segments::default(),
+                });
+                self.connections.last_mut().expect("connection was pushed")
+            }
+        };
+
+        let segments = if packet.src == connection.client {
+            &mut connection.sent
+        } else {
+            &mut connection.received
+        };
+        segments.push(packet);
+    }
+}
+
+/// A TCP connection.
+#[derive(Debug, Clone)]
+pub struct TcpConnection {
+    /// The address of the client.
+    pub client: SocketAddr,
+    /// The address of the server.
+    pub server: SocketAddr,
+    sent: Segments,
+    received: Segments,
+}
+
+impl TcpConnection {
+    /// Returns whether a packet opens a new connection with the same addresses
+    /// as this one, rather than retransmitting its SYN.
+    fn is_reused_by(&self, packet: &Packet<'_>) -> bool {
+        if !packet.syn || packet.ack {
+            return false;
+        }
+
+        let retransmitted = packet.src == self.client
+            && self.sent.isn == Some(packet.seq.wrapping_add(1))
+            && self.sent.segments.is_empty()
+            && self.received.segments.is_empty();
+
+        !retransmitted
+    }
+
+    /// Returns the reassembled streams of the connection.
+    pub fn transcript(&self) -> Result<Transcript, CaptureError> {
+        Ok(Transcript::new(
+            self.sent.reassemble(Direction::Sent)?,
+            self.received.reassemble(Direction::Received)?,
+        ))
+    }
+}
+
+/// The segments sent in one direction of a connection.
+#[derive(Debug, Clone, Default)]
+struct Segments {
+    /// Sequence number of the first byte of the stream.
+    isn: Option<u32>,
+    segments: Vec<(u32, Vec<u8>)>,
+}
+
+impl Segments {
+    fn push(&mut self, packet: Packet<'_>) {
+        if packet.syn {
+            self.isn = Some(packet.seq.wrapping_add(1));
+        }
+        if !packet.payload.is_empty() {
+            self.segments.push((packet.seq, packet.payload.to_vec()));
+        }
+    }
+
+    /// Reassembles the stream, discarding retransmitted data.
+    fn reassemble(&self, direction: Direction) -> Result<Vec<u8>, CaptureError> {
+        let Some(isn) = self
+            .isn
+            .or_else(|| self.segments.first().map(|(seq, _)| *seq))
+        else {
+            return Ok(Vec::new());
+        };
+
+        // Sequence numbers wrap, so order segments by their offset from the
+        // start of the stream.
+        let mut segments: Vec<(u32, &[u8])> = self
+            .segments
+            .iter()
+            .map(|(seq, payload)| (seq.wrapping_sub(isn), payload.as_slice()))
+            .filter(|(offset, _)| *offset < u32::MAX / 2)
+            .collect();
+        segments.sort_by_key(|(offset, payload)| (*offset, std::cmp::Reverse(payload.len())));
+
+        let mut stream = Vec::new();
+        for (offset, payload) in segments {
+            let offset = offset as usize;
+            if offset > stream.len() {
+                return Err(CaptureError::Gap {
+                    direction,
+                    offset: stream.len(),
+                });
+            }
+            if let Some(new) = payload.get(stream.len() - offset..) {
+                stream.extend_from_slice(new);
+            }
+        }
+
+        Ok(stream)
+    }
+}
+
+/// A TCP packet.
+struct Packet<'a> {
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    syn: bool,
+    ack: bool,
+    payload: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn u16(&self, src: &[u8], offset: usize) -> Result<u16, CaptureError> {
+        let bytes = read(src, offset, 2)?.try_into().expect("read 2 bytes");
+        Ok(match self {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, src: &[u8], offset: usize) -> Result<u32, CaptureError> {
+        let bytes = read(src, offset, 4)?.try_into().expect("read 4 bytes");
+        Ok(match self {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        })
+    }
+}
+
+fn read(src: &[u8], offset: usize, len: usize) -> Result<&[u8], CaptureError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| src.get(offset..end))
+        .ok_or(CaptureError::Truncated { offset })
+}
+
+fn parse_pcap(src: &[u8], endian: Endian, capture: &mut Capture) -> Result<(), CaptureError> {
+    let link_type = endian.u32(src, 20)?;
+
+    let mut offset = 24;
+    while offset < src.len() {
+        let len = endian.u32(src, offset + 8)? as usize;
+        let data = read(src, offset + 16, len)?;
+        parse_frame(link_type, data, capture)?;
+        offset += 16 + len;
+    }
+
+    Ok(())
+}
+
+fn parse_pcapng(src: &[u8], capture: &mut Capture) -> Result<(), CaptureError> {
+    let mut endian = Endian::Little;
+    let mut link_types = Vec::new();
+
+    let mut offset = 0;
+    while offset < src.len() {
+        let typ = read(src, offset, 4)?;
+        if typ == [0x0a, 0x0d, 0x0d, 0x0a] {
+            endian = match read(src, offset + 8, 4)? {
+                [0x4d, 0x3c, 0x2b, 0x1a] => Endian::Little,
+                [0x1a, 0x2b, 0x3c, 0x4d] => Endian::Big,
+                _ => return Err(CaptureError::InvalidBlock { offset }),
+            };
+            link_types.clear();
+        }
+
+        let typ = endian.u32(src, offset)?;
+        let len = endian.u32(src, offset + 4)? as usize;
+        if len < 12 || !len.is_multiple_of(4) {
+            return Err(CaptureError::InvalidBlock { offset });
+        }
+        let body = read(src, offset + 8, len - 12)?;
+
+        // Offsets within the body are reported as an invalid block.
+        let invalid = |_| CaptureError::InvalidBlock { offset };
+        match typ {
+            // Interface description block.
+            1 => link_types.push(endian.u16(body, 0).map_err(invalid)? as u32),
+            // Enhanced packet block.
+            6 => {
+                let interface = endian.u32(body, 0).map_err(invalid)?;
+                let len = endian.u32(body, 12).map_err(invalid)? as usize;
+                let link_type = *link_types
+                    .get(interface as usize)
+                    .ok_or(CaptureError::UnknownInterface { offset, interface })?;
+                parse_frame(link_type, read(body, 20, len).map_err(invalid)?, capture)?;
+            }
+            // Simple packet block.
+            3 => {
+                let link_type = *link_types.first().ok_or(CaptureError::UnknownInterface {
+                    offset,
+                    interface: 0,
+                })?;
+                let len = endian.u32(body, 0).map_err(invalid)? as usize;
+                parse_frame(link_type, &body[4..4 + len.min(body.len() - 4)], capture)?;
+            }
+            _ => {}
+        }
+
+        offset += len;
+    }
+
+    Ok(())
+}
+
+/// Parses a frame, adding it to the capture if it is a TCP packet.
+fn parse_frame(link_type: u32, data: &[u8], capture: &mut Capture) -> Result<(), CaptureError> {
+    let (ethertype, ip) = match link_type {
+        // Null/loopback, with the address family in host byte order.
+        0 => match data.get(..4) {
+            Some([2, 0, 0, 0] | [0, 0, 0, 2]) => (0x0800, &data[4..]),
+            Some(_) => (0x86dd, &data[4..]),
+            None => return Ok(()),
+        },
+        // Ethernet, possibly with 802.1Q tags.
+        1 => {
+            let mut offset = 12;
+            loop {
+                let Some(ethertype) = data.get(offset..offset + 2) else {
+                    return Ok(());
+                };
+                let ethertype = u16::from_be_bytes([ethertype[0], ethertype[1]]);
+                if ethertype != 0x8100 {
+                    break (ethertype, &data[offset + 2..]);
+                }
+                offset += 4;
+            }
+        }
+        // Raw IP.
+        101 | 228 | 229 => match data.first().map(|b| b >> 4) {
+            Some(4) => (0x0800, data),
+            Some(6) => (0x86dd, data),
+            _ => return Ok(()),
+        },
+        // Linux cooked capture v1.
+        113 => match data.get(14..16) {
+            Some(ethertype) => (
+                u16::from_be_bytes([ethertype[0], ethertype[1]]),
+                &data[16..],
+            ),
+            None => return Ok(()),
+        },
+        // Linux cooked capture v2.
+        276 => match data.get(..2) {
+            Some(ethertype) if data.len() >= 20 => (
+                u16::from_be_bytes([ethertype[0], ethertype[1]]),
+                &data[20..],
+            ),
+            _ => return Ok(()),
+        },
+        _ => return Err(CaptureError::UnsupportedLinkType(link_type)),
+    };
+
+    if let Some(packet) = parse_ip(ethertype, ip) {
+        capture.push(packet);
+    }
+
+    Ok(())
+}
+
+/// Parses an IP packet, returning it if it is an unfragmented TCP packet.
+fn parse_ip(ethertype: u16, data: &[u8]) -> Option<Packet<'_>> {
+    let (src, dst, tcp) = match ethertype {
+        0x0800 => {
+            let header_len = (*data.first()? & 0x0f) as usize * 4;
+            let total_len = u16::from_be_bytes([*data.get(2)?, *data.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*data.get(6)?, *data.get(7)?]);
+            if *data.get(9)? != 6 || fragment & 0x3fff != 0 || header_len < 20 {
+                return None;
+            }
+            let src: [u8; 4] = data.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = data.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv4Addr::from(src)),
+                IpAddr::from(Ipv4Addr::from(dst)),
+                data.get(header_len..total_len.min(data.len()))?,
+            )
+        }
+        0x86dd => {
+            let payload_len = u16::from_be_bytes([*data.get(4)?, *data.get(5)?]) as usize;
+            if *data.get(6)? != 6 {
+                return None;
+            }
+            let src: [u8; 16] = data.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = data.get(24..40)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv6Addr::from(src)),
+                IpAddr::from(Ipv6Addr::from(dst)),
+                data.get(40..(40 + payload_len).min(data.len()))?,
+            )
+        }
+        _ => return None,
+    };
+
+    let port = |offset: usize| {
+        Some(u16::from_be_bytes([
+            *tcp.get(offset)?,
+            *tcp.get(offset + 1)?,
+        ]))
+    };
+    let header_len = (*tcp.get(12)? >> 4) as usize * 4;
+    let flags = *tcp.get(13)?;
+
+    Some(Packet {
+        src: SocketAddr::new(src, port(0)?),
+        dst: SocketAddr::new(dst, port(2)?),
+        seq: u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?),
+        syn: flags & 0x02 != 0,
+        ack: flags & 0x10 != 0,
+        payload: tcp.get(header_len.max(20)..)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::fixtures::capture::{frame, pcap, pcapng, segments, Segment, ACK, SYN};
+
+    fn frames(segments: &[Segment]) -> Vec<Vec<u8>> {
+        segments.iter().map(frame).collect()
+    }
+
+    #[rstest]
+    #[case::pcap(pcap as fn(&[Vec<u8>]) -> Vec<u8>)]
+    #[case::pcapng(pcapng as fn(&[Vec<u8>]) -> Vec<u8>)]
+    fn test_capture(#[case] format: fn(&[Vec<u8>]) -> Vec<u8>) {
+        let segments = segments(
+            &[b"GET / ", b"HTTP/1.1\r\n\r\n"],
+            &[b"HTTP/1.1 ", b"200 OK"],
+        );
+        let capture = Capture::parse(&format(&frames(&segments))).unwrap();
+
+        assert_eq!(capture.connections().len(), 1);
+        let connection = &capture.connections()[0];
+        assert_eq!(connection.client, "10.0.0.1:50000".parse().unwrap());
+        assert_eq!(connection.server, "10.0.0.2:443".parse().unwrap());
+        assert!(capture
+            .connection_to("10.0.0.2:443".parse().unwrap())
+            .is_some());
+
+        let transcript = connection.transcript().unwrap();
+        assert_eq!(transcript.sent(), b"GET / HTTP/1.1\r\n\r\n".as_slice());
+        // The server's sequence numbers wrap.
+        assert_eq!(transcript.received(), b"HTTP/1.1 200 OK".as_slice());
+    }
+
+    #[rstest]
+    fn test_capture_without_handshake() {
+        // The capture starts after the handshake, with the server sending
+        // first.
+        let mut segments = segments(&[b"GET / HTTP/1.1\r\n\r\n"], &[b"HTTP/1.1 200 OK"]);
+        segments.drain(..2);
+        segments.reverse();
+
+        let capture = Capture::parse(&pcap(&frames(&segments))).unwrap();
+        let connection = &capture.connections()[0];
+        assert_eq!(connection.client, "10.0.0.1:50000".parse().unwrap());
+        assert_eq!(connection.server, "10.0.0.2:443".parse().unwrap());
+
+        let transcript = connection.transcript().unwrap();
+        assert_eq!(transcript.sent(), b"GET / HTTP/1.1\r\n\r\n".as_slice());
+        assert_eq!(transcript.received(), b"HTTP/1.1 200 OK".as_slice());
+    }
+
+    #[rstest]
+    fn test_capture_reused_addresses() {
+        let first = segments(&[b"GET /a HTTP/1.1\r\n\r\n"], &[b"HTTP/1.1 200 OK"]);
+        let mut second = segments(&[b"GET /b HTTP/1.1\r\n\r\n"], &[b"HTTP/1.1 404 Not Found"]);
+        // Retransmit the SYN of the second connection.
+        second.insert(
+            1,
+            Segment {
+                from_client: true,
+                flags: SYN,
+                seq: 1000,
+                payload: Vec::new(),
+            },
+        );
+
+        let capture = Capture::parse(&pcap(&[frames(&first), frames(&second)].concat())).unwrap();
+        let transcripts = capture
+            .connections()
+            .iter()
+            .map(|connection| connection.transcript().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(transcripts.len(), 2);
+        assert_eq!(transcripts[0].sent(), b"GET /a HTTP/1.1\r\n\r\n".as_slice());
+        assert_eq!(transcripts[1].sent(), b"GET /b HTTP/1.1\r\n\r\n".as_slice());
+        assert_eq!(
+            transcripts[1].received(),
+            b"HTTP/1.1 404 Not Found".as_slice()
+        );
+    }
+
+    #[rstest]
+    fn test_reassemble_out_of_order() {
+        let mut segments = segments(&[b"GET / ", b"HTTP/1.1\r\n", b"\r\n"], &[]);
+        // Reorder the data, and retransmit the first segment with more data.
+        segments.swap(2, 4);
+        segments.push(Segment {
+            from_client: true,
+            flags: ACK,
+            seq: 1001,
+            payload: b"GET / HTTP".to_vec(),
+        });
+
+        let capture = Capture::parse(&pcap(&frames(&segments))).unwrap();
+        assert_eq!(
+            capture.connections()[0].transcript().unwrap().sent(),
+            b"GET / HTTP/1.1\r\n\r\n".as_slice()
+        );
+    }
+
+    #[rstest]
+    fn test_reassemble_gap() {
+        let mut segments = segments(&[b"GET / ", b"HTTP/1.1\r\n\r\n"], &[]);
+        segments.remove(2);
+
+        let capture = Capture::parse(&pcap(&frames(&segments))).unwrap();
+        assert_eq!(
+            capture.connections()[0].transcript().err(),
+            Some(CaptureError::Gap {
+                direction: Direction::Sent,
+                offset: 0
+            })
+        );
+    }
+
+    #[rstest]
+    fn test_capture_errors() {
+        assert_eq!(
+            Capture::parse(b"not a capture").unwrap_err(),
+            CaptureError::UnknownFormat
+        );
+
+        let mut truncated = pcap(&frames(&segments(&[b"GET"], &[])));
+        truncated.pop();
+        assert!(matches!(
+            Capture::parse(&truncated).unwrap_err(),
+            CaptureError::Truncated { .. }
+        ));
+
+        let mut unsupported = pcap(&frames(&segments(&[b"GET"], &[])));
+        unsupported[20] = 0xff;
+        assert_eq!(
+            Capture::parse(&unsupported).unwrap_err(),
+            CaptureError::UnsupportedLinkType(0xff)
+        );
+    }
+}
@@ -4,9 +4,10 @@ use std::fmt;
 
 use rustls_pki_types as webpki_types;
 use serde::{Deserialize, Serialize};
-use tls_core::msgs::{codec::Codec, enums::NamedGroup, handshake::ServerECDHParams};
 
-use crate::webpki::{CertificateDer, ServerCertVerifier, ServerCertVerifierError};
+#[cfg(feature = "webpki")]
+use crate::webpki::{ServerCertVerifier, ServerCertVerifierError};
+use crate::{transcript::TranscriptLength, webpki::CertificateDer};
 
 /// TLS version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -18,13 +19,13 @@ pub enum TlsVersion {
     V1_3,
 }
 
-impl TryFrom<tls_core::msgs::enums::ProtocolVersion> for TlsVersion {
+impl TryFrom<u16> for TlsVersion {
     type Error = &'static str;
 
-    fn try_from(value: tls_core::msgs::enums::ProtocolVersion) -> Result<Self, Self::Error> {
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
         Ok(match value {
-            tls_core::msgs::enums::ProtocolVersion::TLSv1_2 => TlsVersion::V1_2,
-            tls_core::msgs::enums::ProtocolVersion::TLSv1_3 => TlsVersion::V1_3,
+            0x0303 => TlsVersion::V1_2,
+            0x0304 => TlsVersion::V1_3,
             _ => return Err("unsupported TLS version"),
         })
     }
@@ -38,6 +39,7 @@ pub enum ServerName {
 }
 
 impl ServerName {
+    #[cfg(feature = "webpki")]
     pub(crate) fn to_webpki(&self) -> webpki_types::ServerName<'static> {
         match self {
             ServerName::Dns(name) => webpki_types::ServerName::DnsName(
@@ -164,31 +166,6 @@ impl fmt::Display for SignatureAlgorithm {
     }
 }
 
-impl From<tls_core::verify::SignatureAlgorithm> for SignatureAlgorithm {
-    fn from(value: tls_core::verify::SignatureAlgorithm) -> Self {
-        use tls_core::verify::SignatureAlgorithm as Core;
-        match value {
-            Core::ECDSA_NISTP256_SHA256 => SignatureAlgorithm::ECDSA_NISTP256_SHA256,
-            Core::ECDSA_NISTP256_SHA384 => SignatureAlgorithm::ECDSA_NISTP256_SHA384,
-            Core::ECDSA_NISTP384_SHA256 => SignatureAlgorithm::ECDSA_NISTP384_SHA256,
-            Core::ECDSA_NISTP384_SHA384 => SignatureAlgorithm::ECDSA_NISTP384_SHA384,
-            Core::ED25519 => SignatureAlgorithm::ED25519,
-            Core::RSA_PKCS1_2048_8192_SHA256 => SignatureAlgorithm::RSA_PKCS1_2048_8192_SHA256,
-            Core::RSA_PKCS1_2048_8192_SHA384 => SignatureAlgorithm::RSA_PKCS1_2048_8192_SHA384,
-            Core::RSA_PKCS1_2048_8192_SHA512 => SignatureAlgorithm::RSA_PKCS1_2048_8192_SHA512,
-            Core::RSA_PSS_2048_8192_SHA256_LEGACY_KEY => {
-                SignatureAlgorithm::RSA_PSS_2048_8192_SHA256_LEGACY_KEY
-            }
-            Core::RSA_PSS_2048_8192_SHA384_LEGACY_KEY => {
-                SignatureAlgorithm::RSA_PSS_2048_8192_SHA384_LEGACY_KEY
-            }
-            Core::RSA_PSS_2048_8192_SHA512_LEGACY_KEY => {
-                SignatureAlgorithm::RSA_PSS_2048_8192_SHA512_LEGACY_KEY
-            }
-        }
-    }
-}
-
 /// Server's signature of the key exchange parameters.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerSignature {
@@ -210,33 +187,18 @@ pub struct ServerEphemKey {
 
 impl ServerEphemKey {
     /// Encodes the key exchange parameters as in TLS.
+    #[cfg(feature = "webpki")]
     pub(crate) fn kx_params(&self) -> Vec<u8> {
-        let group = match self.typ {
-            KeyType::SECP256R1 => NamedGroup::secp256r1,
-        };
-
-        let mut kx_params = Vec::new();
-        ServerECDHParams::new(group, &self.key).encode(&mut kx_params);
+        // ECParameters of a named curve, followed by the public key.
+        let mut kx_params = vec![3];
+        kx_params.extend_from_slice(&(self.typ as u16).to_be_bytes());
+        kx_params.push(self.key.len() as u8);
+        kx_params.extend_from_slice(&self.key);
 
         kx_params
     }
 }
 
-impl TryFrom<tls_core::key::PublicKey> for ServerEphemKey {
-    type Error = &'static str;
-
-    fn try_from(value: tls_core::key::PublicKey) -> Result<Self, Self::Error> {
-        let tls_core::msgs::enums::NamedGroup::secp256r1 = value.group else {
-            return Err("unsupported key type");
-        };
-
-        Ok(ServerEphemKey {
-            typ: KeyType::SECP256R1,
-            key: value.key,
-        })
-    }
-}
-
 /// TLS session information.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConnectionInfo {
@@ -293,12 +255,13 @@ pub struct HandshakeData {
     pub binding: CertBinding,
 }
 
+#[cfg(feature = "webpki")]
 impl HandshakeData {
     /// Verifies the handshake data.
     ///
     /// # Arguments
     ///
-    /// * `verifier` - Cerificate verifier.
+    /// * `verifier` - Certificate verifier.
     /// * `time` - The time of the connection.
     /// * `server_ephemeral_key` - The server's ephemeral key.
     /// * `server_name` - The server name.
@@ -374,6 +337,7 @@ impl HandshakeData {
 }
 
 /// Errors that can occur when verifying a certificate chain or signature.
+#[cfg(feature = "webpki")]
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
 pub enum HandshakeVerificationError {
@@ -389,50 +353,41 @@ pub enum HandshakeVerificationError {
     ServerCert(ServerCertVerifierError),
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "webpki"))]
 mod tests {
     use super::*;
-    use crate::{fixtures::ConnectionFixture, transcript::Transcript, webpki::RootCertStore};
+    use crate::{
+        fixtures::{
+            http::{request::GET_WITH_HEADER, response::OK_JSON},
+            ConnectionFixture,
+        },
+        transcript::Transcript,
+        webpki::RootCertStore,
+    };
 
-    use hex::FromHex;
     use rstest::*;
-    use tlsn_data_fixtures::http::{request::GET_WITH_HEADER, response::OK_JSON};
 
     #[fixture]
     #[once]
     fn verifier() -> ServerCertVerifier {
-        let mut root_store = RootCertStore {
-            roots: webpki_root_certs::TLS_SERVER_ROOT_CERTS
-                .iter()
-                .map(|c| CertificateDer(c.to_vec()))
-                .collect(),
-        };
-
-        // Add a cert which is no longer included in the Mozilla root store.
-        root_store.roots.push(
-            appliedzkp()
-                .server_cert_data
-                .certs
-                .last()
-                .expect("chain is valid")
-                .clone(),
-        );
+        let root_store = RootCertStore::from_pem(include_bytes!("./fixtures/data/roots.pem"))
+            .expect("roots are valid");
 
         ServerCertVerifier::new(&root_store).unwrap()
     }
 
-    fn tlsnotary() -> ConnectionFixture {
-        ConnectionFixture::tlsnotary(Transcript::new(GET_WITH_HEADER, OK_JSON).length())
+    fn ecdsa() -> ConnectionFixture {
+        ConnectionFixture::ecdsa(Transcript::new(GET_WITH_HEADER, OK_JSON).length())
     }
 
-    fn appliedzkp() -> ConnectionFixture {
-        ConnectionFixture::appliedzkp(Transcript::new(GET_WITH_HEADER, OK_JSON).length())
+    fn rsa() -> ConnectionFixture {
+        ConnectionFixture::rsa(Transcript::new(GET_WITH_HEADER, OK_JSON).length())
     }
 
     /// Expect chain verification to succeed.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_sucess_ca_implicit(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -454,8 +409,8 @@ mod tests {
     /// Expect chain verification to succeed even when a trusted CA is provided
     /// among the intermediate certs. webpki handles such cases properly.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_success_ca_explicit(
         verifier: &ServerCertVerifier,
         #[case] data: ConnectionFixture,
@@ -473,8 +428,8 @@ mod tests {
 
     /// Expect to fail since the end entity cert was not valid at the time.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_fail_bad_time(
         verifier: &ServerCertVerifier,
         #[case] data: ConnectionFixture,
@@ -497,8 +452,8 @@ mod tests {
 
     /// Expect to fail when no intermediate cert provided.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_fail_no_interm_cert(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -524,8 +479,8 @@ mod tests {
     /// Expect to fail when no intermediate cert provided even if a trusted CA
     /// cert is provided.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_fail_no_interm_cert_with_ca_cert(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -548,8 +503,8 @@ mod tests {
 
     /// Expect to fail because end-entity cert is wrong.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_fail_bad_ee_cert(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -574,8 +529,8 @@ mod tests {
 
     /// Expect sig verification to fail because client_random is wrong.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_sig_ke_params_fail_bad_client_random(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -599,8 +554,8 @@ mod tests {
 
     /// Expect sig verification to fail because the sig is wrong.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_sig_ke_params_fail_bad_sig(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -622,8 +577,8 @@ mod tests {
 
     /// Expect to fail because the dns name is not in the cert.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_check_dns_name_present_in_cert_fail_bad_host(
         verifier: &ServerCertVerifier,
         #[case] data: ConnectionFixture,
@@ -645,12 +600,12 @@ mod tests {
 
     /// Expect to fail because the ephemeral key provided is wrong.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_invalid_ephemeral_key(verifier: &ServerCertVerifier, #[case] data: ConnectionFixture) {
         let wrong_ephemeral_key = ServerEphemKey {
             typ: KeyType::SECP256R1,
-            key: Vec::<u8>::from_hex(include_bytes!("./fixtures/data/unknown/pubkey")).unwrap(),
+            key: include_bytes!("./fixtures/data/unknown/pubkey").to_vec(),
         };
 
         let err = data.server_cert_data.verify(
@@ -668,8 +623,8 @@ mod tests {
 
     /// Expect to fail when no cert provided.
     #[rstest]
-    #[case::tlsnotary(tlsnotary())]
-    #[case::appliedzkp(appliedzkp())]
+    #[case::ecdsa(ecdsa())]
+    #[case::rsa(rsa())]
     fn test_verify_cert_chain_fail_no_cert(
         verifier: &ServerCertVerifier,
         #[case] mut data: ConnectionFixture,
@@ -0,0 +1,1128 @@
+//! Decryption of TLS application data.
+//!
+//! [`decrypt`] parses the raw byte streams of a TLS connection into records,
+//! decrypts the application data records with the [`SessionKeys`] of the
+//! connection and concatenates their plaintext into a [`Transcript`].
+//!
+//! The resulting [`DecryptedTranscript`] maps every byte of plaintext back to
+//! the record and ciphertext it was decrypted from, so that a disclosure of
+//! the plaintext can be turned into openings of individual records with
+//! [`DecryptedTranscript::openings`].
+
+use std::{fmt, ops::Range};
+
+use aes_gcm::{
+    aead::{Aead as _, KeyInit, Payload},
+    Aes128Gcm, Aes256Gcm,
+};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::SimpleHkdf;
+use hmac::{Mac, SimpleHmac};
+use rangeset::{Intersection, RangeSet};
+use serde::{Deserialize, Serialize};
+use sha2::{digest::core_api::BlockSizeUser, Digest, Sha256, Sha384};
+
+use crate::{
+    tls::record::{ContentType, Record, TlsTranscript, TlsTranscriptError, HEADER_LEN},
+    transcript::{Direction, Transcript},
+};
+
+/// Length of the explicit nonce of TLS 1.2 AES-GCM records.
+pub(crate) const EXPLICIT_NONCE_LEN: usize = 8;
+
+/// A supported cipher suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CipherSuite {
+    /// TLS 1.3 `TLS_AES_128_GCM_SHA256`.
+    Tls13Aes128GcmSha256,
+    /// TLS 1.3 `TLS_AES_256_GCM_SHA384`.
+    Tls13Aes256GcmSha384,
+    /// TLS 1.3 `TLS_CHACHA20_POLY1305_SHA256`.
+    Tls13Chacha20Poly1305Sha256,
+    /// TLS 1.2 `TLS_ECDHE_{ECDSA,RSA}_WITH_AES_128_GCM_SHA256`.
+    Tls12EcdheAes128GcmSha256,
+    /// TLS 1.2 `TLS_ECDHE_{ECDSA,RSA}_WITH_AES_256_GCM_SHA384`.
+    Tls12EcdheAes256GcmSha384,
+}
+
+impl CipherSuite {
+    /// Returns whether the cipher suite is a TLS 1.3 cipher suite.
+    pub fn is_tls13(&self) -> bool {
+        matches!(
+            self,
+            CipherSuite::Tls13Aes128GcmSha256
+                | CipherSuite::Tls13Aes256GcmSha384
+                | CipherSuite::Tls13Chacha20Poly1305Sha256
+        )
+    }
+
+    /// Returns the length of the write key.
+    pub fn key_len(&self) -> usize {
+        match self {
+            CipherSuite::Tls13Aes128GcmSha256 | CipherSuite::Tls12EcdheAes128GcmSha256 => 16,
+            CipherSuite::Tls13Aes256GcmSha384
+            | CipherSuite::Tls13Chacha20Poly1305Sha256
+            | CipherSuite::Tls12EcdheAes256GcmSha384 => 32,
+        }
+    }
+
+    /// Returns the length of the write IV, which for TLS 1.2 is the implicit
+    /// part of the nonce.
+    pub fn iv_len(&self) -> usize {
+        if self.is_tls13() {
+            12
+        } else {
+            12 - EXPLICIT_NONCE_LEN
+        }
+    }
+
+    pub(crate) fn hash(&self) -> HashAlgorithm {
+        match self {
+            CipherSuite::Tls13Aes256GcmSha384 | CipherSuite::Tls12EcdheAes256GcmSha384 => {
+                HashAlgorithm::Sha384
+            }
+            _ => HashAlgorithm::Sha256,
+        }
+    }
+
+    pub(crate) fn aead(&self, key: &[u8]) -> Option<Aead> {
+        match self {
+            CipherSuite::Tls13Chacha20Poly1305Sha256 => Aead::chacha20_poly1305(key),
+            _ => Aead::aes_gcm(key),
+        }
+    }
+}
+
+impl TryFrom<u16> for CipherSuite {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x1301 => CipherSuite::Tls13Aes128GcmSha256,
+            0x1302 => CipherSuite::Tls13Aes256GcmSha384,
+            0x1303 => CipherSuite::Tls13Chacha20Poly1305Sha256,
+            0xc02b | 0xc02f => CipherSuite::Tls12EcdheAes128GcmSha256,
+            0xc02c | 0xc030 => CipherSuite::Tls12EcdheAes256GcmSha384,
+            _ => return Err(value),
+        })
+    }
+}
+
+/// Error for invalid key material.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid key material: {0}")]
+pub struct KeyError(String);
+
+/// The write key and IV of one side of a connection.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TrafficKeys {
+    pub(crate) key: Vec<u8>,
+    pub(crate) iv: Vec<u8>,
+}
+
+impl fmt::Debug for TrafficKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrafficKeys").finish_non_exhaustive()
+    }
+}
+
+impl TrafficKeys {
+    /// Creates traffic keys from a write key and IV.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher_suite` - The cipher suite of the connection.
+    /// * `key` - The write key.
+    /// * `iv` - The write IV, or the implicit nonce for TLS 1.2.
+    pub fn new(
+        cipher_suite: CipherSuite,
+        key: impl Into<Vec<u8>>,
+        iv: impl Into<Vec<u8>>,
+    ) -> Result<Self, KeyError> {
+        let (key, iv) = (key.into(), iv.into());
+
+        if key.len() != cipher_suite.key_len() {
+            return Err(KeyError(format!(
+                "expected a {} byte key, got {}",
+                cipher_suite.key_len(),
+                key.len()
+            )));
+        }
+        if iv.len() != cipher_suite.iv_len() {
+            return Err(KeyError(format!(
+                "expected a {} byte IV, got {}",
+                cipher_suite.iv_len(),
+                iv.len()
+            )));
+        }
+
+        Ok(Self { key, iv })
+    }
+
+    /// Derives traffic keys from a TLS 1.3 traffic secret.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher_suite` - The cipher suite of the connection.
+    /// * `secret` - The traffic secret.
+    pub fn from_secret(cipher_suite: CipherSuite, secret: &[u8]) -> Result<Self, KeyError> {
+        if !cipher_suite.is_tls13() {
+            return Err(KeyError(
+                "traffic secrets are only defined for TLS 1.3".to_string(),
+            ));
+        }
+
+        let hash = cipher_suite.hash();
+        let expand = |label: &[u8], len| {
+            hash.hkdf_expand_label(secret, label, b"", len)
+                .ok_or_else(|| {
+                    KeyError("traffic secret is too short for the cipher suite".to_string())
+                })
+        };
+        Self::new(
+            cipher_suite,
+            expand(b"key", cipher_suite.key_len())?,
+            expand(b"iv", cipher_suite.iv_len())?,
+        )
+    }
+}
+
+/// The keys of a TLS connection.
+///
+/// Records in each direction are decrypted with the keys of that direction in
+/// order. In TLS 1.3, the handshake is encrypted with keys which precede the
+/// application keys. The keys switch at the first record which fails to
+/// authenticate with the current keys but authenticates with a later one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionKeys {
+    cipher_suite: CipherSuite,
+    client: Vec<TrafficKeys>,
+    server: Vec<TrafficKeys>,
+}
+
+impl SessionKeys {
+    /// Creates session keys from the client and server traffic keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher_suite` - The cipher suite of the connection.
+    /// * `client` - The client's application traffic keys.
+    /// * `server` - The server's application traffic keys.
+    pub fn new(cipher_suite: CipherSuite, client: TrafficKeys, server: TrafficKeys) -> Self {
+        Self {
+            cipher_suite,
+            client: vec![client],
+            server: vec![server],
+        }
+    }
+
+    /// Creates session keys from TLS 1.3 application traffic secrets.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher_suite` - The cipher suite of the connection.
+    /// * `client` - The client's application traffic secret.
+    /// * `server` - The server's application traffic secret.
+    pub fn from_traffic_secrets(
+        cipher_suite: CipherSuite,
+        client: &[u8],
+        server: &[u8],
+    ) -> Result<Self, KeyError> {
+        Ok(Self::new(
+            cipher_suite,
+            TrafficKeys::from_secret(cipher_suite, client)?,
+            TrafficKeys::from_secret(cipher_suite, server)?,
+        ))
+    }
+
+    /// Creates session keys from a TLS 1.2 master secret.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher_suite` - The cipher suite of the connection.
+    /// * `master_secret` - The master secret.
+    /// * `client_random` - The client random.
+    /// * `server_random` - The server random.
+    pub fn from_master_secret(
+        cipher_suite: CipherSuite,
+        master_secret: &[u8],
+        client_random: &[u8; 32],
+        server_random: &[u8; 32],
+    ) -> Result<Self, KeyError> {
+        if cipher_suite.is_tls13() {
+            return Err(KeyError(
+                "master secrets are only defined for TLS 1.2".to_string(),
+            ));
+        }
+
+        // AEAD cipher suites have no MAC keys, so the key block is the client
+        // and server write keys followed by their IVs.
+        let (key_len, iv_len) = (cipher_suite.key_len(), cipher_suite.iv_len());
+        let key_block = cipher_suite.hash().prf(
+            master_secret,
+            b"key expansion",
+            &[server_random.as_slice(), client_random].concat(),
+            2 * (key_len + iv_len),
+        );
+        let (keys, ivs) = key_block.split_at(2 * key_len);
+        let (client_key, server_key) = keys.split_at(key_len);
+        let (client_iv, server_iv) = ivs.split_at(iv_len);
+
+        Ok(Self::new(
+            cipher_suite,
+            TrafficKeys::new(cipher_suite, client_key, client_iv)?,
+            TrafficKeys::new(cipher_suite, server_key, server_iv)?,
+        ))
+    }
+
+    /// Adds the TLS 1.3 handshake traffic keys, so that encrypted handshake
+    /// records can be decrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client's handshake traffic keys.
+    /// * `server` - The server's handshake traffic keys.
+    pub fn with_handshake_keys(mut self, client: TrafficKeys, server: TrafficKeys) -> Self {
+        self.client.insert(0, client);
+        self.server.insert(0, server);
+        self
+    }
+
+    /// Returns the cipher suite of the connection.
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
+    pub(crate) fn keys(&self, direction: Direction) -> &[TrafficKeys] {
+        match direction {
+            Direction::Sent => &self.client,
+            Direction::Received => &self.server,
+        }
+    }
+}
+
+/// Error for [`decrypt`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DecryptError {
+    /// The records could not be parsed.
+    #[error(transparent)]
+    Records(#[from] TlsTranscriptError),
+    /// The keys are invalid for the cipher suite.
+    #[error(transparent)]
+    Key(#[from] KeyError),
+    /// A record failed to authenticate with every key.
+    #[error("{direction} record {record} at {offset} failed to authenticate")]
+    Authentication {
+        /// Direction of the record.
+        direction: Direction,
+        /// Index of the record.
+        record: usize,
+        /// Offset of the record in the stream.
+        offset: usize,
+    },
+    /// A record is malformed.
+    #[error("{direction} record {record} at {offset} is invalid: {reason}")]
+    InvalidRecord {
+        /// Direction of the record.
+        direction: Direction,
+        /// Index of the record.
+        record: usize,
+        /// Offset of the record in the stream.
+        offset: usize,
+        /// The reason the record is invalid.
+        reason: &'static str,
+    },
+}
+
+/// The plaintext of an application data record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordMapping {
+    /// Index of the record.
+    pub record: usize,
+    /// Sequence number of the record under its keys.
+    pub seq: u64,
+    /// Range of the plaintext in the decrypted transcript.
+    pub plaintext: Range<usize>,
+    /// Range of the corresponding ciphertext in the raw stream.
+    pub ciphertext: Range<usize>,
+}
+
+/// An opening of part of a record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordOpening {
+    /// Index of the record.
+    pub record: usize,
+    /// Sequence number of the record under its keys.
+    pub seq: u64,
+    /// Indices of the plaintext in the decrypted transcript.
+    pub plaintext: RangeSet<usize>,
+    /// Offsets of the plaintext in the record's plaintext.
+    pub offsets: RangeSet<usize>,
+    /// Indices of the corresponding ciphertext in the raw stream.
+    pub ciphertext: RangeSet<usize>,
+}
+
+/// A transcript of decrypted application data.
+#[derive(Clone)]
+pub struct DecryptedTranscript {
+    transcript: Transcript,
+    records: TlsTranscript,
+    sent: Vec<RecordMapping>,
+    received: Vec<RecordMapping>,
+}
+
+impl fmt::Debug for DecryptedTranscript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Omit the plaintext, like `Transcript`.
+        f.debug_struct("DecryptedTranscript")
+            .field("records", &self.records)
+            .field("sent", &self.sent)
+            .field("received", &self.received)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DecryptedTranscript {
+    /// Returns the decrypted transcript.
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Returns the records of the raw streams.
+    pub fn records(&self) -> &TlsTranscript {
+        &self.records
+    }
+
+    /// Returns the plaintext of each application data record in the given
+    /// direction, in order.
+    pub fn mappings(&self, direction: Direction) -> &[RecordMapping] {
+        match direction {
+            Direction::Sent => &self.sent,
+            Direction::Received => &self.received,
+        }
+    }
+
+    /// Returns the openings of each record needed to reveal the given
+    /// indices of the decrypted transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - The direction of the indices.
+    /// * `idx` - The indices of the decrypted transcript.
+    pub fn openings(&self, direction: Direction, idx: &RangeSet<usize>) -> Vec<RecordOpening> {
+        self.mappings(direction)
+            .iter()
+            .filter_map(|mapping| {
+                let plaintext = idx.intersection(&mapping.plaintext);
+                if plaintext.is_empty() {
+                    return None;
+                }
+
+                let shift = |base: usize| {
+                    RangeSet::from(
+                        plaintext
+                            .iter_ranges()
+                            .map(|range| {
+                                range.start - mapping.plaintext.start + base
+                                    ..range.end - mapping.plaintext.start + base
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                };
+
+                Some(RecordOpening {
+                    record: mapping.record,
+                    seq: mapping.seq,
+                    offsets: shift(0),
+                    ciphertext: shift(mapping.ciphertext.start),
+                    plaintext,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Decrypts the application data of a transcript of the raw byte streams of
+/// a TLS connection.
+///
+/// # Arguments
+///
+/// * `transcript` - The raw byte streams, each starting at a record boundary.
+/// * `keys` - The keys of the connection.
+pub fn decrypt(
+    transcript: &Transcript,
+    keys: &SessionKeys,
+) -> Result<DecryptedTranscript, DecryptError> {
+    let records = TlsTranscript::parse(transcript)?;
+
+    let decrypt = |direction| {
+        let stream = Stream {
+            direction,
+            cipher_suite: keys.cipher_suite,
+            keys: keys.keys(direction),
+            src: match direction {
+                Direction::Sent => transcript.sent(),
+                Direction::Received => transcript.received(),
+            },
+        };
+
+        if keys.cipher_suite.is_tls13() {
+            stream.decrypt_tls13(records.records(direction))
+        } else {
+            stream.decrypt_tls12(records.records(direction))
+        }
+    };
+
+    let (sent, sent_mappings) = decrypt(Direction::Sent)?;
+    let (received, received_mappings) = decrypt(Direction::Received)?;
+
+    Ok(DecryptedTranscript {
+        transcript: Transcript::new(sent, received),
+        records,
+        sent: sent_mappings,
+        received: received_mappings,
+    })
+}
+
+/// The records of one direction of a connection.
+struct Stream<'a> {
+    direction: Direction,
+    cipher_suite: CipherSuite,
+    keys: &'a [TrafficKeys],
+    src: &'a [u8],
+}
+
+type Decrypted = (Vec<u8>, Vec<RecordMapping>);
+
+impl Stream<'_> {
+    fn error(&self, index: usize, record: &Record, reason: &'static str) -> DecryptError {
+        DecryptError::InvalidRecord {
+            direction: self.direction,
+            record: index,
+            offset: record.offset,
+            reason,
+        }
+    }
+
+    fn aead(&self, keys: &TrafficKeys) -> Result<Aead, DecryptError> {
+        if keys.iv.len() != self.cipher_suite.iv_len() {
+            return Err(KeyError("IV is invalid for the cipher suite".to_string()).into());
+        }
+
+        self.cipher_suite
+            .aead(&keys.key)
+            .ok_or_else(|| KeyError("key is invalid for the cipher suite".to_string()).into())
+    }
+
+    /// Decrypts TLS 1.2 records, which are encrypted after the
+    /// ChangeCipherSpec record.
+    fn decrypt_tls12(&self, records: &[Record]) -> Result<Decrypted, DecryptError> {
+        let keys = self
+            .keys
+            .last()
+            .ok_or_else(|| KeyError("missing keys".to_string()))?;
+        let aead = self.aead(keys)?;
+
+        let mut plaintext = Vec::new();
+        let mut mappings = Vec::new();
+        let mut seq = None;
+        for (index, record) in records.iter().enumerate() {
+            let Some(seq) = seq.as_mut() else {
+                match record.content_type {
+                    ContentType::ChangeCipherSpec => seq = Some(0u64),
+                    ContentType::ApplicationData => {
+                        return Err(self.error(index, record, "not encrypted"))
+                    }
+                    _ => {}
+                }
+                continue;
+            };
+
+            let fragment = record
+                .fragment(self.src)
+                .expect("record was parsed from src");
+            if fragment.len() < EXPLICIT_NONCE_LEN + TAG_LEN {
+                return Err(self.error(index, record, "too short"));
+            }
+            let (explicit, ciphertext) = fragment.split_at(EXPLICIT_NONCE_LEN);
+            let len = ciphertext.len() - TAG_LEN;
+
+            let mut nonce = [0u8; 12];
+            nonce[..4].copy_from_slice(&keys.iv);
+            nonce[4..].copy_from_slice(explicit);
+
+            let mut aad = Vec::with_capacity(13);
+            aad.extend_from_slice(&seq.to_be_bytes());
+            aad.push(record.content_type as u8);
+            aad.extend_from_slice(&record.legacy_version.to_be_bytes());
+            aad.extend_from_slice(&(len as u16).to_be_bytes());
+
+            let data = aead
+                .open(&nonce, &aad, ciphertext)
+                .ok_or(DecryptError::Authentication {
+                    direction: self.direction,
+                    record: index,
+                    offset: record.offset,
+                })?;
+
+            if record.content_type == ContentType::ApplicationData && !data.is_empty() {
+                let start = record.fragment_range().start + EXPLICIT_NONCE_LEN;
+                mappings.push(RecordMapping {
+                    record: index,
+                    seq: *seq,
+                    plaintext: plaintext.len()..plaintext.len() + data.len(),
+                    ciphertext: start..start + data.len(),
+                });
+                plaintext.extend_from_slice(&data);
+            }
+            *seq += 1;
+        }
+
+        Ok((plaintext, mappings))
+    }
+
+    /// Decrypts TLS 1.3 records, which are encrypted if their outer content
+    /// type is application data.
+    fn decrypt_tls13(&self, records: &[Record]) -> Result<Decrypted, DecryptError> {
+        let aeads = self
+            .keys
+            .iter()
+            .map(|keys| self.aead(keys))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut plaintext = Vec::new();
+        let mut mappings = Vec::new();
+        let mut epoch = 0;
+        let mut seq = 0u64;
+        for (index, record) in records.iter().enumerate() {
+            if record.content_type != ContentType::ApplicationData {
+                continue;
+            }
+
+            let fragment = record
+                .fragment(self.src)
+                .expect("record was parsed from src");
+            if fragment.len() <= TAG_LEN {
+                return Err(self.error(index, record, "too short"));
+            }
+            let aad = &self.src[record.offset..record.offset + HEADER_LEN];
+
+            let nonce = |epoch: usize, seq: u64| {
+                let mut nonce: [u8; 12] = self.keys[epoch]
+                    .iv
+                    .as_slice()
+                    .try_into()
+                    .expect("IV length was checked");
+                for (n, s) in nonce[4..].iter_mut().zip(seq.to_be_bytes()) {
+                    *n ^= s;
+                }
+                nonce
+            };
+
+            let data = match aeads[epoch].open(&nonce(epoch, seq), aad, fragment) {
+                Some(data) => data,
+                None => {
+                    let (next, data) = (epoch + 1..aeads.len())
+                        .find_map(|next| {
+                            aeads[next]
+                                .open(&nonce(next, 0), aad, fragment)
+                                .map(|data| (next, data))
+                        })
+                        .ok_or(DecryptError::Authentication {
+                            direction: self.direction,
+                            record: index,
+                            offset: record.offset,
+                        })?;
+                    epoch = next;
+                    seq = 0;
+                    data
+                }
+            };
+
+            // The inner plaintext is the content, followed by its type and
+            // zero padding.
+            let Some(end) = data.iter().rposition(|b| *b != 0) else {
+                return Err(self.error(index, record, "missing content type"));
+            };
+            let content_type = ContentType::try_from(data[end])
+                .map_err(|_| self.error(index, record, "invalid content type"))?;
+
+            if content_type == ContentType::ApplicationData && end > 0 {
+                let start = record.fragment_range().start;
+                mappings.push(RecordMapping {
+                    record: index,
+                    seq,
+                    plaintext: plaintext.len()..plaintext.len() + end,
+                    ciphertext: start..start + end,
+                });
+                plaintext.extend_from_slice(&data[..end]);
+            }
+            seq += 1;
+        }
+
+        Ok((plaintext, mappings))
+    }
+}
+
+/// Length of an AEAD tag.
+pub(crate) const TAG_LEN: usize = 16;
+
+/// The hash function of a cipher suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HashAlgorithm {
+    Sha256,
+    Sha384,
+}
+
+impl HashAlgorithm {
+    /// Returns the length of the hash output.
+    #[cfg(test)]
+    pub(crate) fn output_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => <Sha256 as Digest>::output_size(),
+            HashAlgorithm::Sha384 => <Sha384 as Digest>::output_size(),
+        }
+    }
+
+    /// HKDF-Expand-Label, as defined in RFC 8446 section 7.1.
+    ///
+    /// Returns `None` if the secret is shorter than the hash output.
+    pub(crate) fn hkdf_expand_label(
+        &self,
+        secret: &[u8],
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Option<Vec<u8>> {
+        fn expand<D: Digest + BlockSizeUser + Clone>(
+            secret: &[u8],
+            info: &[u8],
+            len: usize,
+        ) -> Option<Vec<u8>> {
+            let mut okm = vec![0; len];
+            SimpleHkdf::<D>::from_prk(secret)
+                .ok()?
+                .expand(info, &mut okm)
+                .ok()?;
+            Some(okm)
+        }
+
+        let label = [b"tls13 ".as_slice(), label].concat();
+        let info = [
+            &(len as u16).to_be_bytes(),
+            [label.len() as u8].as_slice(),
+            &label,
+            &[context.len() as u8],
+            context,
+        ]
+        .concat();
+
+        match self {
+            HashAlgorithm::Sha256 => expand::<Sha256>(secret, &info, len),
+            HashAlgorithm::Sha384 => expand::<Sha384>(secret, &info, len),
+        }
+    }
+
+    /// The TLS 1.2 PRF, as defined in RFC 5246 section 5.
+    pub(crate) fn prf(&self, secret: &[u8], label: &[u8], seed: &[u8], len: usize) -> Vec<u8> {
+        fn p_hash<D: Digest + BlockSizeUser>(secret: &[u8], seed: &[&[u8]], len: usize) -> Vec<u8> {
+            let hmac = |data: &[&[u8]]| {
+                let mut mac = <SimpleHmac<D> as KeyInit>::new_from_slice(secret)
+                    .expect("HMAC accepts keys of any length");
+                for data in data {
+                    mac.update(data);
+                }
+                mac.finalize().into_bytes().to_vec()
+            };
+
+            let mut out = Vec::with_capacity(len);
+            let mut a = hmac(seed);
+            while out.len() < len {
+                out.extend_from_slice(&hmac(&[&[a.as_slice()], seed].concat()));
+                a = hmac(&[&a]);
+            }
+            out.truncate(len);
+
+            out
+        }
+
+        match self {
+            HashAlgorithm::Sha256 => p_hash::<Sha256>(secret, &[label, seed], len),
+            HashAlgorithm::Sha384 => p_hash::<Sha384>(secret, &[label, seed], len),
+        }
+    }
+}
+
+/// The AEAD cipher of a cipher suite.
+///
+/// The AES variants are boxed, as their expanded keys are large.
+#[derive(Clone)]
+pub(crate) enum Aead {
+    Aes128Gcm(Box<Aes128Gcm>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl Aead {
+    /// Creates an AES-GCM cipher from a 16 or 32 byte key.
+    pub(crate) fn aes_gcm(key: &[u8]) -> Option<Self> {
+        match key.len() {
+            16 => Aes128Gcm::new_from_slice(key)
+                .ok()
+                .map(|cipher| Aead::Aes128Gcm(Box::new(cipher))),
+            32 => Aes256Gcm::new_from_slice(key)
+                .ok()
+                .map(|cipher| Aead::Aes256Gcm(Box::new(cipher))),
+            _ => None,
+        }
+    }
+
+    /// Creates a ChaCha20-Poly1305 cipher from a 32 byte key.
+    pub(crate) fn chacha20_poly1305(key: &[u8]) -> Option<Self> {
+        ChaCha20Poly1305::new_from_slice(key)
+            .ok()
+            .map(Aead::ChaCha20Poly1305)
+    }
+
+    /// Decrypts and authenticates `ciphertext`, which ends with the tag.
+    pub(crate) fn open(&self, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        let payload = Payload {
+            msg: ciphertext,
+            aad,
+        };
+
+        match self {
+            Aead::Aes128Gcm(cipher) => cipher.decrypt(nonce.into(), payload),
+            Aead::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), payload),
+            Aead::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), payload),
+        }
+        .ok()
+    }
+
+    /// Encrypts `plaintext`, appending the tag.
+    #[cfg(test)]
+    pub(crate) fn seal(&self, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+
+        match self {
+            Aead::Aes128Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            Aead::Aes256Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            Aead::ChaCha20Poly1305(cipher) => cipher.encrypt(nonce.into(), payload),
+        }
+        .expect("plaintext is not too long")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::fixtures::tls::{plaintext, tls12, tls13};
+
+    #[rstest]
+    #[case::aes_128_gcm(CipherSuite::Tls13Aes128GcmSha256)]
+    #[case::aes_256_gcm(CipherSuite::Tls13Aes256GcmSha384)]
+    #[case::chacha20_poly1305(CipherSuite::Tls13Chacha20Poly1305Sha256)]
+    fn test_decrypt_tls13(#[case] suite: CipherSuite) {
+        let secret = |b: u8| vec![b; suite.hash().output_len()];
+        let keys = SessionKeys::from_traffic_secrets(suite, &secret(1), &secret(2))
+            .unwrap()
+            .with_handshake_keys(
+                TrafficKeys::from_secret(suite, &secret(3)).unwrap(),
+                TrafficKeys::from_secret(suite, &secret(4)).unwrap(),
+            );
+        let [client_hs, client_app] = [&keys.client[0], &keys.client[1]];
+        let [server_hs, server_app] = [&keys.server[0], &keys.server[1]];
+
+        let sent = [
+            plaintext(ContentType::Handshake, b"client hello"),
+            plaintext(ContentType::ChangeCipherSpec, &[1]),
+            tls13(suite, client_hs, 0, ContentType::Handshake, b"finished"),
+            tls13(
+                suite,
+                client_app,
+                0,
+                ContentType::ApplicationData,
+                b"GET / ",
+            ),
+            tls13(
+                suite,
+                client_app,
+                1,
+                ContentType::ApplicationData,
+                b"HTTP/1.1\r\n\r\n",
+            ),
+        ]
+        .concat();
+        let received = [
+            plaintext(ContentType::Handshake, b"server hello"),
+            tls13(suite, server_hs, 0, ContentType::Handshake, b"certificate"),
+            tls13(suite, server_hs, 1, ContentType::Handshake, b"finished"),
+            tls13(suite, server_app, 0, ContentType::Handshake, b"ticket"),
+            tls13(
+                suite,
+                server_app,
+                1,
+                ContentType::ApplicationData,
+                b"HTTP/1.1 200 OK",
+            ),
+            tls13(suite, server_app, 2, ContentType::Alert, &[1, 0]),
+        ]
+        .concat();
+        let raw = Transcript::new(sent, received);
+
+        let decrypted = decrypt(&raw, &keys).unwrap();
+        assert_eq!(
+            decrypted.transcript().sent(),
+            b"GET / HTTP/1.1\r\n\r\n".as_slice()
+        );
+        assert_eq!(
+            decrypted.transcript().received(),
+            b"HTTP/1.1 200 OK".as_slice()
+        );
+
+        let mappings = decrypted.mappings(Direction::Sent);
+        assert_eq!(
+            mappings
+                .iter()
+                .map(|m| (m.record, m.seq))
+                .collect::<Vec<_>>(),
+            [(3, 0), (4, 1)]
+        );
+        for mapping in mappings {
+            let record = &decrypted.records().sent[mapping.record];
+            assert_eq!(mapping.ciphertext.start, record.fragment_range().start);
+            assert_eq!(mapping.ciphertext.len(), mapping.plaintext.len());
+        }
+
+        // "/ HTTP" spans both records.
+        let openings = decrypted.openings(Direction::Sent, &RangeSet::from(4..10));
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].offsets, RangeSet::from(4..6));
+        assert_eq!(openings[1].offsets, RangeSet::from(0..4));
+        assert_eq!(openings[1].plaintext, RangeSet::from(6..10));
+        assert_eq!(
+            openings[1].ciphertext,
+            RangeSet::from(mappings[1].ciphertext.start..mappings[1].ciphertext.start + 4)
+        );
+    }
+
+    #[rstest]
+    fn test_decrypt_tls12() {
+        let suite = CipherSuite::Tls12EcdheAes256GcmSha384;
+        let keys = SessionKeys::new(
+            suite,
+            TrafficKeys::new(suite, [1; 32], [2; 4]).unwrap(),
+            TrafficKeys::new(suite, [3; 32], [4; 4]).unwrap(),
+        );
+
+        let sent = [
+            plaintext(ContentType::Handshake, b"client key exchange"),
+            plaintext(ContentType::ChangeCipherSpec, &[1]),
+            tls12(
+                suite,
+                &keys.client[0],
+                0,
+                ContentType::Handshake,
+                b"finished",
+            ),
+            tls12(
+                suite,
+                &keys.client[0],
+                1,
+                ContentType::ApplicationData,
+                b"GET /",
+            ),
+        ]
+        .concat();
+        let received = [
+            plaintext(ContentType::ChangeCipherSpec, &[1]),
+            tls12(
+                suite,
+                &keys.server[0],
+                0,
+                ContentType::Handshake,
+                b"finished",
+            ),
+            tls12(
+                suite,
+                &keys.server[0],
+                1,
+                ContentType::ApplicationData,
+                b"HTTP/1.1",
+            ),
+            tls12(
+                suite,
+                &keys.server[0],
+                2,
+                ContentType::ApplicationData,
+                b" 200 OK",
+            ),
+        ]
+        .concat();
+        let raw = Transcript::new(sent, received.clone());
+
+        let decrypted = decrypt(&raw, &keys).unwrap();
+        assert_eq!(decrypted.transcript().sent(), b"GET /".as_slice());
+        assert_eq!(
+            decrypted.transcript().received(),
+            b"HTTP/1.1 200 OK".as_slice()
+        );
+
+        let mapping = &decrypted.mappings(Direction::Received)[1];
+        assert_eq!(mapping.seq, 2);
+        let record = &decrypted.records().received[mapping.record];
+        assert_eq!(
+            mapping.ciphertext.start,
+            record.fragment_range().start + EXPLICIT_NONCE_LEN
+        );
+        assert_eq!(&raw.received()[record.range()], &received[record.range()]);
+    }
+
+    #[rstest]
+    fn test_decrypt_errors() {
+        let suite = CipherSuite::Tls13Aes128GcmSha256;
+        let keys = SessionKeys::from_traffic_secrets(suite, &[1; 32], &[2; 32]).unwrap();
+
+        let data = tls13(
+            suite,
+            &keys.client[0],
+            0,
+            ContentType::ApplicationData,
+            b"GET",
+        );
+        let mut tampered = [plaintext(ContentType::Handshake, b"hello"), data.clone()].concat();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+
+        assert_eq!(
+            decrypt(&Transcript::new(tampered, []), &keys).unwrap_err(),
+            DecryptError::Authentication {
+                direction: Direction::Sent,
+                record: 1,
+                offset: 10,
+            }
+        );
+
+        // Records encrypted under the server's keys do not authenticate as the
+        // client's.
+        assert!(matches!(
+            decrypt(&Transcript::new([], data.clone()), &keys).unwrap_err(),
+            DecryptError::Authentication {
+                direction: Direction::Received,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            decrypt(&Transcript::new(data[..10].to_vec(), []), &keys).unwrap_err(),
+            DecryptError::Records(_)
+        ));
+
+        assert!(TrafficKeys::new(suite, [0; 32], [0; 12]).is_err());
+        assert!(
+            TrafficKeys::from_secret(CipherSuite::Tls12EcdheAes128GcmSha256, &[0; 32]).is_err()
+        );
+
+        let mismatched = SessionKeys::new(
+            suite,
+            TrafficKeys::new(CipherSuite::Tls12EcdheAes128GcmSha256, [0; 16], [0; 4]).unwrap(),
+            keys.server[0].clone(),
+        );
+        assert!(matches!(
+            decrypt(&Transcript::new(data, []), &mismatched).unwrap_err(),
+            DecryptError::Key(_)
+        ));
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[rstest]
+    fn test_aes_gcm() {
+        let aead = Aead::aes_gcm(&hex("feffe9928665731c6d6a8f9467308308")).unwrap();
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        );
+        let ciphertext = hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091
+             5bc94fbc3221a5db94fae95ae7121a47",
+        );
+
+        assert_eq!(aead.seal(&nonce, &aad, &plaintext), ciphertext);
+        assert_eq!(aead.open(&nonce, &aad, &ciphertext), Some(plaintext));
+        assert_eq!(aead.open(&nonce, &aad[1..], &ciphertext), None);
+    }
+
+    #[rstest]
+    fn test_chacha20_poly1305() {
+        let aead = Aead::chacha20_poly1305(&hex(
+            "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+        ))
+        .unwrap();
+        let nonce: [u8; 12] = hex("070000004041424344454647").try_into().unwrap();
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+            only one tip for the future, sunscreen would be it."
+            .to_vec();
+        let ciphertext = hex(
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc
+             3ff4def08e4b7a9de576d26586cec64b6116
+             1ae10b594f09e26a7e902ecbd0600691",
+        );
+
+        assert_eq!(aead.seal(&nonce, &aad, &plaintext), ciphertext);
+        assert_eq!(aead.open(&nonce, &aad, &ciphertext), Some(plaintext));
+
+        let mut tampered = ciphertext.clone();
+        tampered[0] ^= 1;
+        assert_eq!(aead.open(&nonce, &aad, &tampered), None);
+    }
+
+    #[rstest]
+    fn test_prf() {
+        let secret = hex("9bbe436ba940f017b17652849a71db35");
+        let seed = hex("a0ba9f936cda311827a6f796ffd5198c");
+
+        assert_eq!(
+            HashAlgorithm::Sha256.prf(&secret, b"test label", &seed, 32),
+            hex("e3f229ba727be17b8d122620557cd453c2aab21d07c3d495329b52d4e61edb5a")
+        );
+    }
+
+    #[rstest]
+    fn test_hkdf_expand_label() {
+        // Server handshake traffic keys from RFC 8448 section 3.
+        let secret = hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38");
+
+        assert_eq!(
+            HashAlgorithm::Sha256.hkdf_expand_label(&secret, b"key", b"", 16),
+            Some(hex("3fce516009c21727d0f2e4e86ee403bc"))
+        );
+        assert_eq!(
+            HashAlgorithm::Sha256.hkdf_expand_label(&secret, b"iv", b"", 12),
+            Some(hex("5d313eb2671276ee13000b30"))
+        );
+        assert_eq!(
+            HashAlgorithm::Sha384.hkdf_expand_label(&secret, b"iv", b"", 12),
+            None
+        );
+    }
+}
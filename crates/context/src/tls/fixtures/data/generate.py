@@ -0,0 +1,114 @@
+"""Generates the certificate fixtures used by the `tls::connection` tests.
+
+Requires the `cryptography` package. Run from this directory:
+
+    python3 generate.py
+
+ECDSA signatures are randomized, so the output differs between runs.
+"""
+
+import datetime
+import os
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec, padding, rsa
+from cryptography.x509.oid import ExtendedKeyUsageOID, NameOID
+
+SERVER_NAME = "test.example"
+NOT_BEFORE = datetime.datetime(2025, 1, 1, tzinfo=datetime.timezone.utc)
+NOT_AFTER = datetime.datetime(2035, 1, 1, tzinfo=datetime.timezone.utc)
+CLIENT_RANDOM = bytes([1] * 32)
+SERVER_RANDOM = bytes([2] * 32)
+
+
+def name(cn):
+    return x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, cn)])
+
+
+def cert(subject, key, issuer, issuer_key, ca, path_len=None, dns=None):
+    builder = (
+        x509.CertificateBuilder()
+        .subject_name(name(subject))
+        .issuer_name(name(issuer))
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(NOT_BEFORE)
+        .not_valid_after(NOT_AFTER)
+        .add_extension(x509.BasicConstraints(ca=ca, path_length=path_len), critical=True)
+    )
+    if ca:
+        builder = builder.add_extension(
+            x509.KeyUsage(False, False, False, False, False, True, True, False, False),
+            critical=True,
+        )
+    else:
+        builder = builder.add_extension(
+            x509.SubjectAlternativeName([x509.DNSName(dns)]), critical=False
+        ).add_extension(
+            x509.ExtendedKeyUsage([ExtendedKeyUsageOID.SERVER_AUTH]), critical=False
+        )
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def der(cert):
+    return cert.public_bytes(serialization.Encoding.DER)
+
+
+def pem(cert):
+    return cert.public_bytes(serialization.Encoding.PEM)
+
+
+def ephemeral_key():
+    return (
+        ec.generate_private_key(ec.SECP256R1())
+        .public_key()
+        .public_bytes(
+            serialization.Encoding.X962, serialization.PublicFormat.UncompressedPoint
+        )
+    )
+
+
+def kx_params(key):
+    # ECParameters with a named curve (secp256r1) followed by the point.
+    return bytes([3, 0x00, 0x17, len(key)]) + key
+
+
+def write(path, data):
+    if os.path.dirname(path):
+        os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "wb") as f:
+        f.write(data)
+
+
+root_key = ec.generate_private_key(ec.SECP256R1())
+root = cert("Test Root CA", root_key, "Test Root CA", root_key, True, 1)
+inter_key = ec.generate_private_key(ec.SECP256R1())
+inter = cert("Test Intermediate CA", inter_key, "Test Root CA", root_key, True, 0)
+
+write("roots.pem", pem(root))
+write("ca.der", der(root))
+write("inter.der", der(inter))
+
+for kind, key in [
+    ("ecdsa", ec.generate_private_key(ec.SECP256R1())),
+    ("rsa", rsa.generate_private_key(public_exponent=65537, key_size=2048)),
+]:
+    ee = cert(SERVER_NAME, key, "Test Intermediate CA", inter_key, False, dns=SERVER_NAME)
+    eph = ephemeral_key()
+    message = CLIENT_RANDOM + SERVER_RANDOM + kx_params(eph)
+    if kind == "ecdsa":
+        sig = key.sign(message, ec.ECDSA(hashes.SHA256()))
+    else:
+        sig = key.sign(message, padding.PKCS1v15(), hashes.SHA256())
+
+    write(f"{kind}/ee.der", der(ee))
+    write(f"{kind}/pubkey", eph)
+    write(f"{kind}/signature", sig)
+
+# A certificate for the same name from an untrusted issuer.
+unknown_key = ec.generate_private_key(ec.SECP256R1())
+unknown_ca_key = ec.generate_private_key(ec.SECP256R1())
+unknown = cert(SERVER_NAME, unknown_key, "Unknown CA", unknown_ca_key, False, dns=SERVER_NAME)
+write("unknown/ee.der", der(unknown))
+write("unknown/pubkey", ephemeral_key())
@@ -0,0 +1,337 @@
+//! Import of captured TLS connections.
+//!
+//! [`import`] decrypts a TCP connection of a packet [`Capture`] with the
+//! secrets of a [`KeyLog`], producing a transcript which can be parsed with
+//! [`HttpTranscript::parse`](crate::http::HttpTranscript::parse). This is
+//! intended for debugging and creating fixtures from real traffic:
+//!
+//! ```no_run
+//! use http_transcript_context::{
+//!     http::HttpTranscript,
+//!     tls::{import, Capture, KeyLog},
+//! };
+//!
+//! let capture = Capture::parse(&std::fs::read("capture.pcapng").unwrap()).unwrap();
+//! let keylog = KeyLog::parse(&std::fs::read_to_string("keylog.txt").unwrap()).unwrap();
+//!
+//! let connection = capture
+//!     .connection_to("93.184.215.14:443".parse().unwrap())
+//!     .unwrap();
+//! let decrypted = import(connection, &keylog).unwrap();
+//! let http = HttpTranscript::parse(decrypted.transcript()).unwrap();
+//! ```
+
+use crate::{
+    tls::{
+        capture::{CaptureError, TcpConnection},
+        decrypt::{
+            decrypt, CipherSuite, DecryptError, DecryptedTranscript, KeyError, SessionKeys,
+            TrafficKeys,
+        },
+        keylog::{
+            KeyLog, CLIENT_HANDSHAKE_TRAFFIC_SECRET, CLIENT_RANDOM, CLIENT_TRAFFIC_SECRET_0,
+            SERVER_HANDSHAKE_TRAFFIC_SECRET, SERVER_TRAFFIC_SECRET_0,
+        },
+        record::{ContentType, Record, TlsTranscript, TlsTranscriptError},
+    },
+    transcript::Direction,
+};
+
+/// Error for [`import`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ImportError {
+    /// The streams of the connection could not be reassembled.
+    #[error(transparent)]
+    Capture(#[from] CaptureError),
+    /// The records could not be parsed.
+    #[error(transparent)]
+    Records(#[from] TlsTranscriptError),
+    /// The ClientHello or ServerHello could not be parsed.
+    #[error("invalid {direction} hello: {reason}")]
+    InvalidHello {
+        /// Direction of the hello.
+        direction: Direction,
+        /// The reason the hello is invalid.
+        reason: &'static str,
+    },
+    /// The negotiated cipher suite is not supported.
+    #[error("unsupported cipher suite {0:#06x}")]
+    UnsupportedCipherSuite(u16),
+    /// The key log has no secret for the connection.
+    #[error("key log is missing {0} for the connection")]
+    MissingSecret(&'static str),
+    /// The secrets are invalid for the cipher suite.
+    #[error(transparent)]
+    Key(#[from] KeyError),
+    /// The records could not be decrypted.
+    #[error(transparent)]
+    Decrypt(#[from] DecryptError),
+}
+
+/// Decrypts a captured TLS connection.
+///
+/// # Arguments
+///
+/// * `connection` - The connection.
+/// * `keylog` - A key log containing the secrets of the connection.
+pub fn import(
+    connection: &TcpConnection,
+    keylog: &KeyLog,
+) -> Result<DecryptedTranscript, ImportError> {
+    let raw = connection.transcript()?;
+    let records = TlsTranscript::parse(&raw)?;
+
+    let client_hello = hello(&records.sent, raw.sent(), Direction::Sent, 1)?;
+    let server_hello = hello(&records.received, raw.received(), Direction::Received, 2)?;
+
+    let client_random = random(&client_hello, Direction::Sent)?;
+    let server_random = random(&server_hello, Direction::Received)?;
+
+    // The cipher suite follows the version, random and session id.
+    let session_id_len = *server_hello.get(34).ok_or(ImportError::InvalidHello {
+        direction: Direction::Received,
+        reason: "missing session id",
+    })? as usize;
+    let cipher_suite = server_hello
+        .get(35 + session_id_len..37 + session_id_len)
+        .ok_or(ImportError::InvalidHello {
+            direction: Direction::Received,
+            reason: "missing cipher suite",
+        })?;
+    let cipher_suite = u16::from_be_bytes([cipher_suite[0], cipher_suite[1]]);
+    let cipher_suite =
+        CipherSuite::try_from(cipher_suite).map_err(ImportError::UnsupportedCipherSuite)?;
+
+    let secret = |label| {
+        keylog
+            .secret(label, &client_random)
+            .ok_or(ImportError::MissingSecret(label))
+    };
+
+    let keys = if cipher_suite.is_tls13() {
+        let keys = SessionKeys::from_traffic_secrets(
+            cipher_suite,
+            secret(CLIENT_TRAFFIC_SECRET_0)?,
+            secret(SERVER_TRAFFIC_SECRET_0)?,
+        )?;
+
+        match (
+            secret(CLIENT_HANDSHAKE_TRAFFIC_SECRET),
+            secret(SERVER_HANDSHAKE_TRAFFIC_SECRET),
+        ) {
+            (Ok(client), Ok(server)) => keys.with_handshake_keys(
+                TrafficKeys::from_secret(cipher_suite, client)?,
+                TrafficKeys::from_secret(cipher_suite, server)?,
+            ),
+            _ => keys,
+        }
+    } else {
+        SessionKeys::from_master_secret(
+            cipher_suite,
+            secret(CLIENT_RANDOM)?,
+            &client_random,
+            &server_random,
+        )?
+    };
+
+    Ok(decrypt(&raw, &keys)?)
+}
+
+/// Returns the body of the first handshake message, which is expected to be
+/// of the given type.
+fn hello(
+    records: &[Record],
+    src: &[u8],
+    direction: Direction,
+    typ: u8,
+) -> Result<Vec<u8>, ImportError> {
+    let error = |reason| ImportError::InvalidHello { direction, reason };
+
+    // Handshake messages may be fragmented over records.
+    let messages: Vec<u8> = records
+        .iter()
+        .take_while(|record| record.content_type == ContentType::Handshake)
+        .filter_map(|record| record.fragment(src))
+        .flatten()
+        .copied()
+        .collect();
+
+    let (header, body) = messages
+        .split_at_checked(4)
+        .ok_or_else(|| error("missing handshake message"))?;
+    if header[0] != typ {
+        return Err(error("unexpected handshake message"));
+    }
+    let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+
+    body.get(..len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| error("truncated handshake message"))
+}
+
+/// Returns the random of a hello, which follows its legacy version.
+fn random(hello: &[u8], direction: Direction) -> Result<[u8; 32], ImportError> {
+    hello
+        .get(2..34)
+        .and_then(|random| random.try_into().ok())
+        .ok_or(ImportError::InvalidHello {
+            direction,
+            reason: "missing random",
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{
+        fixtures::{
+            capture::{frame, pcapng, segments},
+            tls::{client_hello, plaintext, server_hello, tls12, tls13},
+        },
+        http::HttpTranscript,
+        tls::capture::Capture,
+    };
+
+    const CLIENT: [u8; 32] = [1; 32];
+    const SERVER: [u8; 32] = [2; 32];
+
+    fn capture(sent: &[&[u8]], received: &[&[u8]]) -> Capture {
+        let frames: Vec<_> = segments(sent, received).iter().map(frame).collect();
+        Capture::parse(&pcapng(&frames)).unwrap()
+    }
+
+    fn keylog(lines: &[(&str, &[u8])]) -> KeyLog {
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        lines
+            .iter()
+            .map(|(label, secret)| format!("{label} {} {}\n", hex(&CLIENT), hex(secret)))
+            .collect::<String>()
+            .parse()
+            .unwrap()
+    }
+
+    #[rstest]
+    fn test_import_tls13() {
+        let suite = CipherSuite::Tls13Aes128GcmSha256;
+        let secrets: [(&str, &[u8]); 4] = [
+            (CLIENT_HANDSHAKE_TRAFFIC_SECRET, &[3; 32]),
+            (SERVER_HANDSHAKE_TRAFFIC_SECRET, &[4; 32]),
+            (CLIENT_TRAFFIC_SECRET_0, &[5; 32]),
+            (SERVER_TRAFFIC_SECRET_0, &[6; 32]),
+        ];
+        let keys = secrets.map(|(_, secret)| TrafficKeys::from_secret(suite, secret).unwrap());
+
+        let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let sent = [
+            client_hello(CLIENT),
+            tls13(suite, &keys[0], 0, ContentType::Handshake, b"finished"),
+            tls13(suite, &keys[2], 0, ContentType::ApplicationData, request),
+        ];
+        let received = [
+            server_hello(SERVER, 0x1301),
+            plaintext(ContentType::ChangeCipherSpec, &[1]),
+            tls13(suite, &keys[1], 0, ContentType::Handshake, b"finished"),
+            tls13(suite, &keys[3], 0, ContentType::ApplicationData, response),
+        ];
+        let capture = capture(
+            &sent.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            &received.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        );
+
+        let decrypted = import(&capture.connections()[0], &keylog(&secrets)).unwrap();
+        let http = HttpTranscript::parse(decrypted.transcript()).unwrap();
+        assert_eq!(http.requests.len(), 1);
+        assert_eq!(http.responses.len(), 1);
+
+        // Without the handshake secrets, the encrypted handshake records
+        // cannot be decrypted.
+        assert!(matches!(
+            import(&capture.connections()[0], &keylog(&secrets[2..])).unwrap_err(),
+            ImportError::Decrypt(DecryptError::Authentication { .. })
+        ));
+        assert_eq!(
+            import(&capture.connections()[0], &keylog(&secrets[..3])).unwrap_err(),
+            ImportError::MissingSecret(SERVER_TRAFFIC_SECRET_0)
+        );
+    }
+
+    #[rstest]
+    fn test_import_tls12() {
+        let suite = CipherSuite::Tls12EcdheAes128GcmSha256;
+        let master_secret = [7u8; 48];
+        let keys =
+            SessionKeys::from_master_secret(suite, &master_secret, &CLIENT, &SERVER).unwrap();
+        let (client, server) = (
+            &keys.keys(Direction::Sent)[0],
+            &keys.keys(Direction::Received)[0],
+        );
+
+        let ccs = plaintext(ContentType::ChangeCipherSpec, &[1]);
+        let sent = [
+            client_hello(CLIENT),
+            ccs.clone(),
+            tls12(suite, client, 0, ContentType::Handshake, b"finished"),
+            tls12(
+                suite,
+                client,
+                1,
+                ContentType::ApplicationData,
+                b"GET / HTTP/1.1\r\n\r\n",
+            ),
+        ];
+        let received = [
+            server_hello(SERVER, 0xc02f),
+            ccs,
+            tls12(suite, server, 0, ContentType::Handshake, b"finished"),
+            tls12(
+                suite,
+                server,
+                1,
+                ContentType::ApplicationData,
+                b"HTTP/1.1 204 No Content\r\n\r\n",
+            ),
+        ];
+        let capture = capture(
+            &sent.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            &received.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        );
+
+        let decrypted = import(
+            &capture.connections()[0],
+            &keylog(&[(CLIENT_RANDOM, &master_secret)]),
+        )
+        .unwrap();
+        assert_eq!(
+            decrypted.transcript().sent(),
+            b"GET / HTTP/1.1\r\n\r\n".as_slice()
+        );
+        assert_eq!(
+            decrypted.transcript().received(),
+            b"HTTP/1.1 204 No Content\r\n\r\n".as_slice()
+        );
+    }
+
+    #[rstest]
+    fn test_import_errors() {
+        let unsupported = capture(&[&client_hello(CLIENT)], &[&server_hello(SERVER, 0x009c)]);
+        assert_eq!(
+            import(&unsupported.connections()[0], &KeyLog::default()).unwrap_err(),
+            ImportError::UnsupportedCipherSuite(0x009c)
+        );
+
+        let alert = capture(
+            &[&client_hello(CLIENT)],
+            &[&plaintext(ContentType::Alert, &[2, 40])],
+        );
+        assert_eq!(
+            import(&alert.connections()[0], &KeyLog::default()).unwrap_err(),
+            ImportError::InvalidHello {
+                direction: Direction::Received,
+                reason: "missing handshake message"
+            }
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! NSS key log files.
+//!
+//! Parses the key log format written by browsers, curl and other clients
+//! when `SSLKEYLOGFILE` is set. Each line associates a secret with the client
+//! random of the connection it belongs to:
+//!
+//! ```text
+//! # TLS 1.2
+//! CLIENT_RANDOM <client random> <master secret>
+//! # TLS 1.3
+//! CLIENT_TRAFFIC_SECRET_0 <client random> <secret>
+//! ```
+
+use std::{collections::HashMap, str::FromStr};
+
+/// Label of a TLS 1.2 master secret.
+pub const CLIENT_RANDOM: &str = "CLIENT_RANDOM";
+/// Label of a TLS 1.3 client handshake traffic secret.
+pub const CLIENT_HANDSHAKE_TRAFFIC_SECRET: &str = "CLIENT_HANDSHAKE_TRAFFIC_SECRET";
+/// Label of a TLS 1.3 server handshake traffic secret.
+pub const SERVER_HANDSHAKE_TRAFFIC_SECRET: &str = "SERVER_HANDSHAKE_TRAFFIC_SECRET";
+/// Label of the first TLS 1.3 client application traffic secret.
+pub const CLIENT_TRAFFIC_SECRET_0: &str = "CLIENT_TRAFFIC_SECRET_0";
+/// Label of the first TLS 1.3 server application traffic secret.
+pub const SERVER_TRAFFIC_SECRET_0: &str = "SERVER_TRAFFIC_SECRET_0";
+
+/// Error for [`KeyLog::parse`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid key log line {line}: {reason}")]
+pub struct KeyLogError {
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The reason the line is invalid.
+    pub reason: &'static str,
+}
+
+/// The secrets of a key log file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyLog {
+    secrets: HashMap<(String, [u8; 32]), Vec<u8>>,
+}
+
+impl KeyLog {
+    /// Parses a key log file.
+    ///
+    /// Comments and blank lines are ignored. If a secret is logged more than
+    /// once, the last one is kept.
+    pub fn parse(src: &str) -> Result<Self, KeyLogError> {
+        let mut secrets = HashMap::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |reason| KeyLogError {
+                line: i + 1,
+                reason,
+            };
+
+            let mut fields = line.split_ascii_whitespace();
+            let (Some(label), Some(client_random), Some(secret), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(error("expected a label, client random and secret"));
+            };
+
+            let client_random = hex(client_random)
+                .and_then(|random| random.try_into().ok())
+                .ok_or_else(|| error("client random is not 32 bytes of hex"))?;
+            let secret = hex(secret).ok_or_else(|| error("secret is not hex"))?;
+
+            secrets.insert((label.to_string(), client_random), secret);
+        }
+
+        Ok(Self { secrets })
+    }
+
+    /// Returns the secret with the given label for a connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the secret, e.g. [`CLIENT_RANDOM`].
+    /// * `client_random` - The client random of the connection.
+    pub fn secret(&self, label: &str, client_random: &[u8; 32]) -> Option<&[u8]> {
+        self.secrets
+            .get(&(label.to_string(), *client_random))
+            .map(Vec::as_slice)
+    }
+
+    /// Returns the number of secrets.
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+
+    /// Returns whether there are no secrets.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+}
+
+impl FromStr for KeyLog {
+    type Err = KeyLogError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Decodes a hex string.
+fn hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    fn test_parse() {
+        let random = "01".repeat(32);
+        let keylog: KeyLog = format!(
+            "# comment\n\n\
+             CLIENT_RANDOM {random} {}\n\
+             CLIENT_TRAFFIC_SECRET_0 {random} AABB\r\n\
+             CLIENT_TRAFFIC_SECRET_0 {random} ccdd\n",
+            "ff".repeat(48)
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(keylog.len(), 2);
+        assert_eq!(
+            keylog.secret(CLIENT_RANDOM, &[1; 32]),
+            Some([0xff; 48].as_slice())
+        );
+        assert_eq!(
+            keylog.secret(CLIENT_TRAFFIC_SECRET_0, &[1; 32]),
+            Some([0xcc, 0xdd].as_slice())
+        );
+        assert_eq!(keylog.secret(SERVER_TRAFFIC_SECRET_0, &[1; 32]), None);
+        assert_eq!(keylog.secret(CLIENT_RANDOM, &[2; 32]), None);
+    }
+
+    #[rstest]
+    #[case::missing_secret("CLIENT_RANDOM 0101", "expected a label, client random and secret")]
+    #[case::short_random("CLIENT_RANDOM 0101 ff", "client random is not 32 bytes of hex")]
+    #[case::invalid_secret(
+        "CLIENT_RANDOM 0101010101010101010101010101010101010101010101010101010101010101 xyz",
+        "secret is not hex"
+    )]
+    fn test_parse_invalid(#[case] line: &str, #[case] reason: &'static str) {
+        assert_eq!(
+            KeyLog::parse(&format!("# comment\n{line}")).unwrap_err(),
+            KeyLogError { line: 2, reason }
+        );
+    }
+}
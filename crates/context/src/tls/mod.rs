@@ -1 +1,28 @@
-pub mod connection;
\ No newline at end of file
+//! Tooling for working with TLS data.
+
+pub mod capture;
+pub mod connection;
+#[cfg(feature = "decrypt")]
+pub mod decrypt;
+#[cfg(feature = "decrypt")]
+pub mod import;
+pub mod keylog;
+pub mod record;
+
+pub use capture::{Capture, CaptureError, TcpConnection};
+#[cfg(feature = "webpki")]
+pub use connection::HandshakeVerificationError;
+pub use connection::{
+    CertBinding, CertBindingV1_2, ConnectionInfo, DnsName, HandshakeData, InvalidDnsNameError,
+    KeyType, ServerEphemKey, ServerName, ServerSignature, SignatureAlgorithm, TlsVersion,
+    VerifyData,
+};
+#[cfg(feature = "decrypt")]
+pub use decrypt::{
+    decrypt, CipherSuite, DecryptError, DecryptedTranscript, KeyError, RecordMapping,
+    RecordOpening, SessionKeys, TrafficKeys,
+};
+#[cfg(feature = "decrypt")]
+pub use import::{import, ImportError};
+pub use keylog::{KeyLog, KeyLogError};
+pub use record::{ContentType, Record, RecordError, Records, TlsTranscript, TlsTranscriptError};
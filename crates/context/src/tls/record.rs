@@ -0,0 +1,480 @@
+//! TLS record layer.
+//!
+//! Parses the raw byte streams of a TLS connection into records, without
+//! decrypting them. Each [`Record`] locates its header fields and fragment in
+//! the stream it was parsed from, so records can be committed to and revealed
+//! like any other part of a transcript.
+
+use std::{fmt, ops::Range};
+
+use rangeset::{RangeSet, ToRangeSet};
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::{Direction, Transcript};
+
+/// Length of a record header.
+pub const HEADER_LEN: usize = 5;
+
+/// Maximum length of a record fragment, i.e. `2^14` bytes of plaintext plus
+/// the expansion permitted for TLS 1.2 ciphertext.
+pub const MAX_FRAGMENT_LEN: usize = (1 << 14) + 2048;
+
+/// Type of the content of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+    /// ChangeCipherSpec.
+    ChangeCipherSpec = 20,
+    /// Alert.
+    Alert = 21,
+    /// Handshake.
+    Handshake = 22,
+    /// ApplicationData.
+    ApplicationData = 23,
+}
+
+impl TryFrom<u8> for ContentType {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            20 => ContentType::ChangeCipherSpec,
+            21 => ContentType::Alert,
+            22 => ContentType::Handshake,
+            23 => ContentType::ApplicationData,
+            _ => return Err(value),
+        })
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentType::ChangeCipherSpec => write!(f, "change_cipher_spec"),
+            ContentType::Alert => write!(f, "alert"),
+            ContentType::Handshake => write!(f, "handshake"),
+            ContentType::ApplicationData => write!(f, "application_data"),
+        }
+    }
+}
+
+/// A TLS record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Type of the content of the record.
+    pub content_type: ContentType,
+    /// The legacy protocol version, e.g. `0x0303`.
+    pub legacy_version: u16,
+    /// Offset of the record in the stream.
+    pub offset: usize,
+    /// Length of the fragment.
+    pub length: usize,
+}
+
+impl Record {
+    /// Returns the range of the record, including its header.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.fragment_range().end
+    }
+
+    /// Returns the range of the content type.
+    pub fn content_type_range(&self) -> Range<usize> {
+        self.offset..self.offset + 1
+    }
+
+    /// Returns the range of the legacy protocol version.
+    pub fn version_range(&self) -> Range<usize> {
+        self.offset + 1..self.offset + 3
+    }
+
+    /// Returns the range of the fragment length.
+    pub fn length_range(&self) -> Range<usize> {
+        self.offset + 3..self.offset + HEADER_LEN
+    }
+
+    /// Returns the range of the fragment.
+    pub fn fragment_range(&self) -> Range<usize> {
+        self.offset + HEADER_LEN..self.offset + HEADER_LEN + self.length
+    }
+
+    /// Returns the fragment of the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - The stream the record was parsed from.
+    pub fn fragment<'a>(&self, src: &'a [u8]) -> Option<&'a [u8]> {
+        src.get(self.fragment_range())
+    }
+}
+
+impl ToRangeSet<usize> for Record {
+    fn to_range_set(&self) -> RangeSet<usize> {
+        RangeSet::from(self.range())
+    }
+}
+
+/// Error for [`Records`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RecordError {
+    /// The stream ends within a record.
+    #[error("record at {offset} is truncated: expected {expected} bytes, found {actual}")]
+    Truncated {
+        /// Offset of the record.
+        offset: usize,
+        /// Length of the record, or of its header if the header is truncated.
+        expected: usize,
+        /// Number of bytes remaining in the stream.
+        actual: usize,
+    },
+    /// The content type is unknown.
+    #[error("record at {offset} has an invalid content type: {content_type}")]
+    InvalidContentType {
+        /// Offset of the record.
+        offset: usize,
+        /// The content type.
+        content_type: u8,
+    },
+    /// The legacy protocol version is not a TLS version.
+    #[error("record at {offset} has an invalid version: {version:#06x}")]
+    InvalidVersion {
+        /// Offset of the record.
+        offset: usize,
+        /// The legacy protocol version.
+        version: u16,
+    },
+    /// The fragment is longer than permitted.
+    #[error("record at {offset} is too long: {length} > {MAX_FRAGMENT_LEN}")]
+    Overflow {
+        /// Offset of the record.
+        offset: usize,
+        /// Length of the fragment.
+        length: usize,
+    },
+    /// The fragment is empty, which is only permitted for application data.
+    #[error("{content_type} record at {offset} is empty")]
+    Empty {
+        /// Offset of the record.
+        offset: usize,
+        /// Type of the content of the record.
+        content_type: ContentType,
+    },
+}
+
+impl RecordError {
+    /// Returns the offset of the record in the stream.
+    pub fn offset(&self) -> usize {
+        match self {
+            RecordError::Truncated { offset, .. }
+            | RecordError::InvalidContentType { offset, .. }
+            | RecordError::InvalidVersion { offset, .. }
+            | RecordError::Overflow { offset, .. }
+            | RecordError::Empty { offset, .. } => *offset,
+        }
+    }
+}
+
+/// An iterator over the records of a stream.
+///
+/// The iterator ends after the first error.
+#[derive(Debug, Clone)]
+pub struct Records<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Records<'a> {
+    /// Creates an iterator over the records of a stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `src` - The stream, starting at a record boundary.
+    pub fn new(src: &'a [u8]) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn parse(&self) -> Result<Record, RecordError> {
+        let offset = self.pos;
+        let remaining = &self.src[offset..];
+
+        let Some(header) = remaining.get(..HEADER_LEN) else {
+            return Err(RecordError::Truncated {
+                offset,
+                expected: HEADER_LEN,
+                actual: remaining.len(),
+            });
+        };
+
+        let content_type = ContentType::try_from(header[0]).map_err(|content_type| {
+            RecordError::InvalidContentType {
+                offset,
+                content_type,
+            }
+        })?;
+
+        let legacy_version = u16::from_be_bytes([header[1], header[2]]);
+        if !(0x0300..=0x0304).contains(&legacy_version) {
+            return Err(RecordError::InvalidVersion {
+                offset,
+                version: legacy_version,
+            });
+        }
+
+        let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if length > MAX_FRAGMENT_LEN {
+            return Err(RecordError::Overflow { offset, length });
+        }
+        if length == 0 && content_type != ContentType::ApplicationData {
+            return Err(RecordError::Empty {
+                offset,
+                content_type,
+            });
+        }
+        if remaining.len() < HEADER_LEN + length {
+            return Err(RecordError::Truncated {
+                offset,
+                expected: HEADER_LEN + length,
+                actual: remaining.len(),
+            });
+        }
+
+        Ok(Record {
+            content_type,
+            legacy_version,
+            offset,
+            length,
+        })
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<Record, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.src.len() {
+            return None;
+        }
+
+        match self.parse() {
+            Ok(record) => {
+                self.pos = record.range().end;
+                Some(Ok(record))
+            }
+            Err(e) => {
+                self.pos = self.src.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Error for [`TlsTranscript::parse`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("failed to parse {direction} records: {source}")]
+pub struct TlsTranscriptError {
+    /// Direction of the stream.
+    pub direction: Direction,
+    /// The error.
+    #[source]
+    pub source: RecordError,
+}
+
+/// The records of a TLS connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsTranscript {
+    /// The records sent by the client.
+    pub sent: Vec<Record>,
+    /// The records received from the server.
+    pub received: Vec<Record>,
+}
+
+impl TlsTranscript {
+    /// Parses the records of a transcript of the raw byte streams of a TLS
+    /// connection.
+    pub fn parse(transcript: &Transcript) -> Result<Self, TlsTranscriptError> {
+        let parse = |direction, src| {
+            Records::new(src)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|source| TlsTranscriptError { direction, source })
+        };
+
+        Ok(Self {
+            sent: parse(Direction::Sent, transcript.sent())?,
+            received: parse(Direction::Received, transcript.received())?,
+        })
+    }
+
+    /// Returns the records in the given direction.
+    pub fn records(&self, direction: Direction) -> &[Record] {
+        match direction {
+            Direction::Sent => &self.sent,
+            Direction::Received => &self.received,
+        }
+    }
+
+    /// Returns the records of the given content type in the given direction.
+    pub fn records_of(
+        &self,
+        direction: Direction,
+        content_type: ContentType,
+    ) -> impl Iterator<Item = &Record> {
+        self.records(direction)
+            .iter()
+            .filter(move |record| record.content_type == content_type)
+    }
+
+    /// Returns the handshake records in the given direction.
+    pub fn handshake(&self, direction: Direction) -> impl Iterator<Item = &Record> {
+        self.records_of(direction, ContentType::Handshake)
+    }
+
+    /// Returns the alert records in the given direction.
+    pub fn alert(&self, direction: Direction) -> impl Iterator<Item = &Record> {
+        self.records_of(direction, ContentType::Alert)
+    }
+
+    /// Returns the change cipher spec records in the given direction.
+    pub fn change_cipher_spec(&self, direction: Direction) -> impl Iterator<Item = &Record> {
+        self.records_of(direction, ContentType::ChangeCipherSpec)
+    }
+
+    /// Returns the application data records in the given direction.
+    pub fn application_data(&self, direction: Direction) -> impl Iterator<Item = &Record> {
+        self.records_of(direction, ContentType::ApplicationData)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn record(content_type: u8, version: u16, fragment: &[u8]) -> Vec<u8> {
+        let mut record = vec![content_type];
+        record.extend_from_slice(&version.to_be_bytes());
+        record.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+        record.extend_from_slice(fragment);
+        record
+    }
+
+    fn stream() -> Vec<u8> {
+        [
+            record(22, 0x0301, b"client hello"),
+            record(20, 0x0303, &[1]),
+            record(22, 0x0303, b"finished"),
+            record(23, 0x0303, b"GET / HTTP/1.1"),
+            record(23, 0x0303, b""),
+            record(21, 0x0303, &[1, 0]),
+        ]
+        .concat()
+    }
+
+    #[rstest]
+    fn test_parse_records() {
+        let src = stream();
+        let records = Records::new(&src).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| record.content_type)
+                .collect::<Vec<_>>(),
+            [
+                ContentType::Handshake,
+                ContentType::ChangeCipherSpec,
+                ContentType::Handshake,
+                ContentType::ApplicationData,
+                ContentType::ApplicationData,
+                ContentType::Alert,
+            ]
+        );
+        assert_eq!(records.last().unwrap().range().end, src.len());
+
+        let data = &records[3];
+        assert_eq!(data.offset, 17 + 6 + 13);
+        assert_eq!(data.legacy_version, 0x0303);
+        assert_eq!(data.fragment(&src), Some(b"GET / HTTP/1.1".as_slice()));
+        assert_eq!(src[data.content_type_range()], [23]);
+        assert_eq!(src[data.version_range()], [3, 3]);
+        assert_eq!(src[data.length_range()], [0, 14]);
+        assert_eq!(data.to_range_set(), RangeSet::from(data.range()));
+    }
+
+    #[rstest]
+    #[case::truncated_header(
+        &[22, 3, 3],
+        RecordError::Truncated { offset: 0, expected: 5, actual: 3 },
+    )]
+    #[case::truncated_fragment(
+        &[22, 3, 3, 0, 4, 1, 2],
+        RecordError::Truncated { offset: 0, expected: 9, actual: 7 },
+    )]
+    #[case::invalid_content_type(
+        &[24, 3, 3, 0, 1, 0],
+        RecordError::InvalidContentType { offset: 0, content_type: 24 },
+    )]
+    #[case::invalid_version(
+        &[22, 2, 0, 0, 1, 0],
+        RecordError::InvalidVersion { offset: 0, version: 0x0200 },
+    )]
+    #[case::overflow(
+        &[23, 3, 3, 0xff, 0xff],
+        RecordError::Overflow { offset: 0, length: 0xffff },
+    )]
+    #[case::empty(
+        &[22, 3, 3, 0, 0],
+        RecordError::Empty { offset: 0, content_type: ContentType::Handshake },
+    )]
+    fn test_parse_malformed(#[case] src: &[u8], #[case] expected: RecordError) {
+        assert_eq!(Records::new(src).next(), Some(Err(expected)));
+    }
+
+    #[rstest]
+    fn test_parse_error_position() {
+        let mut src = stream();
+        let len = src.len();
+        src.extend_from_slice(&[23, 3, 3, 0, 10, 1]);
+
+        let mut records = Records::new(&src);
+        let err = records.find_map(Result::err).unwrap();
+        assert_eq!(err.offset(), len);
+        assert_eq!(
+            err,
+            RecordError::Truncated {
+                offset: len,
+                expected: 15,
+                actual: 6
+            }
+        );
+        assert_eq!(records.next(), None);
+    }
+
+    #[rstest]
+    fn test_tls_transcript() {
+        let transcript = Transcript::new(
+            stream(),
+            [
+                record(22, 0x0303, b"server hello"),
+                record(23, 0x0303, b"HTTP/1.1 200 OK"),
+            ]
+            .concat(),
+        );
+        let tls = TlsTranscript::parse(&transcript).unwrap();
+
+        assert_eq!(tls.handshake(Direction::Sent).count(), 2);
+        assert_eq!(tls.change_cipher_spec(Direction::Sent).count(), 1);
+        assert_eq!(tls.alert(Direction::Sent).count(), 1);
+        assert_eq!(tls.application_data(Direction::Sent).count(), 2);
+        assert_eq!(tls.application_data(Direction::Received).count(), 1);
+
+        let response = tls.application_data(Direction::Received).next().unwrap();
+        assert_eq!(
+            response.fragment(transcript.received()),
+            Some(b"HTTP/1.1 200 OK".as_slice())
+        );
+
+        let err = TlsTranscript::parse(&Transcript::new(stream(), [21, 3, 3])).unwrap_err();
+        assert_eq!(err.direction, Direction::Received);
+        assert_eq!(err.source.offset(), 0);
+    }
+}
@@ -0,0 +1,558 @@
+//! Analysis of commitment sets.
+//!
+//! The default committers deliberately commit to overlapping ranges, e.g. an
+//! entire request as well as the request without its data and each of its
+//! headers. A [`CommitmentSet`] takes the ranges recorded by a builder and
+//! reports how they overlap, which of them are needed to support a family of
+//! [`Disclosure`]s, and which can be dropped entirely.
+
+use rangeset::{Difference, Disjoint, Intersection, RangeSet, Subset, ToRangeSet, Union};
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::{
+    Direction, PartialTranscript, TranscriptCommitment, TranscriptCommitmentBuilder,
+    TranscriptCommitmentBuilderError,
+};
+
+/// A set of committed ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentSet {
+    commits: Vec<(Direction, RangeSet<usize>)>,
+}
+
+impl CommitmentSet {
+    /// Creates a new commitment set.
+    ///
+    /// Empty and duplicate ranges are discarded.
+    pub fn new(commits: impl IntoIterator<Item = (Direction, RangeSet<usize>)>) -> Self {
+        let mut set = Self::default();
+        for (direction, idx) in commits {
+            if !idx.is_empty()
+                && !set
+                    .commits
+                    .iter()
+                    .any(|(d, i)| *d == direction && *i == idx)
+            {
+                set.commits.push((direction, idx));
+            }
+        }
+        set
+    }
+
+    /// Returns the committed ranges.
+    pub fn commits(&self) -> &[(Direction, RangeSet<usize>)] {
+        &self.commits
+    }
+
+    /// Returns the number of commitments.
+    pub fn len(&self) -> usize {
+        self.commits.len()
+    }
+
+    /// Returns `true` if there are no commitments.
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty()
+    }
+
+    /// Returns the total number of committed bytes, counting overlapping
+    /// bytes once per commitment.
+    pub fn total_bytes(&self) -> usize {
+        self.commits.iter().map(|(_, idx)| idx.len()).sum()
+    }
+
+    /// Returns the union of the committed ranges in the given direction.
+    pub fn coverage(&self, direction: Direction) -> RangeSet<usize> {
+        self.commits
+            .iter()
+            .filter(|(d, _)| *d == direction)
+            .fold(RangeSet::default(), |acc, (_, idx)| acc.union(idx))
+    }
+
+    /// Returns every pair of commitments which share at least one byte.
+    pub fn overlaps(&self) -> Vec<Overlap> {
+        let mut overlaps = Vec::new();
+
+        for (a, (direction, idx_a)) in self.commits.iter().enumerate() {
+            for (b, (_, idx_b)) in self
+                .commits
+                .iter()
+                .enumerate()
+                .skip(a + 1)
+                .filter(|(_, (d, _))| d == direction)
+            {
+                if idx_a.is_disjoint(idx_b) {
+                    continue;
+                }
+
+                let kind = match (idx_a.is_subset(idx_b), idx_b.is_subset(idx_a)) {
+                    (true, true) => OverlapKind::Equal,
+                    (true, false) => OverlapKind::Contained,
+                    (false, true) => OverlapKind::Contains,
+                    (false, false) => OverlapKind::Partial,
+                };
+
+                overlaps.push(Overlap {
+                    a,
+                    b,
+                    direction: *direction,
+                    idx: idx_a.intersection(idx_b),
+                    kind,
+                });
+            }
+        }
+
+        overlaps
+    }
+
+    /// Returns the indices of commitments which are exactly the union of other
+    /// commitments they contain.
+    ///
+    /// Redundant commitments are found from the largest to the smallest, so
+    /// removing all of them leaves every original commitment expressible as a
+    /// union of the remaining ones.
+    pub fn redundant(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.commits.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.commits[i].1.len()));
+
+        let mut removed = vec![false; self.commits.len()];
+        for i in order {
+            let (direction, idx) = &self.commits[i];
+            let covered = self
+                .commits
+                .iter()
+                .enumerate()
+                .filter(|(j, (d, other))| {
+                    *j != i && !removed[*j] && d == direction && other.is_subset(idx)
+                })
+                .fold(RangeSet::default(), |acc, (_, (_, other))| acc.union(other));
+
+            if covered == *idx {
+                removed[i] = true;
+            }
+        }
+
+        removed
+            .iter()
+            .enumerate()
+            .filter_map(|(i, removed)| removed.then_some(i))
+            .collect()
+    }
+
+    /// Returns a copy of the set without redundant commitments.
+    pub fn without_redundant(&self) -> Self {
+        let redundant = self.redundant();
+
+        Self {
+            commits: self
+                .commits
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !redundant.contains(i))
+                .map(|(_, commit)| commit.clone())
+                .collect(),
+        }
+    }
+
+    /// Returns the indices of the commitments which must be opened to reveal
+    /// exactly the given disclosure.
+    ///
+    /// Commitments in `preferred` are used before any others.
+    fn cover(
+        &self,
+        disclosure: &Disclosure,
+        preferred: &[bool],
+    ) -> Result<Vec<usize>, UnsupportedDisclosure> {
+        let mut selected = Vec::new();
+
+        for direction in [Direction::Sent, Direction::Received] {
+            let target = disclosure.idx(direction);
+
+            // Only commitments entirely within the disclosure can be opened
+            // without revealing more than was intended.
+            let candidates: Vec<usize> = self
+                .commits
+                .iter()
+                .enumerate()
+                .filter(|(_, (d, idx))| *d == direction && idx.is_subset(target))
+                .map(|(i, _)| i)
+                .collect();
+
+            let mut uncovered = target.clone();
+            for &i in candidates.iter().filter(|&&i| preferred[i]) {
+                if !self.commits[i].1.is_disjoint(&uncovered) {
+                    uncovered = uncovered.difference(&self.commits[i].1);
+                    selected.push(i);
+                }
+            }
+
+            while !uncovered.is_empty() {
+                let best = candidates
+                    .iter()
+                    .copied()
+                    .filter(|i| !selected.contains(i))
+                    .map(|i| (i, self.commits[i].1.intersection(&uncovered).len()))
+                    .filter(|(_, gain)| *gain > 0)
+                    .max_by_key(|(i, gain)| (*gain, std::cmp::Reverse(*i)));
+
+                let Some((best, _)) = best else {
+                    return Err(UnsupportedDisclosure {
+                        disclosure: 0,
+                        direction,
+                        idx: uncovered,
+                    });
+                };
+
+                uncovered = uncovered.difference(&self.commits[best].1);
+                selected.push(best);
+            }
+        }
+
+        selected.sort_unstable();
+        Ok(selected)
+    }
+
+    /// Returns the indices of a minimal set of commitments which supports
+    /// every disclosure in the family.
+    ///
+    /// A disclosure is supported if it is exactly the union of some of the
+    /// selected commitments. Computing the optimum is a set cover problem, so
+    /// the selection is made greedily, reusing commitments across disclosures
+    /// wherever possible.
+    ///
+    /// # Arguments
+    ///
+    /// * `disclosures` - The disclosures which must be supported.
+    pub fn minimal_cover(
+        &self,
+        disclosures: &[Disclosure],
+    ) -> Result<Vec<usize>, UnsupportedDisclosure> {
+        let mut selected = vec![false; self.commits.len()];
+
+        // Cover larger disclosures first so that smaller ones can reuse their
+        // commitments.
+        let mut order: Vec<usize> = (0..disclosures.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(disclosures[i].len()));
+
+        for i in order {
+            let cover = self.cover(&disclosures[i], &selected).map_err(|mut err| {
+                err.disclosure = i;
+                err
+            })?;
+
+            for j in cover {
+                selected[j] = true;
+            }
+        }
+
+        Ok(selected
+            .iter()
+            .enumerate()
+            .filter_map(|(i, selected)| selected.then_some(i))
+            .collect())
+    }
+
+    /// Returns the indices of the commitments to open for a single
+    /// disclosure.
+    ///
+    /// # Arguments
+    ///
+    /// * `disclosure` - The disclosure to open.
+    pub fn openings_for(
+        &self,
+        disclosure: &Disclosure,
+    ) -> Result<Vec<usize>, UnsupportedDisclosure> {
+        self.cover(disclosure, &vec![false; self.commits.len()])
+    }
+
+    /// Returns a minimal set of commitments which supports every disclosure
+    /// in the family.
+    ///
+    /// See [`CommitmentSet::minimal_cover`].
+    pub fn minimise(&self, disclosures: &[Disclosure]) -> Result<Self, UnsupportedDisclosure> {
+        let cover = self.minimal_cover(disclosures)?;
+
+        Ok(Self {
+            commits: cover.into_iter().map(|i| self.commits[i].clone()).collect(),
+        })
+    }
+
+    /// Commits every range in the set to a builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The builder to commit to.
+    pub fn commit<C: TranscriptCommitmentBuilder>(
+        &self,
+        builder: &mut C,
+    ) -> Result<(), TranscriptCommitmentBuilderError> {
+        for (direction, idx) in &self.commits {
+            builder.commit(idx, *direction)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a report summarising the set.
+    pub fn report(&self) -> OverlapReport {
+        let overlaps = self.overlaps();
+        let redundant = self.redundant();
+        let unique_bytes = [Direction::Sent, Direction::Received]
+            .into_iter()
+            .map(|direction| self.coverage(direction).len())
+            .sum();
+
+        OverlapReport {
+            commitments: self.len(),
+            total_bytes: self.total_bytes(),
+            unique_bytes,
+            overlaps,
+            redundant,
+        }
+    }
+}
+
+impl TranscriptCommitment for CommitmentSet {
+    fn contains(&self, ranges: &dyn ToRangeSet<usize>, direction: Direction) -> bool {
+        let idx = ranges.to_range_set();
+
+        self.commits
+            .iter()
+            .any(|(d, committed)| *d == direction && *committed == idx)
+    }
+}
+
+impl From<&[(Direction, RangeSet<usize>)]> for CommitmentSet {
+    fn from(commits: &[(Direction, RangeSet<usize>)]) -> Self {
+        Self::new(commits.iter().cloned())
+    }
+}
+
+/// A set of ranges to be revealed to a Verifier.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disclosure {
+    /// Sent ranges to reveal.
+    pub sent: RangeSet<usize>,
+    /// Received ranges to reveal.
+    pub received: RangeSet<usize>,
+}
+
+impl Disclosure {
+    /// Creates a new disclosure.
+    pub fn new(sent: RangeSet<usize>, received: RangeSet<usize>) -> Self {
+        Self { sent, received }
+    }
+
+    /// Returns the ranges to reveal in the given direction.
+    pub fn idx(&self, direction: Direction) -> &RangeSet<usize> {
+        match direction {
+            Direction::Sent => &self.sent,
+            Direction::Received => &self.received,
+        }
+    }
+
+    /// Returns the number of bytes to reveal.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.sent.len() + self.received.len()
+    }
+}
+
+impl From<&PartialTranscript> for Disclosure {
+    fn from(transcript: &PartialTranscript) -> Self {
+        Self {
+            sent: transcript.sent_authed().clone(),
+            received: transcript.received_authed().clone(),
+        }
+    }
+}
+
+/// How two commitments overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapKind {
+    /// The commitments are identical.
+    Equal,
+    /// The first commitment is contained in the second.
+    Contained,
+    /// The first commitment contains the second.
+    Contains,
+    /// The commitments share some, but not all, bytes.
+    Partial,
+}
+
+/// An overlap between two commitments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Overlap {
+    /// Index of the first commitment.
+    pub a: usize,
+    /// Index of the second commitment.
+    pub b: usize,
+    /// Direction of both commitments.
+    pub direction: Direction,
+    /// The shared bytes.
+    pub idx: RangeSet<usize>,
+    /// How the commitments overlap.
+    pub kind: OverlapKind,
+}
+
+/// Summary of the overlaps within a [`CommitmentSet`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlapReport {
+    /// Number of commitments.
+    pub commitments: usize,
+    /// Total committed bytes, counting overlapping bytes once per commitment.
+    pub total_bytes: usize,
+    /// Number of distinct committed bytes.
+    pub unique_bytes: usize,
+    /// Every pair of overlapping commitments.
+    pub overlaps: Vec<Overlap>,
+    /// Commitments which are the union of other commitments.
+    pub redundant: Vec<usize>,
+}
+
+/// A disclosure which cannot be revealed exactly with the available
+/// commitments.
+#[derive(Debug, thiserror::Error)]
+#[error("disclosure {disclosure} is not supported: {direction} ranges {idx:?} are not covered")]
+pub struct UnsupportedDisclosure {
+    /// Index of the disclosure in the family.
+    pub disclosure: usize,
+    /// Direction of the uncovered ranges.
+    pub direction: Direction,
+    /// Ranges which no commitment within the disclosure covers.
+    pub idx: RangeSet<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use rangeset::ToRangeSet;
+    use rstest::{fixture, rstest};
+
+    use super::*;
+    use crate::{
+        fixtures::http as fixtures,
+        http::{DefaultHttpCommitter, HttpCommit, HttpTranscript},
+        transcript::{HashCommitmentBuilder, Transcript},
+    };
+
+    #[fixture]
+    fn transcript() -> Transcript {
+        Transcript::new(
+            fixtures::request::GET_WITH_HEADER,
+            fixtures::response::OK_JSON,
+        )
+    }
+
+    #[fixture]
+    fn set(transcript: Transcript) -> CommitmentSet {
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        DefaultHttpCommitter::default()
+            .commit_transcript(&mut builder, &http)
+            .unwrap();
+
+        CommitmentSet::from(builder.commits())
+    }
+
+    #[test]
+    fn test_commitment_set_overlaps() {
+        let set = CommitmentSet::new([
+            (Direction::Sent, RangeSet::from(0..10)),
+            (Direction::Sent, RangeSet::from(0..5)),
+            (Direction::Sent, RangeSet::from(8..12)),
+            (Direction::Received, RangeSet::from(0..10)),
+            (Direction::Sent, RangeSet::from(0..10)),
+        ]);
+
+        assert_eq!(set.len(), 4);
+
+        let overlaps = set.overlaps();
+        assert_eq!(overlaps.len(), 2);
+        assert_eq!(overlaps[0].kind, OverlapKind::Contains);
+        assert_eq!(overlaps[0].idx, RangeSet::from(0..5));
+        assert_eq!(overlaps[1].kind, OverlapKind::Partial);
+        assert_eq!(overlaps[1].idx, RangeSet::from(8..10));
+    }
+
+    #[test]
+    fn test_commitment_set_redundant() {
+        let set = CommitmentSet::new([
+            (Direction::Sent, RangeSet::from(0..10)),
+            (Direction::Sent, RangeSet::from(0..5)),
+            (Direction::Sent, RangeSet::from(5..10)),
+            (Direction::Sent, RangeSet::from(2..5)),
+            (Direction::Sent, RangeSet::from(0..2)),
+        ]);
+
+        // 0..10 = 0..5 ∪ 5..10, and then 0..5 = 0..2 ∪ 2..5.
+        assert_eq!(set.redundant(), vec![0, 1]);
+        assert_eq!(set.without_redundant().len(), 3);
+    }
+
+    #[rstest]
+    fn test_commitment_set_minimal_cover(transcript: Transcript, set: CommitmentSet) {
+        let http = HttpTranscript::parse(&transcript).unwrap();
+        let request = &http.requests[0];
+        let response = &http.responses[0];
+
+        let disclosures = [
+            Disclosure::new(request.without_data(), RangeSet::default()),
+            Disclosure::new(
+                request
+                    .without_data()
+                    .union(&request.request.target.to_range_set()),
+                response.without_data(),
+            ),
+        ];
+
+        let minimal = set.minimise(&disclosures).unwrap();
+        assert_eq!(minimal.len(), 3);
+
+        for disclosure in &disclosures {
+            let openings = minimal.openings_for(disclosure).unwrap();
+            let revealed = openings.iter().map(|&i| &minimal.commits()[i]).fold(
+                Disclosure::default(),
+                |mut acc, (direction, idx)| {
+                    match direction {
+                        Direction::Sent => acc.sent = acc.sent.union(idx),
+                        Direction::Received => acc.received = acc.received.union(idx),
+                    }
+                    acc
+                },
+            );
+
+            assert_eq!(&revealed, disclosure);
+        }
+    }
+
+    #[rstest]
+    fn test_commitment_set_unsupported(set: CommitmentSet) {
+        let err = set
+            .minimal_cover(&[
+                Disclosure::default(),
+                Disclosure::new(RangeSet::from(0..2), RangeSet::default()),
+            ])
+            .unwrap_err();
+
+        assert_eq!(err.disclosure, 1);
+        assert_eq!(err.direction, Direction::Sent);
+    }
+
+    #[rstest]
+    fn test_commitment_set_report(transcript: Transcript, set: CommitmentSet) {
+        let report = set.report();
+
+        assert_eq!(report.commitments, set.len());
+        assert!(report.total_bytes > report.unique_bytes);
+        assert_eq!(
+            report.unique_bytes,
+            transcript.sent().len() + transcript.received().len()
+        );
+        assert!(!report.overlaps.is_empty());
+        assert!(!report.redundant.is_empty());
+
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        set.without_redundant().commit(&mut builder).unwrap();
+        assert_eq!(builder.commits().len(), set.len() - report.redundant.len());
+    }
+}
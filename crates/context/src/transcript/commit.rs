@@ -1,6 +1,6 @@
 use std::fmt;
 
-use rangeset::ToRangeSet;
+use rangeset::{RangeSet, ToRangeSet};
 
 use crate::transcript::Direction;
 
@@ -43,7 +43,7 @@ pub struct TranscriptCommitmentBuilderError {
 }
 
 impl TranscriptCommitmentBuilderError {
-    fn new<E>(kind: ErrorKind, source: E) -> Self
+    pub(crate) fn new<E>(kind: ErrorKind, source: E) -> Self
     where
         E: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
@@ -55,14 +55,16 @@ impl TranscriptCommitmentBuilderError {
 }
 
 #[derive(Debug)]
-enum ErrorKind {
+pub(crate) enum ErrorKind {
     Index,
+    Algorithm,
 }
 
 impl fmt::Display for TranscriptCommitmentBuilderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.kind {
             ErrorKind::Index => f.write_str("index error")?,
+            ErrorKind::Algorithm => f.write_str("algorithm error")?,
         }
 
         if let Some(source) = &self.source {
@@ -71,4 +73,41 @@ impl fmt::Display for TranscriptCommitmentBuilderError {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+/// Records a commitment to the given ranges, checking that they are in bounds
+/// of the transcript.
+///
+/// Empty ranges carry no data and are not recorded, nor are ranges which are
+/// already committed.
+///
+/// # Arguments
+///
+/// * `commits` - The commitments recorded so far.
+/// * `len` - The length of the transcript in the given direction.
+/// * `ranges` - The ranges of the commitment.
+/// * `direction` - The direction of the transcript.
+pub(crate) fn push_commit(
+    commits: &mut Vec<(Direction, RangeSet<usize>)>,
+    len: usize,
+    ranges: &dyn ToRangeSet<usize>,
+    direction: Direction,
+) -> Result<(), TranscriptCommitmentBuilderError> {
+    let idx = ranges.to_range_set();
+
+    if idx.end().unwrap_or(0) > len {
+        return Err(TranscriptCommitmentBuilderError::new(
+            ErrorKind::Index,
+            format!(
+                "index is out of bounds of the {direction} transcript ({}..{} > {len})",
+                idx.min().unwrap_or(0),
+                idx.end().unwrap_or(0),
+            ),
+        ));
+    }
+
+    if !idx.is_empty() && !commits.iter().any(|(d, i)| *d == direction && *i == idx) {
+        commits.push((direction, idx));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! Dry-run commitments and cost estimates.
+//!
+//! Proving backends typically charge per committed range and per committed
+//! byte. [`DryRunBuilder`] is a [`TranscriptCommitmentBuilder`] which only
+//! records what a committer would commit, so that its [`CostReport`] can be
+//! inspected, and committer policies tuned, without producing a commitment.
+
+use rangeset::{RangeSet, ToRangeSet};
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::{
+    commit::push_commit, CommitmentSet, Direction, Transcript, TranscriptCommitment,
+    TranscriptCommitmentBuilder, TranscriptCommitmentBuilderError,
+};
+
+/// A [`TranscriptCommitmentBuilder`] which records commitments without
+/// committing to any data.
+///
+/// The builder accepts and rejects exactly the ranges a
+/// [`HashCommitmentBuilder`](crate::transcript::HashCommitmentBuilder) for the
+/// same transcript would.
+#[derive(Debug, Clone)]
+pub struct DryRunBuilder {
+    len_sent: usize,
+    len_received: usize,
+    commits: Vec<(Direction, RangeSet<usize>)>,
+    requested: usize,
+}
+
+impl DryRunBuilder {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The transcript which would be committed to.
+    pub fn new(transcript: &Transcript) -> Self {
+        Self::with_len(transcript.sent().len(), transcript.received().len())
+    }
+
+    /// Creates a new builder for a transcript with the given lengths.
+    ///
+    /// # Arguments
+    ///
+    /// * `len_sent` - The length of the sent transcript.
+    /// * `len_received` - The length of the received transcript.
+    pub(crate) fn with_len(len_sent: usize, len_received: usize) -> Self {
+        Self {
+            len_sent,
+            len_received,
+            commits: Vec::new(),
+            requested: 0,
+        }
+    }
+
+    /// Returns the recorded indices, in the order they were committed.
+    pub fn commits(&self) -> &[(Direction, RangeSet<usize>)] {
+        &self.commits
+    }
+
+    /// Returns the number of calls to [`commit`](TranscriptCommitmentBuilder::commit)
+    /// which succeeded, including empty and duplicate ranges.
+    pub fn requested(&self) -> usize {
+        self.requested
+    }
+
+    /// Returns the recorded commitments as a [`CommitmentSet`].
+    pub fn commitment_set(&self) -> CommitmentSet {
+        CommitmentSet::from(self.commits.as_slice())
+    }
+
+    /// Returns a report of what would be committed.
+    pub fn report(&self) -> CostReport {
+        let set = self.commitment_set();
+        let unique_bytes = [Direction::Sent, Direction::Received]
+            .into_iter()
+            .map(|direction| set.coverage(direction).len())
+            .sum();
+
+        // `max_by_key` returns the last maximum, so search in reverse to
+        // report the first.
+        let largest = self
+            .commits
+            .iter()
+            .rev()
+            .max_by_key(|(_, idx)| idx.len())
+            .map(|(direction, idx)| LargestCommitment {
+                direction: *direction,
+                idx: idx.clone(),
+                len: idx.len(),
+            });
+
+        CostReport {
+            commitments: self.commits.len(),
+            requested: self.requested,
+            total_bytes: self.commits.iter().map(|(_, idx)| idx.len()).sum(),
+            unique_bytes,
+            largest,
+        }
+    }
+}
+
+impl TranscriptCommitmentBuilder for DryRunBuilder {
+    fn commit(
+        &mut self,
+        ranges: &dyn ToRangeSet<usize>,
+        direction: Direction,
+    ) -> Result<&mut Self, TranscriptCommitmentBuilderError> {
+        let len = match direction {
+            Direction::Sent => self.len_sent,
+            Direction::Received => self.len_received,
+        };
+        push_commit(&mut self.commits, len, ranges, direction)?;
+
+        self.requested += 1;
+
+        Ok(self)
+    }
+
+    fn build(self) -> Result<Box<dyn TranscriptCommitment>, TranscriptCommitmentBuilderError> {
+        Ok(Box::new(self.commitment_set()))
+    }
+}
+
+/// The largest single commitment in a [`CostReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LargestCommitment {
+    /// Direction of the commitment.
+    pub direction: Direction,
+    /// Committed indices.
+    pub idx: RangeSet<usize>,
+    /// Number of committed bytes.
+    pub len: usize,
+}
+
+/// Summary of the commitments recorded by a [`DryRunBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostReport {
+    /// Number of distinct, non-empty commitments.
+    pub commitments: usize,
+    /// Number of commitments requested, including empty and duplicate ranges.
+    pub requested: usize,
+    /// Total committed bytes, counting overlapping bytes once per commitment.
+    pub total_bytes: usize,
+    /// Number of distinct committed bytes.
+    pub unique_bytes: usize,
+    /// The largest commitment, if any. The first is reported on ties.
+    pub largest: Option<LargestCommitment>,
+}
+
+impl CostReport {
+    /// Returns the cost of the commitments under the given model.
+    pub fn cost(&self, model: &CostModel) -> u64 {
+        model.cost(self.commitments, self.total_bytes)
+    }
+}
+
+/// Prices of a proving backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostModel {
+    /// Price per committed range.
+    pub per_commitment: u64,
+    /// Price per committed byte.
+    pub per_byte: u64,
+}
+
+impl CostModel {
+    /// Returns the cost of `commitments` commitments totalling `bytes` bytes,
+    /// saturating on overflow.
+    pub fn cost(&self, commitments: usize, bytes: usize) -> u64 {
+        self.per_commitment
+            .saturating_mul(commitments as u64)
+            .saturating_add(self.per_byte.saturating_mul(bytes as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{
+        fixtures::http as fixtures,
+        http::{DefaultHttpCommitter, HttpCommit, HttpTranscript},
+        transcript::HashCommitmentBuilder,
+    };
+
+    #[rstest]
+    fn test_dry_run_matches_hash_builder() {
+        let transcript = Transcript::new(
+            fixtures::request::POST_JSON,
+            fixtures::response::OK_CHUNKED_JSON,
+        );
+        let http = HttpTranscript::parse(&transcript).unwrap();
+
+        let mut dry_run = DryRunBuilder::new(&transcript);
+        DefaultHttpCommitter::default()
+            .commit_transcript(&mut dry_run, &http)
+            .unwrap();
+
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        DefaultHttpCommitter::default()
+            .commit_transcript(&mut builder, &http)
+            .unwrap();
+
+        assert_eq!(dry_run.commits(), builder.commits());
+
+        let commitment = dry_run.build().unwrap();
+        for (direction, idx) in builder.commits() {
+            assert!(commitment.contains(idx, *direction));
+        }
+    }
+
+    #[rstest]
+    fn test_report() {
+        let transcript = Transcript::new([0u8; 16], [0u8; 8]);
+        let mut builder = DryRunBuilder::new(&transcript);
+
+        builder
+            .commit(&(0..10), Direction::Sent)
+            .unwrap()
+            .commit(&(5..15), Direction::Sent)
+            .unwrap()
+            .commit(&(0..10), Direction::Sent)
+            .unwrap()
+            .commit(&(3..3), Direction::Sent)
+            .unwrap()
+            .commit(&(0..4), Direction::Received)
+            .unwrap();
+        assert!(builder.commit(&(0..9), Direction::Received).is_err());
+
+        let report = builder.report();
+        assert_eq!(report.commitments, 3);
+        assert_eq!(report.requested, 5);
+        assert_eq!(report.total_bytes, 24);
+        assert_eq!(report.unique_bytes, 19);
+        assert_eq!(
+            report.largest,
+            Some(LargestCommitment {
+                direction: Direction::Sent,
+                idx: RangeSet::from(0..10),
+                len: 10,
+            })
+        );
+
+        let model = CostModel {
+            per_commitment: 100,
+            per_byte: 2,
+        };
+        assert_eq!(report.cost(&model), 348);
+    }
+}
@@ -0,0 +1,518 @@
+//! Salted hash commitments to transcript data.
+//!
+//! [`HashCommitmentBuilder`] is a reference implementation of
+//! [`TranscriptCommitmentBuilder`]. Every committed [`RangeSet`] is recorded
+//! per [`Direction`] and bound to a hash of a random salt followed by the
+//! plaintext at those indices.
+//!
+//! The resulting [`HashCommitment`] can be published, while the
+//! [`HashOpening`]s are kept by the Prover and selectively revealed
+//! alongside a [`PartialTranscript`].
+//!
+//! The hash algorithm is selected with the `sha256` and `blake3` features.
+
+use rand::Rng;
+use rangeset::{Difference, RangeSet, ToRangeSet, Union};
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::{
+    commit::{push_commit, ErrorKind},
+    Direction, PartialTranscript, Transcript, TranscriptCommitment, TranscriptCommitmentBuilder,
+    TranscriptCommitmentBuilderError,
+};
+
+/// Length of the salt mixed into each hash, in bytes.
+pub const SALT_LEN: usize = 16;
+
+/// A hash algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// SHA-256.
+    Sha256,
+    /// BLAKE3.
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Returns whether support for the algorithm was compiled in.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            HashAlgorithm::Sha256 => cfg!(feature = "sha256"),
+            HashAlgorithm::Blake3 => cfg!(feature = "blake3"),
+        }
+    }
+
+    /// Hashes the salt followed by the data, returning `None` if the
+    /// algorithm is not supported.
+    pub(crate) fn hash(&self, salt: &[u8; SALT_LEN], data: &[u8]) -> Option<[u8; 32]> {
+        self.digest(&[salt, data])
+    }
+
+    /// Hashes the concatenation of the given parts, returning `None` if the
+    /// algorithm is not supported.
+    pub(crate) fn digest(&self, parts: &[&[u8]]) -> Option<[u8; 32]> {
+        match self {
+            #[cfg(feature = "sha256")]
+            HashAlgorithm::Sha256 => {
+                use sha2::{Digest, Sha256};
+
+                let mut hasher = Sha256::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                Some(hasher.finalize().into())
+            }
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                for part in parts {
+                    hasher.update(part);
+                }
+                Some(hasher.finalize().into())
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = parts;
+                None
+            }
+        }
+    }
+}
+
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        if cfg!(feature = "sha256") {
+            HashAlgorithm::Sha256
+        } else {
+            HashAlgorithm::Blake3
+        }
+    }
+}
+
+/// A hash of a subsequence of the transcript.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaintextHash {
+    /// Direction of the plaintext.
+    pub direction: Direction,
+    /// Index of the plaintext in the transcript.
+    pub idx: RangeSet<usize>,
+    /// Salted hash of the plaintext.
+    pub hash: [u8; 32],
+}
+
+/// A set of salted hash commitments to a transcript.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashCommitment {
+    alg: HashAlgorithm,
+    hashes: Vec<PlaintextHash>,
+}
+
+impl HashCommitment {
+    /// Returns the hash algorithm.
+    pub fn alg(&self) -> HashAlgorithm {
+        self.alg
+    }
+
+    /// Returns the plaintext hashes.
+    pub fn hashes(&self) -> &[PlaintextHash] {
+        &self.hashes
+    }
+
+    /// Returns an iterator over the committed indices in the given direction.
+    pub fn iter_idx(&self, direction: Direction) -> impl Iterator<Item = &RangeSet<usize>> + '_ {
+        self.hashes
+            .iter()
+            .filter(move |hash| hash.direction == direction)
+            .map(|hash| &hash.idx)
+    }
+
+    /// Verifies a single opening against a partial transcript.
+    ///
+    /// The opened indices must be authenticated in the transcript, and the
+    /// salted hash of the transcript data must match the commitment.
+    ///
+    /// # Arguments
+    ///
+    /// * `opening` - The opening to verify.
+    /// * `transcript` - The partial transcript containing the opened data.
+    pub fn verify(
+        &self,
+        opening: &HashOpening,
+        transcript: &PartialTranscript,
+    ) -> Result<(), HashOpeningError> {
+        let hash = self
+            .hashes
+            .get(opening.commitment)
+            .ok_or(HashOpeningError::UnknownCommitment(opening.commitment))?;
+
+        if hash.direction != opening.direction || hash.idx != opening.idx {
+            return Err(HashOpeningError::IndexMismatch(opening.commitment));
+        }
+
+        if !transcript.contains(opening.direction, &opening.idx) {
+            return Err(HashOpeningError::OutOfBounds(opening.commitment));
+        }
+
+        let authed = match opening.direction {
+            Direction::Sent => transcript.sent_authed(),
+            Direction::Received => transcript.received_authed(),
+        };
+
+        if !opening.idx.difference(authed).is_empty() {
+            return Err(HashOpeningError::Unauthenticated(opening.commitment));
+        }
+
+        let data = match opening.direction {
+            Direction::Sent => transcript.sent_unsafe(),
+            Direction::Received => transcript.received_unsafe(),
+        };
+        let plaintext: Vec<u8> = opening.idx.iter().map(|i| data[i]).collect();
+
+        let expected = self
+            .alg
+            .hash(&opening.salt, &plaintext)
+            .ok_or(HashOpeningError::UnsupportedAlgorithm(self.alg))?;
+
+        if expected != hash.hash {
+            return Err(HashOpeningError::HashMismatch(opening.commitment));
+        }
+
+        Ok(())
+    }
+
+    /// Verifies a set of openings against a partial transcript.
+    ///
+    /// Each opening is verified individually, and every authenticated index
+    /// of the transcript must be covered by at least one opening.
+    ///
+    /// # Arguments
+    ///
+    /// * `openings` - The openings to verify.
+    /// * `transcript` - The partial transcript containing the opened data.
+    pub fn verify_transcript(
+        &self,
+        openings: &[HashOpening],
+        transcript: &PartialTranscript,
+    ) -> Result<(), HashOpeningError> {
+        let mut sent = RangeSet::default();
+        let mut received = RangeSet::default();
+
+        for opening in openings {
+            self.verify(opening, transcript)?;
+
+            match opening.direction {
+                Direction::Sent => sent = sent.union(&opening.idx),
+                Direction::Received => received = received.union(&opening.idx),
+            }
+        }
+
+        for (direction, opened, authed) in [
+            (Direction::Sent, sent, transcript.sent_authed()),
+            (Direction::Received, received, transcript.received_authed()),
+        ] {
+            let uncovered = authed.difference(&opened);
+            if !uncovered.is_empty() {
+                return Err(HashOpeningError::Uncovered {
+                    direction,
+                    idx: uncovered,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TranscriptCommitment for HashCommitment {
+    fn contains(&self, ranges: &dyn ToRangeSet<usize>, direction: Direction) -> bool {
+        let idx = ranges.to_range_set();
+
+        self.iter_idx(direction).any(|committed| *committed == idx)
+    }
+}
+
+/// An opening of a [`PlaintextHash`].
+///
+/// Revealing an opening allows a Verifier to check the committed data in a
+/// [`PartialTranscript`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashOpening {
+    /// Index of the hash in the [`HashCommitment`].
+    pub commitment: usize,
+    /// Direction of the plaintext.
+    pub direction: Direction,
+    /// Index of the plaintext in the transcript.
+    pub idx: RangeSet<usize>,
+    /// Salt mixed into the hash.
+    pub salt: [u8; SALT_LEN],
+}
+
+/// Builder for [`HashCommitment`].
+pub struct HashCommitmentBuilder<'a> {
+    transcript: &'a Transcript,
+    alg: HashAlgorithm,
+    commits: Vec<(Direction, RangeSet<usize>)>,
+}
+
+impl<'a> HashCommitmentBuilder<'a> {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The transcript to commit to.
+    pub fn new(transcript: &'a Transcript) -> Self {
+        Self {
+            transcript,
+            alg: HashAlgorithm::default(),
+            commits: Vec::new(),
+        }
+    }
+
+    /// Sets the hash algorithm.
+    pub fn alg(&mut self, alg: HashAlgorithm) -> &mut Self {
+        self.alg = alg;
+        self
+    }
+
+    /// Returns the committed indices, in the order they were committed.
+    pub fn commits(&self) -> &[(Direction, RangeSet<usize>)] {
+        &self.commits
+    }
+
+    /// Builds the commitment, returning it alongside the opening for each hash.
+    pub fn finalize(
+        self,
+    ) -> Result<(HashCommitment, Vec<HashOpening>), TranscriptCommitmentBuilderError> {
+        if !self.alg.is_supported() {
+            return Err(TranscriptCommitmentBuilderError::new(
+                ErrorKind::Algorithm,
+                format!("hash algorithm {:?} is not enabled", self.alg),
+            ));
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut hashes = Vec::with_capacity(self.commits.len());
+        let mut openings = Vec::with_capacity(self.commits.len());
+
+        for (commitment, (direction, idx)) in self.commits.into_iter().enumerate() {
+            let salt: [u8; SALT_LEN] = rng.gen();
+            let plaintext = self
+                .transcript
+                .get(direction, &idx)
+                .expect("index was checked when committed");
+            let hash = self
+                .alg
+                .hash(&salt, plaintext.data())
+                .expect("algorithm is supported");
+
+            hashes.push(PlaintextHash {
+                direction,
+                idx: idx.clone(),
+                hash,
+            });
+            openings.push(HashOpening {
+                commitment,
+                direction,
+                idx,
+                salt,
+            });
+        }
+
+        Ok((
+            HashCommitment {
+                alg: self.alg,
+                hashes,
+            },
+            openings,
+        ))
+    }
+}
+
+impl TranscriptCommitmentBuilder for HashCommitmentBuilder<'_> {
+    fn commit(
+        &mut self,
+        ranges: &dyn ToRangeSet<usize>,
+        direction: Direction,
+    ) -> Result<&mut Self, TranscriptCommitmentBuilderError> {
+        let len = self.transcript.len_of_direction(direction);
+        push_commit(&mut self.commits, len, ranges, direction)?;
+
+        Ok(self)
+    }
+
+    fn build(self) -> Result<Box<dyn TranscriptCommitment>, TranscriptCommitmentBuilderError> {
+        let (commitment, _) = self.finalize()?;
+
+        Ok(Box::new(commitment))
+    }
+}
+
+/// Error for [`HashCommitment::verify`].
+#[derive(Debug, thiserror::Error)]
+pub enum HashOpeningError {
+    /// The opening refers to a hash which does not exist.
+    #[error("opening refers to unknown commitment {0}")]
+    UnknownCommitment(usize),
+    /// The opening index or direction differs from the committed one.
+    #[error("opening index does not match commitment {0}")]
+    IndexMismatch(usize),
+    /// The opened index is out of bounds of the transcript.
+    #[error("opening for commitment {0} is out of bounds of the transcript")]
+    OutOfBounds(usize),
+    /// The opened index is not authenticated in the transcript.
+    #[error("opening for commitment {0} contains unauthenticated data")]
+    Unauthenticated(usize),
+    /// The hash of the transcript data does not match the commitment.
+    #[error("hash mismatch for commitment {0}")]
+    HashMismatch(usize),
+    /// Authenticated data in the transcript is not covered by any opening.
+    #[error("authenticated {direction} data is not covered by an opening: {idx:?}")]
+    Uncovered {
+        /// Direction of the uncovered data.
+        direction: Direction,
+        /// Index of the uncovered data.
+        idx: RangeSet<usize>,
+    },
+    /// The hash algorithm is not enabled.
+    #[error("hash algorithm {0:?} is not enabled")]
+    UnsupportedAlgorithm(HashAlgorithm),
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::{fixture, rstest};
+
+    use super::*;
+
+    #[fixture]
+    fn transcript() -> Transcript {
+        Transcript::new(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.1 200 OK\r\n\r\n")
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_hash_commitment_open_verify(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder
+            .commit(&(0..3), Direction::Sent)
+            .unwrap()
+            .commit(&RangeSet::from([9..12, 13..15]), Direction::Received)
+            .unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+
+        assert!(commitment.contains(&(0..3), Direction::Sent));
+        assert!(!commitment.contains(&(0..3), Direction::Received));
+
+        let partial = transcript.to_partial(RangeSet::from(0..3), RangeSet::from([9..12, 13..15]));
+
+        commitment.verify_transcript(&openings, &partial).unwrap();
+    }
+
+    #[rstest]
+    fn test_hash_commitment_dedup(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder
+            .commit(&(0..3), Direction::Sent)
+            .unwrap()
+            .commit(&(0..3), Direction::Sent)
+            .unwrap()
+            .commit(&(0..3), Direction::Received)
+            .unwrap()
+            .commit(&RangeSet::<usize>::default(), Direction::Received)
+            .unwrap();
+
+        assert_eq!(builder.commits().len(), 2);
+    }
+
+    #[rstest]
+    fn test_hash_commitment_out_of_bounds(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+
+        assert!(builder.commit(&(0..100), Direction::Sent).is_err());
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_hash_commitment_serialization(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder.commit(&(4..5), Direction::Sent).unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+
+        let bytes = bincode::serialize(&commitment).unwrap();
+        let deserialized: HashCommitment = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(commitment, deserialized);
+
+        let bytes = bincode::serialize(&openings).unwrap();
+        let deserialized: Vec<HashOpening> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(openings, deserialized);
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_hash_commitment_tampered_data(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder.commit(&(0..3), Direction::Sent).unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+
+        let tampered = Transcript::new(b"PUT / HTTP/1.1\r\n\r\n", transcript.received());
+        let partial = tampered.to_partial(RangeSet::from(0..3), RangeSet::default());
+
+        assert!(matches!(
+            commitment.verify(&openings[0], &partial),
+            Err(HashOpeningError::HashMismatch(0))
+        ));
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_hash_commitment_unauthenticated(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder.commit(&(0..3), Direction::Sent).unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+
+        let partial = transcript.to_partial(RangeSet::from(0..2), RangeSet::default());
+
+        assert!(matches!(
+            commitment.verify(&openings[0], &partial),
+            Err(HashOpeningError::Unauthenticated(0))
+        ));
+    }
+
+    #[cfg(any(feature = "sha256", feature = "blake3"))]
+    #[rstest]
+    fn test_hash_commitment_uncovered(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder.commit(&(0..3), Direction::Sent).unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+
+        let partial = transcript.to_partial(RangeSet::from(0..5), RangeSet::default());
+
+        assert!(matches!(
+            commitment.verify_transcript(&openings, &partial),
+            Err(HashOpeningError::Uncovered {
+                direction: Direction::Sent,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[rstest]
+    fn test_hash_commitment_blake3(transcript: Transcript) {
+        let mut builder = HashCommitmentBuilder::new(&transcript);
+        builder.alg(HashAlgorithm::Blake3);
+        builder.commit(&(0..3), Direction::Sent).unwrap();
+
+        let (commitment, openings) = builder.finalize().unwrap();
+        assert_eq!(commitment.alg(), HashAlgorithm::Blake3);
+
+        let partial = transcript.to_partial(RangeSet::from(0..3), RangeSet::default());
+        commitment.verify_transcript(&openings, &partial).unwrap();
+    }
+}
@@ -0,0 +1,662 @@
+//! Merkle tree commitments to transcript data.
+//!
+//! [`MerkleCommitmentBuilder`] places every committed [`RangeSet`] into a
+//! leaf of a Merkle tree, so only the root needs to be published regardless
+//! of how many ranges were committed. Any subset of the leaves can later be
+//! opened with a single compact multi-proof.
+//!
+//! Each leaf binds the direction, the committed indices, a random salt and the
+//! plaintext at those indices. Leaves and internal nodes are hashed with
+//! distinct prefixes, and a node without a sibling is promoted to the next
+//! layer unchanged.
+
+use rand::Rng;
+use rangeset::{Difference, RangeSet, ToRangeSet, Union};
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::{
+    commit::{push_commit, ErrorKind},
+    hash::SALT_LEN,
+    Direction, HashAlgorithm, PartialTranscript, Transcript, TranscriptCommitment,
+    TranscriptCommitmentBuilder, TranscriptCommitmentBuilderError,
+};
+
+const LEAF_PREFIX: &[u8] = &[0x00];
+const NODE_PREFIX: &[u8] = &[0x01];
+
+/// A Merkle tree commitment to a transcript.
+///
+/// This is the public part of the commitment which is shared with a Verifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleCommitment {
+    alg: HashAlgorithm,
+    root: [u8; 32],
+    leaf_count: usize,
+}
+
+impl MerkleCommitment {
+    /// Returns the hash algorithm.
+    pub fn alg(&self) -> HashAlgorithm {
+        self.alg
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> &[u8; 32] {
+        &self.root
+    }
+
+    /// Returns the number of leaves in the tree.
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Verifies an opening against a partial transcript.
+    ///
+    /// Every opened leaf must be authenticated in the transcript, the
+    /// multi-proof must reproduce the root, and the authenticated ranges of
+    /// the transcript must be exactly covered by the opened leaves.
+    ///
+    /// # Arguments
+    ///
+    /// * `opening` - The opening to verify.
+    /// * `transcript` - The partial transcript containing the opened data.
+    pub fn verify(
+        &self,
+        opening: &MerkleOpening,
+        transcript: &PartialTranscript,
+    ) -> Result<(), MerkleProofError> {
+        if !self.alg.is_supported() {
+            return Err(MerkleProofError::UnsupportedAlgorithm(self.alg));
+        }
+
+        let mut sent = RangeSet::default();
+        let mut received = RangeSet::default();
+        let mut nodes = Vec::with_capacity(opening.leaves.len());
+
+        for leaf in &opening.leaves {
+            if leaf.index >= self.leaf_count {
+                return Err(MerkleProofError::UnknownLeaf(leaf.index));
+            }
+
+            if !transcript.contains(leaf.direction, &leaf.idx) {
+                return Err(MerkleProofError::OutOfBounds(leaf.index));
+            }
+
+            let (data, authed) = match leaf.direction {
+                Direction::Sent => (transcript.sent_unsafe(), transcript.sent_authed()),
+                Direction::Received => (transcript.received_unsafe(), transcript.received_authed()),
+            };
+
+            if !leaf.idx.difference(authed).is_empty() {
+                return Err(MerkleProofError::Unauthenticated(leaf.index));
+            }
+
+            let plaintext: Vec<u8> = leaf.idx.iter().map(|i| data[i]).collect();
+            nodes.push((
+                leaf.index,
+                leaf_hash(self.alg, leaf.direction, &leaf.idx, &leaf.salt, &plaintext),
+            ));
+
+            match leaf.direction {
+                Direction::Sent => sent = sent.union(&leaf.idx),
+                Direction::Received => received = received.union(&leaf.idx),
+            }
+        }
+
+        nodes.sort_by_key(|(index, _)| *index);
+        if nodes.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(MerkleProofError::DuplicateLeaf);
+        }
+
+        if !nodes.is_empty() {
+            let root = compute_root(self.alg, self.leaf_count, nodes, &opening.proof)?;
+            if root != self.root {
+                return Err(MerkleProofError::RootMismatch);
+            }
+        }
+
+        for (direction, opened, authed) in [
+            (Direction::Sent, sent, transcript.sent_authed()),
+            (Direction::Received, received, transcript.received_authed()),
+        ] {
+            let uncovered = authed.difference(&opened);
+            if !uncovered.is_empty() {
+                return Err(MerkleProofError::Uncovered {
+                    direction,
+                    idx: uncovered,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A leaf of a [`MerkleTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleLeaf {
+    /// Direction of the plaintext.
+    pub direction: Direction,
+    /// Index of the plaintext in the transcript.
+    pub idx: RangeSet<usize>,
+    salt: [u8; SALT_LEN],
+}
+
+/// A Merkle tree over committed transcript ranges.
+///
+/// This is the private part of the commitment which is kept by the Prover,
+/// and used to generate [`MerkleOpening`]s.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    alg: HashAlgorithm,
+    leaves: Vec<MerkleLeaf>,
+    /// The layers of the tree, starting from the leaf hashes.
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Returns the public commitment to the tree.
+    pub fn commitment(&self) -> MerkleCommitment {
+        MerkleCommitment {
+            alg: self.alg,
+            root: self.root(),
+            leaf_count: self.leaves.len(),
+        }
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first().copied())
+            .unwrap_or_default()
+    }
+
+    /// Returns the leaves of the tree.
+    pub fn leaves(&self) -> &[MerkleLeaf] {
+        &self.leaves
+    }
+
+    /// Returns the indices of the leaves with exactly the given direction and
+    /// ranges.
+    pub fn find(&self, ranges: &dyn ToRangeSet<usize>, direction: Direction) -> Option<usize> {
+        let idx = ranges.to_range_set();
+
+        self.leaves
+            .iter()
+            .position(|leaf| leaf.direction == direction && leaf.idx == idx)
+    }
+
+    /// Opens the given leaves with a multi-proof.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves` - Indices of the leaves to open.
+    pub fn prove(&self, leaves: &[usize]) -> Result<MerkleOpening, MerkleProofError> {
+        let mut indices = leaves.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+
+        if let Some(&index) = indices.iter().find(|&&index| index >= self.leaves.len()) {
+            return Err(MerkleProofError::UnknownLeaf(index));
+        }
+
+        let opened = indices
+            .iter()
+            .map(|&index| {
+                let leaf = &self.leaves[index];
+                OpenedLeaf {
+                    index,
+                    direction: leaf.direction,
+                    idx: leaf.idx.clone(),
+                    salt: leaf.salt,
+                }
+            })
+            .collect();
+
+        let mut proof = Vec::new();
+        let mut known = indices;
+
+        for layer in &self.layers[..self.layers.len().saturating_sub(1)] {
+            for (i, &index) in known.iter().enumerate() {
+                let sibling = index ^ 1;
+                let sibling_known = if index % 2 == 0 {
+                    known.get(i + 1) == Some(&sibling)
+                } else {
+                    i > 0 && known[i - 1] == sibling
+                };
+
+                if sibling < layer.len() && !sibling_known {
+                    proof.push(layer[sibling]);
+                }
+            }
+
+            known = known.iter().map(|index| index / 2).collect();
+            known.dedup();
+        }
+
+        Ok(MerkleOpening {
+            leaves: opened,
+            proof,
+        })
+    }
+
+    /// Opens the leaves with exactly the given ranges.
+    ///
+    /// # Arguments
+    ///
+    /// * `ranges` - The committed ranges to open, with their direction.
+    pub fn prove_ranges(
+        &self,
+        ranges: &[(Direction, RangeSet<usize>)],
+    ) -> Result<MerkleOpening, MerkleProofError> {
+        let leaves = ranges
+            .iter()
+            .map(|(direction, idx)| {
+                self.find(idx, *direction)
+                    .ok_or_else(|| MerkleProofError::NotCommitted {
+                        direction: *direction,
+                        idx: idx.clone(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.prove(&leaves)
+    }
+}
+
+impl TranscriptCommitment for MerkleTree {
+    fn contains(&self, ranges: &dyn ToRangeSet<usize>, direction: Direction) -> bool {
+        self.find(ranges, direction).is_some()
+    }
+}
+
+/// A leaf revealed in a [`MerkleOpening`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenedLeaf {
+    /// Index of the leaf in the tree.
+    pub index: usize,
+    /// Direction of the plaintext.
+    pub direction: Direction,
+    /// Index of the plaintext in the transcript.
+    pub idx: RangeSet<usize>,
+    /// Salt mixed into the leaf hash.
+    pub salt: [u8; SALT_LEN],
+}
+
+/// An opening of a subset of the leaves of a [`MerkleTree`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleOpening {
+    /// The opened leaves, sorted by index.
+    pub leaves: Vec<OpenedLeaf>,
+    /// Sibling hashes required to recompute the root, ordered by layer and
+    /// then by position.
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Builder for [`MerkleTree`].
+pub struct MerkleCommitmentBuilder<'a> {
+    transcript: &'a Transcript,
+    alg: HashAlgorithm,
+    commits: Vec<(Direction, RangeSet<usize>)>,
+}
+
+impl<'a> MerkleCommitmentBuilder<'a> {
+    /// Creates a new builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcript` - The transcript to commit to.
+    pub fn new(transcript: &'a Transcript) -> Self {
+        Self {
+            transcript,
+            alg: HashAlgorithm::default(),
+            commits: Vec::new(),
+        }
+    }
+
+    /// Sets the hash algorithm.
+    pub fn alg(&mut self, alg: HashAlgorithm) -> &mut Self {
+        self.alg = alg;
+        self
+    }
+
+    /// Returns the committed indices, in the order they were committed.
+    pub fn commits(&self) -> &[(Direction, RangeSet<usize>)] {
+        &self.commits
+    }
+
+    /// Builds the Merkle tree.
+    pub fn finalize(self) -> Result<MerkleTree, TranscriptCommitmentBuilderError> {
+        if !self.alg.is_supported() {
+            return Err(TranscriptCommitmentBuilderError::new(
+                ErrorKind::Algorithm,
+                format!("hash algorithm {:?} is not enabled", self.alg),
+            ));
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut leaves = Vec::with_capacity(self.commits.len());
+        let mut hashes = Vec::with_capacity(self.commits.len());
+
+        for (direction, idx) in self.commits {
+            let salt: [u8; SALT_LEN] = rng.gen();
+            let plaintext = self
+                .transcript
+                .get(direction, &idx)
+                .expect("index was checked when committed");
+
+            hashes.push(leaf_hash(
+                self.alg,
+                direction,
+                &idx,
+                &salt,
+                plaintext.data(),
+            ));
+            leaves.push(MerkleLeaf {
+                direction,
+                idx,
+                salt,
+            });
+        }
+
+        let mut layers = vec![hashes];
+        while layers.last().expect("at least one layer").len() > 1 {
+            let layer = layers.last().expect("at least one layer");
+            let next = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(self.alg, left, right),
+                    [node] => *node,
+                    _ => unreachable!("chunks are at most 2 elements"),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Ok(MerkleTree {
+            alg: self.alg,
+            leaves,
+            layers,
+        })
+    }
+}
+
+impl TranscriptCommitmentBuilder for MerkleCommitmentBuilder<'_> {
+    fn commit(
+        &mut self,
+        ranges: &dyn ToRangeSet<usize>,
+        direction: Direction,
+    ) -> Result<&mut Self, TranscriptCommitmentBuilderError> {
+        let len = self.transcript.len_of_direction(direction);
+        push_commit(&mut self.commits, len, ranges, direction)?;
+
+        Ok(self)
+    }
+
+    fn build(self) -> Result<Box<dyn TranscriptCommitment>, TranscriptCommitmentBuilderError> {
+        Ok(Box::new(self.finalize()?))
+    }
+}
+
+/// Error for [`MerkleTree::prove`] and [`MerkleCommitment::verify`].
+#[derive(Debug, thiserror::Error)]
+pub enum MerkleProofError {
+    /// The leaf does not exist in the tree.
+    #[error("leaf {0} does not exist")]
+    UnknownLeaf(usize),
+    /// A leaf was opened more than once.
+    #[error("leaf is opened more than once")]
+    DuplicateLeaf,
+    /// The ranges were not committed.
+    #[error("{direction} ranges were not committed: {idx:?}")]
+    NotCommitted {
+        /// Direction of the ranges.
+        direction: Direction,
+        /// The ranges.
+        idx: RangeSet<usize>,
+    },
+    /// The opened leaf is out of bounds of the transcript.
+    #[error("leaf {0} is out of bounds of the transcript")]
+    OutOfBounds(usize),
+    /// The opened leaf is not authenticated in the transcript.
+    #[error("leaf {0} contains unauthenticated data")]
+    Unauthenticated(usize),
+    /// The proof does not contain the expected number of hashes.
+    #[error("malformed multi-proof")]
+    MalformedProof,
+    /// The recomputed root does not match the commitment.
+    #[error("root mismatch")]
+    RootMismatch,
+    /// Authenticated data in the transcript is not covered by an opened leaf.
+    #[error("authenticated {direction} data is not covered by an opened leaf: {idx:?}")]
+    Uncovered {
+        /// Direction of the uncovered data.
+        direction: Direction,
+        /// Index of the uncovered data.
+        idx: RangeSet<usize>,
+    },
+    /// The hash algorithm is not enabled.
+    #[error("hash algorithm {0:?} is not enabled")]
+    UnsupportedAlgorithm(HashAlgorithm),
+}
+
+/// Computes the hash of a leaf.
+fn leaf_hash(
+    alg: HashAlgorithm,
+    direction: Direction,
+    idx: &RangeSet<usize>,
+    salt: &[u8; SALT_LEN],
+    plaintext: &[u8],
+) -> [u8; 32] {
+    let mut encoded_idx = Vec::with_capacity(8 + idx.len_ranges() * 16);
+    encoded_idx.extend_from_slice(&(idx.len_ranges() as u64).to_le_bytes());
+    for range in idx.iter_ranges() {
+        encoded_idx.extend_from_slice(&(range.start as u64).to_le_bytes());
+        encoded_idx.extend_from_slice(&(range.end as u64).to_le_bytes());
+    }
+
+    alg.digest(&[
+        LEAF_PREFIX,
+        &[direction as u8],
+        &encoded_idx,
+        salt,
+        plaintext,
+    ])
+    .expect("algorithm is supported")
+}
+
+/// Computes the hash of an internal node.
+fn node_hash(alg: HashAlgorithm, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    alg.digest(&[NODE_PREFIX, left, right])
+        .expect("algorithm is supported")
+}
+
+/// Recomputes the root from a sorted, deduplicated set of leaf hashes and a
+/// multi-proof.
+fn compute_root(
+    alg: HashAlgorithm,
+    leaf_count: usize,
+    mut nodes: Vec<(usize, [u8; 32])>,
+    proof: &[[u8; 32]],
+) -> Result<[u8; 32], MerkleProofError> {
+    let mut proof = proof.iter();
+    let mut layer_len = leaf_count;
+
+    while layer_len > 1 {
+        let mut next = Vec::with_capacity(nodes.len());
+        let mut i = 0;
+
+        while i < nodes.len() {
+            let (index, hash) = nodes[i];
+            let sibling = index ^ 1;
+
+            let parent = if index % 2 == 0 && nodes.get(i + 1).map(|n| n.0) == Some(sibling) {
+                i += 1;
+                node_hash(alg, &hash, &nodes[i].1)
+            } else if sibling >= layer_len {
+                hash
+            } else {
+                let sibling_hash = proof.next().ok_or(MerkleProofError::MalformedProof)?;
+                if index % 2 == 0 {
+                    node_hash(alg, &hash, sibling_hash)
+                } else {
+                    node_hash(alg, sibling_hash, &hash)
+                }
+            };
+
+            next.push((index / 2, parent));
+            i += 1;
+        }
+
+        nodes = next;
+        layer_len = layer_len.div_ceil(2);
+    }
+
+    if proof.next().is_some() {
+        return Err(MerkleProofError::MalformedProof);
+    }
+
+    Ok(nodes[0].1)
+}
+
+#[cfg(all(test, any(feature = "sha256", feature = "blake3")))]
+mod tests {
+    use rstest::{fixture, rstest};
+
+    use super::*;
+
+    #[fixture]
+    fn transcript() -> Transcript {
+        Transcript::new(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.1 200 OK\r\n\r\n")
+    }
+
+    #[fixture]
+    fn tree(transcript: Transcript) -> MerkleTree {
+        let mut builder = MerkleCommitmentBuilder::new(&transcript);
+        for i in 0..7 {
+            builder.commit(&(i..i + 2), Direction::Sent).unwrap();
+        }
+        builder
+            .commit(&(0..8), Direction::Received)
+            .unwrap()
+            .commit(&RangeSet::from([9..12, 13..15]), Direction::Received)
+            .unwrap();
+
+        builder.finalize().unwrap()
+    }
+
+    #[rstest]
+    #[case::single(vec![0])]
+    #[case::last(vec![8])]
+    #[case::siblings(vec![2, 3])]
+    #[case::scattered(vec![0, 3, 4, 8])]
+    #[case::all(vec![0, 1, 2, 3, 4, 5, 6, 7, 8])]
+    fn test_merkle_prove_verify(
+        transcript: Transcript,
+        tree: MerkleTree,
+        #[case] leaves: Vec<usize>,
+    ) {
+        let opening = tree.prove(&leaves).unwrap();
+
+        let mut sent = RangeSet::default();
+        let mut received = RangeSet::default();
+        for leaf in &opening.leaves {
+            match leaf.direction {
+                Direction::Sent => sent = sent.union(&leaf.idx),
+                Direction::Received => received = received.union(&leaf.idx),
+            }
+        }
+
+        let partial = transcript.to_partial(sent, received);
+
+        tree.commitment().verify(&opening, &partial).unwrap();
+    }
+
+    #[rstest]
+    fn test_merkle_prove_ranges(transcript: Transcript, tree: MerkleTree) {
+        let opening = tree
+            .prove_ranges(&[
+                (Direction::Sent, RangeSet::from(0..2)),
+                (Direction::Received, RangeSet::from([9..12, 13..15])),
+            ])
+            .unwrap();
+
+        let partial = transcript.to_partial(RangeSet::from(0..2), RangeSet::from([9..12, 13..15]));
+
+        tree.commitment().verify(&opening, &partial).unwrap();
+
+        assert!(matches!(
+            tree.prove_ranges(&[(Direction::Sent, RangeSet::from(0..3))]),
+            Err(MerkleProofError::NotCommitted { .. })
+        ));
+    }
+
+    #[rstest]
+    fn test_merkle_compact_proof(tree: MerkleTree) {
+        // Opening every leaf requires no sibling hashes.
+        let opening = tree
+            .prove(&(0..tree.leaves().len()).collect::<Vec<_>>())
+            .unwrap();
+        assert!(opening.proof.is_empty());
+
+        // Siblings share the rest of the path.
+        let single = tree.prove(&[2]).unwrap();
+        let pair = tree.prove(&[2, 3]).unwrap();
+        assert_eq!(pair.proof.len(), single.proof.len() - 1);
+    }
+
+    #[rstest]
+    fn test_merkle_uncovered(transcript: Transcript, tree: MerkleTree) {
+        let opening = tree.prove(&[0]).unwrap();
+        let partial = transcript.to_partial(RangeSet::from(0..3), RangeSet::default());
+
+        assert!(matches!(
+            tree.commitment().verify(&opening, &partial),
+            Err(MerkleProofError::Uncovered {
+                direction: Direction::Sent,
+                ..
+            })
+        ));
+    }
+
+    #[rstest]
+    fn test_merkle_tampered(transcript: Transcript, tree: MerkleTree) {
+        let opening = tree.prove(&[0]).unwrap();
+
+        let tampered = Transcript::new(b"PUT / HTTP/1.1\r\n\r\n", transcript.received());
+        let partial = tampered.to_partial(RangeSet::from(0..2), RangeSet::default());
+
+        assert!(matches!(
+            tree.commitment().verify(&opening, &partial),
+            Err(MerkleProofError::RootMismatch)
+        ));
+
+        let mut opening = tree.prove(&[0, 5]).unwrap();
+        opening.proof.pop();
+        let partial = transcript.to_partial(RangeSet::from([0..2, 5..7]), RangeSet::default());
+
+        assert!(matches!(
+            tree.commitment().verify(&opening, &partial),
+            Err(MerkleProofError::MalformedProof)
+        ));
+    }
+
+    #[rstest]
+    fn test_merkle_serialization(tree: MerkleTree) {
+        let commitment = tree.commitment();
+        let opening = tree.prove(&[1, 6]).unwrap();
+
+        let bytes = bincode::serialize(&commitment).unwrap();
+        assert_eq!(
+            commitment,
+            bincode::deserialize::<MerkleCommitment>(&bytes).unwrap()
+        );
+
+        let bytes = bincode::serialize(&opening).unwrap();
+        assert_eq!(
+            opening,
+            bincode::deserialize::<MerkleOpening>(&bytes).unwrap()
+        );
+    }
+}
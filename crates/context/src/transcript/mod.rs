@@ -1,5 +1,14 @@
+pub mod analysis;
 pub mod commit;
+pub mod estimate;
+pub mod hash;
+pub mod merkle;
+#[allow(clippy::module_inception)]
 pub mod transcript;
 
 pub use transcript::*;
-pub use commit::*;
\ No newline at end of file
+pub use analysis::{CommitmentSet, Disclosure};
+pub use commit::*;
+pub use estimate::{CostModel, CostReport, DryRunBuilder, LargestCommitment};
+pub use hash::{HashAlgorithm, HashCommitment, HashCommitmentBuilder, HashOpening};
+pub use merkle::{MerkleCommitment, MerkleCommitmentBuilder, MerkleOpening, MerkleTree};
\ No newline at end of file
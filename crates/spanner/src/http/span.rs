@@ -14,6 +14,9 @@ use crate::{
 
 const MAX_HEADERS: usize = 128;
 
+/// The body content ranges, the body structure ranges (e.g. chunk boundaries), and the trailer ranges.
+type BodyRanges = (RangeSet<usize>, Option<RangeSet<usize>>, Option<RangeSet<usize>>);
+
 /// Parses an HTTP request.
 pub fn parse_request(src: &[u8]) -> Result<Request, ParseError> {
     parse_request_from_bytes(&Bytes::copy_from_slice(src), 0)
@@ -45,17 +48,13 @@ pub(crate) fn parse_request_from_bytes(src: &Bytes, offset: usize) -> Result<Req
         (method, path, head_end)
     };
 
-    let request_line_end = src[offset..]
-        .windows(2)
-        .position(|w| w == b"\r\n")
-        .expect("request line is terminated with CRLF");
-    let request_line_range = offset..offset + request_line_end + 2;
+    let request_line_range = offset..line_end(src, offset)?;
 
     let headers = headers
         .iter()
         .take_while(|h| *h != &httparse::EMPTY_HEADER)
         .map(|header| from_header(src, header))
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     // httparse allocates a new buffer to store the method for performance reasons,
     // so we have to search for the span in the source. This is quick as the method
@@ -63,13 +62,13 @@ pub(crate) fn parse_request_from_bytes(src: &Bytes, offset: usize) -> Result<Req
     let method = src[offset..]
         .windows(method.len())
         .find(|w| *w == method.as_bytes())
-        .expect("method is present");
+        .ok_or_else(|| ParseError("method missing from request line".to_string()))?;
 
     let mut request = Request {
-        span: Span::new_bytes(src.clone(), (offset..head_end).into()),
+        span: Span::new_bytes(src.clone(), offset..head_end),
         request: RequestLine {
-            span: Span::new_str(src.clone(), request_line_range),
-            method: Method(Span::new_str(src.clone(), get_span_range(src, method))),
+            span: Span::new_str(src.clone(), request_line_range)?,
+            method: Method(Span::new_str(src.clone(), get_span_range(src, method))?),
             target: Target(Span::new_from_str(src.clone(), path)),
         },
         headers,
@@ -97,7 +96,7 @@ pub(crate) fn parse_request_from_bytes(src: &Bytes, offset: usize) -> Result<Req
             .unwrap_or_default();
 
         request.body = Some(parse_body(src, range.clone().into(), content_type)?);
-        request.span = Span::new_bytes(src.clone(), (offset..range.end).into());
+        request.span = Span::new_bytes(src.clone(), offset..range.end);
     }
 
     Ok(request)
@@ -115,7 +114,7 @@ pub(crate) fn parse_response_from_bytes(
 ) -> Result<Response, ParseError> {
     let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
 
-    let (reason, code, head_end) = {
+    let (reason, head_end) = {
         let mut response = httparse::Response::new(&mut headers);
 
         let head_end = match response.parse(&src[offset..]) {
@@ -126,41 +125,40 @@ pub(crate) fn parse_response_from_bytes(
             Err(err) => return Err(ParseError(err.to_string())),
         };
 
-        let code = response
-            .code
-            .ok_or_else(|| ParseError("code missing from response".to_string()))
-            .map(|c| c.to_string())?;
+        if response.code.is_none() {
+            return Err(ParseError("code missing from response".to_string()));
+        }
 
         let reason = response
             .reason
             .ok_or_else(|| ParseError("reason missing from response".to_string()))?;
 
-        (reason, code, head_end)
+        (reason, head_end)
     };
 
-    let status_line_end = src[offset..]
-        .windows(2)
-        .position(|w| w == b"\r\n")
-        .expect("status line is terminated with CRLF");
-    let status_line_range = offset..offset + status_line_end + 2;
+    let status_line_range = offset..line_end(src, offset)?;
 
     let headers = headers
         .iter()
         .take_while(|h| *h != &httparse::EMPTY_HEADER)
         .map(|header| from_header(src, header))
-        .collect();
+        .collect::<Result<_, _>>()?;
 
-    // httparse doesn't preserve the response code span, so we find it.
-    let code = src[offset..]
-        .windows(3)
-        .find(|w| *w == code.as_bytes())
-        .expect("code is present");
+    // httparse doesn't preserve the response code span, so we find it. The
+    // status line is the version and the three digit code, separated by a
+    // single space.
+    let code_start = src[status_line_range.clone()]
+        .iter()
+        .position(|b| *b == b' ')
+        .map(|i| offset + i + 1)
+        .ok_or_else(|| ParseError("code missing from status line".to_string()))?;
+    let code_range = code_start..code_start + 3;
 
     let mut response = Response {
-        span: Span::new_bytes(src.clone(), (offset..head_end).into()),
+        span: Span::new_bytes(src.clone(), offset..head_end),
         status: Status {
-            span: Span::new_str(src.clone(), status_line_range),
-            code: Code(Span::new_str(src.clone(), get_span_range(src, code))),
+            span: Span::new_str(src.clone(), status_line_range)?,
+            code: Code(Span::new_str(src.clone(), code_range)?),
             reason: Reason(Span::new_from_str(src.clone(), reason)),
         },
         headers,
@@ -192,15 +190,16 @@ pub(crate) fn parse_response_from_bytes(
     }
     if let Some(structure_ranges) = structure_ranges {
         response.boundaries = Some(structure_ranges.iter_ranges().map(|range| {
-            Boundary(Span::new_str(src.clone(), range.clone().into()))
-        }).collect());
+            Ok(Boundary(Span::new_str(src.clone(), range.clone())?))
+        }).collect::<Result<_, ParseError>>()?);
         response.span = Span::new_bytes_set(src.clone(), (offset..structure_ranges.end().unwrap() + 2).into());
 
         if let Some(trailer_ranges) = trailer_ranges {
             if !trailer_ranges.is_empty() {
+                response.trailers = Some(parse_trailers(src, trailer_ranges.min().unwrap())?);
                 response.span = Span::new_bytes_set(src.clone(), (offset..trailer_ranges.end().unwrap() + 2).into());
             } else {
-                response.span = Span::new_bytes(src.clone(), (offset..structure_ranges.end().unwrap() + 2).into());
+                response.span = Span::new_bytes(src.clone(), offset..structure_ranges.end().unwrap() + 2);
             }
         }
     }
@@ -208,24 +207,53 @@ pub(crate) fn parse_response_from_bytes(
     Ok(response)
 }
 
+/// Returns the end of the line starting at `start`, including its CRLF.
+fn line_end(src: &Bytes, start: usize) -> Result<usize, ParseError> {
+    let lf = src[start..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|i| start + i)
+        .ok_or_else(|| ParseError("line is not terminated".to_string()))?;
+
+    if lf == start || src[lf - 1] != b'\r' {
+        return Err(ParseError("line is not terminated with CRLF".to_string()));
+    }
+
+    Ok(lf + 1)
+}
+
+/// Parses the trailer section of a chunked body starting at `start`.
+fn parse_trailers(src: &Bytes, start: usize) -> Result<Vec<Header>, ParseError> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+
+    match httparse::parse_headers(&src[start..], &mut headers) {
+        Ok(httparse::Status::Complete(_)) => {}
+        Ok(httparse::Status::Partial) => {
+            return Err(ParseError("incomplete trailer section".to_string()))
+        }
+        Err(err) => return Err(ParseError(err.to_string())),
+    }
+
+    headers
+        .iter()
+        .take_while(|h| *h != &httparse::EMPTY_HEADER)
+        .map(|header| from_header(src, header))
+        .collect()
+}
+
 /// Converts a `httparse::Header` to a `Header`.
-fn from_header(src: &Bytes, header: &httparse::Header) -> Header {
+fn from_header(src: &Bytes, header: &httparse::Header) -> Result<Header, ParseError> {
     let name_range = get_span_range(src, header.name.as_bytes());
     let value_range = get_span_range(src, header.value);
 
-    let crlf_idx = src[value_range.end..]
-        .windows(2)
-        .position(|b| b == b"\r\n")
-        .expect("CRLF is present in a valid header");
-
     // Capture the entire header including trailing whitespace and the CRLF.
-    let header_range = name_range.start..value_range.end + crlf_idx + 2;
+    let header_range = name_range.start..line_end(src, value_range.end)?;
 
-    Header {
-        span: Span::new_bytes(src.clone(), header_range.into()),
-        name: HeaderName(Span::new_str(src.clone(), name_range)),
-        value: HeaderValue(Span::new_bytes(src.clone(), value_range.into())),
-    }
+    Ok(Header {
+        span: Span::new_bytes(src.clone(), header_range),
+        name: HeaderName(Span::new_str(src.clone(), name_range)?),
+        value: HeaderValue(Span::new_bytes(src.clone(), value_range)),
+    })
 }
 
 /// Calculates the length of the request body according to RFC 9112, section 6.
@@ -257,7 +285,7 @@ fn request_body_len(request: &Request) -> Result<usize, ParseError> {
 
 /// Calculates the range(s) of the response body according to RFC 9112, section 6.
 /// Returns a tuple of the body content ranges, the body structure ranges (e.g. chunk boundaries), and the trailer ranges.
-fn response_body_ranges(response: &Response, src: &Bytes, head_end: usize) -> Result<(RangeSet<usize>, Option<RangeSet<usize>>, Option<RangeSet<usize>>), ParseError> {
+fn response_body_ranges(response: &Response, src: &Bytes, head_end: usize) -> Result<BodyRanges, ParseError> {
     // Any response to a HEAD request and any response with a 1xx (Informational), 204 (No Content), or 304 (Not Modified)
     // status code is always terminated by the first empty line after the header fields, regardless of the header fields
     // present in the message, and thus cannot contain a message body or trailer section.
@@ -266,7 +294,7 @@ fn response_body_ranges(response: &Response, src: &Bytes, head_end: usize) -> Re
         .code
         .as_str()
         .parse::<usize>()
-        .expect("code is valid utf-8")
+        .map_err(|err| ParseError(format!("failed to parse status code: {err}")))?
     {
         100..=199 | 204 | 304 => return Ok((RangeSet::new(&[]), None, None)),
         _ => {}
@@ -277,7 +305,7 @@ fn response_body_ranges(response: &Response, src: &Bytes, head_end: usize) -> Re
             b"chunked" => {
                 Ok(chunked_body_ranges(src, head_end)?)
             }
-            _ => return Err(ParseError(format!(
+            _ => Err(ParseError(format!(
                 "Transfer-Encoding not supported: {}",
                 std::str::from_utf8(h.value.0.as_bytes())?
             ))),
@@ -289,7 +317,7 @@ fn response_body_ranges(response: &Response, src: &Bytes, head_end: usize) -> Re
             .parse::<usize>()
             .map_err(|err| ParseError(format!("failed to parse Content-Length value: {err}")))?;
 
-        Ok((RangeSet::new(&[head_end..head_end + len]), None, None))
+        Ok((RangeSet::from(head_end..head_end + len), None, None))
     } else {
         // If this is a response message and none of the above are true, then there is no way to
         // determine the length of the message body except by reading it until the connection is closed.
@@ -301,43 +329,45 @@ fn response_body_ranges(response: &Response, src: &Bytes, head_end: usize) -> Re
     }
 }
 
-fn chunked_body_ranges(src: &Bytes, head_end: usize) -> Result<(RangeSet<usize>, Option<RangeSet<usize>>, Option<RangeSet<usize>>), ParseError> {
+fn chunked_body_ranges(src: &Bytes, head_end: usize) -> Result<BodyRanges, ParseError> {
     let mut content_ranges: Vec<Range<usize>> = Vec::new();
     let mut structure_ranges: Vec<Range<usize>> = Vec::new();
     let mut trailer_ranges: Vec<Range<usize>> = Vec::new();
     let mut pos = head_end;
+    let remaining = |pos: usize| {
+        src.get(pos..)
+            .ok_or_else(|| ParseError(format!("chunk at {pos} exceeds source {}", src.len())))
+    };
     
     // At the beginning of each chunk, a string of hex digits indicate the size of the chunk-data
     // in octets, followed by \r\n and then the chunk itself, followed by another \r\n.
     loop {
         // Read the hex digits until we encounter a CRLF
-        let hex_digits = src[pos..]
-            .iter()
-            .enumerate()
-            .take_while(|(i, b)| {
-                let next = src.get(pos + i + 1);
-                !(**b == b'\r' && next == Some(&b'\n'))
-            })
-            .map(|(_, &b)| b)
-            .collect::<Vec<u8>>();
+        let hex_len = remaining(pos)?
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(|| ParseError("chunk size is not terminated with CRLF".to_string()))?;
+        let hex_digits = &src[pos..pos + hex_len];
         
         let chunk_size = usize::from_str_radix(
-            std::str::from_utf8(&hex_digits)?,
+            std::str::from_utf8(hex_digits)?,
             16,
         ).map_err(|err| ParseError(format!("failed to parse chunk size: {err}")))?;
 
         // The range of the chunk boundary is the length of the hex digits + CRLF.
-        structure_ranges.push(pos..pos + hex_digits.len() + 2);
-        pos += hex_digits.len() + 2;
+        structure_ranges.push(pos..pos + hex_len + 2);
+        pos += hex_len + 2;
 
         // The terminating chunk is a zero-length chunk.
         if chunk_size == 0 {
             // Parse trailing headers
-            if src[pos..].windows(2).next() != Some(b"\r\n") {
-                let trailer_end = src[pos..]
+            let rest = remaining(pos)?;
+            if rest.windows(2).next() != Some(b"\r\n") {
+                // The trailer section includes the CRLF of its last field line.
+                let trailer_end = rest
                     .windows(4)
                     .position(|w| w == b"\r\n\r\n")
-                    .ok_or_else(|| ParseError("missing trailer end".to_string()))? + pos;
+                    .ok_or_else(|| ParseError("missing trailer end".to_string()))? + pos + 2;
                 trailer_ranges.push(pos..trailer_end);
             }
 
@@ -345,8 +375,12 @@ fn chunked_body_ranges(src: &Bytes, head_end: usize) -> Result<(RangeSet<usize>,
         }
 
         // Skip past the chunk header (hex digits + CRLF) and the chunk data (+ CRLF)
-        content_ranges.push(pos..pos + chunk_size);
-        pos += chunk_size + 2;
+        let chunk_end = pos
+            .checked_add(chunk_size)
+            .filter(|end| *end <= src.len())
+            .ok_or_else(|| ParseError(format!("chunk of size {chunk_size} exceeds source {}", src.len())))?;
+        content_ranges.push(pos..chunk_end);
+        pos = chunk_end + 2;
     }
 
     Ok((RangeSet::new(&content_ranges), Some(RangeSet::new(&structure_ranges)), Some(RangeSet::new(&trailer_ranges))))
@@ -580,6 +614,21 @@ mod tests {
         assert_eq!(value.span(), "{\"foo\": \"bar\"}");
     }
 
+    #[test]
+    fn test_parse_malformed_messages() {
+        // Non-UTF-8 target.
+        assert!(parse_request(b"GET /\xff HTTP/1.1\r\n\r\n").is_err());
+        // Line terminated with LF only.
+        assert!(parse_request(b"GET / HTTP/1.1\nHost: a\r\n\r\n").is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 0\n\r\n").is_err());
+        // Chunks exceeding the source.
+        assert!(parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nab"
+        )
+        .is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nab").is_err());
+    }
+
     #[test]
     fn test_parse_chunked_response() {
         let res = parse_response(TEST_CHUNKED_RESPONSE).unwrap();
@@ -590,4 +639,20 @@ mod tests {
 
         assert_eq!(span.as_bytes(), b"Hello, World!");
     }
+
+    #[test]
+    fn test_parse_chunked_response_trailers() {
+        let src = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                    2\r\nab\r\n0\r\nDigest: x\r\nExpires: y\r\n\r\n\
+                    HTTP/1.1 204 No Content\r\n\r\n";
+        let res = parse_response(src).unwrap();
+
+        let trailers = res.trailers.as_ref().unwrap();
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers[0].span.as_bytes(), b"Digest: x\r\n");
+        assert_eq!(trailers[1].name.as_str(), "Expires");
+        assert_eq!(trailers[1].value.as_bytes(), b"y");
+        assert_eq!(res.span.len(), src.len() - 27);
+        assert!(!res.without_data().contains(&66));
+    }
 }
@@ -404,7 +404,7 @@ impl Response {
             .filter(|h| h.name.0.as_str().eq_ignore_ascii_case(name))
     }
 
-    /// Returns the indices of the response excluding the headers and body.
+    /// Returns the indices of the response excluding the headers, body and trailers.
     pub fn without_data(&self) -> RangeSet<usize> {
         let mut indices = self.span.indices.clone();
         for header in &self.headers {
@@ -413,6 +413,9 @@ impl Response {
         if let Some(body) = &self.body {
             indices = indices.difference(body.span.indices());
         }
+        for trailer in self.trailers.iter().flatten() {
+            indices = indices.difference(trailer.span.indices());
+        }
         indices
     }
 
@@ -426,6 +429,9 @@ impl Response {
         if let Some(body) = &mut self.body {
             body.offset(offset);
         }
+        for trailer in self.trailers.iter_mut().flatten() {
+            trailer.offset(offset);
+        }
     }
 }
 
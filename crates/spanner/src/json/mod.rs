@@ -10,7 +10,7 @@
 //! # Example
 //!
 //! ```
-//! use spansy::{json, Spanned};
+//! use spanner::{json, Spanned};
 //!
 //! let src = "{\"foo\": {\"bar\": [42, 14]}}";
 //!
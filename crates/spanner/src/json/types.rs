@@ -68,8 +68,8 @@ impl JsonValue {
     /// # Example
     ///
     /// ```
-    /// use spansy::json::parse_str;
-    /// use spansy::Spanned;
+    /// use spanner::json::parse_str;
+    /// use spanner::Spanned;
     ///
     /// let src = "{\"foo\": {\"bar\": [42, 14]}}";
     ///
@@ -91,7 +91,7 @@ impl JsonValue {
 
     /// Get a reference to the key-value pair using the given path.
     pub fn get_keyvalue(&self, path: &str) -> Option<KeyValue> {
-        let  path_without_last_key = path.rsplitn(2, '.').nth(1);
+        let  path_without_last_key = path.rsplit_once('.').map(|(parent, _)| parent);
 
         if path_without_last_key.is_none() {
             if let JsonValue::Object(obj) = self {
@@ -102,7 +102,7 @@ impl JsonValue {
         }
 
         let  path_without_last_key = path_without_last_key.unwrap();
-        let last_key = path.rsplitn(2, '.').nth(0).unwrap();
+        let last_key = path.rsplit('.').next().unwrap();
 
         match self {
             JsonValue::Array(v) => {
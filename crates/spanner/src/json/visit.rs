@@ -5,8 +5,8 @@ use super::{types, types::JsonValue};
 /// # Example
 ///
 /// ```
-/// use spansy::json::{parse_str, Number, JsonVisit};
-/// use spansy::Spanned;
+/// use spanner::json::{parse_str, Number, JsonVisit};
+/// use spanner::Spanned;
 ///
 /// struct DigitReplacer<'a, 'b> {
 ///     src: &'a mut String,
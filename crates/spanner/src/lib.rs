@@ -150,21 +150,19 @@ impl<T: ?Sized> Span<T> {
 impl Span<str> {
     /// Create a new string span.
     ///
+    /// Returns an error if the span is not a valid UTF-8 string.
+    ///
     /// # Panics
     ///
-    /// Panics if the given range is not within the source bytes, or
-    /// if the span is not a valid UTF-8 string.
-    pub(crate) fn new_str(src: Bytes, range: Range<usize>) -> Self {
-        assert!(
-            std::str::from_utf8(&src[range.clone()]).is_ok(),
-            "span is not a valid UTF-8 string"
-        );
+    /// Panics if the given range is not within the source bytes.
+    pub(crate) fn new_str(src: Bytes, range: Range<usize>) -> Result<Self, ParseError> {
+        std::str::from_utf8(&src[range.clone()])?;
 
-        Self {
+        Ok(Self {
             data: src.slice(range.clone()),
             indices: range.into(),
             _pd: PhantomData,
-        }
+        })
     }
 
     /// Create a new string span from a string slice.
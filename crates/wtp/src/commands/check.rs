@@ -0,0 +1,114 @@
+use std::{io::Write, path::PathBuf};
+
+use clap::Args;
+use context::http::{HttpEnforceError, HttpEnforcer, HttpTemplate};
+
+use crate::{
+    commands::context::write_json,
+    error::CliError,
+    input::{read_config, TranscriptArgs},
+};
+
+/// Arguments of the `check` command.
+#[derive(Debug, Clone, Args)]
+pub(crate) struct CheckArgs {
+    #[command(flatten)]
+    pub transcript: TranscriptArgs,
+    /// The template, as TOML or JSON.
+    #[arg(short, long)]
+    pub template: PathBuf,
+    /// Prints the enforced context as JSON if the transcript matches.
+    #[arg(long)]
+    pub context: bool,
+}
+
+/// Enforces a template on a transcript, printing every mismatch.
+pub(crate) fn check(args: &CheckArgs, out: &mut impl Write) -> Result<(), CliError> {
+    let transcript = args.transcript.load_partial()?;
+    let template: HttpTemplate = read_config(&args.template)?;
+
+    match HttpEnforcer::from_template(transcript, template).build() {
+        Ok(context) if args.context => write_json(out, &context),
+        Ok(context) => {
+            writeln!(
+                out,
+                "ok: {} exchanges match the template",
+                context.exchanges().len()
+            )?;
+            Ok(())
+        }
+        Err(HttpEnforceError::Mismatch(mismatches)) => {
+            for mismatch in &mismatches {
+                writeln!(out, "{mismatch}")?;
+            }
+            Err(CliError::Mismatch(mismatches.len()))
+        }
+        Err(err) => Err(CliError::Enforce(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use context::http::{HttpTranscriptBuilder, RequestBuilder, ResponseBuilder};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{input::Format, tests::TempDir};
+
+    const TEMPLATE: &[u8] = b"\
+        version = 1\n\
+        \n\
+        [[requests]]\n\
+        method = \"GET\"\n\
+        target = \"/balance\"\n\
+        \n\
+        [[responses]]\n\
+        status = 200\n\
+        body = { type = \"json\", shape = { balance = \"*\" } }\n";
+
+    fn args(dir: &TempDir, target: &str, context: bool) -> CheckArgs {
+        let built = HttpTranscriptBuilder::new()
+            .request(RequestBuilder::get(target))
+            .response(ResponseBuilder::ok().json(&serde_json::json!({"balance": 100})))
+            .build();
+
+        CheckArgs {
+            transcript: TranscriptArgs {
+                sent: dir.write("sent", built.transcript.sent()),
+                received: Some(dir.write("received", built.transcript.received())),
+                format: Format::Raw,
+            },
+            template: dir.write("template.toml", TEMPLATE),
+            context,
+        }
+    }
+
+    #[rstest]
+    fn test_check() {
+        let dir = TempDir::new("check");
+
+        let mut out = Vec::new();
+        check(&args(&dir, "/balance", false), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ok: 1 exchanges match the template\n"
+        );
+
+        let mut out = Vec::new();
+        check(&args(&dir, "/balance", true), &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert!(json.get("exchanges").is_some());
+    }
+
+    #[rstest]
+    fn test_check_mismatch() {
+        let dir = TempDir::new("check-mismatch");
+
+        let mut out = Vec::new();
+        let err = check(&args(&dir, "/other", false), &mut out).err().unwrap();
+
+        assert!(matches!(err, CliError::Mismatch(1)));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("request 0: "), "{out}");
+    }
+}
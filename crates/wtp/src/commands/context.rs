@@ -0,0 +1,99 @@
+use std::io::Write;
+
+use clap::Args;
+use context::http::HttpContext;
+
+use crate::{error::CliError, input::TranscriptArgs};
+
+/// Arguments of the `context` command.
+#[derive(Debug, Clone, Args)]
+pub(crate) struct ContextArgs {
+    #[command(flatten)]
+    pub transcript: TranscriptArgs,
+}
+
+/// Prints the [`HttpContext`] of a transcript as JSON.
+pub(crate) fn context(args: &ContextArgs, out: &mut impl Write) -> Result<(), CliError> {
+    let transcript = args.transcript.load_partial()?;
+    let context = HttpContext::builder(transcript).build()?;
+
+    write_json(out, &context)
+}
+
+/// Writes a value as pretty-printed JSON.
+pub(crate) fn write_json(
+    out: &mut impl Write,
+    value: &impl serde::Serialize,
+) -> Result<(), CliError> {
+    serde_json::to_writer_pretty(&mut *out, value)
+        .map_err(|err| CliError::Encode(err.to_string()))?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use context::{
+        http::{HttpTranscriptBuilder, RequestBuilder, ResponseBuilder},
+        transcript::CompressedPartialTranscript,
+    };
+    use rangeset::{Difference, RangeSet};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{input::Format, tests::TempDir};
+
+    #[rstest]
+    fn test_context() {
+        let dir = TempDir::new("context");
+        let built = HttpTranscriptBuilder::new()
+            .request(RequestBuilder::get("/balance").header("Authorization", "secret"))
+            .response(ResponseBuilder::ok().json(&serde_json::json!({"balance": 100})))
+            .build();
+
+        // Redact the value of the Authorization header.
+        let (len_sent, len_received) = built.transcript.len();
+        let value = &built.spans.requests[0].headers[0].value;
+        let partial = built.transcript.to_partial(
+            RangeSet::from(0..len_sent).difference(value),
+            RangeSet::from(0..len_received),
+        );
+        let args = ContextArgs {
+            transcript: TranscriptArgs {
+                sent: dir.write(
+                    "transcript.bin",
+                    &bincode::serialize(&CompressedPartialTranscript::from(partial)).unwrap(),
+                ),
+                received: None,
+                format: Format::Bincode,
+            },
+        };
+
+        let mut out = Vec::new();
+        context(&args, &mut out).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let context: HttpContext = serde_json::from_value(json).unwrap();
+        let exchange = &context.exchanges()[0];
+        assert_eq!(
+            exchange.request().target().revealed().map(String::as_str),
+            Some("/balance")
+        );
+        assert!(exchange
+            .request()
+            .header("Authorization")
+            .unwrap()
+            .revealed()
+            .is_none());
+        assert_eq!(
+            exchange
+                .response()
+                .unwrap()
+                .status()
+                .revealed()
+                .map(|status| status.as_u16()),
+            Some(200)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! Subcommands.
+
+pub(crate) mod check;
+pub(crate) mod context;
+pub(crate) mod parse;
+pub(crate) mod policy;
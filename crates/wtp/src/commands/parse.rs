@@ -0,0 +1,175 @@
+use std::io::Write;
+
+use clap::Args;
+use context::http::{Body, BodyContent, Header, HttpTranscript};
+use rangeset::{RangeSet, ToRangeSet};
+
+use crate::{error::CliError, input::TranscriptArgs};
+
+/// Arguments of the `parse` command.
+#[derive(Debug, Clone, Args)]
+pub(crate) struct ParseArgs {
+    #[command(flatten)]
+    pub transcript: TranscriptArgs,
+}
+
+/// Prints the spanned structure of a transcript.
+///
+/// Unauthenticated data is shown as `*`.
+pub(crate) fn parse(args: &ParseArgs, out: &mut impl Write) -> Result<(), CliError> {
+    let mut transcript = args.transcript.load_partial()?;
+    let http = HttpTranscript::parse_partial(&transcript)?;
+    transcript.set_unauthed(b'*');
+
+    let mut printer = Printer {
+        out,
+        src: transcript.sent_unsafe(),
+    };
+    for (index, request) in http.requests.iter().enumerate() {
+        printer.message(&format!("request {index}"), request)?;
+        printer.part("request line", &request.request)?;
+        printer.part("method", &request.request.method)?;
+        printer.part("target", &request.request.target)?;
+        printer.headers("header", &request.headers)?;
+        printer.body(request.body.as_ref())?;
+    }
+
+    printer.src = transcript.received_unsafe();
+    for (index, response) in http.responses.iter().enumerate() {
+        printer.message(&format!("response {index}"), response)?;
+        printer.part("status line", &response.status)?;
+        printer.part("code", &response.status.code)?;
+        printer.part("reason", &response.status.reason)?;
+        printer.headers("header", &response.headers)?;
+        for boundary in response.boundaries.iter().flatten() {
+            printer.part("chunk", boundary)?;
+        }
+        printer.body(response.body.as_ref())?;
+        printer.headers("trailer", response.trailers.as_deref().unwrap_or_default())?;
+    }
+
+    Ok(())
+}
+
+struct Printer<'a, W> {
+    out: &'a mut W,
+    src: &'a [u8],
+}
+
+impl<W: Write> Printer<'_, W> {
+    fn message(&mut self, name: &str, message: &impl ToRangeSet<usize>) -> Result<(), CliError> {
+        writeln!(self.out, "{name}: {}", ranges(&message.to_range_set()))?;
+        Ok(())
+    }
+
+    fn part(&mut self, name: &str, part: &impl ToRangeSet<usize>) -> Result<(), CliError> {
+        let idx = part.to_range_set();
+        writeln!(
+            self.out,
+            "  {name:<12} {:<12} \"{}\"",
+            ranges(&idx),
+            self.text(&idx)
+        )?;
+        Ok(())
+    }
+
+    fn headers(&mut self, name: &str, headers: &[Header]) -> Result<(), CliError> {
+        headers
+            .iter()
+            .try_for_each(|header| self.part(name, header))
+    }
+
+    fn body(&mut self, body: Option<&Body>) -> Result<(), CliError> {
+        let Some(body) = body else {
+            return Ok(());
+        };
+
+        let idx = body.to_range_set();
+        let kind = match body.content {
+            BodyContent::Json(_) => "json",
+            _ => "unknown",
+        };
+        writeln!(
+            self.out,
+            "  {:<12} {:<12} {kind}, {} bytes",
+            "body",
+            ranges(&idx),
+            idx.len()
+        )?;
+        Ok(())
+    }
+
+    /// Returns the escaped data of a part, without its line ending.
+    fn text(&self, idx: &RangeSet<usize>) -> String {
+        let data: Vec<u8> = idx
+            .iter_ranges()
+            .flat_map(|range| self.src[range].iter().copied())
+            .collect();
+
+        data.strip_suffix(b"\r\n")
+            .unwrap_or(&data)
+            .escape_ascii()
+            .to_string()
+    }
+}
+
+/// Formats ranges as `0..4, 6..8`.
+fn ranges(idx: &RangeSet<usize>) -> String {
+    idx.iter_ranges()
+        .map(|range| format!("{}..{}", range.start, range.end))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use context::http::{HttpTranscriptBuilder, RequestBuilder, ResponseBuilder};
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{input::Format, tests::TempDir};
+
+    #[rstest]
+    fn test_parse() {
+        let dir = TempDir::new("parse");
+        let built = HttpTranscriptBuilder::new()
+            .request(RequestBuilder::get("/").header("Host", "localhost"))
+            .response(
+                ResponseBuilder::ok()
+                    .json(&serde_json::json!({"a": 1}))
+                    .chunked(4)
+                    .trailer("Digest", "x"),
+            )
+            .build();
+        let args = ParseArgs {
+            transcript: TranscriptArgs {
+                sent: dir.write("sent", built.transcript.sent()),
+                received: Some(dir.write("received", built.transcript.received())),
+                format: Format::Raw,
+            },
+        };
+
+        let mut out = Vec::new();
+        parse(&args, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "request 0: 0..35\n\
+             \x20 request line 0..16        \"GET / HTTP/1.1\"\n\
+             \x20 method       0..3         \"GET\"\n\
+             \x20 target       4..5         \"/\"\n\
+             \x20 header       16..33       \"Host: localhost\"\n\
+             response 0: 0..112\n\
+             \x20 status line  0..17        \"HTTP/1.1 200 OK\"\n\
+             \x20 code         9..12        \"200\"\n\
+             \x20 reason       13..15       \"OK\"\n\
+             \x20 header       17..49       \"Content-Type: application/json\"\n\
+             \x20 header       49..77       \"Transfer-Encoding: chunked\"\n\
+             \x20 chunk        79..82       \"4\"\n\
+             \x20 chunk        88..91       \"3\"\n\
+             \x20 chunk        96..99       \"0\"\n\
+             \x20 body         82..86, 91..94 json, 7 bytes\n\
+             \x20 trailer      99..110      \"Digest: x\"\n"
+        );
+    }
+}
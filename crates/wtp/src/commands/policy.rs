@@ -0,0 +1,167 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use clap::Args;
+use context::{
+    http::{DisclosurePolicy, HttpTranscript},
+    transcript::CompressedPartialTranscript,
+};
+
+use crate::{
+    error::CliError,
+    input::{read_config, TranscriptArgs},
+};
+
+/// Arguments of the `policy` command.
+#[derive(Debug, Clone, Args)]
+pub(crate) struct PolicyArgs {
+    #[command(flatten)]
+    pub transcript: TranscriptArgs,
+    /// The disclosure policy, as TOML or JSON.
+    #[arg(short, long)]
+    pub policy: PathBuf,
+    /// Where to write the partial transcript, instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Writes the partial transcript as hex encoded bincode, which is read
+    /// with `--format hex-bincode`.
+    #[arg(long)]
+    pub hex: bool,
+}
+
+/// Applies a disclosure policy to a transcript and writes the resulting
+/// [`CompressedPartialTranscript`].
+///
+/// A summary of the disclosure, and the policy entries which matched nothing,
+/// are written to `log`.
+pub(crate) fn policy(
+    args: &PolicyArgs,
+    out: &mut impl Write,
+    log: &mut impl Write,
+) -> Result<(), CliError> {
+    let transcript = args.transcript.load()?;
+    let policy: DisclosurePolicy = read_config(&args.policy)?;
+
+    let http = HttpTranscript::parse(&transcript)?;
+    let compiled = policy.compile(&http)?;
+
+    for index in &compiled.unmatched {
+        writeln!(log, "warning: policy entry {index} matched nothing")?;
+    }
+    let (len_sent, len_received) = transcript.len();
+    writeln!(
+        log,
+        "revealed {} of {len_sent} sent bytes and {} of {len_received} received bytes",
+        compiled.sent.len(),
+        compiled.received.len()
+    )?;
+
+    let partial = CompressedPartialTranscript::from(compiled.to_partial(&transcript));
+    let mut encoded =
+        bincode::serialize(&partial).map_err(|err| CliError::Encode(err.to_string()))?;
+    if args.hex {
+        encoded = format!("{}\n", hex::encode(encoded)).into_bytes();
+    }
+
+    match &args.output {
+        Some(path) => fs::write(path, encoded).map_err(|source| CliError::Write {
+            path: path.clone(),
+            source,
+        }),
+        None => Ok(out.write_all(&encoded)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use context::{
+        http::{HttpTranscriptBuilder, RequestBuilder, ResponseBuilder},
+        transcript::PartialTranscript,
+    };
+    use rangeset::RangeSet;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{input::Format, tests::TempDir};
+
+    const POLICY: &[u8] = b"\
+        default = \"hidden\"\n\
+        \n\
+        [[reveal]]\n\
+        message = \"request\"\n\
+        select = { header = \"Host\" }\n\
+        \n\
+        [[reveal]]\n\
+        message = \"response\"\n\
+        select = { json = \"$.missing\" }\n";
+
+    #[rstest]
+    fn test_policy(#[values(false, true)] hex: bool) {
+        let dir = TempDir::new(&format!("policy-{hex}"));
+        let built = HttpTranscriptBuilder::new()
+            .request(RequestBuilder::get("/").header("Host", "localhost"))
+            .response(ResponseBuilder::ok().json(&serde_json::json!({"a": 1})))
+            .build();
+        let args = PolicyArgs {
+            transcript: TranscriptArgs {
+                sent: dir.write("sent", built.transcript.sent()),
+                received: Some(dir.write("received", built.transcript.received())),
+                format: Format::Raw,
+            },
+            policy: dir.write("policy.toml", POLICY),
+            output: None,
+            hex,
+        };
+
+        let mut out = Vec::new();
+        let mut log = Vec::new();
+        policy(&args, &mut out, &mut log).unwrap();
+
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "warning: policy entry 1 matched nothing\n\
+             revealed 17 of 35 sent bytes and 0 of 77 received bytes\n"
+        );
+
+        let loaded = TranscriptArgs {
+            sent: dir.write("partial", &out),
+            received: None,
+            format: if hex {
+                Format::HexBincode
+            } else {
+                Format::Bincode
+            },
+        };
+        let partial = loaded.load_partial().unwrap();
+        assert_eq!(
+            partial.sent_authed(),
+            &built.spans.requests[0].headers[0].span
+        );
+        assert_eq!(partial.received_authed(), &RangeSet::default());
+    }
+
+    #[rstest]
+    fn test_policy_output_file() {
+        let dir = TempDir::new("policy-output");
+        let args = PolicyArgs {
+            transcript: TranscriptArgs {
+                sent: dir.write("sent", b"GET / HTTP/1.1\r\n\r\n"),
+                received: Some(dir.write("received", b"")),
+                format: Format::Raw,
+            },
+            policy: dir.write("policy.json", br#"{"default": "structure"}"#),
+            output: Some(dir.path("partial.bin")),
+            hex: false,
+        };
+
+        let mut out = Vec::new();
+        policy(&args, &mut out, &mut Vec::new()).unwrap();
+        assert!(out.is_empty());
+
+        let partial: PartialTranscript = bincode::deserialize::<CompressedPartialTranscript>(
+            &fs::read(dir.path("partial.bin")).unwrap(),
+        )
+        .unwrap()
+        .into();
+        assert!(partial.is_complete());
+    }
+}
@@ -0,0 +1,62 @@
+use std::{io, path::PathBuf};
+
+use context::http::{HttpContextError, HttpEnforceError, PolicyError};
+
+/// Error for the command-line tool.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CliError {
+    /// A file could not be read.
+    #[error("failed to read {path}: {source}")]
+    Read {
+        /// Path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// A file could not be written.
+    #[error("failed to write {path}: {source}")]
+    Write {
+        /// Path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// A file could not be decoded in the given format.
+    #[error("failed to decode {path}: {reason}")]
+    Decode {
+        /// Path of the file.
+        path: PathBuf,
+        /// Why the file could not be decoded.
+        reason: String,
+    },
+    /// The arguments are inconsistent.
+    #[error("{0}")]
+    Usage(&'static str),
+    /// The transcript has unauthenticated data, but the command needs the
+    /// whole transcript.
+    #[error("the transcript is not fully authenticated")]
+    Incomplete,
+    /// The transcript could not be parsed.
+    #[error("failed to parse transcript: {0}")]
+    Parse(#[from] spanner::ParseError),
+    /// The policy could not be compiled.
+    #[error(transparent)]
+    Policy(#[from] PolicyError),
+    /// The context could not be built.
+    #[error(transparent)]
+    Context(#[from] HttpContextError),
+    /// The transcript could not be enforced against the template.
+    #[error(transparent)]
+    Enforce(HttpEnforceError),
+    /// The transcript does not match the template.
+    #[error("the transcript does not match the template ({0} mismatches)")]
+    Mismatch(usize),
+    /// The output could not be encoded.
+    #[error("failed to encode output: {0}")]
+    Encode(String),
+    /// The output could not be written.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
@@ -0,0 +1,251 @@
+//! Reading transcripts and configuration files.
+
+use std::{fs, path::Path, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use context::transcript::{CompressedPartialTranscript, PartialTranscript, Transcript};
+use rangeset::RangeSet;
+use serde::de::DeserializeOwned;
+
+use crate::error::CliError;
+
+/// The format of a transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Format {
+    /// The raw bytes of the sent and received data, in two files.
+    #[default]
+    Raw,
+    /// The sent and received data as hex, in two files. Whitespace is
+    /// ignored.
+    Hex,
+    /// A bincode encoded partial transcript, in one file.
+    Bincode,
+    /// A bincode encoded partial transcript as hex, in one file, as written
+    /// by `policy --hex`. Whitespace is ignored.
+    HexBincode,
+}
+
+/// Arguments selecting a transcript.
+#[derive(Debug, Clone, Args)]
+pub(crate) struct TranscriptArgs {
+    /// The sent data, or the transcript with `--format bincode` or
+    /// `--format hex-bincode`.
+    pub sent: PathBuf,
+    /// The received data.
+    pub received: Option<PathBuf>,
+    /// The format of the transcript.
+    #[arg(short, long, value_enum, default_value_t)]
+    pub format: Format,
+}
+
+impl TranscriptArgs {
+    /// Loads the transcript, which may be partial.
+    ///
+    /// Raw and hex transcripts are fully authenticated.
+    pub(crate) fn load_partial(&self) -> Result<PartialTranscript, CliError> {
+        match (self.format, &self.received) {
+            (Format::Raw | Format::Hex, Some(received)) => {
+                let sent = self.read_data(&self.sent)?;
+                let received = self.read_data(received)?;
+                let transcript = Transcript::new(sent, received);
+                let (len_sent, len_received) = transcript.len();
+
+                Ok(transcript
+                    .to_partial(RangeSet::from(0..len_sent), RangeSet::from(0..len_received)))
+            }
+            (Format::Raw | Format::Hex, None) => Err(CliError::Usage(
+                "the received data is required unless the format is bincode or hex-bincode",
+            )),
+            (Format::Bincode | Format::HexBincode, None) => {
+                let compressed: CompressedPartialTranscript =
+                    bincode::deserialize(&self.read_data(&self.sent)?).map_err(|err| {
+                        CliError::Decode {
+                            path: self.sent.clone(),
+                            reason: err.to_string(),
+                        }
+                    })?;
+
+                Ok(compressed.into())
+            }
+            (Format::Bincode | Format::HexBincode, Some(_)) => Err(CliError::Usage(
+                "a bincode transcript is read from a single file",
+            )),
+        }
+    }
+
+    /// Loads the transcript, which must be fully authenticated.
+    pub(crate) fn load(&self) -> Result<Transcript, CliError> {
+        let partial = self.load_partial()?;
+        if !partial.is_complete() {
+            return Err(CliError::Incomplete);
+        }
+
+        Ok(Transcript::new(
+            partial.sent_unsafe(),
+            partial.received_unsafe(),
+        ))
+    }
+
+    fn read_data(&self, path: &Path) -> Result<Vec<u8>, CliError> {
+        let data = read(path)?;
+        match self.format {
+            Format::Hex | Format::HexBincode => {
+                decode_hex(&data).map_err(|reason| CliError::Decode {
+                    path: path.to_path_buf(),
+                    reason,
+                })
+            }
+            _ => Ok(data),
+        }
+    }
+}
+
+/// Reads a file.
+pub(crate) fn read(path: &Path) -> Result<Vec<u8>, CliError> {
+    fs::read(path).map_err(|source| CliError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Reads a configuration file, such as a policy or template.
+///
+/// Files with a `.json` extension are read as JSON, and any other file as
+/// TOML.
+pub(crate) fn read_config<T: DeserializeOwned>(path: &Path) -> Result<T, CliError> {
+    let src = String::from_utf8(read(path)?).map_err(|err| CliError::Decode {
+        path: path.to_path_buf(),
+        reason: err.to_string(),
+    })?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let config = if is_json {
+        serde_json::from_str(&src).map_err(|err| err.to_string())
+    } else {
+        toml::from_str(&src).map_err(|err| err.to_string())
+    };
+
+    config.map_err(|reason| CliError::Decode {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+/// Decodes hex, ignoring whitespace.
+fn decode_hex(data: &[u8]) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = data
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+
+    hex::decode(digits).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::tests::TempDir;
+
+    #[rstest]
+    #[case::plain(b"48656c6c6f", b"Hello")]
+    #[case::whitespace(b"48 65\n6C 6c\r\n6f\n", b"Hello")]
+    #[case::empty(b"", b"")]
+    fn test_decode_hex(#[case] data: &[u8], #[case] expected: &[u8]) {
+        assert_eq!(decode_hex(data).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::odd(b"486")]
+    #[case::invalid(b"zz")]
+    fn test_decode_hex_invalid(#[case] data: &[u8]) {
+        assert!(decode_hex(data).is_err());
+    }
+
+    #[rstest]
+    fn test_load_formats() {
+        let dir = TempDir::new("load-formats");
+        let transcript = Transcript::new(b"GET / HTTP/1.1\r\n\r\n", b"ok");
+
+        let raw = TranscriptArgs {
+            sent: dir.write("sent", transcript.sent()),
+            received: Some(dir.write("received", transcript.received())),
+            format: Format::Raw,
+        };
+        let hex = TranscriptArgs {
+            sent: dir.write("sent.hex", hex::encode(transcript.sent()).as_bytes()),
+            received: Some(dir.write("received.hex", b"6f 6b\n")),
+            format: Format::Hex,
+        };
+        let partial = transcript.to_partial(RangeSet::from(0..4), RangeSet::from(0..2));
+        let encoded = bincode::serialize(&CompressedPartialTranscript::from(partial)).unwrap();
+        let bincode = TranscriptArgs {
+            sent: dir.write("transcript.bin", &encoded),
+            received: None,
+            format: Format::Bincode,
+        };
+        let hex_bincode = TranscriptArgs {
+            sent: dir.write(
+                "transcript.hex",
+                format!("{}\n", hex::encode(&encoded)).as_bytes(),
+            ),
+            received: None,
+            format: Format::HexBincode,
+        };
+
+        for args in [&raw, &hex] {
+            let loaded = args.load().unwrap();
+            assert_eq!(loaded.sent(), transcript.sent());
+            assert_eq!(loaded.received(), transcript.received());
+        }
+
+        for args in [&bincode, &hex_bincode] {
+            let loaded = args.load_partial().unwrap();
+            assert_eq!(loaded.sent_authed(), &RangeSet::from(0..4));
+            assert_eq!(&loaded.sent_unsafe()[..4], b"GET ");
+            assert!(matches!(args.load(), Err(CliError::Incomplete)));
+        }
+    }
+
+    #[rstest]
+    fn test_load_usage() {
+        let args = TranscriptArgs {
+            sent: PathBuf::from("sent"),
+            received: None,
+            format: Format::Raw,
+        };
+        assert!(matches!(args.load_partial(), Err(CliError::Usage(_))));
+
+        let args = TranscriptArgs {
+            received: Some(PathBuf::from("received")),
+            format: Format::Bincode,
+            ..args
+        };
+        assert!(matches!(args.load_partial(), Err(CliError::Usage(_))));
+    }
+
+    #[rstest]
+    fn test_read_config() {
+        let dir = TempDir::new("read-config");
+
+        let json: serde_json::Value =
+            read_config(&dir.write("config.JSON", br#"{"a": 1}"#)).unwrap();
+        assert_eq!(json["a"], 1);
+
+        let toml: serde_json::Value = read_config(&dir.write("config.toml", b"a = 1")).unwrap();
+        assert_eq!(toml["a"], 1);
+
+        assert!(matches!(
+            read_config::<serde_json::Value>(&dir.write("invalid.toml", b"a = ")),
+            Err(CliError::Decode { .. })
+        ));
+        assert!(matches!(
+            read_config::<serde_json::Value>(&dir.path("missing.toml")),
+            Err(CliError::Read { .. })
+        ));
+    }
+}
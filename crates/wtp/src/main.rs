@@ -0,0 +1,133 @@
+//! Command-line tool for inspecting HTTP transcripts and computing
+//! disclosures.
+//!
+//! ```text
+//! wtp parse sent.bin received.bin
+//! wtp policy sent.bin received.bin --policy policy.toml --output partial.bin
+//! wtp context partial.bin --format bincode
+//! wtp check partial.bin --format bincode --template template.toml
+//! ```
+
+mod commands;
+mod error;
+mod input;
+
+use std::{
+    io::{self, Write},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    commands::{check::CheckArgs, context::ContextArgs, parse::ParseArgs, policy::PolicyArgs},
+    error::CliError,
+};
+
+/// Inspects HTTP transcripts and computes disclosures.
+#[derive(Debug, Parser)]
+#[command(name = "wtp", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the spanned structure of a transcript.
+    Parse(ParseArgs),
+    /// Applies a disclosure policy and writes the partial transcript.
+    Policy(PolicyArgs),
+    /// Prints the HTTP context of a transcript as JSON.
+    Context(ContextArgs),
+    /// Enforces a template on a transcript.
+    Check(CheckArgs),
+}
+
+fn run(command: &Command, out: &mut impl Write, log: &mut impl Write) -> Result<(), CliError> {
+    match command {
+        Command::Parse(args) => commands::parse::parse(args, out),
+        Command::Policy(args) => commands::policy::policy(args, out, log),
+        Command::Context(args) => commands::context::context(args, out),
+        Command::Check(args) => commands::check::check(args, out),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(&cli.command, &mut io::stdout().lock(), &mut io::stderr()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use clap::CommandFactory;
+
+    use super::*;
+
+    /// A temporary directory, removed when dropped.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("wtp-{}-{name}", std::process::id()));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        pub(crate) fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+
+        /// Writes a file, returning its path.
+        pub(crate) fn write(&self, name: &str, data: &[u8]) -> PathBuf {
+            let path = self.path(name);
+            fs::write(&path, data).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_cli_parse_args() {
+        let cli = Cli::try_parse_from([
+            "wtp",
+            "check",
+            "partial.bin",
+            "--format",
+            "bincode",
+            "--template",
+            "template.toml",
+        ])
+        .unwrap();
+
+        let Command::Check(args) = cli.command else {
+            panic!("expected check");
+        };
+        assert_eq!(args.transcript.sent, Path::new("partial.bin"));
+        assert_eq!(args.transcript.received, None);
+        assert_eq!(args.transcript.format, input::Format::Bincode);
+        assert_eq!(args.template, Path::new("template.toml"));
+    }
+}